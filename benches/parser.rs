@@ -0,0 +1,61 @@
+#[macro_use]
+extern crate criterion;
+extern crate strict_yaml_rust;
+
+use criterion::{black_box, Criterion, Throughput};
+use strict_yaml_rust::parser::Parser;
+use strict_yaml_rust::{Event, StrictYamlLoader};
+
+fn sample(entries: usize) -> String {
+    let mut s = String::new();
+    for i in 0..entries {
+        s.push_str(&format!(
+            "service-{}:\n  image: \"registry.local/app:{}.0\"\n  replicas: {}\n  \
+             ports:\n    - 80\n    - 443\n  env:\n    NAME: 'service {}'\n    DEBUG: false\n",
+            i,
+            i,
+            i % 7,
+            i
+        ));
+    }
+    s
+}
+
+fn parser_benches(c: &mut Criterion) {
+    let source = sample(2000);
+    let mut group = c.benchmark_group("parser");
+    group.throughput(Throughput::Bytes(source.len() as u64));
+
+    group.bench_function("next", |b| {
+        b.iter(|| {
+            let mut parser = Parser::new(source.chars());
+            loop {
+                let (ev, _) = parser.next().unwrap();
+                if ev == Event::StreamEnd {
+                    break;
+                }
+                black_box(ev);
+            }
+        })
+    });
+
+    group.bench_function("peek_next", |b| {
+        b.iter(|| {
+            let mut parser = Parser::new(source.chars());
+            loop {
+                if parser.peek().unwrap().0 == Event::StreamEnd {
+                    break;
+                }
+                black_box(parser.next().unwrap());
+            }
+        })
+    });
+
+    group.bench_function("load_from_str", |b| {
+        b.iter(|| black_box(StrictYamlLoader::load_from_str(&source).unwrap()))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, parser_benches);
+criterion_main!(benches);
@@ -1,7 +1,6 @@
 extern crate strict_yaml_rust;
 
 use std::env;
-use std::fs::read_to_string;
 
 use strict_yaml_rust::strict_yaml;
 
@@ -35,13 +34,11 @@ fn dump_node(doc: &strict_yaml::StrictYaml, indent: usize) {
 }
 
 fn main() -> Result<()> {
-    let mut args = env::args().into_iter();
+    let mut args = env::args();
     args.next();
 
     let filename = args.next().expect("Name of file to parse");
-    let s = read_to_string(filename)?;
-
-    let docs = strict_yaml::StrictYamlLoader::load_from_str(&s)?;
+    let docs = strict_yaml::StrictYamlLoader::load_from_file(filename)?;
     for doc in &docs {
         println!("---");
         dump_node(doc, 0);
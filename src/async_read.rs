@@ -0,0 +1,181 @@
+//! Non-blocking loading from `futures_io::AsyncRead` sources.
+//!
+//! Enabled by the `futures-io` feature. The loader is runtime-agnostic: any
+//! reader implementing the `futures-io` traits works, and tokio readers can be
+//! adapted with `tokio-util`'s `compat` module. Untrusted uploads should go
+//! through `load_from_async_reader_with_options`, to bound their nesting and
+//! alias expansion.
+
+use futures_io::AsyncRead;
+use options::{LoadOptions, OptionsReceiver, OptionsState};
+use push::PushParser;
+use scanner::ScanError;
+use std::cell::Cell;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use strict_yaml::{StrictYaml, StrictYamlLoader};
+
+const CHUNK_SIZE: usize = 8 * 1024;
+
+/// Future returned by `StrictYamlLoader::load_from_async_reader` and
+/// `load_from_async_reader_with_options`.
+pub struct LoadFromAsyncReader<R> {
+    reader: R,
+    parser: PushParser,
+    loader: StrictYamlLoader,
+    options: LoadOptions,
+    /// The state of the options between reads.
+    state: OptionsState,
+    /// Number of bytes read so far.
+    bytes: Cell<usize>,
+    buf: Vec<u8>,
+}
+
+impl<R: AsyncRead + Unpin> LoadFromAsyncReader<R> {
+    pub(crate) fn new(reader: R, options: &LoadOptions) -> LoadFromAsyncReader<R> {
+        LoadFromAsyncReader {
+            reader,
+            parser: PushParser::with_options(options),
+            loader: StrictYamlLoader::with_max_depth(options.get_max_depth()),
+            options: options.clone(),
+            state: OptionsState::new(),
+            bytes: Cell::new(0),
+            buf: vec![0; CHUNK_SIZE],
+        }
+    }
+}
+
+fn invalid_data(e: ScanError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+impl<R: AsyncRead + Unpin> Future for LoadFromAsyncReader<R> {
+    type Output = io::Result<Vec<StrictYaml>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        loop {
+            let n = match Pin::new(&mut this.reader).poll_read(cx, &mut this.buf) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(Err(ref e)) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Ready(Ok(n)) => n,
+            };
+            this.bytes.set(this.bytes.get() + n);
+            let mut recv = OptionsReceiver::new(
+                &mut this.loader,
+                &this.options,
+                &this.bytes,
+                &mut this.state,
+            );
+            if n == 0 {
+                this.parser.finish(&mut recv).map_err(invalid_data)?;
+                return Poll::Ready(Ok(this.loader.take_documents()));
+            }
+            this.parser
+                .feed(&this.buf[..n], &mut recv)
+                .map_err(invalid_data)?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::Arc;
+    use std::task::{Wake, Waker};
+
+    /// Yields its input a few bytes at a time, returning `Pending` in between.
+    struct Trickle {
+        data: &'static [u8],
+        ready: bool,
+    }
+
+    impl AsyncRead for Trickle {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context,
+            buf: &mut [u8],
+        ) -> Poll<io::Result<usize>> {
+            if !self.ready {
+                self.ready = true;
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+            self.ready = false;
+            let n = self.data.len().min(buf.len()).min(3);
+            buf[..n].copy_from_slice(&self.data[..n]);
+            self.data = &self.data[n..];
+            Poll::Ready(Ok(n))
+        }
+    }
+
+    struct NoopWaker;
+
+    impl Wake for NoopWaker {
+        fn wake(self: Arc<Self>) {}
+    }
+
+    fn block_on<F: Future + Unpin>(mut fut: F) -> F::Output {
+        let waker = Waker::from(Arc::new(NoopWaker));
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            if let Poll::Ready(out) = Pin::new(&mut fut).poll(&mut cx) {
+                return out;
+            }
+        }
+    }
+
+    #[test]
+    fn test_load_from_async_reader() {
+        let s = "a: 1\nb:\n  - x\n  - 你好\n---\nc\n";
+        let reader = Trickle {
+            data: s.as_bytes(),
+            ready: false,
+        };
+        let docs = block_on(StrictYamlLoader::load_from_async_reader(reader)).unwrap();
+        assert_eq!(docs, StrictYamlLoader::load_from_str(s).unwrap());
+    }
+
+    #[test]
+    fn test_load_with_options() {
+        let load = |data: &'static [u8], options: &LoadOptions| {
+            let reader = Trickle { data, ready: false };
+            let future = StrictYamlLoader::load_from_async_reader_with_options(reader, options);
+            block_on(future).map_err(|e| e.into_inner().unwrap().to_string())
+        };
+        let options = LoadOptions::new().max_depth(1);
+        assert_eq!(
+            load(b"a:\n  b: c\n", &options),
+            Err("collections nested deeper than 1 levels at line 2 column 4".to_owned())
+        );
+        let options = LoadOptions::new()
+            .flow_collections(true)
+            .indentation_step(2);
+        let docs = load(b"a: [b, c]\nd:\n  e: f\n", &options).unwrap();
+        assert_eq!(docs[0]["a"][1].as_str(), Some("c"));
+        assert!(load(b"a:\n   b: c\n", &options).is_err());
+        let options = LoadOptions::new()
+            .reject_control_chars(true)
+            .reject_bom(true);
+        assert_eq!(
+            load(b"a: b\0", &options),
+            Err("null character in input at line 1 column 5".to_owned())
+        );
+        assert!(load(b"\xef\xbb\xbfa: b", &options).is_err());
+        assert!(load(b"\xef\xbb\xbfa: b", &LoadOptions::new()).is_ok());
+    }
+
+    #[test]
+    fn test_async_scan_error() {
+        let reader = Trickle {
+            data: b"a: 'open",
+            ready: false,
+        };
+        let err = block_on(StrictYamlLoader::load_from_async_reader(reader)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.get_ref().unwrap().is::<ScanError>());
+    }
+}
@@ -0,0 +1,165 @@
+//! Non-blocking emission to `futures_io::AsyncWrite` sinks.
+//!
+//! Enabled by the `futures-io` feature, like the async loader.
+
+use emitter::StrictYamlEmitter;
+use futures_io::AsyncWrite;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use strict_yaml::StrictYaml;
+
+/// Emits documents to an `AsyncWrite`.
+///
+/// Each document is rendered in memory, then written out as fast as the sink
+/// accepts it: a sink that is not ready suspends the `Dump` future instead of
+/// blocking the thread.
+pub struct AsyncStrictYamlEmitter<W> {
+    writer: W,
+    compact: bool,
+}
+
+impl<W: AsyncWrite + Unpin> AsyncStrictYamlEmitter<W> {
+    pub fn new(writer: W) -> AsyncStrictYamlEmitter<W> {
+        AsyncStrictYamlEmitter {
+            writer,
+            compact: true,
+        }
+    }
+
+    /// See `StrictYamlEmitter::compact`.
+    pub fn compact(&mut self, compact: bool) {
+        self.compact = compact;
+    }
+
+    pub fn is_compact(&self) -> bool {
+        self.compact
+    }
+
+    /// Writes `doc`, followed by a line break, and flushes the sink. Emit errors
+    /// are reported as `io::ErrorKind::InvalidData` errors.
+    pub fn dump<'a>(&'a mut self, doc: &StrictYaml) -> Dump<'a, W> {
+        let mut text = String::new();
+        let rendered = {
+            let mut emitter = StrictYamlEmitter::new(&mut text);
+            emitter.compact(self.compact);
+            emitter.dump(doc)
+        };
+        text.push('\n');
+        Dump {
+            writer: &mut self.writer,
+            buf: text.into_bytes(),
+            written: 0,
+            error: rendered
+                .err()
+                .map(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// Future returned by `AsyncStrictYamlEmitter::dump`.
+pub struct Dump<'a, W: 'a> {
+    writer: &'a mut W,
+    buf: Vec<u8>,
+    written: usize,
+    error: Option<io::Error>,
+}
+
+impl<'a, W: AsyncWrite + Unpin> Future for Dump<'a, W> {
+    type Output = io::Result<()>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        if let Some(e) = this.error.take() {
+            return Poll::Ready(Err(e));
+        }
+        while this.written < this.buf.len() {
+            match Pin::new(&mut *this.writer).poll_write(cx, &this.buf[this.written..]) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(Err(ref e)) if e.kind() == io::ErrorKind::Interrupted => {}
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Ready(Ok(0)) => return Poll::Ready(Err(io::ErrorKind::WriteZero.into())),
+                Poll::Ready(Ok(n)) => this.written += n,
+            }
+        }
+        Pin::new(&mut *this.writer).poll_flush(cx)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::Arc;
+    use std::task::{Wake, Waker};
+    use strict_yaml::StrictYamlLoader;
+
+    /// Accepts a few bytes at a time, returning `Pending` in between.
+    #[derive(Default)]
+    struct Sluggish {
+        data: Vec<u8>,
+        ready: bool,
+        flushed: bool,
+    }
+
+    impl AsyncWrite for Sluggish {
+        fn poll_write(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            if !self.ready {
+                self.ready = true;
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+            self.ready = false;
+            let n = buf.len().min(3);
+            self.data.extend_from_slice(&buf[..n]);
+            Poll::Ready(Ok(n))
+        }
+
+        fn poll_flush(mut self: Pin<&mut Self>, _: &mut Context) -> Poll<io::Result<()>> {
+            self.flushed = true;
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(self: Pin<&mut Self>, _: &mut Context) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    struct NoopWaker;
+
+    impl Wake for NoopWaker {
+        fn wake(self: Arc<Self>) {}
+    }
+
+    fn block_on<F: Future + Unpin>(mut fut: F) -> F::Output {
+        let waker = Waker::from(Arc::new(NoopWaker));
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            if let Poll::Ready(out) = Pin::new(&mut fut).poll(&mut cx) {
+                return out;
+            }
+        }
+    }
+
+    #[test]
+    fn test_async_dump() {
+        let docs = StrictYamlLoader::load_from_str("a: 1\nb:\n  - x\n  - 你好\n---\nc").unwrap();
+        let mut emitter = AsyncStrictYamlEmitter::new(Sluggish::default());
+        for doc in &docs {
+            block_on(emitter.dump(doc)).unwrap();
+        }
+        let sink = emitter.into_inner();
+        assert!(sink.flushed);
+        let out = String::from_utf8(sink.data).unwrap();
+        assert_eq!(out, "---\na: \"1\"\nb:\n  - x\n  - 你好\n---\nc\n");
+        assert_eq!(StrictYamlLoader::load_from_str(&out).unwrap(), docs);
+    }
+}
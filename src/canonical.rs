@@ -0,0 +1,138 @@
+//! Canonical forms of documents, the same for documents that only differ in
+//! how they are written, for hashing and diffing.
+//!
+//! ```
+//! use strict_yaml_rust::canonical::CanonicalOptions;
+//! use strict_yaml_rust::StrictYaml;
+//!
+//! let a: StrictYaml = "b: 'x'\na: \"  \"".parse().unwrap();
+//! let b: StrictYaml = "a:\nb: x\n".parse().unwrap();
+//! let options = CanonicalOptions::new();
+//! assert_eq!(a.canonicalize(&options), b.canonicalize(&options));
+//! assert_eq!(a.to_canonical_string(&options), "---\na: \"\"\nb: \"x\"");
+//! ```
+
+use strict_yaml::StrictYaml;
+
+/// What `StrictYaml::canonicalize` normalizes. By default, keys are sorted,
+/// line breaks are `\n` and blank scalars are empty.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct CanonicalOptions {
+    sort_keys: bool,
+    line_breaks: bool,
+    blank_scalars: bool,
+    trim_scalars: bool,
+}
+
+impl Default for CanonicalOptions {
+    fn default() -> CanonicalOptions {
+        CanonicalOptions {
+            sort_keys: true,
+            line_breaks: true,
+            blank_scalars: true,
+            trim_scalars: false,
+        }
+    }
+}
+
+impl CanonicalOptions {
+    pub fn new() -> CanonicalOptions {
+        CanonicalOptions::default()
+    }
+
+    /// Sorts the entries of mappings by key, as `StrictYaml::sort_keys` does.
+    pub fn sort_keys(mut self, sort: bool) -> CanonicalOptions {
+        self.sort_keys = sort;
+        self
+    }
+
+    /// Replaces `\r\n` and `\r` in scalars with `\n`.
+    pub fn line_breaks(mut self, normalize: bool) -> CanonicalOptions {
+        self.line_breaks = normalize;
+        self
+    }
+
+    /// Empties scalars made only of whitespace.
+    pub fn blank_scalars(mut self, normalize: bool) -> CanonicalOptions {
+        self.blank_scalars = normalize;
+        self
+    }
+
+    /// Removes the whitespace around scalars, keys included.
+    pub fn trim_scalars(mut self, trim: bool) -> CanonicalOptions {
+        self.trim_scalars = trim;
+        self
+    }
+
+    fn scalar(&self, s: &str) -> String {
+        let mut s = if self.line_breaks && s.contains('\r') {
+            s.replace("\r\n", "\n").replace('\r', "\n")
+        } else {
+            s.to_owned()
+        };
+        if self.trim_scalars {
+            s = s.trim().to_owned();
+        } else if self.blank_scalars && s.trim().is_empty() {
+            s.clear();
+        }
+        s
+    }
+}
+
+/// The canonical form of `node`, see `StrictYaml::canonicalize`.
+pub(crate) fn canonicalize(node: &StrictYaml, options: &CanonicalOptions) -> StrictYaml {
+    let mut node = normalize(node, options);
+    if options.sort_keys {
+        node.sort_keys();
+    }
+    node
+}
+
+fn normalize(node: &StrictYaml, options: &CanonicalOptions) -> StrictYaml {
+    match *node {
+        StrictYaml::String(ref s) => StrictYaml::String(options.scalar(s)),
+        StrictYaml::Array(ref v) => {
+            StrictYaml::Array(v.iter().map(|item| normalize(item, options)).collect())
+        }
+        StrictYaml::Hash(ref h) => StrictYaml::Hash(
+            h.iter()
+                .map(|(k, v)| (normalize(k, options), normalize(v, options)))
+                .collect(),
+        ),
+        StrictYaml::BadValue => StrictYaml::BadValue,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use strict_yaml;
+
+    #[test]
+    fn test_canonicalize() {
+        let doc = strict_yaml!({"b": " x\r\ny ", "a": ["\t", {"d": "1", "c": "2"}]});
+        let options = CanonicalOptions::new();
+        assert_eq!(
+            doc.canonicalize(&options),
+            strict_yaml!({"a": ["", {"c": "2", "d": "1"}], "b": " x\ny "})
+        );
+        let options = CanonicalOptions::new()
+            .sort_keys(false)
+            .line_breaks(false)
+            .blank_scalars(false);
+        assert_eq!(doc.canonicalize(&options), doc);
+        let options = CanonicalOptions::new().trim_scalars(true);
+        assert_eq!(doc.canonicalize(&options)["b"].as_str(), Some("x\ny"));
+
+        let text = doc.to_canonical_string(&CanonicalOptions::new());
+        assert_eq!(
+            text,
+            "---\na:\n  - \"\"\n  - c: \"2\"\n    d: \"1\"\nb: \" x\\ny \""
+        );
+        let reordered = strict_yaml!({"a": [" ", {"c": "2", "d": "1"}], "b": " x\ny "});
+        assert_eq!(
+            reordered.to_canonical_string(&CanonicalOptions::new()),
+            text
+        );
+    }
+}
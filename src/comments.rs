@@ -0,0 +1,445 @@
+//! Keeping the comments of a document through `parse → edit → emit`.
+//!
+//! Comments are attached to the path of a mapping entry or sequence item: the
+//! comment lines just before it are its leading comments, and a comment
+//! ending its first line is its trailing comment. Comments after the last
+//! node are the footer of the document. `StrictYamlEmitter::comments` writes
+//! them back.
+//!
+//! The paths are those of the document as loaded. When the document is
+//! edited before it is emitted, comments follow their node to where the edit
+//! moved it: an item to its new index, an entry to its new key when its
+//! value is unchanged. Entries are told apart by their key and items by their
+//! value; items with a changed value keep the comments of the item they
+//! replace.
+//!
+//! ```
+//! use strict_yaml_rust::comments::load_from_str;
+//! use strict_yaml_rust::path::{PathSegment, YamlPath};
+//! use strict_yaml_rust::StrictYamlEmitter;
+//!
+//! let source = "# the port\nport: \"8080\" # not 80\nhost: localhost";
+//! let (doc, comments) = load_from_str(source).unwrap().remove(0);
+//! let path = YamlPath::from(vec![PathSegment::Key("port".to_owned())]);
+//! let port = comments.get(&path).unwrap();
+//! assert_eq!(port.leading, [" the port"]);
+//! assert_eq!(port.trailing.as_deref(), Some(" not 80"));
+//!
+//! let mut out = String::new();
+//! let mut emitter = StrictYamlEmitter::new(&mut out);
+//! emitter.comments(&comments);
+//! emitter.dump(&doc).unwrap();
+//! assert_eq!(out, format!("---\n{}", source));
+//! ```
+
+use filter::PathTracker;
+use parser::{Event, MarkedEventReceiver, Parser};
+use path::{PathSegment, YamlPath};
+use scanner::{Marker, ScanError};
+use std::collections::HashMap;
+use std::mem;
+use strict_yaml::{StrictYaml, StrictYamlLoader};
+
+/// The comments of a node, without their `#`.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct NodeComments {
+    /// The comment lines before the node.
+    pub leading: Vec<String>,
+    /// The comment ending the first line of the node.
+    pub trailing: Option<String>,
+}
+
+/// The comments of a document, by path.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct Comments {
+    nodes: HashMap<YamlPath, NodeComments>,
+    footer: Vec<String>,
+    /// The document the comments were loaded with, which the paths are in.
+    document: Option<StrictYaml>,
+}
+
+impl Comments {
+    pub fn new() -> Comments {
+        Comments::default()
+    }
+
+    pub fn get(&self, path: &YamlPath) -> Option<&NodeComments> {
+        self.nodes.get(path)
+    }
+
+    /// The comments of `path`, added if there are none.
+    pub fn entry(&mut self, path: YamlPath) -> &mut NodeComments {
+        self.nodes.entry(path).or_default()
+    }
+
+    pub fn remove(&mut self, path: &YamlPath) -> Option<NodeComments> {
+        self.nodes.remove(path)
+    }
+
+    /// The comment lines after the last node.
+    pub fn footer(&self) -> &[String] {
+        &self.footer
+    }
+
+    pub fn footer_mut(&mut self) -> &mut Vec<String> {
+        &mut self.footer
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty() && self.footer.is_empty()
+    }
+
+    /// The paths of the nodes of `doc`, an edited copy of the document the
+    /// comments were loaded with, mapped to the paths they had when loaded.
+    /// `None` when the comments were not loaded with a document, and their
+    /// paths are taken as they are.
+    pub(crate) fn origins(&self, doc: &StrictYaml) -> Option<HashMap<YamlPath, YamlPath>> {
+        let loaded = self.document.as_ref()?;
+        let mut origins = HashMap::new();
+        follow(
+            loaded,
+            doc,
+            &YamlPath::new(),
+            &YamlPath::new(),
+            &mut origins,
+        );
+        Some(origins)
+    }
+
+    /// Attaches `comments` to the nodes starting at `starts`, in document
+    /// order. `lines` are the lines of the source.
+    fn attach(
+        starts: &[(YamlPath, Marker)],
+        comments: Vec<(Marker, String)>,
+        lines: &[&str],
+    ) -> Comments {
+        let mut attached = Comments::new();
+        for (mark, text) in comments {
+            let trailing = starts
+                .iter()
+                .rev()
+                .find(|(_, start)| start.line() == mark.line() && start.index() < mark.index());
+            if let Some((path, _)) = trailing {
+                attached.entry(path.clone()).trailing = Some(text);
+                continue;
+            }
+            let next = starts
+                .iter()
+                .find(|(_, start)| start.index() > mark.index());
+            match next {
+                // `- #...`, before the first key of a mapping item
+                Some((path, _)) if after_dash(lines, mark) => {
+                    attached.entry(path.clone()).trailing = Some(text)
+                }
+                Some((path, _)) => attached.entry(path.clone()).leading.push(text),
+                None => attached.footer.push(text),
+            }
+        }
+        attached
+    }
+}
+
+/// Loads all the documents of `source` with their comments.
+pub fn load_from_str(source: &str) -> Result<Vec<(StrictYaml, Comments)>, ScanError> {
+    let mut recv = CommentsReceiver {
+        loader: StrictYamlLoader::new(),
+        tracker: PathTracker::new(),
+        starts: Vec::new(),
+        item: None,
+        docs: Vec::new(),
+    };
+    let mut parser = Parser::new(source.chars());
+    parser.keep_comments();
+    parser.load(&mut recv, true)?;
+
+    let lines: Vec<&str> = source.lines().collect();
+    let mut comments = parser.take_comments().into_iter().peekable();
+    let mut result = Vec::new();
+    for ((starts, end), doc) in recv.docs.into_iter().zip(recv.loader.take_documents()) {
+        let mut own = Vec::new();
+        while let Some(c) = comments.next_if(|(mark, _)| mark.index() < end.index()) {
+            own.push(c);
+        }
+        let mut attached = Comments::attach(&starts, own, &lines);
+        attached.document = Some(doc.clone());
+        result.push((doc, attached));
+    }
+    // comments after the end of the last document
+    if let Some(&mut (_, ref mut last)) = result.last_mut() {
+        last.footer.extend(comments.map(|(_, text)| text));
+    }
+    Ok(result)
+}
+
+/// Maps the paths of the children of `now`, at `path`, to those of the
+/// children of `then`, at `origin`, they were edited from.
+fn follow(
+    then: &StrictYaml,
+    now: &StrictYaml,
+    origin: &YamlPath,
+    path: &YamlPath,
+    origins: &mut HashMap<YamlPath, YamlPath>,
+) {
+    let mut pair = |from: PathSegment, to: PathSegment, then: &StrictYaml, now: &StrictYaml| {
+        let (origin, path) = (origin.child(from), path.child(to));
+        follow(then, now, &origin, &path, origins);
+        origins.insert(path, origin);
+    };
+    match (then, now) {
+        (StrictYaml::Hash(then), StrictYaml::Hash(now)) => {
+            // entries keep their key, or their value when it was renamed
+            let mut renamed: Vec<_> = then.iter().filter(|&(k, _)| !now.contains_key(k)).collect();
+            for (k, v) in now {
+                let from = match then.get(k) {
+                    Some(old) => Some((k, old)),
+                    None => renamed
+                        .iter()
+                        .position(|&(_, old)| old == v)
+                        .map(|i| renamed.remove(i)),
+                };
+                if let Some((old_key, old)) = from {
+                    if let (Some(old_key), Some(key)) = (old_key.as_str(), k.as_str()) {
+                        let (from, to) = (old_key.to_owned(), key.to_owned());
+                        pair(PathSegment::Key(from), PathSegment::Key(to), old, v);
+                    }
+                }
+            }
+        }
+        (StrictYaml::Array(then), StrictYaml::Array(now)) => {
+            for (i, j) in align(then, now) {
+                pair(
+                    PathSegment::Index(i),
+                    PathSegment::Index(j),
+                    &then[i],
+                    &now[j],
+                );
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Pairs the indexes of the items of `then` and `now` that are the same item:
+/// equal items in order, and between them, the changed items by position.
+fn align(then: &[StrictYaml], now: &[StrictYaml]) -> Vec<(usize, usize)> {
+    let mut equal = Vec::new();
+    let mut from = 0;
+    for (j, item) in now.iter().enumerate() {
+        if let Some(i) = then[from..].iter().position(|old| old == item) {
+            equal.push((from + i, j));
+            from += i + 1;
+        }
+    }
+    let mut pairs = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    for (next_i, next_j) in equal.into_iter().chain(Some((then.len(), now.len()))) {
+        pairs.extend((i..next_i).zip(j..next_j));
+        if next_i < then.len() {
+            pairs.push((next_i, next_j));
+        }
+        i = next_i + 1;
+        j = next_j + 1;
+    }
+    pairs
+}
+
+/// Whether only a `-` is before the comment at `mark` on its line.
+fn after_dash(lines: &[&str], mark: Marker) -> bool {
+    lines.get(mark.line() - 1).is_some_and(|line| {
+        let before: String = line.chars().take(mark.col()).collect();
+        before.trim() == "-"
+    })
+}
+
+/// Loads the documents, recording where their entries and items start.
+struct CommentsReceiver {
+    loader: StrictYamlLoader,
+    tracker: PathTracker,
+    /// The entries and items of the current document, in order.
+    starts: Vec<(YamlPath, Marker)>,
+    /// A mapping item, which starts at its first key.
+    item: Option<(YamlPath, Marker)>,
+    /// The starts and the end of each document loaded.
+    docs: Vec<(Vec<(YamlPath, Marker)>, Marker)>,
+}
+
+impl MarkedEventReceiver for CommentsReceiver {
+    fn on_event(&mut self, ev: Event, mark: Marker) -> Result<(), ScanError> {
+        self.tracker.update(&ev);
+        let key = self.tracker.is_key() && matches!(ev, Event::Scalar(..));
+        if let Some((path, start)) = self.item.take() {
+            self.starts.push((path, if key { mark } else { start }));
+        }
+        match ev {
+            Event::DocumentEnd => {
+                let starts = mem::take(&mut self.starts);
+                self.docs.push((starts, mark));
+            }
+            Event::Scalar(..) | Event::SequenceStart(_) | Event::MappingStart(_) => {
+                let path = self.tracker.path();
+                let item = matches!(path.segments().last(), Some(PathSegment::Index(_)));
+                if key {
+                    self.starts.push((path.clone(), mark));
+                } else if item && !self.tracker.is_key() {
+                    match ev {
+                        Event::MappingStart(_) => self.item = Some((path.clone(), mark)),
+                        _ => self.starts.push((path.clone(), mark)),
+                    }
+                }
+            }
+            _ => {}
+        }
+        self.loader.on_event(ev, mark)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use emitter::{ScalarStyle, StrictYamlEmitter};
+
+    /// The path written `s`, such as `a[1].b`.
+    fn path(s: &str) -> YamlPath {
+        let mut path = YamlPath::new();
+        for part in s.split('.') {
+            let mut pieces = part.split('[');
+            path.push(PathSegment::Key(pieces.next().unwrap().to_owned()));
+            for index in pieces {
+                path.push(PathSegment::Index(
+                    index.trim_end_matches(']').parse().unwrap(),
+                ));
+            }
+        }
+        path
+    }
+
+    #[test]
+    fn test_attach() {
+        let source = "# header
+a: 1 # one
+# before b
+b:
+  # first item
+  - x # x
+  - c: d # d
+    # before e
+    e: | # block
+      text
+  -   # item
+    f: g
+# footer
+---
+h: i
+# end
+";
+        let docs = load_from_str(source).unwrap();
+        assert_eq!(docs.len(), 2);
+        let (ref doc, ref comments) = docs[0];
+        assert_eq!(doc["b"][1]["e"].as_str(), Some("text\n"));
+        let get = |p: &str| comments.get(&path(p)).cloned().unwrap_or_default();
+        assert_eq!(get("a").leading, [" header"]);
+        assert_eq!(get("a").trailing.as_deref(), Some(" one"));
+        assert_eq!(get("b").leading, [" before b"]);
+        assert_eq!(get("b").trailing, None);
+        assert_eq!(get("b[0]").leading, [" first item"]);
+        assert_eq!(get("b[0]").trailing.as_deref(), Some(" x"));
+        assert_eq!(get("b[1].c").trailing.as_deref(), Some(" d"));
+        assert_eq!(get("b[1]").trailing, None);
+        assert_eq!(get("b[1].e").leading, [" before e"]);
+        assert_eq!(get("b[1].e").trailing.as_deref(), Some(" block"));
+        assert_eq!(get("b[2]").trailing.as_deref(), Some(" item"));
+        assert!(get("b[2].f").leading.is_empty());
+        assert_eq!(comments.footer(), [" footer"]);
+        assert_eq!(docs[1].1.footer(), [" end"]);
+        assert!(docs[1].1.get(&path("h")).is_none());
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let source = "---
+# header
+a: one # one
+# before b
+b: # list
+  # first item
+  - x # x
+  - c: d # d
+    # before e
+    e: |- # block
+      some
+      text
+  # last
+  - - y # y
+    - z
+# footer";
+        let (doc, mut comments) = load_from_str(source).unwrap().remove(0);
+        let emit = |comments: &Comments| {
+            let mut out = String::new();
+            let mut emitter = StrictYamlEmitter::new(&mut out);
+            emitter.comments(comments);
+            emitter.style("**.e".parse().unwrap(), ScalarStyle::Literal);
+            emitter.dump(&doc).unwrap();
+            out
+        };
+        assert_eq!(emit(&comments), source);
+
+        comments.entry(path("b[1]")).trailing = Some(" item".to_owned());
+        comments.remove(&path("a"));
+        comments.footer_mut().clear();
+        assert_eq!(
+            emit(&comments),
+            "---
+a: one
+# before b
+b: # list
+  # first item
+  - x # x
+  - # item
+    c: d # d
+    # before e
+    e: |- # block
+      some
+      text
+  # last
+  - - y # y
+    - z"
+        );
+    }
+
+    #[test]
+    fn test_edit_then_emit() {
+        let source = "---
+# servers
+servers:
+  # primary
+  - alpha # a
+  - beta # b
+  - delta # d
+# port
+port: http # http";
+        let (mut doc, comments) = load_from_str(source).unwrap().remove(0);
+        let servers = &mut doc["servers"];
+        servers.insert(0, "zero").unwrap();
+        servers.remove_index(3);
+        servers[2] = StrictYaml::from_str("gamma");
+        let port = doc.remove_key("port").unwrap();
+        doc.set_path("listen", port).unwrap();
+
+        let mut out = String::new();
+        let mut emitter = StrictYamlEmitter::new(&mut out);
+        emitter.comments(&comments);
+        emitter.dump(&doc).unwrap();
+        assert_eq!(
+            out,
+            "---
+# servers
+servers:
+  - zero
+  # primary
+  - alpha # a
+  - gamma # b
+# port
+listen: http # http"
+        );
+    }
+}
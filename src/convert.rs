@@ -0,0 +1,298 @@
+//! `TryFrom` conversions of nodes into Rust values.
+//!
+//! Scalars convert into `i64`, `f64` and `bool` with the strict forms of
+//! `StrictYaml::as_i64`, `as_f64` and `as_bool`, and into `String`. Sequences
+//! convert into `Vec`s and mappings into maps with `String` keys, of any type
+//! their items convert into.
+//!
+//! Converting a `MarkedStrictYaml` rather than a `StrictYaml` gives errors
+//! with the position of the offending node.
+//!
+//! ```
+//! use std::collections::BTreeMap;
+//! use std::convert::TryFrom;
+//! use strict_yaml_rust::marked::MarkedStrictYaml;
+//! use strict_yaml_rust::StrictYamlLoader;
+//!
+//! let doc = StrictYamlLoader::load_from_str("a: 1\nb: 2").unwrap().remove(0);
+//! let map = BTreeMap::<String, i64>::try_from(doc).unwrap();
+//! assert_eq!(map["b"], 2);
+//!
+//! let doc = MarkedStrictYaml::load_from_str("- 1\n- x").unwrap().remove(0);
+//! let err = Vec::<i64>::try_from(doc).unwrap_err();
+//! assert_eq!(err.to_string(), "expected an integer, found \"x\" at line 2 column 3");
+//! ```
+
+use error::code;
+use marked::{MarkedNode, MarkedStrictYaml};
+use scanner::Marker;
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+use std::iter::FromIterator;
+use strict_yaml::{parse_bool, parse_decimal, parse_integer, StrictYaml};
+
+/// Error of converting a node, with the node and its position when known.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ConversionError {
+    expected: &'static str,
+    value: StrictYaml,
+    marker: Option<Marker>,
+}
+
+impl ConversionError {
+    pub fn code(&self) -> &'static str {
+        code::CONVERSION
+    }
+
+    /// What the node should have been, e.g. `an integer`.
+    pub fn expected(&self) -> &'static str {
+        self.expected
+    }
+
+    /// The node that does not convert.
+    pub fn value(&self) -> &StrictYaml {
+        &self.value
+    }
+
+    /// Where the node is, when converting a `MarkedStrictYaml`.
+    pub fn marker(&self) -> Option<Marker> {
+        self.marker
+    }
+}
+
+impl fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "expected {}, found ", self.expected)?;
+        match self.value {
+            StrictYaml::String(ref s) => write!(f, "{:?}", s)?,
+            StrictYaml::Array(_) => f.write_str("a sequence")?,
+            StrictYaml::Hash(_) => f.write_str("a mapping")?,
+            StrictYaml::BadValue => f.write_str("nothing")?,
+        }
+        if let Some(marker) = self.marker {
+            write!(f, " at line {} column {}", marker.line(), marker.col() + 1)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for ConversionError {}
+
+/// A node to convert, marked or not.
+trait Node: Sized {
+    fn error(self, expected: &'static str) -> ConversionError;
+
+    fn as_scalar(&self) -> Option<&str>;
+
+    fn into_items(self) -> Result<Vec<Self>, Self>;
+
+    fn into_entries(self) -> Result<Vec<(Self, Self)>, Self>;
+}
+
+impl Node for StrictYaml {
+    fn error(self, expected: &'static str) -> ConversionError {
+        ConversionError {
+            expected,
+            value: self,
+            marker: None,
+        }
+    }
+
+    fn as_scalar(&self) -> Option<&str> {
+        self.as_str()
+    }
+
+    fn into_items(self) -> Result<Vec<StrictYaml>, StrictYaml> {
+        match self {
+            StrictYaml::Array(v) => Ok(v),
+            node => Err(node),
+        }
+    }
+
+    fn into_entries(self) -> Result<Vec<(StrictYaml, StrictYaml)>, StrictYaml> {
+        match self {
+            StrictYaml::Hash(h) => Ok(h.into_iter().collect()),
+            node => Err(node),
+        }
+    }
+}
+
+impl Node for MarkedStrictYaml {
+    fn error(self, expected: &'static str) -> ConversionError {
+        ConversionError {
+            expected,
+            marker: Some(self.marker()),
+            value: self.into(),
+        }
+    }
+
+    fn as_scalar(&self) -> Option<&str> {
+        self.as_str()
+    }
+
+    fn into_items(self) -> Result<Vec<MarkedStrictYaml>, MarkedStrictYaml> {
+        match *self.node() {
+            MarkedNode::Array(_) => match self.into_node() {
+                MarkedNode::Array(v) => Ok(v),
+                _ => unreachable!(),
+            },
+            _ => Err(self),
+        }
+    }
+
+    fn into_entries(self) -> Result<Vec<(MarkedStrictYaml, MarkedStrictYaml)>, MarkedStrictYaml> {
+        match *self.node() {
+            MarkedNode::Hash(_) => match self.into_node() {
+                MarkedNode::Hash(entries) => Ok(entries),
+                _ => unreachable!(),
+            },
+            _ => Err(self),
+        }
+    }
+}
+
+fn scalar<N: Node, T, F: Fn(&str) -> Option<T>>(
+    node: N,
+    expected: &'static str,
+    parse: F,
+) -> Result<T, ConversionError> {
+    match node.as_scalar().and_then(parse) {
+        Some(v) => Ok(v),
+        None => Err(node.error(expected)),
+    }
+}
+
+fn string<N: Node>(node: N) -> Result<String, ConversionError> {
+    scalar(node, "a string", |s| Some(s.to_owned()))
+}
+
+fn items<N: Node, T: TryFrom<N, Error = ConversionError>>(
+    node: N,
+) -> Result<Vec<T>, ConversionError> {
+    match node.into_items() {
+        Ok(items) => items.into_iter().map(T::try_from).collect(),
+        Err(node) => Err(node.error("a sequence")),
+    }
+}
+
+fn entries<N: Node, T: TryFrom<N, Error = ConversionError>, M: FromIterator<(String, T)>>(
+    node: N,
+) -> Result<M, ConversionError> {
+    match node.into_entries() {
+        Ok(entries) => entries
+            .into_iter()
+            .map(|(k, v)| Ok((string(k)?, T::try_from(v)?)))
+            .collect(),
+        Err(node) => Err(node.error("a mapping")),
+    }
+}
+
+/// Implements the conversions of nodes of each type.
+macro_rules! try_from {
+    ($($node:ty),*) => {
+        $(
+            impl TryFrom<$node> for i64 {
+                type Error = ConversionError;
+
+                fn try_from(node: $node) -> Result<i64, ConversionError> {
+                    scalar(node, "an integer", parse_integer)
+                }
+            }
+
+            impl TryFrom<$node> for f64 {
+                type Error = ConversionError;
+
+                fn try_from(node: $node) -> Result<f64, ConversionError> {
+                    scalar(node, "a number", parse_decimal)
+                }
+            }
+
+            impl TryFrom<$node> for bool {
+                type Error = ConversionError;
+
+                fn try_from(node: $node) -> Result<bool, ConversionError> {
+                    scalar(node, "a boolean", parse_bool)
+                }
+            }
+
+            impl TryFrom<$node> for String {
+                type Error = ConversionError;
+
+                fn try_from(node: $node) -> Result<String, ConversionError> {
+                    string(node)
+                }
+            }
+
+            impl<T: TryFrom<$node, Error = ConversionError>> TryFrom<$node> for Vec<T> {
+                type Error = ConversionError;
+
+                fn try_from(node: $node) -> Result<Vec<T>, ConversionError> {
+                    items(node)
+                }
+            }
+
+            impl<T: TryFrom<$node, Error = ConversionError>> TryFrom<$node> for HashMap<String, T> {
+                type Error = ConversionError;
+
+                fn try_from(node: $node) -> Result<HashMap<String, T>, ConversionError> {
+                    entries(node)
+                }
+            }
+
+            impl<T: TryFrom<$node, Error = ConversionError>> TryFrom<$node> for BTreeMap<String, T> {
+                type Error = ConversionError;
+
+                fn try_from(node: $node) -> Result<BTreeMap<String, T>, ConversionError> {
+                    entries(node)
+                }
+            }
+        )*
+    };
+}
+
+try_from!(StrictYaml, MarkedStrictYaml);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use strict_yaml::StrictYamlLoader;
+
+    #[test]
+    fn test_scalars() {
+        let s = |v: &str| StrictYaml::String(v.to_owned());
+        assert_eq!(i64::try_from(s("-3")), Ok(-3));
+        assert_eq!(f64::try_from(s("2.5")), Ok(2.5));
+        assert_eq!(bool::try_from(s("true")), Ok(true));
+        assert_eq!(String::try_from(s("x")), Ok("x".to_owned()));
+
+        let err = bool::try_from(s("yes")).unwrap_err();
+        assert_eq!(err.to_string(), "expected a boolean, found \"yes\"");
+        assert_eq!(err.value(), &s("yes"));
+        assert_eq!(err.marker(), None);
+        assert_eq!(err.code(), "E501_CONVERSION");
+        let err = String::try_from(StrictYaml::Array(vec![])).unwrap_err();
+        assert_eq!(err.to_string(), "expected a string, found a sequence");
+    }
+
+    #[test]
+    fn test_collections() {
+        let source = "a:\n  - 1\n  - 2\nb:\n  - 3\n";
+        let doc = StrictYamlLoader::load_from_str(source).unwrap().remove(0);
+        let map = HashMap::<String, Vec<i64>>::try_from(doc.clone()).unwrap();
+        assert_eq!(map["a"], [1, 2]);
+        assert_eq!(map["b"], [3]);
+        let err = Vec::<String>::try_from(doc).unwrap_err();
+        assert_eq!(err.to_string(), "expected a sequence, found a mapping");
+
+        let source = "a:\n  x: 1\nb:\n  y: z\n";
+        let doc = MarkedStrictYaml::load_from_str(source).unwrap().remove(0);
+        let err = BTreeMap::<String, BTreeMap<String, i64>>::try_from(doc).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "expected an integer, found \"z\" at line 4 column 6"
+        );
+        assert_eq!(err.marker().map(|m| m.line()), Some(4));
+    }
+}
@@ -0,0 +1,845 @@
+//! A lossless syntax tree, for tools editing configuration files in place.
+//!
+//! `SyntaxTree` keeps the text of a document next to its nodes, each node
+//! knowing the span of text it was read from. Edits rewrite only the text of
+//! the nodes they touch, so whitespace, comments and quoting elsewhere come
+//! out byte for byte as they went in.
+//!
+//! ```
+//! use strict_yaml_rust::cst::SyntaxTree;
+//! use strict_yaml_rust::path::{PathSegment, YamlPath};
+//!
+//! let mut tree = SyntaxTree::parse("# server\nport:   '8080'  # default\nhost: localhost\n").unwrap();
+//! let port = YamlPath::from(vec![PathSegment::Key("port".to_owned())]);
+//! assert_eq!(tree.get(&port).and_then(|n| n.as_str()), Some("8080"));
+//!
+//! tree.set(&port, "9090").unwrap();
+//! tree.insert(&YamlPath::new(), "tls", "on").unwrap();
+//! assert_eq!(
+//!     tree.as_str(),
+//!     "# server\nport:   '9090'  # default\nhost: localhost\ntls: \"on\"\n"
+//! );
+//! ```
+
+use emitter::{escape_str, need_quotes};
+use error::code;
+use parser::{Event, MarkedEventReceiver, Parser};
+use path::{PathSegment, YamlPath};
+use scanner::{Marker, ScanError, TScalarStyle};
+use std::error::Error;
+use std::fmt;
+use std::ops::Range;
+use strict_yaml::{Hash, StrictYaml};
+
+/// The content of a `Node`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum NodeKind {
+    /// The value of a scalar and how it is written.
+    Scalar(String, TScalarStyle),
+    Sequence(Vec<Node>),
+    /// The entries of a mapping, in document order.
+    Mapping(Vec<(Node, Node)>),
+}
+
+/// A node of a `SyntaxTree`, with the span of text it was read from.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Node {
+    kind: NodeKind,
+    span: Range<usize>,
+}
+
+impl Node {
+    pub fn kind(&self) -> &NodeKind {
+        &self.kind
+    }
+
+    /// The byte range of the node in the text of its tree: a scalar with its
+    /// quotes or block header, a collection from its first entry to the end
+    /// of its last one. The span of a scalar without any text, such as the
+    /// value of `key:`, is empty.
+    pub fn span(&self) -> Range<usize> {
+        self.span.clone()
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self.kind {
+            NodeKind::Scalar(ref v, _) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn style(&self) -> Option<TScalarStyle> {
+        match self.kind {
+            NodeKind::Scalar(_, style) => Some(style),
+            _ => None,
+        }
+    }
+
+    /// The node at `path` below this one.
+    pub fn get(&self, path: &YamlPath) -> Option<&Node> {
+        let mut node = self;
+        for segment in path.segments() {
+            node = match (&node.kind, segment) {
+                (NodeKind::Mapping(entries), PathSegment::Key(key)) => entries
+                    .iter()
+                    .find(|(k, _)| k.as_str() == Some(key.as_str()))
+                    .map(|(_, v)| v)?,
+                (NodeKind::Sequence(items), PathSegment::Index(i)) => items.get(*i)?,
+                _ => return None,
+            };
+        }
+        Some(node)
+    }
+
+    /// The node without its text.
+    pub fn to_strict_yaml(&self) -> StrictYaml {
+        match self.kind {
+            NodeKind::Scalar(ref v, _) => StrictYaml::String(v.clone()),
+            NodeKind::Sequence(ref items) => {
+                StrictYaml::Array(items.iter().map(Node::to_strict_yaml).collect())
+            }
+            NodeKind::Mapping(ref entries) => {
+                let mut h = Hash::new();
+                for (k, v) in entries {
+                    h.insert(k.to_strict_yaml(), v.to_strict_yaml());
+                }
+                StrictYaml::Hash(h)
+            }
+        }
+    }
+}
+
+/// An edit which cannot be made, with the reason why.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct EditError(String);
+
+impl fmt::Display for EditError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl EditError {
+    pub(crate) fn new(message: String) -> EditError {
+        EditError(message)
+    }
+
+    pub fn code(&self) -> &'static str {
+        code::EDIT
+    }
+}
+
+impl Error for EditError {}
+
+/// A document with its text, edited in place.
+///
+/// Displays as its text. Each edit parses the edited text again, so the
+/// nodes and their spans always match it.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct SyntaxTree {
+    text: String,
+    root: Node,
+}
+
+impl SyntaxTree {
+    /// Parses `source`, which must hold at most one document.
+    pub fn parse(source: &str) -> Result<SyntaxTree, ScanError> {
+        let mut builder = Builder {
+            text: source,
+            offsets: source
+                .char_indices()
+                .map(|(i, _)| i)
+                .chain(Some(source.len()))
+                .collect(),
+            documents: 0,
+            stack: Vec::new(),
+            root: None,
+        };
+        Parser::new(source.chars()).load(&mut builder, true)?;
+        let root = builder.root.unwrap_or(Node {
+            kind: NodeKind::Scalar(String::new(), TScalarStyle::Plain),
+            span: 0..0,
+        });
+        Ok(SyntaxTree {
+            text: source.to_owned(),
+            root,
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    pub fn root(&self) -> &Node {
+        &self.root
+    }
+
+    pub fn get(&self, path: &YamlPath) -> Option<&Node> {
+        self.root.get(path)
+    }
+
+    /// Sets the scalar at `path` to `value`, keeping its quoting when it
+    /// can hold the value. Plain scalars get double quotes when needed, and
+    /// block scalars keep their header comment.
+    pub fn set(&mut self, path: &YamlPath, value: &str) -> Result<(), EditError> {
+        let node = self.node(path)?;
+        let style = match node.style() {
+            Some(style) => style,
+            None => return Err(EditError(format!("{} is not a scalar", describe(path)))),
+        };
+        let span = node.span();
+        let written = match style {
+            _ if span.is_empty() => format!(" {}", plain_or_quoted(value)),
+            TScalarStyle::SingleQuoted if !value.contains('\n') => {
+                format!("'{}'", value.replace('\'', "''"))
+            }
+            TScalarStyle::Literal | TScalarStyle::Foled => self
+                .block(span.clone(), style, value)
+                .unwrap_or_else(|| double_quoted(value)),
+            TScalarStyle::Plain => plain_or_quoted(value),
+            _ => double_quoted(value),
+        };
+        self.replace(span, &written)
+    }
+
+    /// Adds `key: value` after the last entry of the mapping at `path`.
+    pub fn insert(&mut self, path: &YamlPath, key: &str, value: &str) -> Result<(), EditError> {
+        let node = self.node(path)?;
+        let entries = match node.kind {
+            NodeKind::Mapping(ref entries) => entries,
+            _ => return Err(EditError(format!("{} is not a mapping", describe(path)))),
+        };
+        if entries.iter().any(|(k, _)| k.as_str() == Some(key)) {
+            let path = path.child(PathSegment::Key(key.to_owned()));
+            return Err(EditError(format!("{} already exists", describe(&path))));
+        }
+        let col = self.column(entries[0].0.span.start);
+        let line = format!("{}: {}", plain_or_quoted(key), plain_or_quoted(value));
+        self.append_line(node.span.end, col, &line)
+    }
+
+    /// Adds `value` after the last item of the sequence at `path`.
+    pub fn push(&mut self, path: &YamlPath, value: &str) -> Result<(), EditError> {
+        let node = self.node(path)?;
+        match node.kind {
+            NodeKind::Sequence(_) => {}
+            _ => return Err(EditError(format!("{} is not a sequence", describe(path)))),
+        }
+        let col = self.column(node.span.start);
+        let line = format!("- {}", plain_or_quoted(value));
+        self.append_line(node.span.end, col, &line)
+    }
+
+    /// Removes the mapping entry or sequence item at `path`, with the rest
+    /// of its last line. A collection cannot lose its only entry, as it
+    /// would then be read as an empty scalar.
+    pub fn remove(&mut self, path: &YamlPath) -> Result<(), EditError> {
+        let mut parent_path = path.clone();
+        let segment = match parent_path.pop() {
+            Some(segment) => segment,
+            None => return Err(EditError("cannot remove the root".to_owned())),
+        };
+        let parent = self.node(&parent_path)?;
+        let starts = self.entry_starts(parent);
+        let index = match (&parent.kind, &segment) {
+            (NodeKind::Mapping(entries), PathSegment::Key(key)) => entries
+                .iter()
+                .position(|(k, _)| k.as_str() == Some(key.as_str())),
+            (NodeKind::Sequence(items), &PathSegment::Index(i)) if i < items.len() => Some(i),
+            _ => None,
+        };
+        let index = match index {
+            Some(index) => index,
+            None => return Err(EditError(format!("no node at {}", describe(path)))),
+        };
+        if starts.len() == 1 {
+            return Err(EditError(format!(
+                "cannot remove the only entry of {}",
+                describe(&parent_path)
+            )));
+        }
+        let start = starts[index];
+        let end = match parent.kind {
+            NodeKind::Mapping(ref entries) => entries[index].1.span.end,
+            NodeKind::Sequence(ref items) => items[index].span.end,
+            NodeKind::Scalar(..) => unreachable!(),
+        };
+        let line_start = line_start(&self.text, start);
+        let range = if self.text[line_start..start].trim().is_empty() {
+            line_start..next_line(&self.text, end)
+        } else {
+            // the first entry of a compact collection, as in `- a: b`, gives
+            // its place to the next one
+            start..starts[index + 1]
+        };
+        self.replace(range, "")
+    }
+
+    fn node(&self, path: &YamlPath) -> Result<&Node, EditError> {
+        self.get(path)
+            .ok_or_else(|| EditError(format!("no node at {}", describe(path))))
+    }
+
+    /// Where the entries of a collection start: their key or their `-`.
+    fn entry_starts(&self, node: &Node) -> Vec<usize> {
+        match node.kind {
+            NodeKind::Mapping(ref entries) => entries.iter().map(|(k, _)| k.span.start).collect(),
+            NodeKind::Sequence(ref items) => {
+                let mut starts = vec![node.span.start];
+                for item in &items[..items.len() - 1] {
+                    starts.push(skip_trivia(&self.text, item.span.end));
+                }
+                starts
+            }
+            NodeKind::Scalar(..) => Vec::new(),
+        }
+    }
+
+    /// The column of the character at `pos`.
+    fn column(&self, pos: usize) -> usize {
+        self.text[line_start(&self.text, pos)..pos].chars().count()
+    }
+
+    /// Writes `line`, indented by `col`, after the line holding `pos`.
+    fn append_line(&mut self, pos: usize, col: usize, line: &str) -> Result<(), EditError> {
+        let at = next_line(&self.text, pos);
+        let line = format!("{:col$}{}", "", line, col = col);
+        let eol = self.line_break();
+        if at == self.text.len() && !self.text.ends_with('\n') {
+            self.replace(at..at, &format!("{}{}", eol, line))
+        } else {
+            self.replace(at..at, &format!("{}{}", line, eol))
+        }
+    }
+
+    /// The line break of the document, as found at the end of its first line.
+    fn line_break(&self) -> &'static str {
+        match self.text.find('\n') {
+            Some(i) if self.text[..i].ends_with('\r') => "\r\n",
+            _ => "\n",
+        }
+    }
+
+    /// `value` as a block scalar replacing the one at `span`, if it can be
+    /// written as one.
+    fn block(&self, span: Range<usize>, style: TScalarStyle, value: &str) -> Option<String> {
+        if value.is_empty() || value.starts_with([' ', '\n']) {
+            return None;
+        }
+        let old = &self.text[span.clone()];
+        let header_end = old.find('\n').unwrap_or(old.len());
+        let indicator_end = old[1..]
+            .find(|c: char| !c.is_ascii_digit() && c != '+' && c != '-')
+            .map_or(header_end, |i| i + 1);
+        let comment = old[indicator_end..header_end].trim_end_matches('\r');
+        let indent = match old[header_end..].lines().find(|l| !l.trim().is_empty()) {
+            Some(l) => l.len() - l.trim_start_matches(' ').len(),
+            None => {
+                let start = line_start(&self.text, span.start);
+                let line = &self.text[start..span.start];
+                line.len() - line.trim_start_matches(' ').len() + 2
+            }
+        };
+
+        let content = value.strip_suffix('\n').unwrap_or(value);
+        let folded = style == TScalarStyle::Foled && !content.contains('\n');
+        let mut written = String::from(if folded { ">" } else { "|" });
+        if value.ends_with("\n\n") {
+            written.push('+');
+        } else if !value.ends_with('\n') {
+            written.push('-');
+        }
+        written.push_str(comment);
+        let eol = self.line_break();
+        for line in content.split('\n') {
+            written.push_str(eol);
+            if !line.is_empty() {
+                written.push_str(&format!("{:indent$}{}", "", line, indent = indent));
+            }
+        }
+        Some(written)
+    }
+
+    fn replace(&mut self, range: Range<usize>, with: &str) -> Result<(), EditError> {
+        let mut text = self.text.clone();
+        text.replace_range(range, with);
+        *self = SyntaxTree::parse(&text)
+            .map_err(|e| EditError(format!("the edited text does not parse: {}", e)))?;
+        Ok(())
+    }
+}
+
+impl fmt::Display for SyntaxTree {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+pub(crate) fn describe(path: &YamlPath) -> String {
+    if path.is_empty() {
+        "the root".to_owned()
+    } else {
+        path.to_string()
+    }
+}
+
+fn double_quoted(value: &str) -> String {
+    let mut written = String::new();
+    escape_str(&mut written, value).expect("writing to a String");
+    written
+}
+
+fn plain_or_quoted(value: &str) -> String {
+    if need_quotes(value) {
+        double_quoted(value)
+    } else {
+        value.to_owned()
+    }
+}
+
+fn line_start(text: &str, pos: usize) -> usize {
+    text[..pos].rfind('\n').map_or(0, |i| i + 1)
+}
+
+/// The start of the line after the one holding `pos`.
+fn next_line(text: &str, pos: usize) -> usize {
+    text[pos..].find('\n').map_or(text.len(), |i| pos + i + 1)
+}
+
+/// Skips whitespace, line breaks and comments from `pos`.
+fn skip_trivia(text: &str, mut pos: usize) -> usize {
+    while let Some(c) = text[pos..].chars().next() {
+        match c {
+            ' ' | '\t' | '\r' | '\n' => pos += 1,
+            '#' => pos = next_line(text, pos),
+            _ => break,
+        }
+    }
+    pos
+}
+
+/// Builds the nodes of a `SyntaxTree`, finding where each scalar ends in the
+/// text.
+struct Builder<'s> {
+    text: &'s str,
+    /// The byte offset of each character, then the length of the text.
+    offsets: Vec<usize>,
+    documents: usize,
+    stack: Vec<Open>,
+    root: Option<Node>,
+}
+
+/// A collection being built.
+struct Open {
+    node: Node,
+    /// The key waiting for its value, in a mapping.
+    key: Option<Node>,
+}
+
+impl<'s> Builder<'s> {
+    fn open(&mut self, kind: NodeKind, mark: Marker) {
+        let start = self.offsets[mark.index()];
+        self.stack.push(Open {
+            node: Node {
+                kind,
+                span: start..start,
+            },
+            key: None,
+        });
+    }
+
+    fn close(&mut self, ev: &Event, mark: Marker) -> Result<(), ScanError> {
+        let end = match self.stack.last() {
+            None => return Err(unexpected("a node", ev, mark)),
+            Some(Open { key: Some(_), .. }) => {
+                return Err(unexpected("the value of a mapping entry", ev, mark))
+            }
+            Some(Open { node, .. }) => match node.kind {
+                NodeKind::Sequence(_) => Event::SequenceEnd,
+                _ => Event::MappingEnd,
+            },
+        };
+        if *ev != end {
+            return Err(unexpected(&format!("{:?}", end), ev, mark));
+        }
+        match self.stack.pop() {
+            Some(open) => self.insert(open.node, mark),
+            // the stack was just found not to be empty
+            None => unreachable!(),
+        }
+    }
+
+    fn insert(&mut self, node: Node, mark: Marker) -> Result<(), ScanError> {
+        let top = match self.stack.last_mut() {
+            Some(top) => top,
+            None => {
+                self.root = Some(node);
+                return Ok(());
+            }
+        };
+        top.node.span.end = node.span.end;
+        match top.node.kind {
+            NodeKind::Sequence(ref mut items) => items.push(node),
+            NodeKind::Mapping(ref mut entries) => match top.key.take() {
+                None => {
+                    if node.style().is_none() {
+                        return Err(ScanError::limit(
+                            mark,
+                            "complex keys are not supported in a syntax tree",
+                        ));
+                    }
+                    // the start of a mapping is marked past its first key
+                    if entries.is_empty() {
+                        top.node.span.start = node.span.start;
+                    }
+                    top.key = Some(node);
+                }
+                Some(key) => entries.push((key, node)),
+            },
+            // only collections are opened
+            NodeKind::Scalar(..) => unreachable!(),
+        }
+        Ok(())
+    }
+
+    /// Where the next node of the innermost collection may start: past the
+    /// `:` of its key or the `-` of its item.
+    fn anchor(&self) -> Option<usize> {
+        let top = self.stack.last()?;
+        let indicator = match (&top.node.kind, &top.key) {
+            (NodeKind::Mapping(_), Some(key)) => skip_trivia(self.text, key.span.end),
+            (NodeKind::Sequence(items), _) => match items.last() {
+                Some(last) => skip_trivia(self.text, last.span.end),
+                None => top.node.span.start,
+            },
+            _ => return None,
+        };
+        Some(indicator + 1)
+    }
+
+    fn scalar_span(&self, value: &str, style: TScalarStyle, mark: Marker) -> Range<usize> {
+        let text = self.text;
+        let start = self.offsets[mark.index()];
+        match style {
+            // nothing is written, and the mark is at whatever follows
+            TScalarStyle::Plain if value.is_empty() => {
+                let at = self.anchor().unwrap_or(start);
+                at..at
+            }
+            TScalarStyle::Plain | TScalarStyle::Any => start..plain_end(text, start, value),
+            TScalarStyle::SingleQuoted => start..quoted_end(text, start, '\''),
+            TScalarStyle::DoubleQuoted => start..quoted_end(text, start, '"'),
+            TScalarStyle::Literal | TScalarStyle::Foled => {
+                // the mark is at the content, after the header
+                let header = match self.anchor() {
+                    Some(at) => skip_trivia(text, at),
+                    None => root_header(text, start).unwrap_or(start),
+                };
+                let header_end = header
+                    + text[header + 1..]
+                        .find(|c: char| !c.is_ascii_digit() && c != '+' && c != '-')
+                        .map_or(text.len() - header, |i| i + 1);
+                if start < next_line(text, header) {
+                    return header..header_end;
+                }
+                header..block_end(text, start, mark.col())
+            }
+        }
+    }
+}
+
+/// Error for an event `ev` found where `expected` should have been, which a
+/// parser never sends.
+fn unexpected(expected: &str, ev: &Event, mark: Marker) -> ScanError {
+    ScanError::parser(mark, &format!("expected {}, found {:?}", expected, ev))
+}
+
+/// The end of a plain scalar of `value` starting at `start`, where runs of
+/// whitespace and line breaks fold into spaces or line breaks.
+fn plain_end(text: &str, start: usize, value: &str) -> usize {
+    let mut source = text[start..].char_indices().peekable();
+    let mut value = value.chars().peekable();
+    let mut end = start;
+    while let (Some(&(i, c)), Some(&v)) = (source.peek(), value.peek()) {
+        if c.is_whitespace() && v.is_whitespace() {
+            while source.next_if(|&(_, c)| c.is_whitespace()).is_some() {}
+            while value.next_if(|v| v.is_whitespace()).is_some() {}
+        } else if c == v {
+            end = start + i + c.len_utf8();
+            source.next();
+            value.next();
+        } else {
+            break;
+        }
+    }
+    end
+}
+
+/// The end of a scalar quoted with `quote`, starting at `start`.
+fn quoted_end(text: &str, start: usize, quote: char) -> usize {
+    let mut chars = text[start + 1..].char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        if quote == '"' && c == '\\' {
+            chars.next();
+        } else if c == quote {
+            // `''` is a quote in a single quoted scalar
+            if quote == '\'' && chars.next_if(|&(_, c)| c == '\'').is_some() {
+                continue;
+            }
+            return start + 1 + i + 1;
+        }
+    }
+    text.len()
+}
+
+/// The end of the last line of a block scalar whose content starts at
+/// `start`, indented by `indent`.
+fn block_end(text: &str, start: usize, indent: usize) -> usize {
+    let mut end = start;
+    let mut pos = line_start(text, start);
+    while pos < text.len() {
+        let next = next_line(text, pos);
+        let line = text[pos..next].trim_end_matches(['\n', '\r']);
+        if !line.trim().is_empty() {
+            if line.len() - line.trim_start_matches(' ').len() < indent {
+                break;
+            }
+            end = pos + line.len();
+        }
+        pos = next;
+    }
+    end
+}
+
+/// The header of a block scalar at the root, on the last line with text
+/// before its content at `start`.
+fn root_header(text: &str, start: usize) -> Option<usize> {
+    let mut pos = line_start(text, start);
+    while pos > 0 {
+        let line = line_start(text, pos - 1);
+        let content = &text[line..pos - 1];
+        if !content.trim().is_empty() {
+            return content
+                .char_indices()
+                .find(|&(i, c)| matches!(c, '|' | '>') && (i == 0 || content[..i].ends_with(' ')))
+                .map(|(i, _)| line + i);
+        }
+        pos = line;
+    }
+    None
+}
+
+impl<'s> MarkedEventReceiver for Builder<'s> {
+    fn on_event(&mut self, ev: Event, mark: Marker) -> Result<(), ScanError> {
+        match ev {
+            Event::DocumentStart => {
+                self.documents += 1;
+                if self.documents > 1 {
+                    return Err(ScanError::parser(
+                        mark,
+                        "expected a single document, found another",
+                    ));
+                }
+                Ok(())
+            }
+            Event::SequenceStart(_) => {
+                self.open(NodeKind::Sequence(Vec::new()), mark);
+                Ok(())
+            }
+            Event::MappingStart(_) => {
+                self.open(NodeKind::Mapping(Vec::new()), mark);
+                Ok(())
+            }
+            Event::SequenceEnd | Event::MappingEnd => self.close(&ev, mark),
+            Event::Scalar(v, style, _) => {
+                let span = self.scalar_span(&v, style, mark);
+                let node = Node {
+                    kind: NodeKind::Scalar(v, style),
+                    span,
+                };
+                self.insert(node, mark)
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use strict_yaml::StrictYamlLoader;
+
+    /// The path written `s`, such as `a[1].b`.
+    fn path(s: &str) -> YamlPath {
+        let mut path = YamlPath::new();
+        for part in s.split('.').filter(|p| !p.is_empty()) {
+            let mut pieces = part.split('[');
+            let key = pieces.next().unwrap();
+            if !key.is_empty() {
+                path.push(PathSegment::Key(key.to_owned()));
+            }
+            for index in pieces {
+                path.push(PathSegment::Index(
+                    index.trim_end_matches(']').parse().unwrap(),
+                ));
+            }
+        }
+        path
+    }
+
+    const SOURCE: &str = "# settings
+name:   demo   # the name
+quoted: 'it''s'
+escaped: \"a\\\"b\"
+empty:
+multi: first
+  second
+script: | # run
+  make
+  make test
+
+ports:
+  - 80
+  -   443 # tls
+  - - nested
+    - 'list'
+servers:
+  - host: a
+    port: 1
+  - host: b
+";
+
+    #[test]
+    fn test_spans() {
+        let tree = SyntaxTree::parse(SOURCE).unwrap();
+        assert_eq!(tree.to_string(), SOURCE);
+        let text = |p: &str| &SOURCE[tree.get(&path(p)).unwrap().span()];
+        assert_eq!(text("name"), "demo");
+        assert_eq!(text("quoted"), "'it''s'");
+        assert_eq!(text("escaped"), "\"a\\\"b\"");
+        assert_eq!(text("empty"), "");
+        assert_eq!(text("multi"), "first\n  second");
+        assert_eq!(text("script"), "| # run\n  make\n  make test");
+        assert_eq!(text("ports[1]"), "443");
+        assert_eq!(text("ports[2]"), "- nested\n    - 'list'");
+        assert_eq!(text("servers[0]"), "host: a\n    port: 1");
+        assert_eq!(
+            tree.root().to_strict_yaml(),
+            StrictYamlLoader::load_from_str(SOURCE).unwrap()[0]
+        );
+    }
+
+    #[test]
+    fn test_set() {
+        let mut tree = SyntaxTree::parse(SOURCE).unwrap();
+        tree.set(&path("name"), "other").unwrap();
+        tree.set(&path("quoted"), "they're").unwrap();
+        tree.set(&path("escaped"), "x").unwrap();
+        tree.set(&path("empty"), "true").unwrap();
+        tree.set(&path("script"), "make all\n").unwrap();
+        tree.set(&path("ports[1]"), "8443").unwrap();
+        tree.set(&path("ports[2][1]"), "two\nlines").unwrap();
+        let expected = SOURCE
+            .replace("demo", "other")
+            .replace("'it''s'", "'they''re'")
+            .replace("\"a\\\"b\"", "\"x\"")
+            .replace("empty:", "empty: \"true\"")
+            .replace("  make\n  make test\n", "  make all\n")
+            .replace("443", "\"8443\"")
+            .replace("'list'", "\"two\\nlines\"");
+        assert_eq!(tree.as_str(), expected);
+        assert_eq!(tree.get(&path("name")).unwrap().as_str(), Some("other"));
+        assert_eq!(
+            tree.get(&path("script")).unwrap().as_str(),
+            Some("make all\n")
+        );
+
+        let err = tree.set(&path("ports"), "x").unwrap_err();
+        assert_eq!(err.to_string(), "ports is not a scalar");
+        let err = tree.set(&path("missing"), "x").unwrap_err();
+        assert_eq!(err.to_string(), "no node at missing");
+    }
+
+    #[test]
+    fn test_insert_push_remove() {
+        let mut tree = SyntaxTree::parse(SOURCE).unwrap();
+        tree.insert(&path("servers[1]"), "port", "2").unwrap();
+        tree.push(&path("ports[2]"), "last").unwrap();
+        tree.remove(&path("ports[1]")).unwrap();
+        tree.remove(&path("multi")).unwrap();
+        tree.remove(&path("servers[0].host")).unwrap();
+        let expected = SOURCE
+            .replace("  -   443 # tls\n", "")
+            .replace("multi: first\n  second\n", "")
+            .replace("    - 'list'\n", "    - 'list'\n    - last\n")
+            .replace("  - host: a\n    port: 1", "  - port: 1")
+            .replace("  - host: b\n", "  - host: b\n    port: \"2\"\n");
+        assert_eq!(tree.as_str(), expected);
+
+        let mut tree = SyntaxTree::parse("a: b").unwrap();
+        tree.insert(&YamlPath::new(), "c", "d").unwrap();
+        assert_eq!(tree.as_str(), "a: b\nc: d");
+        let err = tree.insert(&YamlPath::new(), "a", "x").unwrap_err();
+        assert_eq!(err.to_string(), "a already exists");
+        tree.remove(&path("a")).unwrap();
+        let err = tree.remove(&path("c")).unwrap_err();
+        assert_eq!(err.to_string(), "cannot remove the only entry of the root");
+        assert_eq!(tree.as_str(), "c: d");
+
+        let mut tree =
+            SyntaxTree::parse("a: 1\r\nb: 2\r\nl:\r\n  - x\r\ns: | # run\r\n  x\r\n").unwrap();
+        tree.insert(&YamlPath::new(), "c", "3").unwrap();
+        tree.push(&path("l"), "y").unwrap();
+        tree.set(&path("s"), "make\nmake test\n").unwrap();
+        assert_eq!(
+            tree.as_str(),
+            "a: 1\r\nb: 2\r\nl:\r\n  - x\r\n  - y\r\ns: | # run\r\n  make\r\n  make test\r\nc: \"3\"\r\n"
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let err = SyntaxTree::parse("a: b\n---\nc: d").unwrap_err();
+        assert_eq!(err.info(), "expected a single document, found another");
+        assert_eq!(err.code(), code::PARSE);
+        let err = SyntaxTree::parse("? - a\n: b").unwrap_err();
+        assert_eq!(err.code(), code::LIMIT);
+        let tree = SyntaxTree::parse("").unwrap();
+        assert_eq!(tree.root().as_str(), Some(""));
+        let tree = SyntaxTree::parse("--- |\n  text\n").unwrap();
+        assert_eq!(&tree.as_str()[tree.root().span()], "|\n  text");
+
+        let build = |events: Vec<Event>| {
+            let mut builder = Builder {
+                text: "a",
+                offsets: vec![0, 1],
+                documents: 0,
+                stack: Vec::new(),
+                root: None,
+            };
+            let mark = Marker::new(0, 1, 0);
+            for ev in events {
+                if let Err(e) = builder.on_event(ev, mark) {
+                    return e.info().to_owned();
+                }
+            }
+            String::new()
+        };
+        assert_eq!(
+            build(vec![Event::SequenceEnd]),
+            "expected a node, found SequenceEnd"
+        );
+        assert_eq!(
+            build(vec![Event::MappingStart(0), Event::SequenceEnd]),
+            "expected MappingEnd, found SequenceEnd"
+        );
+        assert_eq!(
+            build(vec![
+                Event::MappingStart(0),
+                Event::Scalar("a".into(), TScalarStyle::Plain, 0),
+                Event::MappingEnd,
+            ]),
+            "expected the value of a mapping entry, found MappingEnd"
+        );
+    }
+}
@@ -0,0 +1,594 @@
+//! Deserializing Rust values from strict YAML with serde.
+//!
+//! Every scalar of a strict YAML document is a string; numbers and booleans
+//! are parsed from it when the target type asks for one. Booleans are
+//! `true`/`false`, `yes`/`no` or `on`/`off`, in any case.
+//!
+//! ```
+//! # extern crate serde;
+//! use std::collections::BTreeMap;
+//! use strict_yaml_rust::de::from_str;
+//!
+//! let ports: BTreeMap<String, u16> = from_str("http: 80\nhttps: 443").unwrap();
+//! assert_eq!(ports["https"], 443);
+//!
+//! let err = from_str::<BTreeMap<String, u16>>("http: eighty").unwrap_err();
+//! assert_eq!(err.to_string(), "invalid u16 \"eighty\" at http");
+//! ```
+
+use error::code;
+use parser::{Event, MarkedEventReceiver};
+use path::{PathSegment, YamlPath};
+use scanner::{Marker, ScanError, TScalarStyle};
+use serde::de::{self, DeserializeOwned, IntoDeserializer, Visitor};
+use std::error;
+use std::fmt;
+use std::str::FromStr;
+use strict_yaml::{StrictYaml, StrictYamlLoader};
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Error {
+    msg: String,
+    /// Where the error is, unknown for errors of the document as a whole.
+    path: Option<YamlPath>,
+}
+
+impl Error {
+    fn new(msg: String, path: &YamlPath) -> Error {
+        Error {
+            msg,
+            path: Some(path.clone()),
+        }
+    }
+
+    /// Locates an error raised without a path, such as a missing field.
+    fn at(mut self, path: &YamlPath) -> Error {
+        if self.path.is_none() {
+            self.path = Some(path.clone());
+        }
+        self
+    }
+
+    pub fn path(&self) -> Option<&YamlPath> {
+        self.path.as_ref()
+    }
+
+    pub fn code(&self) -> &'static str {
+        code::DESERIALIZE
+    }
+}
+
+impl error::Error for Error {}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.path {
+            Some(ref path) if !path.is_empty() => write!(f, "{} at {}", self.msg, path),
+            _ => f.write_str(&self.msg),
+        }
+    }
+}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Error {
+        Error {
+            msg: msg.to_string(),
+            path: None,
+        }
+    }
+}
+
+impl From<ScanError> for Error {
+    fn from(e: ScanError) -> Error {
+        Error {
+            msg: e.to_string(),
+            path: None,
+        }
+    }
+}
+
+/// Deserializes a value from a source holding a single strict YAML document.
+pub fn from_str<T: DeserializeOwned>(source: &str) -> Result<T, Error> {
+    let docs = StrictYamlLoader::load_from_str_with_filter(source, empty_collections)?;
+    match docs.len() {
+        1 => from_strict_yaml(&docs[0]),
+        n => Err(de::Error::custom(format_args!(
+            "expected a single document, found {}",
+            n
+        ))),
+    }
+}
+
+/// Reads the plain `[]` and `{}` the serializer writes for empty collections
+/// as collections; quoted, they stay strings.
+fn empty_collections(
+    ev: Event,
+    mark: Marker,
+    out: &mut dyn MarkedEventReceiver,
+) -> Result<(), ScanError> {
+    match ev {
+        Event::Scalar(ref v, TScalarStyle::Plain, aid) if v == "[]" => {
+            out.on_event(Event::SequenceStart(aid), mark)?;
+            out.on_event(Event::SequenceEnd, mark)
+        }
+        Event::Scalar(ref v, TScalarStyle::Plain, aid) if v == "{}" => {
+            out.on_event(Event::MappingStart(aid), mark)?;
+            out.on_event(Event::MappingEnd, mark)
+        }
+        ev => out.on_event(ev, mark),
+    }
+}
+
+/// Deserializes a value from a `StrictYaml` tree. Unlike `from_str`, the
+/// tree cannot tell a plain `[]` or `{}` from a quoted one: both are strings,
+/// so empty collections have to be loaded as such, for instance with
+/// `LoadOptions::flow_collections`.
+pub fn from_strict_yaml<'de, T: de::Deserialize<'de>>(doc: &'de StrictYaml) -> Result<T, Error> {
+    T::deserialize(Deserializer::new(doc))
+}
+
+/// Reads a value out of a `StrictYaml` node.
+pub struct Deserializer<'de> {
+    node: &'de StrictYaml,
+    path: YamlPath,
+}
+
+impl<'de> Deserializer<'de> {
+    pub fn new(node: &'de StrictYaml) -> Deserializer<'de> {
+        Deserializer {
+            node,
+            path: YamlPath::new(),
+        }
+    }
+
+    fn child(&self, node: &'de StrictYaml, segment: PathSegment) -> Deserializer<'de> {
+        Deserializer {
+            node,
+            path: self.path.child(segment),
+        }
+    }
+
+    fn error(&self, msg: String) -> Error {
+        Error::new(msg, &self.path)
+    }
+
+    fn expected(&self, what: &str) -> Error {
+        let found = match *self.node {
+            StrictYaml::String(ref s) => format!("{:?}", s),
+            StrictYaml::Array(_) => "a sequence".to_owned(),
+            StrictYaml::Hash(_) => "a mapping".to_owned(),
+            StrictYaml::BadValue => "nothing".to_owned(),
+        };
+        self.error(format!("expected {}, found {}", what, found))
+    }
+
+    fn scalar(&self) -> Result<&'de str, Error> {
+        match *self.node {
+            StrictYaml::String(ref s) => Ok(s),
+            _ => Err(self.expected("a scalar")),
+        }
+    }
+
+    fn parse<T: FromStr>(&self, what: &str) -> Result<T, Error> {
+        let s = self.scalar()?;
+        s.parse()
+            .map_err(|_| self.error(format!("invalid {} {:?}", what, s)))
+    }
+
+    fn parse_bool(&self) -> Result<bool, Error> {
+        let s = self.scalar()?;
+        match s.to_lowercase().as_str() {
+            "true" | "yes" | "on" => Ok(true),
+            "false" | "no" | "off" => Ok(false),
+            _ => Err(self.error(format!("invalid bool {:?}", s))),
+        }
+    }
+
+    fn items(&self) -> Result<&'de [StrictYaml], Error> {
+        match *self.node {
+            StrictYaml::Array(ref v) => Ok(v),
+            _ => Err(self.expected("a sequence")),
+        }
+    }
+
+    fn entries(&self) -> Result<Vec<(&'de StrictYaml, &'de StrictYaml)>, Error> {
+        match *self.node {
+            StrictYaml::Hash(ref h) => Ok(h.iter().collect()),
+            _ => Err(self.expected("a mapping")),
+        }
+    }
+}
+
+macro_rules! deserialize_parsed {
+    ($($method:ident => $visit:ident, $ty:ident;)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                visitor.$visit(self.parse::<$ty>(stringify!($ty))?)
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for Deserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match *self.node {
+            StrictYaml::String(ref s) => visitor.visit_borrowed_str(s),
+            StrictYaml::Array(_) => self.deserialize_seq(visitor),
+            StrictYaml::Hash(_) => self.deserialize_map(visitor),
+            StrictYaml::BadValue => visitor.visit_none(),
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_bool(self.parse_bool()?)
+    }
+
+    deserialize_parsed! {
+        deserialize_i8 => visit_i8, i8;
+        deserialize_i16 => visit_i16, i16;
+        deserialize_i32 => visit_i32, i32;
+        deserialize_i64 => visit_i64, i64;
+        deserialize_i128 => visit_i128, i128;
+        deserialize_u8 => visit_u8, u8;
+        deserialize_u16 => visit_u16, u16;
+        deserialize_u32 => visit_u32, u32;
+        deserialize_u64 => visit_u64, u64;
+        deserialize_u128 => visit_u128, u128;
+        deserialize_f32 => visit_f32, f32;
+        deserialize_f64 => visit_f64, f64;
+        deserialize_char => visit_char, char;
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_borrowed_str(self.scalar()?)
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match *self.node {
+            StrictYaml::String(ref s) => visitor.visit_borrowed_bytes(s.as_bytes()),
+            _ => self.deserialize_seq(visitor),
+        }
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match *self.node {
+            StrictYaml::BadValue => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match *self.node {
+            StrictYaml::String(ref s) if s.is_empty() => visitor.visit_unit(),
+            _ => Err(self.expected("an empty scalar")),
+        }
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let items = self.items()?;
+        let mut seq = SeqAccess {
+            de: &self,
+            items: items.iter().enumerate(),
+        };
+        let value = visitor.visit_seq(&mut seq).map_err(|e| e.at(&self.path))?;
+        match seq.items.len() {
+            0 => Ok(value),
+            n => Err(self.error(format!("{} unexpected items", n))),
+        }
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let entries = self.entries()?;
+        let mut map = MapAccess {
+            de: &self,
+            entries: entries.into_iter(),
+            value: None,
+        };
+        visitor.visit_map(&mut map).map_err(|e| e.at(&self.path))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        match *self.node {
+            StrictYaml::String(ref s) => visitor.visit_enum(s.as_str().into_deserializer()),
+            StrictYaml::Hash(ref h) if h.len() == 1 => {
+                let (variant, value) = h.iter().next().unwrap();
+                let variant = self
+                    .child(variant, PathSegment::Key(String::new()))
+                    .scalar()?;
+                let value = self.child(value, PathSegment::Key(variant.to_owned()));
+                visitor
+                    .visit_enum(EnumAccess { variant, value })
+                    .map_err(|e| e.at(&self.path))
+            }
+            _ => Err(self.expected("a variant name or a single pair mapping")),
+        }
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+}
+
+struct SeqAccess<'a, 'de: 'a> {
+    de: &'a Deserializer<'de>,
+    items: ::std::iter::Enumerate<::std::slice::Iter<'de, StrictYaml>>,
+}
+
+impl<'a, 'de> de::SeqAccess<'de> for SeqAccess<'a, 'de> {
+    type Error = Error;
+
+    fn next_element_seed<T: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Error> {
+        match self.items.next() {
+            Some((i, item)) => seed
+                .deserialize(self.de.child(item, PathSegment::Index(i)))
+                .map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.items.len())
+    }
+}
+
+struct MapAccess<'a, 'de: 'a> {
+    de: &'a Deserializer<'de>,
+    entries: ::std::vec::IntoIter<(&'de StrictYaml, &'de StrictYaml)>,
+    /// The value of the last key, with its path.
+    value: Option<Deserializer<'de>>,
+}
+
+impl<'a, 'de> de::MapAccess<'de> for MapAccess<'a, 'de> {
+    type Error = Error;
+
+    fn next_key_seed<K: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        match self.entries.next() {
+            Some((k, v)) => {
+                let segment = PathSegment::Key(k.as_str().unwrap_or_default().to_owned());
+                let value = self.de.child(v, segment);
+                let key = seed.deserialize(Deserializer {
+                    node: k,
+                    path: value.path.clone(),
+                })?;
+                self.value = Some(value);
+                Ok(Some(key))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: de::DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        let value = self
+            .value
+            .take()
+            .expect("next_value_seed called before next_key_seed");
+        let path = value.path.clone();
+        seed.deserialize(value).map_err(|e| e.at(&path))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.entries.len())
+    }
+}
+
+struct EnumAccess<'de> {
+    variant: &'de str,
+    value: Deserializer<'de>,
+}
+
+impl<'de> de::EnumAccess<'de> for EnumAccess<'de> {
+    type Error = Error;
+    type Variant = Deserializer<'de>;
+
+    fn variant_seed<V: de::DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Deserializer<'de>), Error> {
+        let variant: Result<_, Error> = seed.deserialize(self.variant.into_deserializer());
+        Ok((variant?, self.value))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for Deserializer<'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        de::Deserialize::deserialize(self)
+    }
+
+    fn newtype_variant_seed<T: de::DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, Error> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_seq(self, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_map(self, visitor)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ser::to_string;
+    use serde::{Deserialize, Serialize};
+    use std::collections::BTreeMap;
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    enum Protocol {
+        Tcp,
+        Udp,
+        Unix(String),
+        Range(u16, u16),
+    }
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Listener {
+        port: u16,
+        protocol: Protocol,
+        #[serde(default)]
+        tls: bool,
+        cert: Option<String>,
+    }
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Config {
+        name: String,
+        ratio: f64,
+        listeners: Vec<Listener>,
+        tags: BTreeMap<String, String>,
+        empty: Vec<String>,
+    }
+
+    #[test]
+    fn test_from_str() {
+        let source = "
+name: demo
+ratio: 0.5
+listeners:
+  - port: 80
+    protocol: Tcp
+  - port: 443
+    protocol: Tcp
+    tls: Yes
+    cert: /etc/cert.pem
+  - port: 53
+    protocol:
+      Range:
+        - 1000
+        - 2000
+  - port: 0
+    protocol:
+      Unix: /tmp/sock
+tags:
+  env: prod
+empty: []
+";
+        let config: Config = from_str(source).unwrap();
+        assert_eq!(config.name, "demo");
+        assert_eq!(config.listeners[1].cert.as_deref(), Some("/etc/cert.pem"));
+        assert!(config.listeners[1].tls);
+        assert!(!config.listeners[0].tls);
+        assert_eq!(config.listeners[2].protocol, Protocol::Range(1000, 2000));
+        assert_eq!(
+            config.listeners[3].protocol,
+            Protocol::Unix("/tmp/sock".to_owned())
+        );
+        assert!(config.empty.is_empty());
+
+        let back: Config = from_str(&to_string(&config).unwrap()).unwrap();
+        assert_eq!(back, config);
+    }
+
+    #[test]
+    fn test_errors() {
+        let err = |source: &str| from_str::<Config>(source).unwrap_err().to_string();
+        let base = "name: x\nratio: 1\ntags: {}\nempty: []\nlisteners:\n  - protocol: Udp\n";
+        assert_eq!(
+            err(&format!("{}    port: high", base)),
+            "invalid u16 \"high\" at listeners[0].port"
+        );
+        assert_eq!(err(base), "missing field `port` at listeners[0]");
+        assert_eq!(
+            err(&format!("{}    port: 1\n    tls: maybe", base)),
+            "invalid bool \"maybe\" at listeners[0].tls"
+        );
+        assert_eq!(
+            err(&format!("{}    port: 1", base.replace("Udp", "Sctp"))),
+            "unknown variant `Sctp`, expected one of `Tcp`, `Udp`, `Unix`, `Range` at listeners[0].protocol"
+        );
+        assert_eq!(
+            err("name:\n  - x"),
+            "expected a scalar, found a sequence at name"
+        );
+        assert_eq!(
+            err("a: 1\n---\nb: 2"),
+            "expected a single document, found 2"
+        );
+        assert_eq!(
+            err(&format!("{}    port: 1", base.replace("[]", "'[]'"))),
+            "expected a sequence, found \"[]\" at empty"
+        );
+        assert_eq!(
+            from_str::<BTreeMap<String, String>>("a: '[]'\nb: \"{}\"").unwrap()["b"],
+            "{}"
+        );
+        assert_eq!(
+            err("a: b: c"),
+            "mapping values are not allowed in this context at line 1 column 5"
+        );
+    }
+}
@@ -0,0 +1,184 @@
+//! `miette::Diagnostic` for the errors of this crate, with the `miette`
+//! feature.
+//!
+//! Errors carry their code, and a label at their position when they have one.
+//! Errors do not keep the source they were found in: attach it with
+//! `miette::Report::with_source_code` to see the labels. Labels are at
+//! `Marker::index`, a count of characters, which is the byte offset miette
+//! expects as long as the source is ASCII.
+
+use convert::ConversionError;
+use cst::EditError;
+use emitter::EmitError;
+use error::LoadError;
+use miette::{Diagnostic, LabeledSpan, SourceSpan};
+use path::PatternError;
+use query::QueryError;
+use scanner::{Marker, ScanError};
+use schema::{self, SchemaError};
+use std::fmt::Display;
+use strict_yaml::FileError;
+use transcode::TranscodeError;
+
+/// An empty span at `marker`.
+fn at(marker: Marker, label: Option<String>) -> LabeledSpan {
+    LabeledSpan::new_primary_with_span(label, SourceSpan::new(marker.index().into(), 0))
+}
+
+fn code<'a>(code: &'static str) -> Option<Box<dyn Display + 'a>> {
+    Some(Box::new(code))
+}
+
+type Labels<'a> = Option<Box<dyn Iterator<Item = LabeledSpan> + 'a>>;
+
+impl Diagnostic for ScanError {
+    fn code<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        code(self.code())
+    }
+
+    fn labels(&self) -> Labels<'_> {
+        Some(Box::new(Some(at(*self.marker(), None)).into_iter()))
+    }
+}
+
+impl Diagnostic for LoadError {
+    fn code<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        code(self.code())
+    }
+
+    fn labels(&self) -> Labels<'_> {
+        Some(Box::new(Some(at(self.marker(), None)).into_iter()))
+    }
+}
+
+impl Diagnostic for FileError {
+    fn code<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        code(self.code())
+    }
+
+    fn labels(&self) -> Labels<'_> {
+        match *self {
+            FileError::Scan(_, ref err) => err.labels(),
+            FileError::Io(..) => None,
+        }
+    }
+}
+
+impl Diagnostic for SchemaError {
+    fn code<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        code(self.code())
+    }
+
+    fn labels(&self) -> Labels<'_> {
+        let label = self.marker().map(|m| at(m, Some(self.kind().to_string())));
+        Some(Box::new(label.into_iter()))
+    }
+}
+
+impl Diagnostic for ConversionError {
+    fn code<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        code(self.code())
+    }
+
+    fn labels(&self) -> Labels<'_> {
+        Some(Box::new(self.marker().map(|m| at(m, None)).into_iter()))
+    }
+}
+
+impl Diagnostic for schema::SchemaLoadError {
+    fn code<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        code(self.code())
+    }
+
+    fn labels(&self) -> Labels<'_> {
+        match *self {
+            schema::SchemaLoadError::Load(ref err) => err.labels(),
+            schema::SchemaLoadError::Schema(_) => None,
+        }
+    }
+
+    /// Each error of the schema, with its label.
+    fn related<'a>(&'a self) -> Option<Box<dyn Iterator<Item = &'a dyn Diagnostic> + 'a>> {
+        match *self {
+            schema::SchemaLoadError::Load(_) => None,
+            schema::SchemaLoadError::Schema(ref errors) => {
+                Some(Box::new(errors.iter().map(|e| e as &dyn Diagnostic)))
+            }
+        }
+    }
+}
+
+impl Diagnostic for TranscodeError {
+    fn code<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        code(self.code())
+    }
+
+    fn labels(&self) -> Labels<'_> {
+        match *self {
+            TranscodeError::ScanError(ref err) => err.labels(),
+            TranscodeError::EmitError(_) => None,
+        }
+    }
+}
+
+/// Implements `Diagnostic` for errors without a position.
+macro_rules! unlabelled {
+    ($($t:ty),*) => {
+        $(
+            impl Diagnostic for $t {
+                fn code<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+                    code(self.code())
+                }
+            }
+        )*
+    };
+}
+
+unlabelled!(EmitError, EditError, PatternError, QueryError);
+#[cfg(feature = "serde")]
+unlabelled!(::de::Error, ::ser::Error);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use miette::{NarratableReportHandler, Report};
+    use schema::{load_with_schema, Int, Map};
+    use strict_yaml::StrictYamlLoader;
+
+    fn render(diagnostic: &dyn Diagnostic) -> String {
+        let mut out = String::new();
+        NarratableReportHandler::new()
+            .render_report(&mut out, diagnostic)
+            .unwrap();
+        out
+    }
+
+    #[test]
+    fn test_labels() {
+        let source = "a: 1\nb: 2\na: 3\n";
+        let err = StrictYamlLoader::load_from_str(source).unwrap_err();
+        let err = LoadError::from(err);
+        assert_eq!(
+            Diagnostic::code(&err).unwrap().to_string(),
+            "E001_DUPLICATE_KEY"
+        );
+        let labels: Vec<_> = err.labels().unwrap().collect();
+        assert_eq!(labels[0].offset(), 13);
+
+        let report = Report::new(err).with_source_code(source);
+        let out = render(report.as_ref());
+        assert!(out.contains("code: E001_DUPLICATE_KEY"), "{}", out);
+        assert!(out.contains("line 3: a: 3"), "{}", out);
+        assert!(out.contains("label at line 3, column 4"), "{}", out);
+
+        let schema = Map::new().required("a", Int);
+        let err = load_with_schema("a: x", &schema).unwrap_err();
+        assert_eq!(Diagnostic::code(&err).unwrap().to_string(), "E100_SCHEMA");
+        let related: Vec<_> = err.related().unwrap().collect();
+        assert_eq!(related.len(), 1);
+        assert_eq!(related[0].code().unwrap().to_string(), "E101_EXPECTED");
+        let label = related[0].labels().unwrap().next().unwrap();
+        assert_eq!(label.offset(), 3);
+        assert_eq!(label.label(), Some("expected an integer, found \"x\""));
+    }
+}
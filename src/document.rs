@@ -0,0 +1,168 @@
+//! Loaded documents together with what is known about how they were loaded.
+
+use options::{LoadOptions, Warning};
+use parser::{Event, MarkedEventReceiver};
+use scanner::{Marker, ScanError};
+use std::mem;
+use std::ops::Deref;
+use std::sync::{Arc, Mutex};
+use strict_yaml::{StrictYaml, StrictYamlLoader};
+
+/// A document of a stream, with the name of its source, the options it was
+/// loaded with and the warnings raised while loading it.
+///
+/// Dereferences to its root node.
+///
+/// ```
+/// use strict_yaml_rust::{LoadOptions, StrictYamlDocument};
+///
+/// let source = "a: b\r\nc: d\n---\ne: f";
+/// let docs = StrictYamlDocument::load_from_str(source, Some("config.yaml"), &LoadOptions::new())
+///     .unwrap();
+/// assert_eq!(docs[0]["c"].as_str(), Some("d"));
+/// assert_eq!(docs[0].source_name(), Some("config.yaml"));
+/// assert_eq!(docs[0].warnings().len(), 1);
+/// assert!(docs[1].warnings().is_empty());
+/// ```
+#[derive(Clone, Debug)]
+pub struct StrictYamlDocument {
+    root: StrictYaml,
+    source_name: Option<String>,
+    options: LoadOptions,
+    warnings: Vec<Warning>,
+}
+
+impl StrictYamlDocument {
+    pub fn new(root: StrictYaml) -> StrictYamlDocument {
+        StrictYamlDocument {
+            root,
+            source_name: None,
+            options: LoadOptions::new(),
+            warnings: Vec::new(),
+        }
+    }
+
+    /// Loads all the documents of `source` like
+    /// `StrictYamlLoader::load_from_str_with_options`, keeping the warnings
+    /// of each document with it. Warnings still go to the hook of `options`.
+    pub fn load_from_str(
+        source: &str,
+        source_name: Option<&str>,
+        options: &LoadOptions,
+    ) -> Result<Vec<StrictYamlDocument>, ScanError> {
+        let warnings = Arc::new(Mutex::new(Vec::new()));
+        let sink = warnings.clone();
+        let user = options.clone();
+        let recording = options.clone().warnings(move |w| {
+            sink.lock().unwrap().push(w.clone());
+            user.warn(w.clone());
+        });
+
+        let mut ends = DocumentEnds {
+            loader: StrictYamlLoader::with_max_depth(options.get_max_depth()),
+            ends: Vec::new(),
+        };
+        StrictYamlLoader::load_with_options(source, &recording, &mut ends)?;
+
+        let mut warnings = mem::take(&mut *warnings.lock().unwrap());
+        warnings.sort_by_key(|w| w.marker().index());
+        let mut warnings = warnings.into_iter().peekable();
+        let mut docs: Vec<StrictYamlDocument> = ends
+            .loader
+            .take_documents()
+            .into_iter()
+            .zip(ends.ends)
+            .map(|(root, end)| {
+                let mut doc = StrictYamlDocument::new(root);
+                doc.source_name = source_name.map(str::to_owned);
+                doc.options = options.clone();
+                while let Some(w) = warnings.next_if(|w| w.marker().index() <= end.index()) {
+                    doc.warnings.push(w);
+                }
+                doc
+            })
+            .collect();
+        // warnings past the end of the last document, e.g. about trailing line breaks
+        if let Some(last) = docs.last_mut() {
+            last.warnings.extend(warnings);
+        }
+        Ok(docs)
+    }
+
+    pub fn root(&self) -> &StrictYaml {
+        &self.root
+    }
+
+    pub fn into_root(self) -> StrictYaml {
+        self.root
+    }
+
+    pub fn source_name(&self) -> Option<&str> {
+        self.source_name.as_deref()
+    }
+
+    pub fn set_source_name(&mut self, name: &str) {
+        self.source_name = Some(name.to_owned());
+    }
+
+    /// The options the document was loaded with; the defaults for a document
+    /// built with `new`.
+    pub fn options(&self) -> &LoadOptions {
+        &self.options
+    }
+
+    /// The warnings raised while loading the document, in source order.
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+}
+
+impl Deref for StrictYamlDocument {
+    type Target = StrictYaml;
+
+    fn deref(&self) -> &StrictYaml {
+        &self.root
+    }
+}
+
+/// Records where each document ends while loading it.
+struct DocumentEnds {
+    loader: StrictYamlLoader,
+    ends: Vec<Marker>,
+}
+
+impl MarkedEventReceiver for DocumentEnds {
+    fn on_event(&mut self, ev: Event, mark: Marker) -> Result<(), ScanError> {
+        if ev == Event::DocumentEnd {
+            self.ends.push(mark);
+        }
+        self.loader.on_event(ev, mark)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_document_warnings() {
+        let source = "a: b\rc: d\r---\re: !!str f\r---\rg: !!int 1\r";
+        let options = LoadOptions::new().strip_tags(true);
+        let docs = StrictYamlDocument::load_from_str(source, None, &options).unwrap();
+        let warnings: Vec<Vec<String>> = docs
+            .iter()
+            .map(|d| d.warnings().iter().map(|w| w.to_string()).collect())
+            .collect();
+        assert_eq!(
+            warnings,
+            vec![
+                vec!["bare CR line break, read as LF at line 1 column 5".to_owned()],
+                vec!["explicit tag !!str stripped at line 4 column 4".to_owned()],
+                vec!["explicit tag !!int stripped at line 6 column 4".to_owned()],
+            ]
+        );
+        assert_eq!(docs[1]["e"].as_str(), Some("f"));
+        assert_eq!(docs[2].source_name(), None);
+        assert_eq!(docs[2].clone().into_root()["g"].as_str(), Some("1"));
+    }
+}
@@ -0,0 +1,128 @@
+//! Rendering the structure of a document as a Graphviz DOT graph.
+//!
+//! Sequences and mappings are boxes, scalars are plain labels, and each edge
+//! goes from a collection to one of its children, labeled with its key or
+//! index. Render the output with e.g. `dot -Tsvg`.
+//!
+//! ```
+//! use strict_yaml_rust::dot::write_dot;
+//! use strict_yaml_rust::StrictYamlLoader;
+//!
+//! let doc = &StrictYamlLoader::load_from_str("name: demo\nports:\n  - 80").unwrap()[0];
+//! let mut out = String::new();
+//! write_dot(doc, &mut out).unwrap();
+//! assert!(out.starts_with("digraph {\n"));
+//! assert!(out.contains("n0 -> n1 [label=\"name\"];"));
+//! ```
+
+use std::fmt;
+use strict_yaml::StrictYaml;
+
+/// Longest scalar label, in characters; longer values are cut.
+const MAX_LABEL: usize = 40;
+
+/// Writes `doc` to `writer` as a DOT graph.
+pub fn write_dot(doc: &StrictYaml, writer: &mut dyn fmt::Write) -> fmt::Result {
+    writeln!(writer, "digraph {{")?;
+    writeln!(writer, "  node [shape=plaintext];")?;
+    let mut next_id = 0;
+    write_node(doc, writer, &mut next_id)?;
+    writeln!(writer, "}}")
+}
+
+/// Writes `node` and its children, returning the id of `node`.
+fn write_node(
+    node: &StrictYaml,
+    writer: &mut dyn fmt::Write,
+    next_id: &mut usize,
+) -> Result<usize, fmt::Error> {
+    let id = *next_id;
+    *next_id += 1;
+    match *node {
+        StrictYaml::String(ref s) => {
+            writeln!(writer, "  n{} [label={}];", id, Label(s))?;
+        }
+        StrictYaml::Array(ref v) => {
+            let label = format!("sequence ({})", v.len());
+            writeln!(writer, "  n{} [shape=box, label={}];", id, Label(&label))?;
+            for (i, child) in v.iter().enumerate() {
+                let child = write_node(child, writer, next_id)?;
+                let label = format!("[{}]", i);
+                writeln!(writer, "  n{} -> n{} [label={}];", id, child, Label(&label))?;
+            }
+        }
+        StrictYaml::Hash(ref h) => {
+            let label = format!("mapping ({})", h.len());
+            writeln!(writer, "  n{} [shape=box, label={}];", id, Label(&label))?;
+            for (k, v) in h {
+                let child = write_node(v, writer, next_id)?;
+                let key = match *k {
+                    StrictYaml::String(ref s) => s.clone(),
+                    ref k => format!("{:?}", k),
+                };
+                writeln!(writer, "  n{} -> n{} [label={}];", id, child, Label(&key))?;
+            }
+        }
+        StrictYaml::BadValue => {
+            writeln!(writer, "  n{} [label=\"(no value)\", fontcolor=gray];", id)?;
+        }
+    }
+    Ok(id)
+}
+
+/// A quoted DOT string, cut to `MAX_LABEL` characters.
+struct Label<'a>(&'a str);
+
+impl<'a> fmt::Display for Label<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("\"")?;
+        for (i, c) in self.0.chars().enumerate() {
+            if i == MAX_LABEL {
+                f.write_str("...")?;
+                break;
+            }
+            match c {
+                '"' => f.write_str("\\\"")?,
+                '\\' => f.write_str("\\\\")?,
+                '\n' => f.write_str("\\n")?,
+                '\r' => f.write_str("\\r")?,
+                c => write!(f, "{}", c)?,
+            }
+        }
+        f.write_str("\"")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use strict_yaml::StrictYamlLoader;
+
+    #[test]
+    fn test_write_dot() {
+        let source = "a: \"say \\\"hi\\\"\\\\\\n\"\nb:\n  - c\n  - d: e\nf: 0123456789012345678901234567890123456789xyz";
+        let doc = &StrictYamlLoader::load_from_str(source).unwrap()[0];
+        let mut out = String::new();
+        write_dot(doc, &mut out).unwrap();
+        assert_eq!(
+            out,
+            "digraph {
+  node [shape=plaintext];
+  n0 [shape=box, label=\"mapping (3)\"];
+  n1 [label=\"say \\\"hi\\\"\\\\\\n\"];
+  n0 -> n1 [label=\"a\"];
+  n2 [shape=box, label=\"sequence (2)\"];
+  n3 [label=\"c\"];
+  n2 -> n3 [label=\"[0]\"];
+  n4 [shape=box, label=\"mapping (1)\"];
+  n5 [label=\"e\"];
+  n4 -> n5 [label=\"d\"];
+  n2 -> n4 [label=\"[1]\"];
+  n0 -> n2 [label=\"b\"];
+  n6 [label=\"0123456789012345678901234567890123456789...\"];
+  n0 -> n6 [label=\"f\"];
+}
+"
+        );
+    }
+}
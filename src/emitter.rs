@@ -1,6 +1,13 @@
+use comments::{Comments, NodeComments};
+use error::code;
+use path::{PathPattern, PathSegment, YamlPath};
+use secret::Envelope;
+use std::borrow::Cow;
+use std::collections::HashMap;
 use std::convert::From;
 use std::error::Error;
-use std::fmt::{self, Display};
+use std::fmt::{self, Display, Write};
+use std::io;
 use strict_yaml::{Hash, StrictYaml};
 
 #[derive(Copy, Clone, Debug)]
@@ -9,7 +16,23 @@ pub enum EmitError {
     BadHashmapKey,
 }
 
-impl Error for EmitError {}
+impl EmitError {
+    pub fn code(&self) -> &'static str {
+        match *self {
+            EmitError::FmtError(_) => code::FMT,
+            EmitError::BadHashmapKey => code::BAD_HASHMAP_KEY,
+        }
+    }
+}
+
+impl Error for EmitError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            EmitError::FmtError(ref err) => Some(err),
+            EmitError::BadHashmapKey => None,
+        }
+    }
+}
 
 impl Display for EmitError {
     fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
@@ -26,18 +49,269 @@ impl From<fmt::Error> for EmitError {
     }
 }
 
-pub struct StrictYamlEmitter<'a> {
-    writer: &'a mut dyn fmt::Write,
-    best_indent: usize,
+/// Lets `StrictYamlEmitter` write to an `io::Write`, such as a file or a
+/// socket, without rendering the document in a `String` first. The emitter
+/// makes many small writes, so an unbuffered sink is best wrapped in an
+/// `io::BufWriter`.
+///
+/// An I/O error surfaces from the emitter as a bare `EmitError::FmtError`;
+/// `finish` recovers the actual error.
+///
+/// ```
+/// use strict_yaml_rust::{IoWriter, StrictYaml, StrictYamlEmitter};
+///
+/// let doc = StrictYaml::from_str("a value");
+/// let mut out = IoWriter::new(Vec::new());
+/// let res = StrictYamlEmitter::new(&mut out).dump(&doc);
+/// out.finish(res).unwrap();
+/// assert_eq!(out.into_inner(), b"---\na value");
+/// ```
+pub struct IoWriter<W> {
+    inner: W,
+    error: Option<io::Error>,
+}
+
+impl<W: io::Write> IoWriter<W> {
+    pub fn new(inner: W) -> IoWriter<W> {
+        IoWriter { inner, error: None }
+    }
+
+    /// Turns the result of an emission to this writer into an I/O result,
+    /// flushing the sink on success. The I/O error that stopped the emission
+    /// is returned as is, other emit errors as `io::ErrorKind::InvalidData`
+    /// errors.
+    pub fn finish(&mut self, res: EmitResult) -> io::Result<()> {
+        if let Some(e) = self.error.take() {
+            return Err(e);
+        }
+        res.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        self.inner.flush()
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: io::Write> fmt::Write for IoWriter<W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        if self.error.is_some() {
+            return Err(fmt::Error);
+        }
+        self.inner.write_all(s.as_bytes()).map_err(|e| {
+            self.error = Some(e);
+            fmt::Error
+        })
+    }
+}
+
+/// Forwards to a writer, keeping track of the column the next character is
+/// written at.
+struct ColumnWriter<'a> {
+    inner: &'a mut dyn fmt::Write,
+    column: usize,
+}
+
+impl<'a> fmt::Write for ColumnWriter<'a> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.inner.write_str(s)?;
+        match s.rfind('\n') {
+            Some(i) => self.column = s[i + 1..].chars().count(),
+            None => self.column += s.chars().count(),
+        }
+        Ok(())
+    }
+}
+
+/// Style forced on the scalars at some paths, see `StrictYamlEmitter::style`.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum ScalarStyle {
+    /// Always double quoted.
+    Quoted,
+    /// A literal block (`|`), for scalars that can be written as one; others
+    /// get the default style.
+    Literal,
+    /// A folded block (`>`) wrapped at the fold width, for scalars of a single
+    /// line with spaces; others get the default style.
+    Folded,
+}
+
+/// Column folded blocks are wrapped at when no width is set.
+const DEFAULT_FOLD_WIDTH: usize = 80;
+
+/// When scalars are quoted, see `StrictYamlEmitter::quote_style`.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum QuoteStyle {
+    /// Double quoted when they would not load back as the same string, or
+    /// could be taken for a number, boolean or null by other YAML parsers.
+    Minimal,
+    /// Always double quoted.
+    Always,
+    /// Quoted like `Minimal`, with single quotes unless they have characters
+    /// that must be escaped.
+    SingleWhenPossible,
+}
+
+/// How documents are laid out by a `StrictYamlEmitter`. The setters of the
+/// emitter change the same settings.
+///
+/// ```
+/// use strict_yaml_rust::emitter::EmitterOptions;
+/// use strict_yaml_rust::{StrictYaml, StrictYamlEmitter};
+///
+/// let doc: StrictYaml = "a:\n  - b: x\n    c: y".parse().unwrap();
+/// let mut out = String::new();
+/// let options = EmitterOptions::new().indent(4);
+/// StrictYamlEmitter::with_options(&mut out, options).dump(&doc).unwrap();
+/// assert_eq!(out, "---\na:\n    -   b: x\n        c: y");
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct EmitterOptions {
+    indent: usize,
     compact: bool,
+    compact_single_pairs: bool,
+    block_indent: Option<usize>,
+    normalize_blocks: bool,
+    explicit_end: bool,
+    line_width: Option<usize>,
+    quote_style: QuoteStyle,
+    multiline_literals: bool,
+    fold_width: Option<usize>,
+}
+
+impl Default for EmitterOptions {
+    fn default() -> EmitterOptions {
+        EmitterOptions {
+            indent: 2,
+            compact: true,
+            compact_single_pairs: true,
+            block_indent: None,
+            normalize_blocks: false,
+            explicit_end: false,
+            line_width: None,
+            quote_style: QuoteStyle::Minimal,
+            multiline_literals: false,
+            fold_width: None,
+        }
+    }
+}
+
+impl EmitterOptions {
+    pub fn new() -> EmitterOptions {
+        EmitterOptions::default()
+    }
+
+    /// Spaces per level of nesting, 2 by default. In compact inline notation,
+    /// the `-` of a sequence entry is followed by spaces up to the next level.
+    ///
+    /// # Panics
+    ///
+    /// If `indent` is less than 2, which leaves no room for `- `.
+    pub fn indent(mut self, indent: usize) -> EmitterOptions {
+        assert!(
+            indent >= 2,
+            "indentation must be at least 2, not {}",
+            indent
+        );
+        self.indent = indent;
+        self
+    }
+
+    /// See `StrictYamlEmitter::compact`.
+    pub fn compact(mut self, compact: bool) -> EmitterOptions {
+        self.compact = compact;
+        self
+    }
+
+    /// See `StrictYamlEmitter::compact_single_pairs`.
+    pub fn compact_single_pairs(mut self, compact: bool) -> EmitterOptions {
+        self.compact_single_pairs = compact;
+        self
+    }
+
+    /// See `StrictYamlEmitter::block_indent`.
+    pub fn block_indent(mut self, indent: usize) -> EmitterOptions {
+        assert!(
+            (1..=9).contains(&indent),
+            "block indentation must be between 1 and 9, not {}",
+            indent
+        );
+        self.block_indent = Some(indent);
+        self
+    }
+
+    /// See `StrictYamlEmitter::normalize_block_indentation`.
+    pub fn normalize_block_indentation(mut self, normalize: bool) -> EmitterOptions {
+        self.normalize_blocks = normalize;
+        self
+    }
+
+    /// See `StrictYamlEmitter::explicit_document_end`.
+    pub fn explicit_document_end(mut self, explicit: bool) -> EmitterOptions {
+        self.explicit_end = explicit;
+        self
+    }
+
+    /// See `StrictYamlEmitter::line_width`.
+    pub fn line_width(mut self, width: usize) -> EmitterOptions {
+        self.line_width = Some(width);
+        self
+    }
+
+    /// See `StrictYamlEmitter::quote_style`.
+    pub fn quote_style(mut self, style: QuoteStyle) -> EmitterOptions {
+        self.quote_style = style;
+        self
+    }
+
+    /// See `StrictYamlEmitter::multiline_literals`.
+    pub fn multiline_literals(mut self, literal: bool) -> EmitterOptions {
+        self.multiline_literals = literal;
+        self
+    }
+
+    /// See `StrictYamlEmitter::fold_width`.
+    pub fn fold_width(mut self, width: usize) -> EmitterOptions {
+        self.fold_width = Some(width);
+        self
+    }
+}
+
+/// Values to encrypt, see `StrictYamlEmitter::encrypt`.
+type Encryption = (
+    PathPattern,
+    Envelope,
+    Box<dyn Fn(&YamlPath, &str) -> String>,
+);
+
+pub struct StrictYamlEmitter<'a> {
+    writer: ColumnWriter<'a>,
+    options: EmitterOptions,
+    styles: Vec<(PathPattern, ScalarStyle)>,
+    encryptions: Vec<Encryption>,
+    comments: Option<&'a Comments>,
+    /// Paths of the document being emitted in the document `comments` were
+    /// loaded with, see `Comments::origins`.
+    origins: Option<HashMap<YamlPath, YamlPath>>,
 
     level: isize,
+    /// Path of the node being emitted.
+    path: YamlPath,
+    /// Depth in complex keys, whose nodes have no path.
+    in_key: usize,
+    /// Whether the last node emitted is a literal block, whose last line
+    /// break is left to what follows.
+    last_literal: bool,
 }
 
 pub type EmitResult = Result<(), EmitError>;
 
 // from serialize::json
-fn escape_str(wr: &mut dyn fmt::Write, v: &str) -> Result<(), fmt::Error> {
+pub(crate) fn escape_str(wr: &mut dyn fmt::Write, v: &str) -> Result<(), fmt::Error> {
     wr.write_str("\"")?;
     let mut start = 0;
 
@@ -99,12 +373,28 @@ fn escape_str(wr: &mut dyn fmt::Write, v: &str) -> Result<(), fmt::Error> {
 }
 
 impl<'a> StrictYamlEmitter<'a> {
-    pub fn new(writer: &'a mut dyn fmt::Write) -> StrictYamlEmitter {
+    pub fn new(writer: &'a mut dyn fmt::Write) -> StrictYamlEmitter<'a> {
+        StrictYamlEmitter::with_options(writer, EmitterOptions::default())
+    }
+
+    pub fn with_options(
+        writer: &'a mut dyn fmt::Write,
+        options: EmitterOptions,
+    ) -> StrictYamlEmitter<'a> {
         StrictYamlEmitter {
-            writer,
-            best_indent: 2,
-            compact: true,
+            writer: ColumnWriter {
+                inner: writer,
+                column: 0,
+            },
+            options,
+            styles: Vec::new(),
+            encryptions: Vec::new(),
+            comments: None,
+            origins: None,
             level: -1,
+            path: YamlPath::new(),
+            in_key: 0,
+            last_literal: false,
         }
     }
 
@@ -117,19 +407,262 @@ impl<'a> StrictYamlEmitter<'a> {
     /// or tags), which should be OK, because this emitter doesn't
     /// (currently) emit those anyways.
     pub fn compact(&mut self, compact: bool) {
-        self.compact = compact;
+        self.options.compact = compact;
     }
 
     /// Determine if this emitter is using 'compact inline notation'.
     pub fn is_compact(&self) -> bool {
-        self.compact
+        self.options.compact
+    }
+
+    /// Set how sequence entries that are mappings of a single pair are
+    /// emitted, whatever the `compact` setting: on the line of their `-` when
+    /// on (`- key: value`), else on their own indented line.
+    pub fn compact_single_pairs(&mut self, compact: bool) {
+        self.options.compact_single_pairs = compact;
+    }
+
+    /// Determine if single pair sequence entries use 'compact inline notation'.
+    pub fn is_compact_single_pairs(&self) -> bool {
+        self.options.compact_single_pairs
+    }
+
+    /// Forces `style` on the scalar values at the paths matched by `pattern`,
+    /// e.g. literal blocks for `**.script`. When several patterns match, the
+    /// last one added applies, as for `encrypt`. Mapping keys keep the
+    /// default style.
+    ///
+    /// ```
+    /// use strict_yaml_rust::emitter::ScalarStyle;
+    /// use strict_yaml_rust::{StrictYamlEmitter, StrictYamlLoader};
+    ///
+    /// let doc = &StrictYamlLoader::load_from_str("run: \"make\\nmake test\"\nversion: v1").unwrap()[0];
+    /// let mut out = String::new();
+    /// let mut emitter = StrictYamlEmitter::new(&mut out);
+    /// emitter.style("**.run".parse().unwrap(), ScalarStyle::Literal);
+    /// emitter.style("**.version".parse().unwrap(), ScalarStyle::Quoted);
+    /// emitter.dump(doc).unwrap();
+    /// assert_eq!(out, "---\nrun: |-\n  make\n  make test\nversion: \"v1\"");
+    /// ```
+    pub fn style(&mut self, pattern: PathPattern, style: ScalarStyle) {
+        self.styles.push((pattern, style));
+    }
+
+    /// Set the indentation of the content of literal blocks relative to
+    /// their parent, always written as an indentation indicator (`|2`). By
+    /// default, blocks are indented like collections and the indicator is
+    /// only written when the content starts with a space.
+    ///
+    /// # Panics
+    ///
+    /// If `indent` is not between 1 and 9, the range of indicators.
+    pub fn block_indent(&mut self, indent: usize) {
+        self.options = self.options.block_indent(indent);
+    }
+
+    /// Determine the indentation of the content of literal blocks, when set.
+    pub fn get_block_indent(&self) -> Option<usize> {
+        self.options.block_indent
+    }
+
+    /// Set the column past which scalar values are wrapped, at spaces. Values
+    /// of a single line are folded into a block (`>-`), and values that must
+    /// be quoted are continued on the next lines with escaped line breaks.
+    /// Mapping keys and values without spaces are never wrapped.
+    ///
+    /// ```
+    /// use strict_yaml_rust::{StrictYaml, StrictYamlEmitter};
+    ///
+    /// let doc: StrictYaml = "text: the quick brown fox jumps over the lazy dog".parse().unwrap();
+    /// let mut out = String::new();
+    /// let mut emitter = StrictYamlEmitter::new(&mut out);
+    /// emitter.line_width(25);
+    /// emitter.dump(&doc).unwrap();
+    /// assert_eq!(out, "---\ntext: >-\n  the quick brown fox\n  jumps over the lazy dog\n");
+    /// ```
+    pub fn line_width(&mut self, width: usize) {
+        self.options.line_width = Some(width);
+    }
+
+    /// Determine the column past which scalar values are wrapped, when set.
+    pub fn get_line_width(&self) -> Option<usize> {
+        self.options.line_width
+    }
+
+    /// Set the column past which folded blocks are wrapped, whether forced
+    /// by `style` or chosen for values past the `line_width`. By default, it
+    /// is the line width, or 80 when there is none.
+    ///
+    /// ```
+    /// use strict_yaml_rust::emitter::ScalarStyle;
+    /// use strict_yaml_rust::{StrictYaml, StrictYamlEmitter};
+    ///
+    /// let doc: StrictYaml = "about: a short text in a few words\nname: x y".parse().unwrap();
+    /// let mut out = String::new();
+    /// let mut emitter = StrictYamlEmitter::new(&mut out);
+    /// emitter.style("about".parse().unwrap(), ScalarStyle::Folded);
+    /// emitter.fold_width(16);
+    /// emitter.dump(&doc).unwrap();
+    /// assert_eq!(out, "---\nabout: >-\n  a short text\n  in a few words\nname: x y");
+    /// ```
+    pub fn fold_width(&mut self, width: usize) {
+        self.options.fold_width = Some(width);
+    }
+
+    /// Determine the column past which folded blocks are wrapped, when set.
+    pub fn get_fold_width(&self) -> Option<usize> {
+        self.options.fold_width
+    }
+
+    /// Set when scalars, mapping keys included, are quoted: `Minimal` by
+    /// default. Styles forced by `style` take precedence.
+    ///
+    /// ```
+    /// use strict_yaml_rust::emitter::QuoteStyle;
+    /// use strict_yaml_rust::{StrictYaml, StrictYamlEmitter};
+    ///
+    /// let doc: StrictYaml = "a: x\nb: 'true'\nc: it's".parse().unwrap();
+    /// let mut out = String::new();
+    /// let mut emitter = StrictYamlEmitter::new(&mut out);
+    /// emitter.quote_style(QuoteStyle::SingleWhenPossible);
+    /// emitter.dump(&doc).unwrap();
+    /// assert_eq!(out, "---\na: x\nb: 'true'\nc: 'it''s'");
+    /// ```
+    pub fn quote_style(&mut self, style: QuoteStyle) {
+        self.options.quote_style = style;
+    }
+
+    /// Determine when scalars are quoted.
+    pub fn get_quote_style(&self) -> QuoteStyle {
+        self.options.quote_style
+    }
+
+    /// Set whether scalar values of several lines are written as literal
+    /// blocks (`|`) rather than double quoted with escaped line breaks, when
+    /// they can be. This applies whatever the `quote_style`, but styles forced
+    /// by `style` take precedence.
+    ///
+    /// ```
+    /// use strict_yaml_rust::{StrictYaml, StrictYamlEmitter};
+    ///
+    /// let doc: StrictYaml = "script: \"make\\nmake test\\n\"".parse().unwrap();
+    /// let mut out = String::new();
+    /// let mut emitter = StrictYamlEmitter::new(&mut out);
+    /// emitter.multiline_literals(true);
+    /// emitter.dump(&doc).unwrap();
+    /// assert_eq!(out, "---\nscript: |\n  make\n  make test\n");
+    /// ```
+    pub fn multiline_literals(&mut self, literal: bool) {
+        self.options.multiline_literals = literal;
+    }
+
+    /// Determine if scalar values of several lines are written as literal
+    /// blocks.
+    pub fn is_multiline_literals(&self) -> bool {
+        self.options.multiline_literals
+    }
+
+    /// Set whether the whitespace leading all the lines of a literal block is
+    /// removed, e.g. when the value was pasted from an indented source. The
+    /// value loaded back is the normalized one.
+    ///
+    /// ```
+    /// use strict_yaml_rust::emitter::ScalarStyle;
+    /// use strict_yaml_rust::{StrictYaml, StrictYamlEmitter};
+    ///
+    /// let doc = StrictYaml::String("    make\n      --jobs 4\n".into());
+    /// let mut out = String::new();
+    /// let mut emitter = StrictYamlEmitter::new(&mut out);
+    /// emitter.style("**".parse().unwrap(), ScalarStyle::Literal);
+    /// emitter.normalize_block_indentation(true);
+    /// emitter.dump(&doc).unwrap();
+    /// assert_eq!(out, "---\n|\n  make\n    --jobs 4\n");
+    /// ```
+    pub fn normalize_block_indentation(&mut self, normalize: bool) {
+        self.options.normalize_blocks = normalize;
+    }
+
+    /// Determine if the indentation of literal blocks is normalized.
+    pub fn is_block_indentation_normalized(&self) -> bool {
+        self.options.normalize_blocks
+    }
+
+    /// Set whether each document is ended with a `...` marker.
+    pub fn explicit_document_end(&mut self, explicit: bool) {
+        self.options.explicit_end = explicit;
+    }
+
+    /// Determine if documents are ended with a `...` marker.
+    pub fn is_explicit_document_end(&self) -> bool {
+        self.options.explicit_end
+    }
+
+    /// Replaces the scalar values at the paths matched by `pattern` with what
+    /// `encrypt` returns for their path and value, wrapped in `envelope`.
+    /// When several patterns match, the last one added applies, as for
+    /// `style`. Values already in the envelope are left as they are. See the
+    /// `secret` module.
+    pub fn encrypt<F>(&mut self, pattern: PathPattern, envelope: Envelope, encrypt: F)
+    where
+        F: Fn(&YamlPath, &str) -> String + 'static,
+    {
+        self.encryptions
+            .push((pattern, envelope, Box::new(encrypt)));
+    }
+
+    /// Writes `comments` back at the nodes they were attached to, wherever
+    /// edits moved them, see the `comments` module.
+    pub fn comments(&mut self, comments: &'a Comments) {
+        self.comments = Some(comments);
     }
 
     pub fn dump(&mut self, doc: &StrictYaml) -> EmitResult {
         // write DocumentStart
         writeln!(self.writer, "---")?;
         self.level = -1;
-        self.emit_node(doc)
+        self.path = YamlPath::new();
+        self.origins = self.comments.and_then(|c| c.origins(doc));
+        self.emit_node(doc)?;
+        if let Some(comments) = self.comments {
+            for line in comments.footer() {
+                writeln!(self.writer)?;
+                write!(self.writer, "#{}", line)?;
+                self.last_literal = false;
+            }
+        }
+        if self.last_literal {
+            writeln!(self.writer)?;
+        }
+        if self.options.explicit_end {
+            if !self.last_literal {
+                writeln!(self.writer)?;
+            }
+            write!(self.writer, "...")?;
+            self.last_literal = false;
+        }
+        Ok(())
+    }
+
+    /// Writes `docs` as a stream of documents, each starting with `---`, that
+    /// `StrictYamlLoader::load_from_str` loads back.
+    ///
+    /// ```
+    /// use strict_yaml_rust::{StrictYaml, StrictYamlEmitter};
+    ///
+    /// let docs = ["a: x".parse().unwrap(), StrictYaml::from_str("y")];
+    /// let mut out = String::new();
+    /// StrictYamlEmitter::new(&mut out).dump_all(&docs).unwrap();
+    /// assert_eq!(out, "---\na: x\n---\ny");
+    /// ```
+    pub fn dump_all(&mut self, docs: &[StrictYaml]) -> EmitResult {
+        for (i, doc) in docs.iter().enumerate() {
+            // a literal block ending the previous document ends its line
+            if i > 0 && !self.last_literal {
+                writeln!(self.writer)?;
+            }
+            self.dump(doc)?;
+        }
+        Ok(())
     }
 
     fn write_indent(&mut self) -> EmitResult {
@@ -137,7 +670,7 @@ impl<'a> StrictYamlEmitter<'a> {
             return Ok(());
         }
         for _ in 0..self.level {
-            for _ in 0..self.best_indent {
+            for _ in 0..self.options.indent {
                 write!(self.writer, " ")?;
             }
         }
@@ -145,23 +678,236 @@ impl<'a> StrictYamlEmitter<'a> {
     }
 
     fn emit_node(&mut self, node: &StrictYaml) -> EmitResult {
+        self.last_literal = false;
         match *node {
             StrictYaml::Array(ref v) => self.emit_array(v),
             StrictYaml::Hash(ref h) => self.emit_hash(h),
             StrictYaml::String(ref v) => {
-                if need_quotes(v) {
-                    escape_str(self.writer, v)?;
-                } else {
-                    write!(self.writer, "{}", v)?;
+                let v = self.encrypted(v);
+                let column = self.writer.column + v.chars().count();
+                let long = self.wrap_width().is_some_and(|width| column > width);
+                match self.forced_style() {
+                    Some(ScalarStyle::Literal) if is_literal_safe(&v) => self.emit_literal(&v),
+                    Some(ScalarStyle::Quoted) => self.emit_quoted(&v),
+                    Some(ScalarStyle::Folded) if is_foldable(&v) => self.emit_folded(&v),
+                    _ if self.literal_multiline(&v) => self.emit_literal(&v),
+                    _ if self.options.quote_style == QuoteStyle::Always => self.emit_quoted(&v),
+                    _ if long && is_foldable(&v) => self.emit_folded(&v),
+                    _ if need_quotes(&v) && !self.single_quoted(&v) => self.emit_quoted(&v),
+                    _ => self.emit_str(&v),
                 }
-                Ok(())
             }
             // XXX(chenyh) Alias
             _ => Ok(()),
         }
     }
 
+    /// The comments of the node being emitted.
+    fn node_comments(&self) -> Option<&'a NodeComments> {
+        match self.comments {
+            Some(comments) if self.in_key == 0 => match self.origins {
+                Some(ref origins) => origins.get(&self.path).and_then(|p| comments.get(p)),
+                None => comments.get(&self.path),
+            },
+            _ => None,
+        }
+    }
+
+    /// Writes the leading comments of the node being emitted, each followed
+    /// by a line break and the current indentation.
+    fn write_leading_comments(&mut self) -> EmitResult {
+        if let Some(c) = self.node_comments() {
+            for line in &c.leading {
+                writeln!(self.writer, "#{}", line)?;
+                self.write_indent()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_trailing_comment(&mut self) -> EmitResult {
+        if let Some(line) = self.node_comments().and_then(|c| c.trailing.as_ref()) {
+            write!(self.writer, " #{}", line)?;
+        }
+        Ok(())
+    }
+
+    fn has_trailing_comment(&self) -> bool {
+        self.node_comments().is_some_and(|c| c.trailing.is_some())
+    }
+
+    fn encrypted<'v>(&self, v: &'v str) -> Cow<'v, str> {
+        if self.in_key > 0 {
+            return Cow::Borrowed(v);
+        }
+        let found = self
+            .encryptions
+            .iter()
+            .rev()
+            .find(|(pattern, _, _)| pattern.matches(&self.path));
+        match found {
+            Some((_, envelope, encrypt)) if envelope.open(v).is_none() => {
+                Cow::Owned(envelope.seal(&encrypt(&self.path, v)))
+            }
+            _ => Cow::Borrowed(v),
+        }
+    }
+
+    fn forced_style(&self) -> Option<ScalarStyle> {
+        if self.in_key > 0 {
+            return None;
+        }
+        self.styles
+            .iter()
+            .rev()
+            .find(|(pattern, _)| pattern.matches(&self.path))
+            .map(|&(_, style)| style)
+    }
+
+    /// The column past which values are wrapped, if any. Complex keys are
+    /// not wrapped.
+    fn wrap_width(&self) -> Option<usize> {
+        match self.options.line_width {
+            Some(width) if self.in_key == 0 => Some(width),
+            _ => None,
+        }
+    }
+
+    /// Whether `v` is a value of several lines to write as a literal block.
+    /// Complex keys are not.
+    fn literal_multiline(&self, v: &str) -> bool {
+        self.options.multiline_literals
+            && self.in_key == 0
+            && v.contains('\n')
+            && is_literal_safe(v)
+    }
+
+    /// Whether `v` is to be single quoted, when quoted.
+    fn single_quoted(&self, v: &str) -> bool {
+        self.options.quote_style == QuoteStyle::SingleWhenPossible && is_single_quotable(v)
+    }
+
+    fn emit_str(&mut self, v: &str) -> EmitResult {
+        if self.options.quote_style == QuoteStyle::Always {
+            escape_str(&mut self.writer, v)?;
+        } else if need_quotes(v) && self.single_quoted(v) {
+            write!(self.writer, "'{}'", v.replace('\'', "''"))?;
+        } else if need_quotes(v) {
+            escape_str(&mut self.writer, v)?;
+        } else {
+            write!(self.writer, "{}", v)?;
+        }
+        Ok(())
+    }
+
+    /// Emits `v` double quoted, wrapped after spaces at the line width with
+    /// escaped line breaks.
+    fn emit_quoted(&mut self, v: &str) -> EmitResult {
+        let width = match self.wrap_width() {
+            Some(width) => width,
+            None => return Ok(escape_str(&mut self.writer, v)?),
+        };
+        let mut quoted = String::new();
+        escape_str(&mut quoted, v)?;
+        // the leading spaces of continuation lines are dropped, so lines
+        // break after the last of a run of spaces, with the closing quote
+        // kept on the last line
+        let bytes = quoted.as_bytes();
+        let breaks: Vec<(usize, usize)> = (1..bytes.len() - 1)
+            .filter(|&i| bytes[i - 1] == b' ' && bytes[i] != b' ')
+            .map(|i| (i, i))
+            .collect();
+        let indent = (self.level.max(0) as usize + 1) * self.options.indent;
+        let lines = wrap(&quoted, &breaks, self.writer.column, indent, width, 1);
+        for (i, line) in lines.iter().enumerate() {
+            if i > 0 {
+                writeln!(self.writer, "\\")?;
+                write!(self.writer, "{:indent$}", "", indent = indent)?;
+            }
+            self.writer.write_str(line)?;
+        }
+        Ok(())
+    }
+
+    /// Emits `v`, a single line, as a folded block wrapped at the fold width,
+    /// indented like a literal block.
+    fn emit_folded(&mut self, v: &str) -> EmitResult {
+        let step = self.options.block_indent.unwrap_or(self.options.indent);
+        let indent = self.level.max(0) as usize * self.options.indent + step;
+        if self.options.block_indent.is_some() {
+            write!(self.writer, ">{}-", step)?;
+        } else {
+            write!(self.writer, ">-")?;
+        }
+        self.write_trailing_comment()?;
+        // a line break between two lines folds into a space, and lines that
+        // start with whitespace would be kept apart
+        let bytes = v.as_bytes();
+        let breaks: Vec<(usize, usize)> = (1..bytes.len() - 1)
+            .filter(|&i| {
+                bytes[i] == b' '
+                    && !matches!(bytes[i - 1], b' ' | b'\t')
+                    && !matches!(bytes[i + 1], b' ' | b'\t')
+            })
+            .map(|i| (i, i + 1))
+            .collect();
+        let width = self
+            .options
+            .fold_width
+            .or(self.options.line_width)
+            .unwrap_or(DEFAULT_FOLD_WIDTH);
+        for line in wrap(v, &breaks, indent, indent, width, 0) {
+            writeln!(self.writer)?;
+            write!(self.writer, "{:indent$}{}", "", line, indent = indent)?;
+        }
+        self.last_literal = true;
+        Ok(())
+    }
+
+    /// Emits `v` as a literal block indented one level deeper than the current
+    /// collection, or than the document for a root scalar.
+    fn emit_literal(&mut self, v: &str) -> EmitResult {
+        let v = if self.options.normalize_blocks {
+            dedent(v)
+        } else {
+            Cow::Borrowed(v)
+        };
+        let body = v.trim_end_matches('\n');
+        let chomping = match v.len() - body.len() {
+            0 => "-",
+            1 => "",
+            _ => "+",
+        };
+        let step = self.options.block_indent.unwrap_or(self.options.indent);
+        let indent = self.level.max(0) as usize * self.options.indent + step;
+        // leading blanks would be taken for indentation
+        let leading_space = body
+            .split('\n')
+            .find(|line| !line.is_empty())
+            .is_some_and(|line| line.starts_with([' ', '\t']));
+        if leading_space || self.options.block_indent.is_some() {
+            write!(self.writer, "|{}{}", step, chomping)?;
+        } else {
+            write!(self.writer, "|{}", chomping)?;
+        }
+        self.write_trailing_comment()?;
+        for line in body.split('\n') {
+            writeln!(self.writer)?;
+            if !line.is_empty() {
+                write!(self.writer, "{:indent$}{}", "", line, indent = indent)?;
+            }
+        }
+        // kept line breaks past the one ending the last line, which is
+        // written by what follows
+        for _ in 1..v.len() - body.len() {
+            writeln!(self.writer)?;
+        }
+        self.last_literal = true;
+        Ok(())
+    }
+
     fn emit_array(&mut self, v: &[StrictYaml]) -> EmitResult {
+        self.last_literal = false;
         if v.is_empty() {
             write!(self.writer, "[]")?;
         } else {
@@ -171,8 +917,15 @@ impl<'a> StrictYamlEmitter<'a> {
                     writeln!(self.writer)?;
                     self.write_indent()?;
                 }
+                self.path.push(PathSegment::Index(cnt));
+                self.write_leading_comments()?;
                 write!(self.writer, "-")?;
-                self.emit_val(true, x)?;
+                let compact = match *x {
+                    StrictYaml::Hash(ref h) if h.len() == 1 => self.options.compact_single_pairs,
+                    _ => self.options.compact,
+                };
+                self.emit_val(compact, x)?;
+                self.path.pop();
             }
             self.level -= 1;
         }
@@ -180,6 +933,7 @@ impl<'a> StrictYamlEmitter<'a> {
     }
 
     fn emit_hash(&mut self, h: &Hash) -> EmitResult {
+        self.last_literal = false;
         if h.is_empty() {
             self.writer.write_str("{}")?;
         } else {
@@ -192,15 +946,26 @@ impl<'a> StrictYamlEmitter<'a> {
                 }
                 if complex_key {
                     write!(self.writer, "?")?;
-                    self.emit_val(true, k)?;
+                    self.in_key += 1;
+                    self.emit_val(self.options.compact, k)?;
+                    self.in_key -= 1;
                     writeln!(self.writer)?;
                     self.write_indent()?;
                     write!(self.writer, ":")?;
-                    self.emit_val(true, v)?;
+                    self.path.push(PathSegment::Key(format!("{:?}", k)));
+                    self.emit_val(self.options.compact, v)?;
+                    self.path.pop();
                 } else {
-                    self.emit_node(k)?;
+                    let key = k.as_str().unwrap_or_default().to_owned();
+                    self.path.push(PathSegment::Key(key));
+                    self.write_leading_comments()?;
+                    match *k {
+                        StrictYaml::String(ref k) => self.emit_str(k)?,
+                        _ => self.emit_node(k)?,
+                    }
                     write!(self.writer, ":")?;
                     self.emit_val(false, v)?;
+                    self.path.pop();
                 }
             }
             self.level -= 1;
@@ -210,38 +975,144 @@ impl<'a> StrictYamlEmitter<'a> {
 
     /// Emit a yaml as a hash or array value: i.e., which should appear
     /// following a ":" or "-", either after a space, or on a new line.
-    /// If `inline` is true, then the preceeding characters are distinct
-    /// and short enough to respect the compact flag.
-    fn emit_val(&mut self, inline: bool, val: &StrictYaml) -> EmitResult {
+    /// If `compact` is true, then the preceeding characters are distinct
+    /// and short enough for compact inline notation, which is wanted.
+    fn emit_val(&mut self, compact: bool, val: &StrictYaml) -> EmitResult {
+        // a trailing comment ends the line, so the value cannot follow it
+        let compact = compact && !self.has_trailing_comment();
         match *val {
+            StrictYaml::Array(ref v) if v.is_empty() => {
+                write!(self.writer, " ")?;
+                self.emit_array(v)?;
+                self.write_trailing_comment()
+            }
+            StrictYaml::Hash(ref h) if h.is_empty() => {
+                write!(self.writer, " ")?;
+                self.emit_hash(h)?;
+                self.write_trailing_comment()
+            }
             StrictYaml::Array(ref v) => {
-                if (inline && self.compact) || v.is_empty() {
-                    write!(self.writer, " ")?;
-                } else {
-                    writeln!(self.writer)?;
-                    self.level += 1;
-                    self.write_indent()?;
-                    self.level -= 1;
-                }
+                self.start_collection(compact)?;
                 self.emit_array(v)
             }
             StrictYaml::Hash(ref h) => {
-                if (inline && self.compact) || h.is_empty() {
-                    write!(self.writer, " ")?;
-                } else {
-                    writeln!(self.writer)?;
-                    self.level += 1;
-                    self.write_indent()?;
-                    self.level -= 1;
-                }
+                self.start_collection(compact)?;
                 self.emit_hash(h)
             }
             _ => {
                 write!(self.writer, " ")?;
-                self.emit_node(val)
+                self.emit_node(val)?;
+                if !self.last_literal {
+                    self.write_trailing_comment()?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Moves to where a non-empty collection value starts: on the same line
+    /// if `compact`, at the indentation of its level, else on the next line.
+    fn start_collection(&mut self, compact: bool) -> EmitResult {
+        if compact {
+            write!(self.writer, "{:1$}", "", self.options.indent - 1)?;
+        } else {
+            self.write_trailing_comment()?;
+            writeln!(self.writer)?;
+            self.level += 1;
+            self.write_indent()?;
+            self.level -= 1;
+        }
+        Ok(())
+    }
+}
+
+/// Removes the spaces and tabs leading all the lines with content. Lines of
+/// whitespace only lose theirs up to the same length.
+fn dedent(v: &str) -> Cow<'_, str> {
+    let is_blank = |c: char| c == ' ' || c == '\t';
+    let mut lines = v
+        .split('\n')
+        .filter(|line| !line.trim_start_matches(is_blank).is_empty());
+    let mut prefix = match lines.next() {
+        Some(line) => &line[..line.len() - line.trim_start_matches(is_blank).len()],
+        None => return Cow::Borrowed(v),
+    };
+    for line in lines {
+        let common = prefix
+            .bytes()
+            .zip(line.bytes())
+            .take_while(|(a, b)| a == b)
+            .count();
+        prefix = &prefix[..common];
+    }
+    if prefix.is_empty() {
+        return Cow::Borrowed(v);
+    }
+    let lines: Vec<&str> = v
+        .split('\n')
+        .map(|line| match line.strip_prefix(prefix) {
+            Some(rest) => rest,
+            None => line.trim_start_matches(is_blank),
+        })
+        .collect();
+    Cow::Owned(lines.join("\n"))
+}
+
+/// Splits `v` into lines that fit in `width` columns where possible, the
+/// first starting at column `first` and the others at `indent`, with `reserve`
+/// columns left at the end of all but the last. A line may end at the first
+/// offset of a break, with the next one starting at the second.
+fn wrap<'v>(
+    v: &'v str,
+    breaks: &[(usize, usize)],
+    first: usize,
+    indent: usize,
+    width: usize,
+    reserve: usize,
+) -> Vec<&'v str> {
+    let mut lines = Vec::new();
+    let mut start = 0;
+    let mut column = first;
+    let mut last = None;
+    for &(end, next) in breaks.iter().chain(Some(&(v.len(), v.len()))) {
+        // the last line needs no room at its end
+        let reserve = if end < v.len() { reserve } else { 0 };
+        if column + v[start..end].chars().count() + reserve > width {
+            if let Some((last_end, last_next)) = last {
+                lines.push(&v[start..last_end]);
+                start = last_next;
+                column = indent;
             }
         }
+        last = Some((end, next));
     }
+    lines.push(&v[start..]);
+    lines
+}
+
+/// Check if the string can be written as a folded block of several lines with
+/// the same value: a single line with spaces, safe for a literal block, that
+/// does not start with whitespace.
+fn is_foldable(v: &str) -> bool {
+    !v.contains('\n') && v.trim().contains(' ') && is_literal_safe(v) && !v.starts_with([' ', '\t'])
+}
+
+/// Check if the string can be written single quoted with the same value: it
+/// must have no characters that are line breaks to YAML, or that must be
+/// escaped.
+fn is_single_quotable(v: &str) -> bool {
+    !v.contains(|c: char| c.is_control() || matches!(c, '\u{2028}' | '\u{2029}' | '\u{feff}'))
+}
+
+/// Check if the string can be written as a literal block with the same value:
+/// it must have a line with content, and no characters that are line breaks
+/// to YAML, or that must be escaped.
+fn is_literal_safe(v: &str) -> bool {
+    v.contains(|c: char| c != '\n')
+        && !v.contains(|c: char| {
+            (c.is_control() && c != '\n' && c != '\t')
+                || matches!(c, '\u{2028}' | '\u{2029}' | '\u{feff}')
+        })
 }
 
 /// Check if the string requires quoting.
@@ -261,7 +1132,7 @@ impl<'a> StrictYamlEmitter<'a> {
 /// * When the string is null or ~ (otherwise, it would be considered as a null value);
 /// * When the string looks like a number, such as integers (e.g. 2, 14, etc.), floats (e.g. 2.6, 14.9) and exponential numbers (e.g. 12e7, etc.) (otherwise, it would be treated as a numeric value);
 /// * When the string looks like a date (e.g. 2014-12-31) (otherwise it would be automatically converted into a Unix timestamp).
-fn need_quotes(string: &str) -> bool {
+pub(crate) fn need_quotes(string: &str) -> bool {
     fn need_quotes_spaces(string: &str) -> bool {
         string.starts_with(' ') || string.ends_with(' ')
     }
@@ -314,7 +1185,7 @@ a4:
     - [a1, a2]
     - 2
 ";
-        let docs = StrictYamlLoader::load_from_str(&s).unwrap();
+        let docs = StrictYamlLoader::load_from_str(s).unwrap();
         let doc = &docs[0];
         let mut writer = String::new();
         {
@@ -338,9 +1209,9 @@ cataloge:
   product2: "&cookies  { name: Cookies!,  price: 3.40 ,  unit: 400g}"
 
 products:
-  *coffee:
+  "*coffee":
     amount: 4
-  *cookies:
+  "*cookies":
     amount: 4
   [1,2,3,4]:
     array key
@@ -352,7 +1223,7 @@ products:
     empty hash key
             "#;
 
-        let docs = StrictYamlLoader::load_from_str(&s).unwrap();
+        let docs = StrictYamlLoader::load_from_str(s).unwrap();
         let doc = &docs[0];
         let mut writer = String::new();
         {
@@ -401,7 +1272,7 @@ x: test
 y: avoid quoting here
 z: string with spaces"#;
 
-        let docs = StrictYamlLoader::load_from_str(&s).unwrap();
+        let docs = StrictYamlLoader::load_from_str(s).unwrap();
         let doc = &docs[0];
         let mut writer = String::new();
         {
@@ -448,7 +1319,7 @@ e:
     h: []"#
         };
 
-        let docs = StrictYamlLoader::load_from_str(&s).unwrap();
+        let docs = StrictYamlLoader::load_from_str(s).unwrap();
         let doc = &docs[0];
         let mut writer = String::new();
         {
@@ -471,7 +1342,7 @@ a:
     - - e
       - f"#;
 
-        let docs = StrictYamlLoader::load_from_str(&s).unwrap();
+        let docs = StrictYamlLoader::load_from_str(s).unwrap();
         let doc = &docs[0];
         let mut writer = String::new();
         {
@@ -495,7 +1366,7 @@ a:
       - - f
       - - e"#;
 
-        let docs = StrictYamlLoader::load_from_str(&s).unwrap();
+        let docs = StrictYamlLoader::load_from_str(s).unwrap();
         let doc = &docs[0];
         let mut writer = String::new();
         {
@@ -517,7 +1388,7 @@ a:
       d:
         e: f"#;
 
-        let docs = StrictYamlLoader::load_from_str(&s).unwrap();
+        let docs = StrictYamlLoader::load_from_str(s).unwrap();
         let doc = &docs[0];
         let mut writer = String::new();
         {
@@ -529,4 +1400,449 @@ a:
 
         assert_eq!(s, writer);
     }
+
+    #[test]
+    fn test_compact_single_pairs() {
+        let s = "---\n- a: x\n- b: y\n  c: z\n- - d: w";
+        let doc = &StrictYamlLoader::load_from_str(s).unwrap()[0];
+        let emit = |compact, single_pairs| {
+            let mut writer = String::new();
+            let mut emitter = StrictYamlEmitter::new(&mut writer);
+            emitter.compact(compact);
+            emitter.compact_single_pairs(single_pairs);
+            emitter.dump(doc).unwrap();
+            writer
+        };
+        assert_eq!(emit(true, true), s);
+        assert_eq!(
+            emit(true, false),
+            "---\n-\n  a: x\n- b: y\n  c: z\n- -\n    d: w"
+        );
+        assert_eq!(
+            emit(false, true),
+            "---\n- a: x\n-\n  b: y\n  c: z\n-\n  - d: w"
+        );
+        for &(compact, single_pairs) in &[(true, false), (false, true), (false, false)] {
+            let docs = StrictYamlLoader::load_from_str(&emit(compact, single_pairs)).unwrap();
+            assert_eq!(&docs[0], doc);
+        }
+    }
+
+    #[test]
+    fn test_complex_keys_follow_compact() {
+        // `compact_single_pairs` only applies to sequence entries: complex
+        // keys and their values stay inline exactly when `compact` is on
+        let mut pair = Hash::new();
+        pair.insert(
+            StrictYaml::String("a".into()),
+            StrictYaml::String("b".into()),
+        );
+        let mut h = Hash::new();
+        h.insert(
+            StrictYaml::Array(vec![
+                StrictYaml::String("x".into()),
+                StrictYaml::String("y".into()),
+            ]),
+            StrictYaml::Hash(pair.clone()),
+        );
+        h.insert(
+            StrictYaml::Hash(pair),
+            StrictYaml::Array(vec![StrictYaml::String("z".into())]),
+        );
+        let doc = StrictYaml::Hash(h);
+        let emit = |compact, single_pairs| {
+            let mut writer = String::new();
+            let mut emitter = StrictYamlEmitter::new(&mut writer);
+            emitter.compact(compact);
+            emitter.compact_single_pairs(single_pairs);
+            emitter.dump(&doc).unwrap();
+            writer
+        };
+        for &single_pairs in &[true, false] {
+            assert_eq!(
+                emit(true, single_pairs),
+                "---\n? - x\n  - y\n: a: b\n? a: b\n: - z"
+            );
+            assert_eq!(
+                emit(false, single_pairs),
+                "---\n?\n  - x\n  - y\n:\n  a: b\n?\n  a: b\n:\n  - z"
+            );
+        }
+    }
+
+    #[test]
+    fn test_style_overrides() {
+        let values = [
+            "a",
+            "a\n",
+            "a\n\n",
+            "a\nb",
+            "\na\n\nb\n",
+            " a\nb",
+            "\n  a",
+            "a \n\tb",
+            "\ta\nb",
+            "",
+            "\n",
+            "a\rb",
+            "a\u{85}b",
+            "- a\n# b",
+        ];
+        let mut doc = Hash::new();
+        let mut list = Vec::new();
+        for (i, v) in values.iter().enumerate() {
+            doc.insert(
+                StrictYaml::String(format!("k{}", i)),
+                StrictYaml::String(v.to_string()),
+            );
+            let mut item = Hash::new();
+            item.insert(
+                StrictYaml::String("script".into()),
+                StrictYaml::String(v.to_string()),
+            );
+            list.push(StrictYaml::Hash(item));
+        }
+        let mut nested = Hash::new();
+        nested.insert(StrictYaml::String("jobs".into()), StrictYaml::Array(list));
+        for root in vec![StrictYaml::Hash(doc), StrictYaml::Hash(nested)]
+            .into_iter()
+            .chain(values.iter().map(|v| StrictYaml::String(v.to_string())))
+        {
+            let mut writer = String::new();
+            let mut emitter = StrictYamlEmitter::new(&mut writer);
+            emitter.style("**".parse().unwrap(), ScalarStyle::Literal);
+            emitter.dump(&root).unwrap();
+            let docs = StrictYamlLoader::load_from_str(&writer).unwrap();
+            assert_eq!(docs[0], root, "{}", writer);
+        }
+
+        let s = "---\njobs:\n  - script: \"make\\nmake test\\n\"\n    version: 1\n  - name: x";
+        let doc = &StrictYamlLoader::load_from_str(s).unwrap()[0];
+        let mut writer = String::new();
+        let mut emitter = StrictYamlEmitter::new(&mut writer);
+        emitter.style("**".parse().unwrap(), ScalarStyle::Quoted);
+        emitter.style("**.script".parse().unwrap(), ScalarStyle::Literal);
+        emitter.style("jobs[1].*".parse().unwrap(), ScalarStyle::Quoted);
+        emitter.dump(doc).unwrap();
+        assert_eq!(
+            writer,
+            "---\njobs:\n  - script: |\n      make\n      make test\n    version: \"1\"\n  - name: \"x\""
+        );
+
+        let mut writer = String::new();
+        let mut emitter = StrictYamlEmitter::new(&mut writer);
+        emitter.style("**".parse().unwrap(), ScalarStyle::Literal);
+        emitter.dump(&StrictYaml::from_str("\ta\nb")).unwrap();
+        assert_eq!(writer, "---\n|2-\n  \ta\n  b\n");
+    }
+
+    /// Accepts `room` bytes, then fails.
+    struct Full {
+        room: usize,
+    }
+
+    impl io::Write for Full {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if self.room == 0 {
+                return Err(io::Error::new(io::ErrorKind::WriteZero, "disk full"));
+            }
+            let n = buf.len().min(self.room);
+            self.room -= n;
+            Ok(n)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_io_writer() {
+        let doc = &StrictYamlLoader::load_from_str("a: 1\nb:\n  - c\n  - d").unwrap()[0];
+        let mut expected = String::new();
+        StrictYamlEmitter::new(&mut expected).dump(doc).unwrap();
+
+        let mut out = IoWriter::new(Vec::new());
+        let res = StrictYamlEmitter::new(&mut out).dump(doc);
+        out.finish(res).unwrap();
+        assert_eq!(out.into_inner(), expected.as_bytes());
+
+        let mut out = IoWriter::new(Full { room: 10 });
+        let res = StrictYamlEmitter::new(&mut out).dump(doc);
+        let err = out.finish(res).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::WriteZero);
+        assert_eq!(out.get_ref().room, 0);
+
+        let mut out = IoWriter::new(Vec::new());
+        let err = out.finish(Err(EmitError::BadHashmapKey)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_indent() {
+        let source = "a:\n  - b: x\n    c:\n      - - y\n        - |\n          z\n  - d: w\ne: v";
+        let doc = StrictYamlLoader::load_from_str(source).unwrap().remove(0);
+        let emit = |options: EmitterOptions| {
+            let mut writer = String::new();
+            let mut emitter = StrictYamlEmitter::with_options(&mut writer, options);
+            emitter.style("**".parse().unwrap(), ScalarStyle::Literal);
+            emitter.dump(&doc).unwrap();
+            writer
+        };
+        assert_eq!(
+            emit(EmitterOptions::new().indent(4)),
+            "---\na:\n    -   b: |-\n            x\n        c:\n            -   - |-\n                    y\n                - |\n                    z\n    -   d: |-\n            w\ne: |-\n    v\n"
+        );
+        for indent in 2..7 {
+            for &compact in &[true, false] {
+                let options = EmitterOptions::new().indent(indent).compact(compact);
+                let text = emit(options.compact_single_pairs(compact));
+                let docs = StrictYamlLoader::load_from_str(&text).unwrap();
+                assert_eq!(docs[0], doc, "{}", text);
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "indentation must be at least 2, not 1")]
+    fn test_indent_too_small() {
+        EmitterOptions::new().indent(1);
+    }
+
+    #[test]
+    fn test_line_width() {
+        let values = [
+            "the quick brown fox jumps over the lazy dog",
+            "two  spaces and\ttabs, trailing ",
+            "- looks like: a sequence # not a comment",
+            "line\nbreaks in a \"quoted\" value that is long",
+            "  leading spaces, so quoted rather than folded",
+            "a_single_long_word_that_cannot_be_wrapped_anywhere",
+            "   ",
+        ];
+        let mut doc = Hash::new();
+        for (i, v) in values.iter().enumerate() {
+            doc.insert(
+                StrictYaml::from_str(&i.to_string()),
+                StrictYaml::from_str(v),
+            );
+        }
+        let doc = StrictYaml::Array(vec![StrictYaml::Hash(doc)]);
+        let emit = |width: usize, quoted: bool| {
+            let mut writer = String::new();
+            let options = EmitterOptions::new().line_width(width);
+            let mut emitter = StrictYamlEmitter::with_options(&mut writer, options);
+            if quoted {
+                emitter.style("**".parse().unwrap(), ScalarStyle::Quoted);
+            }
+            emitter.dump(&doc).unwrap();
+            writer
+        };
+        assert_eq!(
+            emit(30, false),
+            "---\n- \"0\": >-\n    the quick brown fox jumps\n    over the lazy dog\n  \"1\": >-\n    two  spaces and\ttabs,\n    trailing \n  \"2\": >-\n    - looks like: a sequence #\n    not a comment\n  \"3\": \"line\\nbreaks in a \\\n    \\\"quoted\\\" value that is \\\n    long\"\n  \"4\": \"  leading spaces, so \\\n    quoted rather than folded\"\n  \"5\": a_single_long_word_that_cannot_be_wrapped_anywhere\n  \"6\": \"   \""
+        );
+        for width in 1..60 {
+            for &quoted in &[false, true] {
+                let text = emit(width, quoted);
+                let docs = StrictYamlLoader::load_from_str(&text).unwrap();
+                assert_eq!(docs[0], doc, "{}", text);
+            }
+        }
+    }
+
+    #[test]
+    fn test_quote_style() {
+        let source = "a: x\n'1': true\n'b c': \"it's\"\nd: \"\\t\\\\\"\ne:\n  - ''\n  - '-'";
+        let doc = StrictYamlLoader::load_from_str(source).unwrap().remove(0);
+        let emit = |style: QuoteStyle| {
+            let mut writer = String::new();
+            let options = EmitterOptions::new().quote_style(style);
+            StrictYamlEmitter::with_options(&mut writer, options)
+                .dump(&doc)
+                .unwrap();
+            writer
+        };
+        assert_eq!(
+            emit(QuoteStyle::Minimal),
+            "---\na: x\n\"1\": \"true\"\nb c: \"it's\"\nd: \"\\t\\\\\"\ne:\n  - \"\"\n  - \"-\""
+        );
+        assert_eq!(
+            emit(QuoteStyle::Always),
+            "---\n\"a\": \"x\"\n\"1\": \"true\"\n\"b c\": \"it's\"\n\"d\": \"\\t\\\\\"\n\"e\":\n  - \"\"\n  - \"-\""
+        );
+        assert_eq!(
+            emit(QuoteStyle::SingleWhenPossible),
+            "---\na: x\n'1': 'true'\nb c: 'it''s'\nd: \"\\t\\\\\"\ne:\n  - ''\n  - '-'"
+        );
+        for &style in &[
+            QuoteStyle::Minimal,
+            QuoteStyle::Always,
+            QuoteStyle::SingleWhenPossible,
+        ] {
+            let text = emit(style);
+            assert_eq!(
+                StrictYamlLoader::load_from_str(&text).unwrap()[0],
+                doc,
+                "{}",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn test_multiline_literals() {
+        let values = [
+            "a\nb",
+            "a\nb\n",
+            "a\n\n",
+            "\n a\n",
+            "  x\n\ty ",
+            "\tx\ny",
+            "- a\n# b",
+            "one line",
+            "\n",
+            "bad\r\nbreak",
+        ];
+        let items: Vec<StrictYaml> = values.iter().map(|v| StrictYaml::from_str(v)).collect();
+        let mut doc = Hash::new();
+        doc.insert(StrictYaml::from_str("k\ney"), StrictYaml::Array(items));
+        let doc = StrictYaml::Hash(doc);
+        let emit = |options: EmitterOptions| {
+            let mut writer = String::new();
+            StrictYamlEmitter::with_options(&mut writer, options.multiline_literals(true))
+                .dump(&doc)
+                .unwrap();
+            writer
+        };
+        let text = emit(EmitterOptions::new());
+        assert_eq!(
+            text,
+            "---\n\"k\\ney\":\n  - |-\n    a\n    b\n  - |\n    a\n    b\n  - |+\n    a\n\n  - |2\n\n     a\n  - |2-\n      x\n    \ty \n  - |2-\n    \tx\n    y\n  - |-\n    - a\n    # b\n  - one line\n  - \"\\n\"\n  - \"bad\\r\\nbreak\""
+        );
+        assert_eq!(StrictYamlLoader::load_from_str(&text).unwrap()[0], doc);
+        for options in &[
+            EmitterOptions::new().quote_style(QuoteStyle::Always),
+            EmitterOptions::new().indent(4).block_indent(1),
+            EmitterOptions::new().compact(false).line_width(1),
+        ] {
+            let text = emit(*options);
+            assert_eq!(
+                StrictYamlLoader::load_from_str(&text).unwrap()[0],
+                doc,
+                "{}",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn test_folded() {
+        let text = "a b c d e f g h i j k l m n o p q r s t u v w x y z ".repeat(4);
+        let source = format!(
+            "a: {}\nb:\n  - \"{}\"\n  - no_space\n  - '  x y'",
+            text.trim(),
+            text
+        );
+        let doc = StrictYamlLoader::load_from_str(&source).unwrap().remove(0);
+        let emit = |options: EmitterOptions| {
+            let mut writer = String::new();
+            let mut emitter = StrictYamlEmitter::with_options(&mut writer, options);
+            emitter.style("**".parse().unwrap(), ScalarStyle::Folded);
+            emitter.dump(&doc).unwrap();
+            writer
+        };
+        let text = emit(EmitterOptions::new());
+        assert!(text.lines().all(|line| line.len() <= 80), "{}", text);
+        assert!(text.contains("\n  - no_space\n  - \"  x y\""), "{}", text);
+        assert_eq!(StrictYamlLoader::load_from_str(&text).unwrap()[0], doc);
+
+        for width in 1..40 {
+            let options = EmitterOptions::new().fold_width(width).line_width(100);
+            let text = emit(options);
+            // the longest line that cannot be wrapped is `  - no_space`
+            let longest = text.lines().map(str::len).max().unwrap();
+            assert!(longest <= width.max(12), "{}", text);
+            assert_eq!(StrictYamlLoader::load_from_str(&text).unwrap()[0], doc);
+        }
+    }
+
+    #[test]
+    fn test_dump_all() {
+        let load = |source: &str| StrictYamlLoader::load_from_str(source).unwrap().remove(0);
+        let docs = [
+            load("a: b\nc: |\n  x\n"),
+            load("- d"),
+            StrictYaml::from_str("y"),
+        ];
+        let emit = |explicit_end: bool| {
+            let mut writer = String::new();
+            let mut emitter = StrictYamlEmitter::new(&mut writer);
+            emitter.style("c".parse().unwrap(), ScalarStyle::Literal);
+            emitter.explicit_document_end(explicit_end);
+            emitter.dump_all(&docs).unwrap();
+            writer
+        };
+        let text = emit(false);
+        assert_eq!(text, "---\na: b\nc: |\n  x\n---\n- d\n---\ny");
+        assert_eq!(StrictYamlLoader::load_from_str(&text).unwrap(), docs);
+        let text = emit(true);
+        assert_eq!(
+            text,
+            "---\na: b\nc: |\n  x\n...\n---\n- d\n...\n---\ny\n..."
+        );
+        assert_eq!(StrictYamlLoader::load_from_str(&text).unwrap(), docs);
+
+        let mut writer = String::new();
+        StrictYamlEmitter::new(&mut writer).dump_all(&[]).unwrap();
+        assert_eq!(writer, "");
+    }
+
+    #[test]
+    fn test_block_indentation() {
+        let emit = |v: &str, indent: Option<usize>, normalize: bool| {
+            let mut writer = String::new();
+            let mut emitter = StrictYamlEmitter::new(&mut writer);
+            emitter.style("**".parse().unwrap(), ScalarStyle::Literal);
+            if let Some(indent) = indent {
+                emitter.block_indent(indent);
+            }
+            emitter.normalize_block_indentation(normalize);
+            let mut doc = Hash::new();
+            let mut inner = Hash::new();
+            inner.insert(StrictYaml::from_str("b"), StrictYaml::from_str(v));
+            doc.insert(StrictYaml::from_str("a"), StrictYaml::Hash(inner));
+            emitter.dump(&StrictYaml::Hash(doc)).unwrap();
+            writer
+        };
+        assert_eq!(
+            emit("x\n y\n", None, false),
+            "---\na:\n  b: |\n    x\n     y\n"
+        );
+        assert_eq!(
+            emit("x\n y\n", Some(4), false),
+            "---\na:\n  b: |4\n      x\n       y\n"
+        );
+        assert_eq!(
+            emit("  x\n   y", None, false),
+            "---\na:\n  b: |2-\n      x\n       y\n"
+        );
+        assert_eq!(
+            emit("  x\n   y", None, true),
+            "---\na:\n  b: |-\n    x\n     y\n"
+        );
+        assert_eq!(
+            emit("\t  x\n\n \n\t  y\n\t   z", Some(1), true),
+            "---\na:\n  b: |1-\n   x\n\n\n   y\n    z\n"
+        );
+        assert_eq!(dedent(" \n  x\n\t y"), " \n  x\n\t y");
+
+        for v in &["  x\n   y", "\n\n   x\n  y\n", " x", "x\n\n"] {
+            for indent in 1..10 {
+                let text = emit(v, Some(indent), false);
+                let docs = StrictYamlLoader::load_from_str(&text).unwrap();
+                assert_eq!(docs[0]["a"]["b"].as_str(), Some(*v), "{}", text);
+            }
+        }
+    }
 }
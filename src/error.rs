@@ -0,0 +1,208 @@
+//! The errors of loading documents, told apart by what went wrong.
+//!
+//! Loading functions return a `ScanError`, which converts into a `LoadError`
+//! to match on the kind of error rather than on its message.
+//!
+//! ```
+//! use strict_yaml_rust::{LoadError, StrictYamlLoader};
+//!
+//! let err = StrictYamlLoader::load_from_str("a: 1\nb: 2\na: 3").unwrap_err();
+//! match LoadError::from(err) {
+//!     LoadError::DuplicateKey(e) => assert_eq!(e.marker().line(), 3),
+//!     e => panic!("unexpected error: {}", e),
+//! }
+//! ```
+//!
+//! Every error of the crate has a `code()`, one of the constants of `code`,
+//! for tooling to match on without parsing messages.
+
+use scanner::{Marker, Origin, ScanError};
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+/// The codes of the errors of this crate, which stay the same across
+/// releases: `E0..` for loading, `E1..` for schemas, `E2..` for emitting,
+/// `E3..` for serde, `E4..` for paths, edits and queries, `E5..` for
+/// conversions.
+pub mod code {
+    pub const DUPLICATE_KEY: &str = "E001_DUPLICATE_KEY";
+    pub const SCAN: &str = "E002_SCAN";
+    pub const PARSE: &str = "E003_PARSE";
+    pub const LIMIT: &str = "E004_LIMIT";
+    pub const IO: &str = "E005_IO";
+    pub const OTHER: &str = "E006_OTHER";
+    pub const DECRYPT: &str = "E007_DECRYPT";
+    /// A document failing its schema, for all its `SchemaError`s.
+    pub const SCHEMA: &str = "E100_SCHEMA";
+    pub const EXPECTED: &str = "E101_EXPECTED";
+    pub const MISSING_KEY: &str = "E102_MISSING_KEY";
+    pub const UNEXPECTED_KEY: &str = "E103_UNEXPECTED_KEY";
+    pub const UNEXPECTED_ITEM: &str = "E104_UNEXPECTED_ITEM";
+    pub const DUPLICATE_ITEM: &str = "E105_DUPLICATE_ITEM";
+    pub const NOT_ALLOWED: &str = "E106_NOT_ALLOWED";
+    pub const ENTRIES: &str = "E107_ENTRIES";
+    pub const FMT: &str = "E201_FMT";
+    pub const BAD_HASHMAP_KEY: &str = "E202_BAD_HASHMAP_KEY";
+    pub const DESERIALIZE: &str = "E301_DESERIALIZE";
+    pub const SERIALIZE: &str = "E302_SERIALIZE";
+    pub const PATTERN: &str = "E401_PATTERN";
+    pub const EDIT: &str = "E402_EDIT";
+    pub const QUERY: &str = "E403_QUERY";
+    pub const CONVERSION: &str = "E501_CONVERSION";
+}
+
+/// An error loading documents, with the position where it was found.
+#[derive(Debug)]
+pub enum LoadError {
+    /// Malformed input, such as an unterminated quoted scalar, a null
+    /// character or bytes that are not valid UTF-8.
+    Scan(ScanError),
+    /// Well-formed tokens that do not make a document, such as an alias of an
+    /// unknown anchor.
+    Parse(ScanError),
+    /// A key repeated in a mapping.
+    DuplicateKey(ScanError),
+    /// Input rejected by a `LoadOptions` setting, by the alias budget or by
+    /// the nesting limit, a complex key in a syntax tree, or loading
+    /// cancelled.
+    Limit(ScanError),
+    /// Reading the input failed, after reading up to `marker`.
+    Io { error: io::Error, marker: Marker },
+    /// A secret that its decrypting function refused.
+    Decrypt(ScanError),
+    /// An error raised by an event receiver outside this crate.
+    Other(ScanError),
+}
+
+impl LoadError {
+    pub fn code(&self) -> &'static str {
+        match *self {
+            LoadError::Io { .. } => code::IO,
+            _ => self.scan_error().unwrap().code(),
+        }
+    }
+
+    /// Where the error was found.
+    pub fn marker(&self) -> Marker {
+        match *self {
+            LoadError::Io { marker, .. } => marker,
+            _ => *self.scan_error().unwrap().marker(),
+        }
+    }
+
+    /// The underlying `ScanError`, for all errors but `Io`.
+    pub fn scan_error(&self) -> Option<&ScanError> {
+        match *self {
+            LoadError::Scan(ref e)
+            | LoadError::Parse(ref e)
+            | LoadError::DuplicateKey(ref e)
+            | LoadError::Limit(ref e)
+            | LoadError::Decrypt(ref e)
+            | LoadError::Other(ref e) => Some(e),
+            LoadError::Io { .. } => None,
+        }
+    }
+}
+
+impl From<ScanError> for LoadError {
+    fn from(e: ScanError) -> LoadError {
+        match e.origin() {
+            Origin::Scanner => LoadError::Scan(e),
+            Origin::Parser => LoadError::Parse(e),
+            Origin::DuplicateKey => LoadError::DuplicateKey(e),
+            Origin::Limit => LoadError::Limit(e),
+            Origin::Decrypt => LoadError::Decrypt(e),
+            Origin::Other => LoadError::Other(e),
+        }
+    }
+}
+
+impl From<LoadError> for ScanError {
+    fn from(e: LoadError) -> ScanError {
+        match e {
+            LoadError::Scan(e)
+            | LoadError::Parse(e)
+            | LoadError::DuplicateKey(e)
+            | LoadError::Limit(e)
+            | LoadError::Decrypt(e)
+            | LoadError::Other(e) => e,
+            LoadError::Io { error, marker } => ScanError::new(marker, &error.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LoadError::Io {
+                ref error,
+                ref marker,
+            } => write!(
+                formatter,
+                "{} at line {} column {}",
+                error,
+                marker.line(),
+                marker.col() + 1
+            ),
+            _ => self.scan_error().unwrap().fmt(formatter),
+        }
+    }
+}
+
+impl Error for LoadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            LoadError::Io { ref error, .. } => Some(error),
+            _ => self.scan_error().map(|e| e as &(dyn Error + 'static)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use options::LoadOptions;
+    use strict_yaml::StrictYamlLoader;
+
+    fn load(source: &str, options: &LoadOptions) -> LoadError {
+        StrictYamlLoader::load_from_str_with_options(source, options)
+            .unwrap_err()
+            .into()
+    }
+
+    #[test]
+    fn test_kinds() {
+        let options = LoadOptions::new();
+        assert!(matches!(load("a: 'b", &options), LoadError::Scan(_)));
+        assert!(matches!(load("a: *b", &options), LoadError::Parse(_)));
+        let err = load("a: 1\nb: 2\na: 3", &options);
+        assert!(matches!(err, LoadError::DuplicateKey(_)));
+        assert_eq!(err.code(), "E001_DUPLICATE_KEY");
+        assert_eq!(err.source().unwrap().to_string(), err.to_string());
+        assert_eq!((err.marker().line(), err.marker().col()), (3, 3));
+        let options = LoadOptions::new().reject_control_chars(true);
+        assert!(matches!(load("a: b\0", &options), LoadError::Scan(_)));
+        let options = LoadOptions::new().indentation_step(2);
+        let err = load("a:\n   b: c", &options);
+        assert!(matches!(err, LoadError::Limit(_)));
+        assert_eq!(err.code(), code::LIMIT);
+        assert_eq!(
+            err.to_string(),
+            "indented by 3 spaces instead of 2 at line 2 column 4"
+        );
+        let err = LoadError::from(ScanError::new(Marker::new(0, 1, 0), "custom"));
+        assert!(matches!(err, LoadError::Other(_)));
+
+        let err = LoadError::Io {
+            error: io::Error::new(io::ErrorKind::BrokenPipe, "broken"),
+            marker: Marker::new(5, 2, 1),
+        };
+        assert_eq!(err.to_string(), "broken at line 2 column 2");
+        assert_eq!(err.code(), code::IO);
+        let source = err.source().unwrap().downcast_ref::<io::Error>();
+        assert_eq!(source.unwrap().kind(), io::ErrorKind::BrokenPipe);
+        let err = ScanError::from(err);
+        assert_eq!(err.to_string(), "broken at line 2 column 2");
+    }
+}
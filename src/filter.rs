@@ -0,0 +1,386 @@
+//! Composable transformations of event streams.
+//!
+//! An `EventFilter` sits between a `Parser` and a `MarkedEventReceiver` and may
+//! drop, rewrite or inject events. Filters are combined with `EventFilter::then`
+//! and attached to a receiver with `Pipeline`.
+//!
+//! ```
+//! use strict_yaml_rust::filter::{DropPaths, EventFilter, MapScalars};
+//! use strict_yaml_rust::{PathSegment, StrictYamlLoader, YamlPath};
+//!
+//! let secret = YamlPath::from(vec![PathSegment::Key("password".to_owned())]);
+//! let filter = DropPaths::new(vec![secret]).then(MapScalars::new(|_, v| v.to_uppercase()));
+//! let docs = StrictYamlLoader::load_from_str_with_filter("user: bob\npassword: hunter2", filter).unwrap();
+//! assert_eq!(docs[0]["user"].as_str(), Some("BOB"));
+//! assert!(docs[0]["password"].is_badvalue());
+//! ```
+
+use parser::{Event, MarkedEventReceiver};
+use path::{PathSegment, YamlPath};
+use scanner::{Marker, ScanError};
+
+/// A stage of an event pipeline.
+pub trait EventFilter {
+    /// Handles one event, passing any number of (possibly rewritten) events on to `out`.
+    fn filter(
+        &mut self,
+        ev: Event,
+        mark: Marker,
+        out: &mut dyn MarkedEventReceiver,
+    ) -> Result<(), ScanError>;
+
+    /// Returns a filter feeding the output of this one into `next`.
+    fn then<G: EventFilter>(self, next: G) -> Chain<Self, G>
+    where
+        Self: Sized,
+    {
+        Chain {
+            first: self,
+            second: next,
+        }
+    }
+}
+
+impl<F> EventFilter for F
+where
+    F: FnMut(Event, Marker, &mut dyn MarkedEventReceiver) -> Result<(), ScanError>,
+{
+    fn filter(
+        &mut self,
+        ev: Event,
+        mark: Marker,
+        out: &mut dyn MarkedEventReceiver,
+    ) -> Result<(), ScanError> {
+        self(ev, mark, out)
+    }
+}
+
+/// Two filters applied one after the other, see `EventFilter::then`.
+pub struct Chain<A, B> {
+    first: A,
+    second: B,
+}
+
+impl<A: EventFilter, B: EventFilter> EventFilter for Chain<A, B> {
+    fn filter(
+        &mut self,
+        ev: Event,
+        mark: Marker,
+        out: &mut dyn MarkedEventReceiver,
+    ) -> Result<(), ScanError> {
+        let mut next = Forward {
+            filter: &mut self.second,
+            out,
+        };
+        self.first.filter(ev, mark, &mut next)
+    }
+}
+
+struct Forward<'a, F: 'a> {
+    filter: &'a mut F,
+    out: &'a mut dyn MarkedEventReceiver,
+}
+
+impl<'a, F: EventFilter> MarkedEventReceiver for Forward<'a, F> {
+    fn on_event(&mut self, ev: Event, mark: Marker) -> Result<(), ScanError> {
+        self.filter.filter(ev, mark, self.out)
+    }
+}
+
+/// A receiver passing every event through `filter` before handing it to `sink`.
+pub struct Pipeline<'a, F, R: 'a> {
+    filter: F,
+    sink: &'a mut R,
+}
+
+impl<'a, F: EventFilter, R: MarkedEventReceiver> Pipeline<'a, F, R> {
+    pub fn new(filter: F, sink: &'a mut R) -> Pipeline<'a, F, R> {
+        Pipeline { filter, sink }
+    }
+}
+
+impl<'a, F: EventFilter, R: MarkedEventReceiver> MarkedEventReceiver for Pipeline<'a, F, R> {
+    fn on_event(&mut self, ev: Event, mark: Marker) -> Result<(), ScanError> {
+        self.filter.filter(ev, mark, self.sink)
+    }
+}
+
+enum Frame {
+    /// Index of the next item.
+    Sequence(usize),
+    /// Whether the key of the current entry has been seen.
+    Mapping(bool),
+}
+
+/// Keeps track of the location of the events of a stream, for filters that need it.
+///
+/// Call `update` with every event, in order; `path` and `is_key` then describe
+/// that event. Collection end events have the path of the collection. Entries
+/// with a complex (collection) key get an empty key segment.
+#[derive(Default)]
+pub struct PathTracker {
+    frames: Vec<Frame>,
+    path: YamlPath,
+    /// Nesting depth inside a complex key.
+    key_depth: usize,
+    is_key: bool,
+    /// Whether the previous event completed a node.
+    leave: bool,
+}
+
+impl PathTracker {
+    pub fn new() -> PathTracker {
+        PathTracker::default()
+    }
+
+    pub fn update(&mut self, ev: &Event) {
+        if self.leave {
+            self.leave = false;
+            match self.frames.last_mut() {
+                Some(&mut Frame::Sequence(ref mut index)) => {
+                    *index += 1;
+                    self.path.pop();
+                }
+                Some(&mut Frame::Mapping(ref mut has_key)) => {
+                    *has_key = false;
+                    self.path.pop();
+                }
+                None => {}
+            }
+        }
+
+        if self.key_depth > 0 {
+            match *ev {
+                Event::SequenceStart(_) | Event::MappingStart(_) => self.key_depth += 1,
+                Event::SequenceEnd | Event::MappingEnd => {
+                    self.key_depth -= 1;
+                    if self.key_depth == 0 {
+                        self.path.push(PathSegment::Key(String::new()));
+                        if let Some(&mut Frame::Mapping(ref mut has_key)) = self.frames.last_mut() {
+                            *has_key = true;
+                        }
+                    }
+                }
+                _ => {}
+            }
+            return;
+        }
+
+        self.is_key = false;
+        match *ev {
+            Event::Scalar(..) | Event::SequenceStart(_) | Event::MappingStart(_) => {
+                match self.frames.last_mut() {
+                    Some(&mut Frame::Sequence(index)) => self.path.push(PathSegment::Index(index)),
+                    Some(&mut Frame::Mapping(ref mut has_key)) if !*has_key => {
+                        self.is_key = true;
+                        if let Event::Scalar(ref key, ..) = *ev {
+                            self.path.push(PathSegment::Key(key.clone()));
+                            *has_key = true;
+                        } else {
+                            self.key_depth = 1;
+                        }
+                        return;
+                    }
+                    _ => {}
+                }
+                match *ev {
+                    Event::SequenceStart(_) => self.frames.push(Frame::Sequence(0)),
+                    Event::MappingStart(_) => self.frames.push(Frame::Mapping(false)),
+                    _ => self.leave = true,
+                }
+            }
+            Event::SequenceEnd | Event::MappingEnd => {
+                self.frames.pop();
+                self.leave = true;
+            }
+            _ => {
+                self.frames.clear();
+                self.path = YamlPath::new();
+            }
+        }
+    }
+
+    /// Path of the node the last event belongs to. For a mapping key, the path
+    /// of the value it introduces.
+    pub fn path(&self) -> &YamlPath {
+        &self.path
+    }
+
+    /// Whether the last event is (part of) a mapping key.
+    pub fn is_key(&self) -> bool {
+        self.is_key || self.key_depth > 0
+    }
+}
+
+/// Removes the nodes at the given paths. Mapping entries are removed along with
+/// their key.
+pub struct DropPaths {
+    paths: Vec<YamlPath>,
+    tracker: PathTracker,
+    /// Nesting depth inside the node being dropped, if any.
+    dropping: Option<usize>,
+}
+
+impl DropPaths {
+    pub fn new(paths: Vec<YamlPath>) -> DropPaths {
+        DropPaths {
+            paths,
+            tracker: PathTracker::new(),
+            dropping: None,
+        }
+    }
+}
+
+impl EventFilter for DropPaths {
+    fn filter(
+        &mut self,
+        ev: Event,
+        mark: Marker,
+        out: &mut dyn MarkedEventReceiver,
+    ) -> Result<(), ScanError> {
+        self.tracker.update(&ev);
+        if self.dropping.is_none() && self.paths.contains(self.tracker.path()) {
+            match ev {
+                // drop the key now and the value next
+                Event::Scalar(..) if self.tracker.is_key() => {
+                    self.dropping = Some(0);
+                    return Ok(());
+                }
+                Event::Scalar(..) | Event::SequenceStart(_) | Event::MappingStart(_) => {
+                    self.dropping = Some(0);
+                }
+                _ => {}
+            }
+        }
+        let depth = match self.dropping {
+            Some(depth) => depth,
+            None => return out.on_event(ev, mark),
+        };
+        self.dropping = match ev {
+            Event::SequenceStart(_) | Event::MappingStart(_) => Some(depth + 1),
+            Event::SequenceEnd | Event::MappingEnd if depth > 1 => Some(depth - 1),
+            Event::Scalar(..) if depth > 0 => Some(depth),
+            _ => None,
+        };
+        Ok(())
+    }
+}
+
+/// Rewrites the value scalars (not the keys) with a function of their path and value.
+pub struct MapScalars<F> {
+    f: F,
+    tracker: PathTracker,
+}
+
+impl<F: FnMut(&YamlPath, String) -> String> MapScalars<F> {
+    pub fn new(f: F) -> MapScalars<F> {
+        MapScalars {
+            f,
+            tracker: PathTracker::new(),
+        }
+    }
+}
+
+impl<F: FnMut(&YamlPath, String) -> String> EventFilter for MapScalars<F> {
+    fn filter(
+        &mut self,
+        ev: Event,
+        mark: Marker,
+        out: &mut dyn MarkedEventReceiver,
+    ) -> Result<(), ScanError> {
+        self.tracker.update(&ev);
+        match ev {
+            Event::Scalar(value, style, aid) if !self.tracker.is_key() => {
+                let value = (self.f)(self.tracker.path(), value);
+                out.on_event(Event::Scalar(value, style, aid), mark)
+            }
+            ev => out.on_event(ev, mark),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use parser::Parser;
+    use scanner::TScalarStyle;
+    use strict_yaml::StrictYamlLoader;
+
+    fn path(s: &str) -> YamlPath {
+        YamlPath::from(
+            s.split('.')
+                .map(|seg| match seg.parse() {
+                    Ok(i) => PathSegment::Index(i),
+                    Err(_) => PathSegment::Key(seg.to_owned()),
+                })
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    #[test]
+    fn test_path_tracker() {
+        let mut parser = Parser::new("a: [x]\nb:\n  - c: d\n  - e\n? - k\n: v\n".chars());
+        let mut tracker = PathTracker::new();
+        let mut seen = Vec::new();
+        loop {
+            let (ev, _) = parser.next().unwrap();
+            tracker.update(&ev);
+            match ev {
+                Event::StreamEnd => break,
+                Event::Scalar(v, ..) => {
+                    seen.push((tracker.path().to_string(), v, tracker.is_key()))
+                }
+                _ => {}
+            }
+        }
+        let expected = vec![
+            ("a", "a", true),
+            ("a", "[x]", false),
+            ("b", "b", true),
+            ("b[0].c", "c", true),
+            ("b[0].c", "d", false),
+            ("b[1]", "e", false),
+            ("", "k", true),
+            ("", "v", false),
+        ];
+        let expected: Vec<(String, String, bool)> = expected
+            .into_iter()
+            .map(|(p, v, k)| (p.to_owned(), v.to_owned(), k))
+            .collect();
+        assert_eq!(seen, expected);
+    }
+
+    #[test]
+    fn test_drop_paths() {
+        let s = "a: 1\nb:\n  - x\n  - c: 2\n    d:\n      - 3\n  - y\ne: 4\n";
+        let filter = DropPaths::new(vec![path("a"), path("b.1.d"), path("b.2")]);
+        let docs = StrictYamlLoader::load_from_str_with_filter(s, filter).unwrap();
+        let expected = StrictYamlLoader::load_from_str("b:\n  - x\n  - c: 2\ne: 4\n").unwrap();
+        assert_eq!(docs, expected);
+    }
+
+    #[test]
+    fn test_chain_and_closure() {
+        let inject = |ev: Event, mark: Marker, out: &mut dyn MarkedEventReceiver| {
+            if ev == Event::MappingEnd {
+                // a default for a missing key, assuming a flat document
+                let scalar = |s: &str| Event::Scalar(s.to_owned(), TScalarStyle::Plain, 0);
+                out.on_event(scalar("port"), mark)?;
+                out.on_event(scalar("80"), mark)?;
+            }
+            out.on_event(ev, mark)
+        };
+        let filter = MapScalars::new(|p: &YamlPath, v: String| {
+            if p.to_string() == "host" {
+                v.replace("old", "new")
+            } else {
+                v
+            }
+        })
+        .then(inject);
+        let docs =
+            StrictYamlLoader::load_from_str_with_filter("host: old.example", filter).unwrap();
+        let expected = StrictYamlLoader::load_from_str("host: new.example\nport: 80").unwrap();
+        assert_eq!(docs, expected);
+    }
+}
@@ -0,0 +1,213 @@
+//! Layering configuration documents, such as defaults, then a site file, then
+//! local overrides.
+//!
+//! Mappings are merged key by key, at any depth; any other node of a later
+//! layer replaces the node of the earlier layers.
+//!
+//! ```
+//! use strict_yaml_rust::layer::Layers;
+//! use strict_yaml_rust::StrictYamlLoader;
+//!
+//! let defaults = StrictYamlLoader::load_from_str("db:\n  host: localhost\n  port: 5432").unwrap();
+//! let site = StrictYamlLoader::load_from_str("db:\n  host: db.example.com").unwrap();
+//! let (config, conflicts) = Layers::new()
+//!     .report_conflicts(true)
+//!     .layer("defaults.yaml", defaults[0].clone())
+//!     .layer("site.yaml", site[0].clone())
+//!     .merge();
+//! assert_eq!(config["db"]["host"].as_str(), Some("db.example.com"));
+//! assert_eq!(config["db"]["port"].as_str(), Some("5432"));
+//! assert_eq!(
+//!     conflicts[0].to_string(),
+//!     "db.host: \"localhost\" from defaults.yaml overridden by \"db.example.com\" from site.yaml"
+//! );
+//! ```
+
+use path::{PathSegment, YamlPath};
+use std::collections::HashMap;
+use std::fmt;
+use strict_yaml::StrictYaml;
+
+/// A scalar of an earlier layer overridden by a different scalar.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Conflict {
+    pub path: YamlPath,
+    pub old_value: StrictYaml,
+    /// Name of the layer the old value came from.
+    pub old_source: String,
+    pub new_value: StrictYaml,
+    /// Name of the layer the new value came from.
+    pub new_source: String,
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let value = |v: &StrictYaml| match *v {
+            StrictYaml::String(ref s) => format!("{:?}", s),
+            ref v => format!("{:?}", v),
+        };
+        write!(
+            f,
+            "{}: {} from {} overridden by {} from {}",
+            self.path,
+            value(&self.old_value),
+            self.old_source,
+            value(&self.new_value),
+            self.new_source
+        )
+    }
+}
+
+/// Documents to merge, from the lowest to the highest precedence.
+#[derive(Clone, Debug, Default)]
+pub struct Layers {
+    layers: Vec<(String, StrictYaml)>,
+    report_conflicts: bool,
+}
+
+impl Layers {
+    pub fn new() -> Layers {
+        Layers::default()
+    }
+
+    /// Adds `doc` over the previous layers, `source` naming it in conflicts.
+    pub fn layer(mut self, source: &str, doc: StrictYaml) -> Layers {
+        self.layers.push((source.to_owned(), doc));
+        self
+    }
+
+    /// Reports the scalars overridden by a different value, which are
+    /// otherwise replaced silently.
+    pub fn report_conflicts(mut self, report: bool) -> Layers {
+        self.report_conflicts = report;
+        self
+    }
+
+    /// Merges the layers, returning the result and the conflicts in the order
+    /// they were found.
+    pub fn merge(&self) -> (StrictYaml, Vec<Conflict>) {
+        let mut merge = Merge {
+            layers: &self.layers,
+            report_conflicts: self.report_conflicts,
+            origins: HashMap::new(),
+            conflicts: Vec::new(),
+        };
+        let mut result = StrictYaml::BadValue;
+        for (i, (_, doc)) in self.layers.iter().enumerate() {
+            merge.merge(&mut result, doc, &mut YamlPath::new(), i);
+        }
+        (result, merge.conflicts)
+    }
+}
+
+struct Merge<'a> {
+    layers: &'a [(String, StrictYaml)],
+    report_conflicts: bool,
+    /// The layer each scalar of the result comes from.
+    origins: HashMap<YamlPath, usize>,
+    conflicts: Vec<Conflict>,
+}
+
+impl<'a> Merge<'a> {
+    fn merge(
+        &mut self,
+        base: &mut StrictYaml,
+        over: &StrictYaml,
+        path: &mut YamlPath,
+        layer: usize,
+    ) {
+        match (base, over) {
+            (StrictYaml::Hash(base), StrictYaml::Hash(over)) => {
+                for (k, v) in over {
+                    path.push(PathSegment::Key(k.as_str().unwrap_or_default().to_owned()));
+                    match base.get_mut(k) {
+                        Some(b) => self.merge(b, v, path, layer),
+                        None => {
+                            self.record(v, path, layer);
+                            base.insert(k.clone(), v.clone());
+                        }
+                    }
+                    path.pop();
+                }
+            }
+            (base, over) => {
+                let scalars = matches!(
+                    (&*base, over),
+                    (&StrictYaml::String(_), &StrictYaml::String(_))
+                );
+                if self.report_conflicts && scalars && *base != *over {
+                    let old = self.origins[path];
+                    self.conflicts.push(Conflict {
+                        path: path.clone(),
+                        old_value: base.clone(),
+                        old_source: self.layers[old].0.clone(),
+                        new_value: over.clone(),
+                        new_source: self.layers[layer].0.clone(),
+                    });
+                }
+                self.record(over, path, layer);
+                *base = over.clone();
+            }
+        }
+    }
+
+    /// Records `layer` as the origin of the scalars of `node`.
+    fn record(&mut self, node: &StrictYaml, path: &mut YamlPath, layer: usize) {
+        if !self.report_conflicts {
+            return;
+        }
+        match *node {
+            StrictYaml::String(_) => {
+                self.origins.insert(path.clone(), layer);
+            }
+            StrictYaml::Hash(ref h) => {
+                for (k, v) in h {
+                    path.push(PathSegment::Key(k.as_str().unwrap_or_default().to_owned()));
+                    self.record(v, path, layer);
+                    path.pop();
+                }
+            }
+            StrictYaml::Array(ref a) => {
+                for (i, v) in a.iter().enumerate() {
+                    path.push(PathSegment::Index(i));
+                    self.record(v, path, layer);
+                    path.pop();
+                }
+            }
+            StrictYaml::BadValue => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use strict_yaml::StrictYamlLoader;
+
+    fn load(s: &str) -> StrictYaml {
+        StrictYamlLoader::load_from_str(s).unwrap().remove(0)
+    }
+
+    #[test]
+    fn test_merge() {
+        let layers = Layers::new()
+            .layer("a", load("x: 1\ny:\n  z: 2\n  w:\n    - 3\nv: 4"))
+            .layer("b", load("y:\n  z: 5\n  w:\n    - 6\n    - 7\nu: 8\nv: 4"))
+            .layer("c", load("y:\n  z: 9\n  w: 10\nx:\n  t: 11"));
+        let expected = load("x:\n  t: 11\ny:\n  z: 9\n  w: 10\nv: 4\nu: 8");
+        let (merged, conflicts) = layers.merge();
+        assert_eq!(merged, expected);
+        assert!(conflicts.is_empty());
+
+        let (merged, conflicts) = layers.report_conflicts(true).merge();
+        assert_eq!(merged, expected);
+        let conflicts: Vec<String> = conflicts.iter().map(|c| c.to_string()).collect();
+        assert_eq!(
+            conflicts,
+            vec![
+                "y.z: \"2\" from a overridden by \"5\" from b",
+                "y.z: \"5\" from b overridden by \"9\" from c",
+            ]
+        );
+    }
+}
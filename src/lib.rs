@@ -37,24 +37,81 @@
 //! ```
 
 #![doc(html_root_url = "https://docs.rs/strict-yaml-rust/0.1.0")]
-#![cfg_attr(feature = "cargo-clippy", allow(renamed_and_removed_lints))]
-#![cfg_attr(
-    feature = "cargo-clippy",
-    allow(match_same_arms, should_implement_trait)
-)]
+#![allow(clippy::match_same_arms, clippy::should_implement_trait)]
 
+#[cfg(feature = "futures-io")]
+extern crate futures_io;
+#[cfg(feature = "icu_collator")]
+extern crate icu_collator;
 extern crate linked_hash_map;
+#[cfg(feature = "miette")]
+extern crate miette;
+#[cfg(feature = "notify")]
+extern crate notify;
+#[cfg(feature = "regex")]
+extern crate regex;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(feature = "tracing")]
+extern crate tracing;
+#[cfg(feature = "unicode-normalization")]
+extern crate unicode_normalization;
 
+#[cfg(feature = "futures-io")]
+pub mod async_read;
+#[cfg(feature = "futures-io")]
+pub mod async_write;
+pub mod canonical;
+pub mod comments;
+pub mod convert;
+pub mod cst;
+#[cfg(feature = "serde")]
+pub mod de;
+#[cfg(feature = "miette")]
+mod diagnostic;
+pub mod document;
+pub mod dot;
 pub mod emitter;
+pub mod error;
+pub mod filter;
+pub mod layer;
+pub mod marked;
+pub mod options;
 pub mod parser;
+pub mod path;
+pub mod push;
+pub mod query;
 pub mod scanner;
+pub mod schema;
+pub mod secret;
+#[cfg(feature = "serde")]
+pub mod ser;
+pub mod skim;
+pub mod source_map;
 pub mod strict_yaml;
+pub mod testing;
+pub mod transcode;
+#[cfg(feature = "typed")]
+pub mod typed;
+pub mod visit;
+#[cfg(feature = "notify")]
+pub mod watch;
 
 // reexport key APIs
-pub use emitter::{EmitError, StrictYamlEmitter};
-pub use parser::Event;
-pub use scanner::ScanError;
-pub use strict_yaml::{StrictYaml, StrictYamlLoader};
+pub use document::StrictYamlDocument;
+pub use emitter::{EmitError, EmitterOptions, IoWriter, StrictYamlEmitter};
+pub use error::LoadError;
+#[cfg(feature = "unicode-normalization")]
+pub use options::Normalization;
+pub use options::{
+    CancellationToken, DocumentMetrics, LineBreaks, LoadOptions, Progress, TrailingWhitespace,
+    Warning,
+};
+pub use parser::{Directive, Event};
+pub use path::{PathPattern, PathSegment, YamlPath};
+pub use push::PushParser;
+pub use scanner::{InvalidSequence, ScanError};
+pub use strict_yaml::{FileError, StrictYaml, StrictYamlLoader};
 
 #[cfg(test)]
 mod tests {
@@ -0,0 +1,395 @@
+//! Nodes carrying their position in the source, for reporting where a value
+//! came from once the document is loaded.
+//!
+//! ```
+//! use strict_yaml_rust::marked::MarkedStrictYaml;
+//!
+//! let docs = MarkedStrictYaml::load_from_str("name: demo\nports:\n  - 80\n  - http").unwrap();
+//! let port = docs[0].get("ports").and_then(|p| p.at(1)).unwrap();
+//! assert_eq!(port.as_str(), Some("http"));
+//! assert_eq!((port.marker().line(), port.marker().col()), (4, 4));
+//! let key = docs[0].key("ports").unwrap();
+//! assert_eq!((key.marker().line(), key.marker().col()), (2, 0));
+//! ```
+//!
+//! Nodes also know where they end, for the text of a whole subtree:
+//!
+//! ```
+//! use strict_yaml_rust::marked::MarkedStrictYaml;
+//!
+//! let source = "db:\n  host: 'localhost'  # local\n  port: 5432\nname: demo\n";
+//! let docs = MarkedStrictYaml::load_from_str(source).unwrap();
+//! let db = docs[0].get("db").unwrap();
+//! assert_eq!(db.source_slice(source), "host: 'localhost'  # local\n  port: 5432");
+//! assert_eq!(db.get("host").unwrap().source_slice(source), "'localhost'");
+//! ```
+
+use options::LoadOptions;
+use parser::{Event, Parser, SpannedEventReceiver};
+use scanner::{Marker, ScanError, Span, TScalarStyle};
+use std::collections::HashSet;
+use strict_yaml::{Hash, StrictYaml, StrictYamlLoader};
+
+/// The content of a `MarkedStrictYaml`, mirroring `StrictYaml`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum MarkedNode {
+    String(String),
+    Array(Vec<MarkedStrictYaml>),
+    /// The entries of a mapping, in document order.
+    Hash(Vec<(MarkedStrictYaml, MarkedStrictYaml)>),
+    /// A document without any node.
+    BadValue,
+}
+
+/// A node with the position where it starts: the first character of a
+/// scalar, the `-` of the first item of a block sequence, or the first key of
+/// a block mapping. The empty value of `key:` takes the position of its key.
+///
+/// The span of a node goes from there to the end of its last character, or of
+/// the last scalar of a collection.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct MarkedStrictYaml {
+    node: MarkedNode,
+    span: Span,
+}
+
+impl MarkedStrictYaml {
+    /// A node with an empty span at `marker`.
+    pub fn new(node: MarkedNode, marker: Marker) -> MarkedStrictYaml {
+        MarkedStrictYaml::with_span(node, Span::empty(marker))
+    }
+
+    pub fn with_span(node: MarkedNode, span: Span) -> MarkedStrictYaml {
+        MarkedStrictYaml { node, span }
+    }
+
+    /// Loads all the documents of `source`, like `StrictYamlLoader::load_from_str`.
+    pub fn load_from_str(source: &str) -> Result<Vec<MarkedStrictYaml>, ScanError> {
+        let mut loader = MarkedLoader::default();
+        Parser::new(source.chars()).load_spanned(&mut loader, true)?;
+        Ok(loader.docs)
+    }
+
+    /// Like `load_from_str`, applying `options` while loading.
+    pub fn load_from_str_with_options(
+        source: &str,
+        options: &LoadOptions,
+    ) -> Result<Vec<MarkedStrictYaml>, ScanError> {
+        let mut loader = MarkedLoader::default();
+        StrictYamlLoader::load_with_options(source, options, &mut loader)?;
+        Ok(loader.docs)
+    }
+
+    pub fn node(&self) -> &MarkedNode {
+        &self.node
+    }
+
+    pub fn into_node(self) -> MarkedNode {
+        self.node
+    }
+
+    pub fn marker(&self) -> Marker {
+        self.span.start()
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// The line where the node starts, from 1. A section `name:` with its
+    /// entries below starts at the line of its first entry, and its key at
+    /// the line of the name.
+    pub fn start_line(&self) -> usize {
+        self.span.start().line()
+    }
+
+    /// The line of the last character of the node, from 1.
+    pub fn end_line(&self) -> usize {
+        self.span.end().line()
+    }
+
+    /// The text of the node in `source`, the text it was loaded from, as
+    /// written: with the quotes of a scalar, and the comments and line breaks
+    /// inside a collection.
+    pub fn source_slice<'s>(&self, source: &'s str) -> &'s str {
+        // markers count characters
+        let byte = |mark: Marker| {
+            source
+                .char_indices()
+                .nth(mark.index())
+                .map_or(source.len(), |(i, _)| i)
+        };
+        &source[byte(self.span.start())..byte(self.span.end())]
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self.node {
+            MarkedNode::String(ref s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// The item at `index` of a sequence.
+    pub fn at(&self, index: usize) -> Option<&MarkedStrictYaml> {
+        match self.node {
+            MarkedNode::Array(ref v) => v.get(index),
+            _ => None,
+        }
+    }
+
+    /// The value of `key` in a mapping.
+    pub fn get(&self, key: &str) -> Option<&MarkedStrictYaml> {
+        self.entry(key).map(|(_, v)| v)
+    }
+
+    /// The key node equal to `key` in a mapping, for the position of the key
+    /// rather than of its value.
+    pub fn key(&self, key: &str) -> Option<&MarkedStrictYaml> {
+        self.entry(key).map(|(k, _)| k)
+    }
+
+    fn entry(&self, key: &str) -> Option<(&MarkedStrictYaml, &MarkedStrictYaml)> {
+        match self.node {
+            MarkedNode::Hash(ref entries) => entries
+                .iter()
+                .find(|(k, _)| k.as_str() == Some(key))
+                .map(|(k, v)| (k, v)),
+            _ => None,
+        }
+    }
+
+    /// The node without its positions.
+    pub fn to_strict_yaml(&self) -> StrictYaml {
+        match self.node {
+            MarkedNode::String(ref s) => StrictYaml::String(s.clone()),
+            MarkedNode::Array(ref v) => {
+                StrictYaml::Array(v.iter().map(MarkedStrictYaml::to_strict_yaml).collect())
+            }
+            MarkedNode::Hash(ref entries) => {
+                let mut h = Hash::new();
+                for (k, v) in entries {
+                    h.insert(k.to_strict_yaml(), v.to_strict_yaml());
+                }
+                StrictYaml::Hash(h)
+            }
+            MarkedNode::BadValue => StrictYaml::BadValue,
+        }
+    }
+}
+
+impl From<MarkedStrictYaml> for StrictYaml {
+    fn from(node: MarkedStrictYaml) -> StrictYaml {
+        node.to_strict_yaml()
+    }
+}
+
+/// Builds `MarkedStrictYaml` documents, like `StrictYamlLoader` builds
+/// `StrictYaml` ones.
+#[derive(Default)]
+struct MarkedLoader {
+    docs: Vec<MarkedStrictYaml>,
+    /// Number of documents before the current one.
+    doc_start: usize,
+    stack: Vec<Collection>,
+}
+
+/// A collection being built.
+struct Collection {
+    node: MarkedStrictYaml,
+    /// The key waiting for its value, in a mapping.
+    key: Option<MarkedStrictYaml>,
+    /// The keys of a mapping so far, to find repeated ones.
+    keys: HashSet<StrictYaml>,
+}
+
+impl MarkedLoader {
+    fn open(&mut self, node: MarkedNode, mark: Marker) {
+        self.stack.push(Collection {
+            node: MarkedStrictYaml::new(node, mark),
+            key: None,
+            keys: HashSet::new(),
+        });
+    }
+
+    fn close(&mut self, mark: Marker) -> Result<(), ScanError> {
+        match self.stack.pop() {
+            Some(Collection {
+                node, key: None, ..
+            }) => self.insert(node, mark),
+            _ => Err(unexpected(mark)),
+        }
+    }
+
+    fn insert(&mut self, node: MarkedStrictYaml, mark: Marker) -> Result<(), ScanError> {
+        let top = match self.stack.last_mut() {
+            Some(top) => {
+                if node.span.end().index() > top.node.span.end().index() {
+                    top.node.span = Span::new(top.node.span.start(), node.span.end());
+                }
+                top
+            }
+            None if self.docs.len() == self.doc_start => {
+                self.docs.push(node);
+                return Ok(());
+            }
+            // a second root node
+            None => return Err(unexpected(mark)),
+        };
+        match top.node.node {
+            MarkedNode::Array(ref mut v) => v.push(node),
+            MarkedNode::Hash(ref mut entries) => match top.key.take() {
+                None => {
+                    // the start of a mapping is marked past its first key,
+                    // so take the position of the key
+                    if entries.is_empty() {
+                        top.node.span = node.span;
+                    }
+                    if !top.keys.insert(node.to_strict_yaml()) {
+                        return Err(ScanError::duplicate_key(
+                            mark,
+                            "Error handling node: Key already exists in the hash map",
+                        ));
+                    }
+                    top.key = Some(node);
+                }
+                Some(key) => entries.push((key, node)),
+            },
+            _ => return Err(unexpected(mark)),
+        }
+        Ok(())
+    }
+}
+
+fn unexpected(mark: Marker) -> ScanError {
+    ScanError::parser(mark, "Error handling node: Unexpected event")
+}
+
+impl SpannedEventReceiver for MarkedLoader {
+    fn on_spanned_event(&mut self, ev: Event, span: Span) -> Result<(), ScanError> {
+        let mark = span.start();
+        match ev {
+            Event::DocumentStart => {
+                self.doc_start = self.docs.len();
+                Ok(())
+            }
+            Event::DocumentEnd => {
+                if !self.stack.is_empty() {
+                    return Err(unexpected(mark));
+                }
+                if self.docs.len() == self.doc_start {
+                    self.docs
+                        .push(MarkedStrictYaml::new(MarkedNode::BadValue, mark));
+                }
+                Ok(())
+            }
+            Event::SequenceStart(_) => {
+                self.open(MarkedNode::Array(Vec::new()), mark);
+                Ok(())
+            }
+            Event::MappingStart(_) => {
+                self.open(MarkedNode::Hash(Vec::new()), mark);
+                Ok(())
+            }
+            Event::SequenceEnd | Event::MappingEnd => self.close(mark),
+            Event::Scalar(v, style, _) => {
+                let span = match self.stack.last() {
+                    // nothing is written for the value of `key:`, which is
+                    // marked at whatever follows, so take the position of
+                    // the key
+                    Some(Collection { key: Some(key), .. })
+                        if v.is_empty() && style == TScalarStyle::Plain =>
+                    {
+                        Span::empty(key.marker())
+                    }
+                    _ => span,
+                };
+                let node = MarkedStrictYaml::with_span(MarkedNode::String(v), span);
+                self.insert(node, mark)
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn position(node: &MarkedStrictYaml) -> (usize, usize) {
+        (node.marker().line(), node.marker().col())
+    }
+
+    #[test]
+    fn test_markers() {
+        let source = "a:\n  - x\n  - y: z\n    w: v\nb:   c\ne:\n---\n---\nd\n";
+        let docs = MarkedStrictYaml::load_from_str(source).unwrap();
+        assert_eq!(docs.len(), 3);
+        let doc = &docs[0];
+        assert_eq!(position(doc), (1, 0));
+        let a = doc.get("a").unwrap();
+        assert_eq!(position(a), (2, 2));
+        assert_eq!(position(a.at(0).unwrap()), (2, 4));
+        let y = a.at(1).unwrap();
+        assert_eq!(position(y), (3, 4));
+        assert_eq!(position(y.key("w").unwrap()), (4, 4));
+        assert_eq!(position(y.get("w").unwrap()), (4, 7));
+        assert_eq!(position(doc.key("b").unwrap()), (5, 0));
+        assert_eq!(position(doc.get("b").unwrap()), (5, 5));
+        assert_eq!(position(doc.get("e").unwrap()), (6, 0));
+        assert_eq!(docs[1].as_str(), Some(""));
+        assert_eq!(docs[2].as_str(), Some("d"));
+
+        let plain = StrictYamlLoader::load_from_str(source).unwrap();
+        let stripped: Vec<StrictYaml> = docs.into_iter().map(StrictYaml::from).collect();
+        assert_eq!(stripped, plain);
+    }
+
+    #[test]
+    fn test_lines() {
+        let source =
+            "name: demo\n\ndatabase:\n  host: localhost\n  options: |\n    a\n    b\n\nport: 80\n";
+        let doc = MarkedStrictYaml::load_from_str(source).unwrap().remove(0);
+        let lines = |node: &MarkedStrictYaml| (node.start_line(), node.end_line());
+        assert_eq!(lines(doc.get("database").unwrap()), (4, 7));
+        assert_eq!(
+            lines(doc.get("database").unwrap().get("options").unwrap()),
+            (6, 7)
+        );
+        assert_eq!(lines(doc.get("name").unwrap()), (1, 1));
+        assert_eq!(lines(&doc), (1, 9));
+    }
+
+    #[test]
+    fn test_source_slice() {
+        let source = "a:\n  - \"ü\"\n  - b: |\n      text\n\n    c:\nd: e # f\n";
+        let docs = MarkedStrictYaml::load_from_str(source).unwrap();
+        let doc = &docs[0];
+        let a = doc.get("a").unwrap();
+        assert_eq!(a.at(0).unwrap().source_slice(source), "\"ü\"");
+        assert_eq!(
+            a.source_slice(source),
+            "- \"ü\"\n  - b: |\n      text\n\n    c"
+        );
+        let b = a.at(1).unwrap();
+        assert_eq!(b.get("b").unwrap().source_slice(source), "text");
+        assert_eq!(b.get("c").unwrap().source_slice(source), "");
+        assert_eq!(doc.get("d").unwrap().source_slice(source), "e");
+        assert_eq!(doc.source_slice(source), source.trim_end_matches(" # f\n"));
+
+        let options = LoadOptions::new();
+        let docs = MarkedStrictYaml::load_from_str_with_options(source, &options).unwrap();
+        assert_eq!(docs, MarkedStrictYaml::load_from_str(source).unwrap());
+    }
+
+    #[test]
+    fn test_errors() {
+        let err = MarkedStrictYaml::load_from_str("a: 1\nb: 2\na: 3").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Error handling node: Key already exists in the hash map at line 3 column 1"
+        );
+        let options = LoadOptions::new().indentation_step(2);
+        let err = MarkedStrictYaml::load_from_str_with_options("a:\n   b: c", &options);
+        assert!(err.is_err());
+    }
+}
@@ -0,0 +1,1701 @@
+//! Options controlling how documents are loaded.
+
+use error::LoadError;
+#[cfg(feature = "unicode-normalization")]
+use filter::PathTracker;
+use parser::{leading_anchor, Event, Parser, SpannedEventReceiver};
+use scanner::{InvalidSequence, Marker, ScanError, Span, TScalarStyle};
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::fmt;
+use std::io;
+use std::iter::Peekable;
+use std::str;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+#[cfg(feature = "unicode-normalization")]
+use unicode_normalization::{is_nfc, UnicodeNormalization};
+
+/// Number of events between two calls of the progress hook.
+const PROGRESS_INTERVAL: usize = 1024;
+
+/// A flag that can be raised from another thread to abort a load in progress.
+///
+/// Clones share the same flag.
+///
+/// ```
+/// use strict_yaml_rust::{CancellationToken, LoadOptions, StrictYamlLoader};
+///
+/// let token = CancellationToken::new();
+/// let options = LoadOptions::new().cancellation(token.clone());
+/// token.cancel(); // e.g. from a watchdog thread once a deadline has passed
+/// assert!(StrictYamlLoader::load_from_str_with_options("a: b", &options).is_err());
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> CancellationToken {
+        CancellationToken::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// How far loading has gone, as reported to the progress hook.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Progress {
+    /// Bytes of input read so far.
+    pub bytes: usize,
+    /// Parser events produced so far.
+    pub events: usize,
+}
+
+/// What loading a document took, as reported to the metrics hook.
+///
+/// The duration and bytes run from the end of the previous document, so they
+/// add up over the stream.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct DocumentMetrics {
+    /// Time spent loading the document.
+    pub duration: Duration,
+    /// Bytes of input read for the document.
+    pub bytes: usize,
+    /// Parser events of the document, from its start to its end.
+    pub events: usize,
+    pub scalars: usize,
+    pub sequences: usize,
+    pub mappings: usize,
+}
+
+/// A problem with the input that did not prevent it from being loaded.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Warning {
+    mark: Marker,
+    info: String,
+}
+
+impl Warning {
+    pub fn new(mark: Marker, info: &str) -> Warning {
+        Warning {
+            mark,
+            info: info.to_owned(),
+        }
+    }
+
+    pub fn marker(&self) -> &Marker {
+        &self.mark
+    }
+
+    pub fn info(&self) -> &str {
+        &self.info
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            formatter,
+            "{} at line {} column {}",
+            self.info,
+            self.mark.line(),
+            self.mark.col() + 1
+        )
+    }
+}
+
+/// How line breaks other than a consistent use of `\n` or `\r\n` are handled.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum LineBreaks {
+    /// Bare `\r` line breaks are read as `\n`, and files mixing several kinds of
+    /// line breaks are accepted. Both cases are reported as warnings.
+    #[default]
+    Normalize,
+    /// Bare `\r` line breaks and mixed line breaks are errors.
+    Reject,
+}
+
+/// How spaces and tabs ending a plain scalar, before a line break or the end
+/// of the input, are handled. They are invisible in most editors and usually
+/// a mistake.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum TrailingWhitespace {
+    /// Dropped silently, as YAML does.
+    #[default]
+    Ignore,
+    /// Dropped, with a warning.
+    Trim,
+    /// Kept in the value, with a warning.
+    Preserve,
+}
+
+/// Scalars to convert to Unicode Normalization Form C while loading, so that
+/// keys that look the same compare equal.
+#[cfg(feature = "unicode-normalization")]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Normalization {
+    #[default]
+    None,
+    Keys,
+    KeysAndValues,
+}
+
+type ProgressHook = Arc<dyn Fn(Progress) + Send + Sync>;
+type MetricsHook = Arc<dyn Fn(&DocumentMetrics) + Send + Sync>;
+type WarningHook = Arc<dyn Fn(&Warning) + Send + Sync>;
+
+/// Options for `StrictYamlLoader::load_from_str_with_options`.
+#[derive(Clone, Default)]
+pub struct LoadOptions {
+    cancellation: Option<CancellationToken>,
+    progress: Option<ProgressHook>,
+    metrics: Option<MetricsHook>,
+    warnings: Option<WarningHook>,
+    line_breaks: LineBreaks,
+    reject_bom: bool,
+    lossy: bool,
+    reject_control_chars: bool,
+    alias_budget: Option<usize>,
+    flow_collections: bool,
+    strip_tags: bool,
+    reject_tags: bool,
+    anchors_as_text: bool,
+    indentation_step: Option<usize>,
+    trailing_whitespace: TrailingWhitespace,
+    max_depth: Option<usize>,
+    #[cfg(feature = "unicode-normalization")]
+    normalization: Normalization,
+}
+
+impl fmt::Debug for LoadOptions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut f = f.debug_struct("LoadOptions");
+        f.field("cancellation", &self.cancellation)
+            .field("progress", &self.progress.is_some())
+            .field("metrics", &self.metrics.is_some())
+            .field("warnings", &self.warnings.is_some())
+            .field("line_breaks", &self.line_breaks)
+            .field("reject_bom", &self.reject_bom)
+            .field("lossy", &self.lossy)
+            .field("reject_control_chars", &self.reject_control_chars)
+            .field("alias_budget", &self.alias_budget)
+            .field("flow_collections", &self.flow_collections)
+            .field("strip_tags", &self.strip_tags)
+            .field("reject_tags", &self.reject_tags)
+            .field("anchors_as_text", &self.anchors_as_text)
+            .field("indentation_step", &self.indentation_step)
+            .field("trailing_whitespace", &self.trailing_whitespace)
+            .field("max_depth", &self.get_max_depth());
+        #[cfg(feature = "unicode-normalization")]
+        f.field("normalization", &self.normalization);
+        f.finish()
+    }
+}
+
+impl LoadOptions {
+    pub fn new() -> LoadOptions {
+        LoadOptions::default()
+    }
+
+    /// Aborts loading with an error as soon as `token` is cancelled. The token is
+    /// checked before every parser event.
+    pub fn cancellation(mut self, token: CancellationToken) -> LoadOptions {
+        self.cancellation = Some(token);
+        self
+    }
+
+    /// Calls `hook` periodically during loading, and once more when the end of
+    /// the stream is reached, e.g. to drive a progress bar.
+    ///
+    /// ```
+    /// use std::sync::atomic::{AtomicUsize, Ordering};
+    /// use std::sync::Arc;
+    /// use strict_yaml_rust::{LoadOptions, StrictYamlLoader};
+    ///
+    /// let read = Arc::new(AtomicUsize::new(0));
+    /// let seen = read.clone();
+    /// let options = LoadOptions::new().progress(move |p| seen.store(p.bytes, Ordering::Relaxed));
+    /// StrictYamlLoader::load_from_str_with_options("a: b\n", &options).unwrap();
+    /// assert_eq!(read.load(Ordering::Relaxed), 5);
+    /// ```
+    pub fn progress<F>(mut self, hook: F) -> LoadOptions
+    where
+        F: Fn(Progress) + Send + Sync + 'static,
+    {
+        self.progress = Some(Arc::new(hook));
+        self
+    }
+
+    /// Calls `hook` at the end of every document with what loading it took,
+    /// e.g. to export to a monitoring system.
+    ///
+    /// ```
+    /// use std::sync::{Arc, Mutex};
+    /// use strict_yaml_rust::{LoadOptions, StrictYamlLoader};
+    ///
+    /// let metrics = Arc::new(Mutex::new(Vec::new()));
+    /// let sink = metrics.clone();
+    /// let options = LoadOptions::new().metrics(move |m| sink.lock().unwrap().push(*m));
+    /// StrictYamlLoader::load_from_str_with_options("a: b\n---\n- c\n- d\n", &options).unwrap();
+    /// let metrics = metrics.lock().unwrap();
+    /// assert_eq!((metrics[0].mappings, metrics[0].scalars), (1, 2));
+    /// assert_eq!((metrics[1].sequences, metrics[1].scalars), (1, 2));
+    /// ```
+    pub fn metrics<F>(mut self, hook: F) -> LoadOptions
+    where
+        F: Fn(&DocumentMetrics) + Send + Sync + 'static,
+    {
+        self.metrics = Some(Arc::new(hook));
+        self
+    }
+
+    /// Calls `hook` with every warning raised while loading. Warnings are
+    /// dropped when no hook is set.
+    pub fn warnings<F>(mut self, hook: F) -> LoadOptions
+    where
+        F: Fn(&Warning) + Send + Sync + 'static,
+    {
+        self.warnings = Some(Arc::new(hook));
+        self
+    }
+
+    /// Sets how bare `\r` and mixed line breaks are handled, see `LineBreaks`.
+    ///
+    /// ```
+    /// use strict_yaml_rust::{LineBreaks, LoadOptions, StrictYamlLoader};
+    ///
+    /// let options = LoadOptions::new().line_breaks(LineBreaks::Reject);
+    /// assert!(StrictYamlLoader::load_from_str_with_options("a: b\r\nc: d\r\n", &options).is_ok());
+    /// assert!(StrictYamlLoader::load_from_str_with_options("a: b\r\nc: d\n", &options).is_err());
+    /// ```
+    pub fn line_breaks(mut self, line_breaks: LineBreaks) -> LoadOptions {
+        self.line_breaks = line_breaks;
+        self
+    }
+
+    /// Makes a byte order mark at the start of the input an error. By default,
+    /// it is skipped.
+    pub fn reject_bom(mut self, reject: bool) -> LoadOptions {
+        self.reject_bom = reject;
+        self
+    }
+
+    /// Makes `load_from_bytes_with_options` replace invalid sequences in the input with
+    /// U+FFFD, raising a warning for each, instead of failing.
+    ///
+    /// ```
+    /// use strict_yaml_rust::{LoadOptions, StrictYamlLoader};
+    ///
+    /// let options = LoadOptions::new().lossy(true);
+    /// let docs = StrictYamlLoader::load_from_bytes_with_options(b"a: caf\xe9", &options).unwrap();
+    /// assert_eq!(docs[0]["a"].as_str(), Some("caf\u{fffd}"));
+    /// ```
+    pub fn lossy(mut self, lossy: bool) -> LoadOptions {
+        self.lossy = lossy;
+        self
+    }
+
+    /// Makes control characters other than tabs and line breaks in scalars an
+    /// error, as well as null characters anywhere in the input. Such characters
+    /// usually point at corrupted input.
+    ///
+    /// ```
+    /// use strict_yaml_rust::{LoadOptions, StrictYamlLoader};
+    ///
+    /// let options = LoadOptions::new().reject_control_chars(true);
+    /// assert!(StrictYamlLoader::load_from_str_with_options("a: \"b\\tc\"", &options).is_ok());
+    /// assert!(StrictYamlLoader::load_from_str_with_options("a: b\u{7}", &options).is_err());
+    /// ```
+    pub fn reject_control_chars(mut self, reject: bool) -> LoadOptions {
+        self.reject_control_chars = reject;
+        self
+    }
+
+    /// Sets which scalars are NFC-normalized, see `Normalization`. Duplicate keys
+    /// are detected after normalization.
+    ///
+    /// ```
+    /// use strict_yaml_rust::{LoadOptions, Normalization, StrictYamlLoader};
+    ///
+    /// let options = LoadOptions::new().normalization(Normalization::Keys);
+    /// // "é" as a single code point, then as "e" and a combining accent
+    /// let source = "caf\u{e9}: 1\ncafe\u{301}: 2";
+    /// assert!(StrictYamlLoader::load_from_str(source).is_ok());
+    /// assert!(StrictYamlLoader::load_from_str_with_options(source, &options).is_err());
+    /// ```
+    #[cfg(feature = "unicode-normalization")]
+    pub fn normalization(mut self, normalization: Normalization) -> LoadOptions {
+        self.normalization = normalization;
+        self
+    }
+
+    /// Accepts anchors and aliases, expanding each alias into a copy of its
+    /// anchored node, so that legacy YAML can be loaded and re-emitted as
+    /// StrictYAML. See `Parser::expand_aliases` for the meaning of `budget`.
+    ///
+    /// ```
+    /// use strict_yaml_rust::{LoadOptions, StrictYamlLoader};
+    ///
+    /// let source = "base: &base\n  image: nginx\nweb: *base";
+    /// let options = LoadOptions::new().expand_aliases(1000);
+    /// let docs = StrictYamlLoader::load_from_str_with_options(source, &options).unwrap();
+    /// assert_eq!(docs[0]["web"]["image"].as_str(), Some("nginx"));
+    /// ```
+    pub fn expand_aliases(mut self, budget: usize) -> LoadOptions {
+        self.alias_budget = Some(budget);
+        self
+    }
+
+    pub(crate) fn alias_budget(&self) -> Option<usize> {
+        self.alias_budget
+    }
+
+    /// Parses flow collections into sequences and mappings instead of
+    /// reading them as plain scalars, for loading legacy YAML.
+    ///
+    /// ```
+    /// use strict_yaml_rust::{LoadOptions, StrictYamlLoader};
+    ///
+    /// let source = "ports: [80, 443]";
+    /// let options = LoadOptions::new().flow_collections(true);
+    /// let docs = StrictYamlLoader::load_from_str_with_options(source, &options).unwrap();
+    /// assert_eq!(docs[0]["ports"][1].as_str(), Some("443"));
+    /// ```
+    pub fn flow_collections(mut self, enabled: bool) -> LoadOptions {
+        self.flow_collections = enabled;
+        self
+    }
+
+    pub(crate) fn are_flow_collections_parsed(&self) -> bool {
+        self.flow_collections
+    }
+
+    /// Strips explicit tags such as `!!str`, `!!int` or `!secret` from the
+    /// start of plain scalars, reporting a warning for each, so that documents
+    /// migrated from YAML don't carry the tag text in their values. The tags
+    /// stripped are those `reject_tags` rejects.
+    ///
+    /// ```
+    /// use strict_yaml_rust::{LoadOptions, StrictYamlLoader};
+    ///
+    /// let options = LoadOptions::new().strip_tags(true);
+    /// let docs = StrictYamlLoader::load_from_str_with_options("a: !!int 8080", &options).unwrap();
+    /// assert_eq!(docs[0]["a"].as_str(), Some("8080"));
+    /// ```
+    pub fn strip_tags(mut self, strip: bool) -> LoadOptions {
+        self.strip_tags = strip;
+        self
+    }
+
+    /// Makes it an error for a plain scalar to start with a tag, such as
+    /// `!!int 100` or `!secret x`, which are otherwise read as part of the
+    /// value. Tags are rejected before they could be stripped.
+    ///
+    /// ```
+    /// use strict_yaml_rust::{LoadOptions, StrictYamlLoader};
+    ///
+    /// let options = LoadOptions::new().reject_tags(true);
+    /// let err = StrictYamlLoader::load_from_str_with_options("a: !!int 100", &options).unwrap_err();
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     "tags are not part of StrictYAML, found !!int at line 1 column 4"
+    /// );
+    /// ```
+    pub fn reject_tags(mut self, reject: bool) -> LoadOptions {
+        self.reject_tags = reject;
+        self
+    }
+
+    /// Reads `&anchor` and `*alias` at the start of plain scalars as part of
+    /// their text, reporting a warning for each, instead of failing on them.
+    /// Has no effect on the anchors and aliases expanded by `expand_aliases`.
+    ///
+    /// ```
+    /// use strict_yaml_rust::{LoadOptions, StrictYamlLoader};
+    ///
+    /// let err = StrictYamlLoader::load_from_str("a: &x b").unwrap_err();
+    /// assert_eq!(err.to_string(), "anchors are not part of StrictYAML, found &x at line 1 column 4");
+    /// let options = LoadOptions::new().anchors_as_text(true);
+    /// let docs = StrictYamlLoader::load_from_str_with_options("a: &x b", &options).unwrap();
+    /// assert_eq!(docs[0]["a"].as_str(), Some("&x b"));
+    /// ```
+    pub fn anchors_as_text(mut self, as_text: bool) -> LoadOptions {
+        self.anchors_as_text = as_text;
+        self
+    }
+
+    pub(crate) fn are_anchors_text(&self) -> bool {
+        self.anchors_as_text
+    }
+
+    /// Makes it an error for a nested sequence or mapping to be indented by
+    /// anything but `step` spaces more than its parent, so that e.g. a 2
+    /// spaces convention can be relied upon. This includes collections
+    /// nested on the same line: with a step of 4, a mapping in a sequence is
+    /// written `-   a: b`.
+    ///
+    /// ```
+    /// use strict_yaml_rust::{LoadOptions, StrictYamlLoader};
+    ///
+    /// let options = LoadOptions::new().indentation_step(2);
+    /// assert!(StrictYamlLoader::load_from_str_with_options("a:\n  b: c", &options).is_ok());
+    /// let err = StrictYamlLoader::load_from_str_with_options("a:\n    b: c", &options).unwrap_err();
+    /// assert_eq!(err.to_string(), "indented by 4 spaces instead of 2 at line 2 column 5");
+    /// ```
+    pub fn indentation_step(mut self, step: usize) -> LoadOptions {
+        self.indentation_step = Some(step);
+        self
+    }
+
+    /// Sets how spaces and tabs ending plain scalars are handled, see
+    /// `TrailingWhitespace`.
+    ///
+    /// ```
+    /// use strict_yaml_rust::{LoadOptions, StrictYamlLoader, TrailingWhitespace};
+    ///
+    /// let options = LoadOptions::new().trailing_whitespace(TrailingWhitespace::Preserve);
+    /// let docs = StrictYamlLoader::load_from_str_with_options("a: b \nc: d", &options).unwrap();
+    /// assert_eq!(docs[0]["a"].as_str(), Some("b "));
+    /// ```
+    pub fn trailing_whitespace(mut self, handling: TrailingWhitespace) -> LoadOptions {
+        self.trailing_whitespace = handling;
+        self
+    }
+
+    /// Makes it an error for collections to be nested more than `depth`
+    /// levels deep, 256 by default. Nodes are dropped, compared and emitted
+    /// recursively, so a tree much deeper than this could overflow the stack.
+    ///
+    /// ```
+    /// use strict_yaml_rust::{LoadOptions, StrictYamlLoader};
+    ///
+    /// let options = LoadOptions::new().max_depth(2);
+    /// assert!(StrictYamlLoader::load_from_str_with_options("a:\n  - b", &options).is_ok());
+    /// let err = StrictYamlLoader::load_from_str_with_options("a:\n  - - b", &options).unwrap_err();
+    /// assert_eq!(err.to_string(), "collections nested deeper than 2 levels at line 2 column 5");
+    /// ```
+    pub fn max_depth(mut self, depth: usize) -> LoadOptions {
+        self.max_depth = Some(depth);
+        self
+    }
+
+    pub(crate) fn get_max_depth(&self) -> usize {
+        self.max_depth.unwrap_or(DEFAULT_MAX_DEPTH)
+    }
+
+    /// Sets up `parser` for the options it applies itself.
+    pub(crate) fn configure<T: Iterator<Item = char>>(&self, parser: &mut Parser<T>) {
+        if let Some(budget) = self.alias_budget() {
+            parser.expand_aliases(budget);
+        }
+        if self.are_flow_collections_parsed() {
+            parser.parse_flow_collections();
+        }
+        if self.are_anchors_text() {
+            parser.anchors_as_text();
+        }
+        if self.are_trailing_blanks_kept() {
+            parser.keep_trailing_blanks();
+        }
+    }
+
+    pub(crate) fn are_trailing_blanks_kept(&self) -> bool {
+        self.trailing_whitespace != TrailingWhitespace::Ignore
+    }
+
+    pub(crate) fn are_control_chars_rejected(&self) -> bool {
+        self.reject_control_chars
+    }
+
+    pub(crate) fn is_bom_rejected(&self) -> bool {
+        self.reject_bom
+    }
+
+    pub(crate) fn warn(&self, warning: Warning) {
+        if let Some(ref hook) = self.warnings {
+            hook(&warning);
+        }
+    }
+}
+
+/// Levels of nested collections allowed unless `LoadOptions::max_depth` is set.
+pub(crate) const DEFAULT_MAX_DEPTH: usize = 256;
+
+/// The error for a collection starting at `mark`, nested more than
+/// `max_depth` levels deep.
+pub(crate) fn too_deep(mark: Marker, max_depth: usize) -> ScanError {
+    let info = format!("collections nested deeper than {} levels", max_depth);
+    ScanError::limit(mark, &info)
+}
+
+/// Counts the bytes of the characters read from `chars`.
+pub(crate) struct CountBytes<'a, I> {
+    chars: I,
+    count: &'a Cell<usize>,
+}
+
+impl<'a, I: Iterator<Item = char>> CountBytes<'a, I> {
+    pub(crate) fn new(chars: I, count: &'a Cell<usize>) -> CountBytes<'a, I> {
+        CountBytes { chars, count }
+    }
+}
+
+impl<'a, I: Iterator<Item = char>> Iterator for CountBytes<'a, I> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        self.count.set(self.count.get() + c.len_utf8());
+        Some(c)
+    }
+}
+
+/// Size of the reads of `ReadChars`.
+const READ_CHUNK: usize = 8 * 1024;
+
+/// The characters of UTF-8 input, decoded as it is read. A read error or an
+/// invalid sequence ends the characters, and is left in `error`.
+pub(crate) struct ReadChars<'a, R> {
+    reader: R,
+    buf: Box<[u8]>,
+    /// The bytes of `buf` read but not decoded yet.
+    start: usize,
+    end: usize,
+    /// Number of bytes decoded so far.
+    offset: usize,
+    mark: Marker,
+    error: &'a Cell<Option<LoadError>>,
+    failed: bool,
+}
+
+impl<'a, R: io::Read> ReadChars<'a, R> {
+    pub(crate) fn new(reader: R, error: &'a Cell<Option<LoadError>>) -> ReadChars<'a, R> {
+        ReadChars {
+            reader,
+            buf: vec![0; READ_CHUNK].into_boxed_slice(),
+            start: 0,
+            end: 0,
+            offset: 0,
+            mark: Marker::new(0, 1, 0),
+            error,
+            failed: false,
+        }
+    }
+
+    /// Reads more input after the bytes not decoded yet, returning false at
+    /// the end of the input.
+    fn fill(&mut self) -> io::Result<bool> {
+        self.buf.copy_within(self.start..self.end, 0);
+        self.end -= self.start;
+        self.start = 0;
+        loop {
+            match self.reader.read(&mut self.buf[self.end..]) {
+                Ok(0) => return Ok(false),
+                Ok(n) => {
+                    self.end += n;
+                    return Ok(true);
+                }
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// The error for the `len` invalid bytes at the decoding position.
+    fn invalid(&self, len: usize) -> LoadError {
+        let bytes = &self.buf[self.start..self.start + len];
+        let seq = InvalidSequence::new("UTF-8", self.offset, bytes);
+        LoadError::Scan(ScanError::from_invalid_sequence(self.mark, seq))
+    }
+
+    fn decode(&mut self) -> Result<Option<char>, LoadError> {
+        loop {
+            if let Some(&first) = self.buf[self.start..self.end].first() {
+                let width = match first {
+                    0x00..=0x7f => 1,
+                    0xc2..=0xdf => 2,
+                    0xe0..=0xef => 3,
+                    0xf0..=0xf4 => 4,
+                    _ => return Err(self.invalid(1)),
+                };
+                if self.end - self.start >= width {
+                    let bytes = &self.buf[self.start..self.start + width];
+                    let c = match str::from_utf8(bytes) {
+                        Ok(s) => s.chars().next().unwrap(),
+                        Err(e) => return Err(self.invalid(e.error_len().unwrap_or(width))),
+                    };
+                    self.start += width;
+                    self.offset += width;
+                    self.mark = advance(self.mark, c);
+                    return Ok(Some(c));
+                }
+            }
+            let more = self.fill().map_err(|error| LoadError::Io {
+                error,
+                marker: self.mark,
+            })?;
+            if !more {
+                return match self.end - self.start {
+                    0 => Ok(None),
+                    // the input ends in the middle of a sequence
+                    n => Err(self.invalid(n)),
+                };
+            }
+        }
+    }
+}
+
+impl<'a, R: io::Read> Iterator for ReadChars<'a, R> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        if self.failed {
+            return None;
+        }
+        match self.decode() {
+            Ok(c) => c,
+            Err(e) => {
+                self.failed = true;
+                self.error.set(Some(e));
+                None
+            }
+        }
+    }
+}
+
+/// The position following `c`, read at `mark`.
+fn advance(mark: Marker, c: char) -> Marker {
+    if c == '\n' {
+        Marker::new(mark.index() + 1, mark.line() + 1, 0)
+    } else {
+        Marker::new(mark.index() + 1, mark.line(), mark.col() + 1)
+    }
+}
+
+/// Returns an error at the first null character of `source`, which the scanner
+/// would take for the end of the input.
+pub(crate) fn check_nulls(source: &str) -> Result<(), ScanError> {
+    match source.find('\0') {
+        Some(i) => {
+            let mark = source[..i].chars().fold(Marker::new(0, 1, 0), advance);
+            Err(ScanError::scanner(mark, "null character in input"))
+        }
+        None => Ok(()),
+    }
+}
+
+/// Encodings of byte input, detected as described in section 5.2 of the YAML
+/// 1.2 spec.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Encoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Utf32Le,
+    Utf32Be,
+}
+
+impl Encoding {
+    /// Detects the encoding from a byte order mark or, lacking one, from the
+    /// null bytes around the first character, which must be ASCII.
+    fn detect(source: &[u8]) -> Encoding {
+        match source {
+            [0, 0, 0xfe, 0xff, ..] | [0, 0, 0, _, ..] => Encoding::Utf32Be,
+            [0xff, 0xfe, 0, 0, ..] | [_, 0, 0, 0, ..] => Encoding::Utf32Le,
+            [0xfe, 0xff, ..] | [0, _, ..] => Encoding::Utf16Be,
+            [0xff, 0xfe, ..] | [_, 0, ..] => Encoding::Utf16Le,
+            _ => Encoding::Utf8,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "UTF-8",
+            Encoding::Utf16Le | Encoding::Utf16Be => "UTF-16",
+            Encoding::Utf32Le | Encoding::Utf32Be => "UTF-32",
+        }
+    }
+}
+
+/// Accumulates decoded text, keeping track of positions for error reporting.
+struct Decoder<'a> {
+    encoding: Encoding,
+    options: &'a LoadOptions,
+    text: String,
+    mark: Marker,
+}
+
+impl<'a> Decoder<'a> {
+    fn push(&mut self, c: char) {
+        self.text.push(c);
+        self.mark = advance(self.mark, c);
+    }
+
+    /// Handles the invalid sequence `bytes`, found at byte `offset`.
+    fn invalid(&mut self, offset: usize, bytes: &[u8]) -> Result<(), ScanError> {
+        let seq = InvalidSequence::new(self.encoding.name(), offset, bytes);
+        if !self.options.lossy {
+            return Err(ScanError::from_invalid_sequence(self.mark, seq));
+        }
+        let info = format!(
+            "invalid {} sequence at byte {}, replaced with U+FFFD",
+            seq.encoding(),
+            seq.offset()
+        );
+        self.options.warn(Warning::new(self.mark, &info));
+        self.push('\u{fffd}');
+        Ok(())
+    }
+}
+
+/// Decodes `source`, in UTF-8 unless another Unicode encoding is detected.
+/// Invalid sequences are errors, or replaced with U+FFFD if `options` are lossy.
+pub(crate) fn decode<'a>(
+    source: &'a [u8],
+    options: &LoadOptions,
+) -> Result<Cow<'a, str>, ScanError> {
+    let encoding = Encoding::detect(source);
+    if encoding == Encoding::Utf8 {
+        if let Ok(text) = str::from_utf8(source) {
+            return Ok(Cow::Borrowed(text));
+        }
+    }
+    let mut decoder = Decoder {
+        encoding,
+        options,
+        text: String::with_capacity(source.len()),
+        mark: Marker::new(0, 1, 0),
+    };
+    match encoding {
+        Encoding::Utf8 => {
+            let mut offset = 0;
+            for chunk in source.utf8_chunks() {
+                chunk.valid().chars().for_each(|c| decoder.push(c));
+                offset += chunk.valid().len();
+                if !chunk.invalid().is_empty() {
+                    decoder.invalid(offset, chunk.invalid())?;
+                    offset += chunk.invalid().len();
+                }
+            }
+        }
+        Encoding::Utf16Le | Encoding::Utf16Be => {
+            let units = source.chunks_exact(2).map(|b| {
+                if encoding == Encoding::Utf16Le {
+                    u16::from_le_bytes([b[0], b[1]])
+                } else {
+                    u16::from_be_bytes([b[0], b[1]])
+                }
+            });
+            let mut offset = 0;
+            for c in char::decode_utf16(units) {
+                match c {
+                    Ok(c) => {
+                        decoder.push(c);
+                        offset += 2 * c.len_utf16();
+                    }
+                    Err(_) => {
+                        decoder.invalid(offset, &source[offset..offset + 2])?;
+                        offset += 2;
+                    }
+                }
+            }
+            if offset < source.len() {
+                decoder.invalid(offset, &source[offset..])?;
+            }
+        }
+        Encoding::Utf32Le | Encoding::Utf32Be => {
+            let mut offset = 0;
+            for b in source.chunks_exact(4) {
+                let unit = if encoding == Encoding::Utf32Le {
+                    u32::from_le_bytes([b[0], b[1], b[2], b[3]])
+                } else {
+                    u32::from_be_bytes([b[0], b[1], b[2], b[3]])
+                };
+                match char::from_u32(unit) {
+                    Some(c) => decoder.push(c),
+                    None => decoder.invalid(offset, b)?,
+                }
+                offset += 4;
+            }
+            if offset < source.len() {
+                decoder.invalid(offset, &source[offset..])?;
+            }
+        }
+    }
+    Ok(Cow::Owned(decoder.text))
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Break {
+    Lf,
+    CrLf,
+    Cr,
+}
+
+impl Break {
+    fn name(self) -> &'static str {
+        match self {
+            Break::Lf => "LF",
+            Break::CrLf => "CRLF",
+            Break::Cr => "CR",
+        }
+    }
+}
+
+/// Applies the `LineBreaks` option to the characters read from `chars`.
+///
+/// Errors cannot be returned from an iterator: the first one is stored in
+/// `error`, and the input ends there.
+pub(crate) struct CheckLineBreaks<'a, I: Iterator<Item = char>> {
+    chars: Peekable<I>,
+    options: &'a LoadOptions,
+    error: &'a Cell<Option<ScanError>>,
+    mark: Marker,
+    /// The kind of the first line break, which the others should match.
+    first: Option<Break>,
+    /// Whether the next character is the `\n` of a `\r\n`.
+    in_crlf: bool,
+    warned_cr: bool,
+    warned_mixed: bool,
+    failed: bool,
+}
+
+impl<'a, I: Iterator<Item = char>> CheckLineBreaks<'a, I> {
+    pub(crate) fn new(
+        chars: I,
+        options: &'a LoadOptions,
+        error: &'a Cell<Option<ScanError>>,
+    ) -> CheckLineBreaks<'a, I> {
+        CheckLineBreaks {
+            chars: chars.peekable(),
+            options,
+            error,
+            mark: Marker::new(0, 1, 0),
+            first: None,
+            in_crlf: false,
+            warned_cr: false,
+            warned_mixed: false,
+            failed: false,
+        }
+    }
+
+    fn check(&mut self, kind: Break) -> Result<(), ScanError> {
+        let reject = self.options.line_breaks == LineBreaks::Reject;
+        if kind == Break::Cr {
+            if reject {
+                return Err(ScanError::limit(
+                    self.mark,
+                    "bare CR line breaks are not allowed",
+                ));
+            }
+            if !self.warned_cr {
+                self.warned_cr = true;
+                self.options
+                    .warn(Warning::new(self.mark, "bare CR line break, read as LF"));
+            }
+        }
+        let first = *self.first.get_or_insert(kind);
+        if kind != first {
+            let info = format!(
+                "{} line break in a file using {} line breaks",
+                kind.name(),
+                first.name()
+            );
+            if reject {
+                return Err(ScanError::limit(self.mark, &info));
+            }
+            if !self.warned_mixed {
+                self.warned_mixed = true;
+                self.options.warn(Warning::new(self.mark, &info));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'a, I: Iterator<Item = char>> Iterator for CheckLineBreaks<'a, I> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        if self.failed {
+            return None;
+        }
+        let mut c = self.chars.next()?;
+        let kind = match c {
+            '\r' if self.chars.peek() == Some(&'\n') => {
+                self.in_crlf = true;
+                Some(Break::CrLf)
+            }
+            '\r' => Some(Break::Cr),
+            '\n' if self.in_crlf => {
+                self.in_crlf = false;
+                None
+            }
+            '\n' => Some(Break::Lf),
+            _ => None,
+        };
+        if let Some(kind) = kind {
+            if let Err(e) = self.check(kind) {
+                self.failed = true;
+                self.error.set(Some(e));
+                return None;
+            }
+            if kind == Break::Cr {
+                c = '\n';
+            }
+        }
+        self.mark = advance(self.mark, c);
+        Some(c)
+    }
+}
+
+/// What `OptionsReceiver` keeps from one event to the next, held apart so
+/// that a load fed in several rounds, as from an async reader, keeps it
+/// between them.
+pub(crate) struct OptionsState {
+    events: usize,
+    /// Metrics of the current document, and when and where the previous one
+    /// ended.
+    document: DocumentMetrics,
+    since: (Instant, usize),
+    /// Columns of the open block collections, `None` until known.
+    indents: Vec<Option<usize>>,
+    /// Depth of the open flow collections, where indentation is free.
+    flow: usize,
+    /// Depth of all the open collections.
+    depth: usize,
+    #[cfg(feature = "unicode-normalization")]
+    tracker: PathTracker,
+}
+
+impl OptionsState {
+    pub(crate) fn new() -> OptionsState {
+        OptionsState {
+            events: 0,
+            document: DocumentMetrics::default(),
+            since: (Instant::now(), 0),
+            indents: Vec::new(),
+            flow: 0,
+            depth: 0,
+            #[cfg(feature = "unicode-normalization")]
+            tracker: PathTracker::new(),
+        }
+    }
+}
+
+/// Applies `options` to the events flowing into `inner`.
+pub(crate) struct OptionsReceiver<'a, R: 'a> {
+    inner: &'a mut R,
+    options: &'a LoadOptions,
+    bytes: &'a Cell<usize>,
+    state: &'a mut OptionsState,
+}
+
+impl<'a, R: SpannedEventReceiver> OptionsReceiver<'a, R> {
+    /// `bytes` is the running count of input bytes, see `CountBytes`.
+    pub(crate) fn new(
+        inner: &'a mut R,
+        options: &'a LoadOptions,
+        bytes: &'a Cell<usize>,
+        state: &'a mut OptionsState,
+    ) -> OptionsReceiver<'a, R> {
+        OptionsReceiver {
+            inner,
+            options,
+            bytes,
+            state,
+        }
+    }
+
+    fn check_indentation(&mut self, ev: &Event, span: Span) -> Result<(), ScanError> {
+        let step = match self.options.indentation_step {
+            Some(step) => step,
+            None => return Ok(()),
+        };
+        let mark = span.start();
+        if self.state.flow > 0 {
+            match *ev {
+                Event::SequenceStart(_) | Event::MappingStart(_) => self.state.flow += 1,
+                Event::SequenceEnd | Event::MappingEnd => self.state.flow -= 1,
+                _ => {}
+            }
+            return Ok(());
+        }
+        // the mark of a mapping start is off, so a mapping gets its column
+        // from its first key
+        if self.state.indents.last() == Some(&None) {
+            self.state.indents.pop();
+            self.indent(mark, step)?;
+        }
+        match *ev {
+            // only flow collection starts cover their `[` or `{`
+            Event::SequenceStart(_) | Event::MappingStart(_) if !span.is_empty() => {
+                self.state.flow = 1;
+                Ok(())
+            }
+            Event::SequenceStart(_) => self.indent(mark, step),
+            Event::MappingStart(_) => {
+                self.state.indents.push(None);
+                Ok(())
+            }
+            Event::SequenceEnd | Event::MappingEnd => {
+                self.state.indents.pop();
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Opens a collection at the column of `mark`.
+    fn indent(&mut self, mark: Marker, step: usize) -> Result<(), ScanError> {
+        if let Some(&Some(parent)) = self.state.indents.last() {
+            if mark.col() != parent + step {
+                let info = format!(
+                    "indented by {} spaces instead of {}",
+                    mark.col() as isize - parent as isize,
+                    step
+                );
+                return Err(ScanError::limit(mark, &info));
+            }
+        }
+        self.state.indents.push(Some(mark.col()));
+        Ok(())
+    }
+
+    fn measure(&mut self, ev: &Event) {
+        let hook = match self.options.metrics {
+            Some(ref hook) => hook,
+            None => return,
+        };
+        self.state.document.events += 1;
+        match *ev {
+            Event::DocumentStart => {
+                self.state.document = DocumentMetrics {
+                    events: 1,
+                    ..Default::default()
+                }
+            }
+            Event::Scalar(..) => self.state.document.scalars += 1,
+            Event::SequenceStart(_) => self.state.document.sequences += 1,
+            Event::MappingStart(_) => self.state.document.mappings += 1,
+            Event::DocumentEnd => {
+                let now = (Instant::now(), self.bytes.get());
+                self.state.document.duration = now.0 - self.state.since.0;
+                self.state.document.bytes = now.1 - self.state.since.1;
+                self.state.since = now;
+                hook(&self.state.document);
+            }
+            _ => {}
+        }
+    }
+
+    #[cfg(feature = "unicode-normalization")]
+    fn normalize(&mut self, ev: Event) -> Event {
+        if self.options.normalization == Normalization::None {
+            return ev;
+        }
+        self.state.tracker.update(&ev);
+        match ev {
+            Event::Scalar(value, style, aid)
+                if !is_nfc(&value)
+                    && (self.state.tracker.is_key()
+                        || self.options.normalization == Normalization::KeysAndValues) =>
+            {
+                Event::Scalar(value.nfc().collect(), style, aid)
+            }
+            ev => ev,
+        }
+    }
+}
+
+impl<'a, R: SpannedEventReceiver> SpannedEventReceiver for OptionsReceiver<'a, R> {
+    fn on_spanned_event(&mut self, ev: Event, span: Span) -> Result<(), ScanError> {
+        let mark = span.start();
+        if let Some(ref token) = self.options.cancellation {
+            if token.is_cancelled() {
+                #[cfg(feature = "tracing")]
+                tracing::debug!(line = mark.line(), "loading cancelled");
+                return Err(ScanError::limit(mark, "loading cancelled"));
+            }
+        }
+        if self.options.reject_control_chars {
+            if let Event::Scalar(ref value, ..) = ev {
+                let control = value
+                    .chars()
+                    .find(|&c| c.is_control() && c != '\t' && c != '\n');
+                if let Some(c) = control {
+                    let info = format!("control character U+{:04X} in scalar", c as u32);
+                    return Err(ScanError::limit(mark, &info));
+                }
+            }
+        }
+        match ev {
+            Event::SequenceStart(_) | Event::MappingStart(_) => {
+                if self.state.depth == self.options.get_max_depth() {
+                    return Err(too_deep(mark, self.state.depth));
+                }
+                self.state.depth += 1;
+            }
+            Event::SequenceEnd | Event::MappingEnd => {
+                self.state.depth = self.state.depth.saturating_sub(1)
+            }
+            _ => {}
+        }
+        self.check_indentation(&ev, span)?;
+        let ev = match ev {
+            Event::Scalar(value, TScalarStyle::Plain, aid)
+                if value.ends_with([' ', '\t'])
+                    && self.options.trailing_whitespace != TrailingWhitespace::Ignore =>
+            {
+                if self.options.trailing_whitespace == TrailingWhitespace::Trim {
+                    let trimmed = value.trim_end_matches([' ', '\t']).to_owned();
+                    self.options
+                        .warn(Warning::new(mark, "trailing whitespace trimmed"));
+                    Event::Scalar(trimmed, TScalarStyle::Plain, aid)
+                } else {
+                    self.options
+                        .warn(Warning::new(mark, "trailing whitespace kept"));
+                    Event::Scalar(value, TScalarStyle::Plain, aid)
+                }
+            }
+            ev => ev,
+        };
+        if self.options.reject_tags {
+            if let Event::Scalar(ref value, TScalarStyle::Plain, _) = ev {
+                if let Some((tag, _)) = split_tag(value) {
+                    let info = format!("tags are not part of StrictYAML, found {}", tag);
+                    return Err(ScanError::limit(mark, &info));
+                }
+            }
+        }
+        if self.options.anchors_as_text {
+            if let Event::Scalar(ref value, TScalarStyle::Plain, _) = ev {
+                if let Some(found) = leading_anchor(value) {
+                    let what = if found.starts_with('&') {
+                        "anchor"
+                    } else {
+                        "alias"
+                    };
+                    let info = format!("{} {} read as text", what, found);
+                    self.options.warn(Warning::new(mark, &info));
+                }
+            }
+        }
+        let ev = match ev {
+            Event::Scalar(value, TScalarStyle::Plain, aid) if self.options.strip_tags => {
+                match split_tag(&value) {
+                    Some((tag, rest)) => {
+                        let info = format!("explicit tag {} stripped", tag);
+                        self.options.warn(Warning::new(mark, &info));
+                        Event::Scalar(rest.to_owned(), TScalarStyle::Plain, aid)
+                    }
+                    None => Event::Scalar(value, TScalarStyle::Plain, aid),
+                }
+            }
+            ev => ev,
+        };
+        #[cfg(feature = "unicode-normalization")]
+        let ev = self.normalize(ev);
+        self.state.events += 1;
+        self.measure(&ev);
+        if let Some(ref hook) = self.options.progress {
+            if self.state.events.is_multiple_of(PROGRESS_INTERVAL) || ev == Event::StreamEnd {
+                hook(Progress {
+                    bytes: self.bytes.get(),
+                    events: self.state.events,
+                });
+            }
+        }
+        self.inner.on_spanned_event(ev, span)
+    }
+}
+
+/// Splits the `!name` or `!!name` tag starting `value` off it, if followed by
+/// a space or nothing, returning the tag and the rest of the value after the
+/// separating spaces.
+fn split_tag(value: &str) -> Option<(&str, &str)> {
+    let name = value
+        .strip_prefix("!!")
+        .or_else(|| value.strip_prefix('!'))?;
+    let len = name
+        .find(|c: char| !(c.is_ascii_alphanumeric() || "-_./:".contains(c)))
+        .unwrap_or(name.len());
+    let (tag, rest) = value.split_at(value.len() - name.len() + len);
+    if len == 0 || !(rest.is_empty() || rest.starts_with(' ')) {
+        return None;
+    }
+    Some((tag, rest.trim_start_matches(' ')))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use strict_yaml::{StrictYaml, StrictYamlLoader};
+
+    #[test]
+    fn test_cancellation() {
+        let token = CancellationToken::new();
+        let options = LoadOptions::new().cancellation(token.clone());
+        let docs = StrictYamlLoader::load_from_str_with_options("a: b", &options).unwrap();
+        assert_eq!(docs[0]["a"].as_str(), Some("b"));
+
+        token.cancel();
+        let err = StrictYamlLoader::load_from_str_with_options("a: b", &options).unwrap_err();
+        assert_eq!(err.to_string(), "loading cancelled at line 1 column 1");
+    }
+
+    #[test]
+    fn test_line_breaks() {
+        use std::sync::Mutex;
+
+        let warnings = Arc::new(Mutex::new(Vec::new()));
+        let sink = warnings.clone();
+        let options =
+            LoadOptions::new().warnings(move |w| sink.lock().unwrap().push(w.to_string()));
+        let docs = StrictYamlLoader::load_from_str_with_options(
+            "a: b\rc: |\r  x\r  y\r\nd: e\n",
+            &options,
+        )
+        .unwrap();
+        let expected = StrictYamlLoader::load_from_str("a: b\nc: |\n  x\n  y\nd: e\n").unwrap();
+        assert_eq!(docs, expected);
+        assert_eq!(
+            *warnings.lock().unwrap(),
+            vec![
+                "bare CR line break, read as LF at line 1 column 5",
+                "CRLF line break in a file using CR line breaks at line 4 column 4",
+            ]
+        );
+
+        let options = LoadOptions::new().line_breaks(LineBreaks::Reject);
+        let load = |s| StrictYamlLoader::load_from_str_with_options(s, &options);
+        assert!(load("a: b\nc: d\n").is_ok());
+        assert!(load("a: b\r\nc: d\r\n").is_ok());
+        assert_eq!(
+            load("a: b\rc: d").unwrap_err().to_string(),
+            "bare CR line breaks are not allowed at line 1 column 5"
+        );
+        assert_eq!(
+            load("a: b\r\nc: d\n").unwrap_err().to_string(),
+            "LF line break in a file using CRLF line breaks at line 2 column 5"
+        );
+    }
+
+    #[test]
+    fn test_reject_bom() {
+        let source = "\u{feff}a: b";
+        let docs =
+            StrictYamlLoader::load_from_str_with_options(source, &LoadOptions::new()).unwrap();
+        assert_eq!(docs[0]["a"].as_str(), Some("b"));
+
+        let options = LoadOptions::new().reject_bom(true);
+        let err = StrictYamlLoader::load_from_str_with_options(source, &options).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "byte order mark at the start of the input at line 1 column 1"
+        );
+    }
+
+    #[test]
+    fn test_lossy() {
+        use std::sync::Mutex;
+
+        let source = b"a: caf\xe9\nb: \xf0\x9f\x98\nc: d\xff\xfe";
+        let err = StrictYamlLoader::load_from_bytes(source).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid UTF-8 sequence at byte 6 at line 1 column 7"
+        );
+
+        let warnings = Arc::new(Mutex::new(Vec::new()));
+        let sink = warnings.clone();
+        let options = LoadOptions::new()
+            .lossy(true)
+            .warnings(move |w| sink.lock().unwrap().push(w.to_string()));
+        let docs = StrictYamlLoader::load_from_bytes_with_options(source, &options).unwrap();
+        assert_eq!(docs[0]["a"].as_str(), Some("caf\u{fffd}"));
+        assert_eq!(docs[0]["b"].as_str(), Some("\u{fffd}"));
+        assert_eq!(docs[0]["c"].as_str(), Some("d\u{fffd}\u{fffd}"));
+        assert_eq!(
+            *warnings.lock().unwrap(),
+            vec![
+                "invalid UTF-8 sequence at byte 6, replaced with U+FFFD at line 1 column 7",
+                "invalid UTF-8 sequence at byte 11, replaced with U+FFFD at line 2 column 4",
+                "invalid UTF-8 sequence at byte 19, replaced with U+FFFD at line 3 column 5",
+                "invalid UTF-8 sequence at byte 20, replaced with U+FFFD at line 3 column 6",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_encodings() {
+        fn utf16(s: &str, be: bool) -> Vec<u8> {
+            s.encode_utf16()
+                .flat_map(|u| if be { u.to_be_bytes() } else { u.to_le_bytes() })
+                .collect()
+        }
+        fn utf32(s: &str, be: bool) -> Vec<u8> {
+            s.chars()
+                .flat_map(|c| {
+                    let u = c as u32;
+                    if be {
+                        u.to_be_bytes()
+                    } else {
+                        u.to_le_bytes()
+                    }
+                })
+                .collect()
+        }
+
+        let text = "a: b\nc:\n  - 你好\n  - 😀\n";
+        let expected = StrictYamlLoader::load_from_str(text).unwrap();
+        for &be in &[false, true] {
+            for source in &[utf16(text, be), utf32(text, be)] {
+                assert_eq!(StrictYamlLoader::load_from_bytes(source).unwrap(), expected);
+            }
+            // with a byte order mark
+            let text = format!("\u{feff}{}", text);
+            for source in &[utf16(&text, be), utf32(&text, be)] {
+                assert_eq!(StrictYamlLoader::load_from_bytes(source).unwrap(), expected);
+            }
+        }
+
+        // an unpaired surrogate and a truncated unit
+        let mut source = utf16("a: b", false);
+        source.extend_from_slice(&[0x00, 0xd8, b'c', 0, b'\n']);
+        let err = StrictYamlLoader::load_from_bytes(&source).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid UTF-16 sequence at byte 8 at line 1 column 5"
+        );
+        let options = LoadOptions::new().lossy(true);
+        let docs = StrictYamlLoader::load_from_bytes_with_options(&source, &options).unwrap();
+        assert_eq!(docs[0]["a"].as_str(), Some("b\u{fffd}c\u{fffd}"));
+
+        let mut source = utf32("a: b", true);
+        source.extend_from_slice(&[0, 0x11, 0, 0]);
+        let err = StrictYamlLoader::load_from_bytes(&source).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid UTF-32 sequence at byte 16 at line 1 column 5"
+        );
+    }
+
+    #[test]
+    fn test_reject_control_chars() {
+        let options = LoadOptions::new().reject_control_chars(true);
+        let load = |s| StrictYamlLoader::load_from_str_with_options(s, &options);
+        assert!(load("a:\tb\tc\nd: |\n  e\n  f\ng: \"h\\ti\\nj\"").is_ok());
+        assert_eq!(
+            load("a: b\nc: d\u{1}").unwrap_err().to_string(),
+            "control character U+0001 in scalar at line 2 column 4"
+        );
+        // a raw null character would otherwise end the input
+        assert_eq!(
+            load("a: b\nc: d\u{0}e: f").unwrap_err().to_string(),
+            "null character in input at line 2 column 5"
+        );
+        assert_eq!(
+            load("- \"\\e[31m\"").unwrap_err().to_string(),
+            "control character U+001B in scalar at line 1 column 3"
+        );
+        assert!(load("\u{7f}: x").is_err());
+        assert!(
+            StrictYamlLoader::load_from_str_with_options("a: b\u{0}", &LoadOptions::new()).is_ok()
+        );
+    }
+
+    #[test]
+    fn test_indentation_step() {
+        let load = |source: &str, step| {
+            let options = LoadOptions::new()
+                .indentation_step(step)
+                .flow_collections(true);
+            StrictYamlLoader::load_from_str_with_options(source, &options)
+                .map(|_| ())
+                .map_err(|e| e.to_string())
+        };
+        let source =
+            "a:\n  b:\n    - c\n    -\n      d: e\n    - f: g\n      h: i\n    - - j\nk: l";
+        assert_eq!(load(source, 2), Ok(()));
+        assert_eq!(
+            load(source, 4),
+            Err("indented by 2 spaces instead of 4 at line 2 column 3".to_owned())
+        );
+        assert_eq!(
+            load("a:\n    b:\n        -   c: d\n            e: f", 4),
+            Ok(())
+        );
+        assert_eq!(
+            load("a:\n    - b: c", 4),
+            Err("indented by 2 spaces instead of 4 at line 2 column 7".to_owned())
+        );
+        assert_eq!(
+            load("a:\n  b: c\nd:\n   e: f", 2),
+            Err("indented by 3 spaces instead of 2 at line 4 column 4".to_owned())
+        );
+        assert_eq!(load("a: [b, c]\nd:\n  e: f", 2), Ok(()));
+        assert_eq!(
+            load(
+                "a:\n  - [b,\n c, {d: [e]}]\n  - {f: g}\n  - h:\n      i: j",
+                2
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            load("a: {b: c}\nd:\n   e: f", 2),
+            Err("indented by 3 spaces instead of 2 at line 3 column 4".to_owned())
+        );
+        assert_eq!(
+            load("a:\n- b", 2),
+            Err("indented by 0 spaces instead of 2 at line 2 column 1".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_trailing_whitespace() {
+        use std::sync::Mutex;
+
+        let source = "a: b \nc:\t\n  - d  e\t \n  - f \n    g  \nh: \"i \" \nj  : k  # l\nm: n \t";
+        let load = |handling| {
+            let warnings = Arc::new(Mutex::new(Vec::new()));
+            let sink = warnings.clone();
+            let options = LoadOptions::new()
+                .trailing_whitespace(handling)
+                .warnings(move |w| sink.lock().unwrap().push(w.to_string()));
+            let doc = StrictYamlLoader::load_from_str_with_options(source, &options)
+                .unwrap()
+                .remove(0);
+            let warnings = warnings.lock().unwrap().clone();
+            (doc, warnings)
+        };
+        let values = |doc: &StrictYaml| {
+            vec![
+                doc["a"].as_str().unwrap().to_owned(),
+                doc["c"][0].as_str().unwrap().to_owned(),
+                doc["c"][1].as_str().unwrap().to_owned(),
+                doc["h"].as_str().unwrap().to_owned(),
+                doc["j"].as_str().unwrap().to_owned(),
+                doc["m"].as_str().unwrap().to_owned(),
+            ]
+        };
+        let trimmed = ["b", "d  e", "f g", "i ", "k", "n"];
+
+        let (doc, warnings) = load(TrailingWhitespace::Ignore);
+        assert_eq!(values(&doc), trimmed);
+        assert!(warnings.is_empty());
+
+        let (doc, warnings) = load(TrailingWhitespace::Trim);
+        assert_eq!(values(&doc), trimmed);
+        assert_eq!(
+            warnings,
+            [
+                "trailing whitespace trimmed at line 1 column 4",
+                "trailing whitespace trimmed at line 3 column 5",
+                "trailing whitespace trimmed at line 4 column 5",
+                "trailing whitespace trimmed at line 8 column 4",
+            ]
+        );
+
+        let (doc, warnings) = load(TrailingWhitespace::Preserve);
+        assert_eq!(values(&doc), ["b ", "d  e\t ", "f g  ", "i ", "k", "n \t"]);
+        assert_eq!(warnings[0], "trailing whitespace kept at line 1 column 4");
+        assert_eq!(warnings.len(), 4);
+    }
+
+    #[test]
+    fn test_strip_tags() {
+        use std::sync::{Arc, Mutex};
+
+        let warnings = Arc::new(Mutex::new(Vec::new()));
+        let sink = warnings.clone();
+        let options = LoadOptions::new()
+            .strip_tags(true)
+            .warnings(move |w| sink.lock().unwrap().push(w.to_string()));
+        let source = "!!str a: !!str b\nc: !!float 1.5\nd: !!null\n\
+                      e: '!!str f'\ng: !!str!\nh: !! i\nj: !x k\nl: !my.tag:v1 m";
+        let docs = StrictYamlLoader::load_from_str_with_options(source, &options).unwrap();
+        let doc = &docs[0];
+        assert_eq!(doc["a"].as_str(), Some("b"));
+        assert_eq!(doc["c"].as_str(), Some("1.5"));
+        assert_eq!(doc["d"].as_str(), Some(""));
+        assert_eq!(doc["e"].as_str(), Some("!!str f"));
+        assert_eq!(doc["g"].as_str(), Some("!!str!"));
+        assert_eq!(doc["h"].as_str(), Some("!! i"));
+        assert_eq!(doc["j"].as_str(), Some("k"));
+        assert_eq!(doc["l"].as_str(), Some("m"));
+        assert_eq!(
+            *warnings.lock().unwrap(),
+            vec![
+                "explicit tag !!str stripped at line 1 column 1",
+                "explicit tag !!str stripped at line 1 column 10",
+                "explicit tag !!float stripped at line 2 column 4",
+                "explicit tag !!null stripped at line 3 column 4",
+                "explicit tag !x stripped at line 7 column 4",
+                "explicit tag !my.tag:v1 stripped at line 8 column 4",
+            ]
+        );
+
+        let docs = StrictYamlLoader::load_from_str("a: !!str b").unwrap();
+        assert_eq!(docs[0]["a"].as_str(), Some("!!str b"));
+    }
+
+    #[test]
+    fn test_reject_tags() {
+        let options = LoadOptions::new().reject_tags(true).strip_tags(true);
+        let err = |source: &str| {
+            StrictYamlLoader::load_from_str_with_options(source, &options)
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(
+            err("a: b\n!!str c: d"),
+            "tags are not part of StrictYAML, found !!str at line 2 column 1"
+        );
+        assert_eq!(
+            err("a:\n  - !secret x"),
+            "tags are not part of StrictYAML, found !secret at line 2 column 5"
+        );
+        assert_eq!(
+            err("a: !!null"),
+            "tags are not part of StrictYAML, found !!null at line 1 column 4"
+        );
+        let source = "a: '!!str b'\nc: !important!\nd: ! e\nf: x !!str\ng: !!\nh: \"!i\"";
+        let docs = StrictYamlLoader::load_from_str_with_options(source, &options).unwrap();
+        assert_eq!(docs[0]["a"].as_str(), Some("!!str b"));
+        assert_eq!(docs[0]["c"].as_str(), Some("!important!"));
+        assert_eq!(docs[0]["d"].as_str(), Some("! e"));
+        assert_eq!(docs[0]["g"].as_str(), Some("!!"));
+        assert_eq!(docs[0]["h"].as_str(), Some("!i"));
+    }
+
+    #[test]
+    fn test_anchors_as_text() {
+        use std::sync::{Arc, Mutex};
+
+        let warnings = Arc::new(Mutex::new(Vec::new()));
+        let sink = warnings.clone();
+        let options = LoadOptions::new()
+            .anchors_as_text(true)
+            .warnings(move |w| sink.lock().unwrap().push(w.to_string()));
+        let source = "a: &x b\n*y: c\nd: '&z'\ne: Q&A";
+        let docs = StrictYamlLoader::load_from_str_with_options(source, &options).unwrap();
+        assert_eq!(docs[0]["a"].as_str(), Some("&x b"));
+        assert_eq!(docs[0]["*y"].as_str(), Some("c"));
+        assert_eq!(
+            *warnings.lock().unwrap(),
+            vec![
+                "anchor &x read as text at line 1 column 4",
+                "alias *y read as text at line 2 column 1",
+            ]
+        );
+
+        let err =
+            StrictYamlLoader::load_from_str_with_options(source, &LoadOptions::new()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "anchors are not part of StrictYAML, found &x at line 1 column 4"
+        );
+        let options = LoadOptions::new().expand_aliases(10);
+        let docs =
+            StrictYamlLoader::load_from_str_with_options("a: &x b\nc: *x", &options).unwrap();
+        assert_eq!(docs[0]["c"].as_str(), Some("b"));
+    }
+
+    #[cfg(feature = "unicode-normalization")]
+    #[test]
+    fn test_normalization() {
+        let source = "cafe\u{301}: cafe\u{301}\nlist:\n  - cafe\u{301}";
+        let load = |n| {
+            let options = LoadOptions::new().normalization(n);
+            StrictYamlLoader::load_from_str_with_options(source, &options).unwrap()
+        };
+        let docs = load(Normalization::Keys);
+        assert_eq!(docs[0]["caf\u{e9}"].as_str(), Some("cafe\u{301}"));
+        assert_eq!(docs[0]["list"][0].as_str(), Some("cafe\u{301}"));
+        let docs = load(Normalization::KeysAndValues);
+        assert_eq!(docs[0]["caf\u{e9}"].as_str(), Some("caf\u{e9}"));
+        assert_eq!(docs[0]["list"][0].as_str(), Some("caf\u{e9}"));
+        let docs = load(Normalization::None);
+        assert!(docs[0]["caf\u{e9}"].is_badvalue());
+    }
+
+    #[test]
+    fn test_metrics() {
+        use std::sync::Mutex;
+
+        let metrics = Arc::new(Mutex::new(Vec::new()));
+        let sink = metrics.clone();
+        let options = LoadOptions::new().metrics(move |m| sink.lock().unwrap().push(*m));
+        let source = "a: b\nc:\n  - d\n  - e: f\n---\ng\n";
+        StrictYamlLoader::load_from_str_with_options(source, &options).unwrap();
+
+        let metrics: Vec<DocumentMetrics> = metrics
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|m| DocumentMetrics {
+                duration: Duration::default(),
+                ..*m
+            })
+            .collect();
+        assert_eq!(
+            metrics,
+            [
+                DocumentMetrics {
+                    duration: Duration::default(),
+                    // the end of the document is found at the next `---`
+                    bytes: 27,
+                    events: 14,
+                    scalars: 6,
+                    sequences: 1,
+                    mappings: 2,
+                },
+                DocumentMetrics {
+                    duration: Duration::default(),
+                    bytes: source.len() - 27,
+                    events: 3,
+                    scalars: 1,
+                    sequences: 0,
+                    mappings: 0,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_progress() {
+        use std::sync::Mutex;
+
+        let mut source = String::new();
+        for i in 0..1000 {
+            source.push_str(&format!("key{}: value\n", i));
+        }
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let sink = reports.clone();
+        let options = LoadOptions::new().progress(move |p| sink.lock().unwrap().push(p));
+        StrictYamlLoader::load_from_str_with_options(&source, &options).unwrap();
+
+        let reports = reports.lock().unwrap();
+        // 2006 events: stream, document and mapping start/end plus 2000 scalars
+        assert_eq!(reports.len(), 2);
+        assert_eq!(reports[0].events, 1024);
+        assert!(reports[0].bytes < source.len());
+        assert_eq!(
+            reports[1],
+            Progress {
+                bytes: source.len(),
+                events: 2006
+            }
+        );
+    }
+}
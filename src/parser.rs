@@ -1,9 +1,13 @@
 use scanner::*;
+use std::collections::{HashMap, VecDeque};
+use std::error::Error;
+use std::fmt;
 
 #[derive(Clone, Copy, PartialEq, Debug, Eq)]
 enum State {
     StreamStart,
     ImplicitDocumentStart,
+    ExplicitDocumentStart,
     DocumentStart,
     DocumentContent,
     DocumentEnd,
@@ -15,6 +19,15 @@ enum State {
     BlockMappingFirstKey,
     BlockMappingKey,
     BlockMappingValue,
+    FlowSequenceFirstEntry,
+    FlowSequenceEntry,
+    FlowSequenceEntryMappingKey,
+    FlowSequenceEntryMappingValue,
+    FlowSequenceEntryMappingEnd,
+    FlowMappingFirstKey,
+    FlowMappingKey,
+    FlowMappingValue,
+    FlowMappingEmptyValue,
     End,
 }
 
@@ -37,6 +50,19 @@ pub enum Event {
     /// Anchor ID
     MappingStart(usize),
     MappingEnd,
+    /// A directive, reported before the `DocumentStart` of its document
+    Directive(Directive),
+}
+
+/// A `%` directive line at the start of a document.
+#[derive(Clone, PartialEq, Debug, Eq)]
+pub enum Directive {
+    /// `%YAML major.minor`
+    Version(u32, u32),
+    /// `%TAG handle prefix`
+    Tag(String, String),
+    /// Any other directive, by name; its parameters are skipped
+    Reserved(String),
 }
 
 impl Event {
@@ -46,13 +72,123 @@ impl Event {
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Parser<T> {
     scanner: Scanner<T>,
     states: Vec<State>,
     state: State,
     token: Option<Token>,
     current: Option<(Event, Marker)>,
+    /// The end of the last event parsed.
+    end: Marker,
+    /// The end of the scalar token or flow collection indicator of the event
+    /// being parsed.
+    token_end: Option<Marker>,
+    /// Kind and start of the collections being parsed, innermost last.
+    open: Vec<(&'static str, Marker)>,
+    aliases: Option<Aliases>,
+    /// Whether `&anchor` and `*alias` starting plain scalars are read as
+    /// text rather than rejected.
+    anchors_as_text: bool,
+}
+
+/// State of alias expansion, see `Parser::expand_aliases`.
+#[derive(Clone, Debug)]
+struct Aliases {
+    /// Number of events that may still be replayed.
+    budget: usize,
+    /// Events of the anchored nodes of the current document.
+    anchors: HashMap<String, Vec<Event>>,
+    /// Anchored nodes being parsed: name, events so far and nesting depth.
+    recording: Vec<(String, Vec<Event>, usize)>,
+    /// Anchor of the node about to be parsed.
+    pending: Option<String>,
+    /// Remaining events of the alias being expanded.
+    replay: VecDeque<(Event, Marker)>,
+    last_id: usize,
+}
+
+impl Aliases {
+    fn new(budget: usize) -> Aliases {
+        Aliases {
+            budget,
+            anchors: HashMap::new(),
+            recording: Vec::new(),
+            pending: None,
+            replay: VecDeque::new(),
+            last_id: 0,
+        }
+    }
+
+    /// Registers an anchor for the next node and returns its id.
+    fn anchor(&mut self, name: String) -> usize {
+        self.pending = Some(name);
+        self.last_id += 1;
+        self.last_id
+    }
+
+    /// Returns the first event of the node anchored as `name`, queueing the
+    /// others for replay.
+    fn expand(&mut self, name: &str, mark: Marker) -> ParseResult {
+        let events = match self.anchors.get(name) {
+            Some(events) => events,
+            None if self.recording.iter().any(|r| r.0 == name) => {
+                return Err(ScanError::parser(
+                    mark,
+                    &format!("alias *{} refers to a node containing it", name),
+                ))
+            }
+            None => {
+                return Err(ScanError::parser(
+                    mark,
+                    &format!("alias *{} refers to an unknown anchor", name),
+                ))
+            }
+        };
+        if events.len() > self.budget {
+            #[cfg(feature = "tracing")]
+            tracing::debug!(alias = name, budget = self.budget, "alias budget exceeded");
+            return Err(ScanError::limit(
+                mark,
+                &format!("expanding alias *{} exceeds the alias budget", name),
+            ));
+        }
+        self.budget -= events.len();
+        self.replay
+            .extend(events[1..].iter().map(|ev| (ev.clone(), mark)));
+        Ok((events[0].clone(), mark))
+    }
+
+    /// Keeps the events of anchored nodes.
+    fn record(&mut self, ev: &Event) {
+        if let Some(name) = self.pending.take() {
+            self.recording.push((name, Vec::new(), 0));
+        }
+        for &mut (_, ref mut events, ref mut depth) in &mut self.recording {
+            events.push(ev.clone());
+            match *ev {
+                Event::SequenceStart(_) | Event::MappingStart(_) => *depth += 1,
+                Event::SequenceEnd | Event::MappingEnd => *depth -= 1,
+                _ => {}
+            }
+        }
+        while let Some(&(_, _, 0)) = self.recording.last() {
+            if let Some((name, events, _)) = self.recording.pop() {
+                self.anchors.insert(name, events);
+            }
+        }
+        if *ev == Event::DocumentEnd {
+            // anchors are local to their document
+            self.anchors.clear();
+        }
+    }
+
+    fn reset(&mut self) {
+        self.anchors.clear();
+        self.recording.clear();
+        self.pending = None;
+        self.replay.clear();
+    }
 }
 
 pub trait EventReceiver {
@@ -69,6 +205,110 @@ impl<R: EventReceiver> MarkedEventReceiver for R {
     }
 }
 
+/// A receiver given the span of each event, see `Parser::load_spanned`.
+pub trait SpannedEventReceiver {
+    fn on_spanned_event(&mut self, ev: Event, span: Span) -> Result<(), ScanError>;
+}
+
+impl<R: MarkedEventReceiver> SpannedEventReceiver for R {
+    fn on_spanned_event(&mut self, ev: Event, span: Span) -> Result<(), ScanError> {
+        self.on_event(ev, span.start())
+    }
+}
+
+/// A receiver that can abort loading with an error of its own, see `Parser::try_load`.
+pub trait TryMarkedEventReceiver {
+    type Error;
+
+    fn try_on_event(&mut self, ev: Event, mark: Marker) -> Result<(), Self::Error>;
+}
+
+/// Error returned by `Parser::try_load`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum ReceiveError<E> {
+    /// The input is not valid.
+    ScanError(ScanError),
+    /// The receiver aborted the load.
+    Receiver(E),
+}
+
+impl<E> From<ScanError> for ReceiveError<E> {
+    fn from(e: ScanError) -> ReceiveError<E> {
+        ReceiveError::ScanError(e)
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for ReceiveError<E> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ReceiveError::ScanError(ref err) => err.fmt(formatter),
+            ReceiveError::Receiver(ref err) => err.fmt(formatter),
+        }
+    }
+}
+
+impl<E: Error + 'static> Error for ReceiveError<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            ReceiveError::ScanError(ref err) => Some(err),
+            ReceiveError::Receiver(ref err) => Some(err),
+        }
+    }
+}
+
+/// Error for an event `load` cannot handle, which happens when the parser was
+/// advanced by hand to the middle of a document.
+fn unexpected<E>(ev: &Event, mark: Marker) -> ReceiveError<E> {
+    ReceiveError::ScanError(ScanError::parser(
+        mark,
+        &format!("unexpected event {:?}", ev),
+    ))
+}
+
+/// The mark past the one character `[` or `{` at `mark`.
+fn past_indicator(mark: Marker) -> Marker {
+    Marker::new(mark.index() + 1, mark.line(), mark.col() + 1)
+}
+
+/// Lets `load` drive a `MarkedEventReceiver` through `try_load`.
+struct Infallible<'a, R: 'a>(&'a mut R);
+
+impl<'a, R: MarkedEventReceiver> TryMarkedEventReceiver for Infallible<'a, R> {
+    type Error = ScanError;
+
+    fn try_on_event(&mut self, ev: Event, mark: Marker) -> Result<(), ScanError> {
+        self.0.on_event(ev, mark)
+    }
+}
+
+/// A receiver `load_events` drives, given the span of each event.
+trait Receive {
+    type Error;
+
+    fn receive(&mut self, ev: Event, span: Span) -> Result<(), Self::Error>;
+}
+
+/// Gives a `TryMarkedEventReceiver` the start of each span.
+struct Starts<'a, R: 'a>(&'a mut R);
+
+impl<'a, R: TryMarkedEventReceiver> Receive for Starts<'a, R> {
+    type Error = R::Error;
+
+    fn receive(&mut self, ev: Event, span: Span) -> Result<(), R::Error> {
+        self.0.try_on_event(ev, span.start())
+    }
+}
+
+struct Spans<'a, R: 'a>(&'a mut R);
+
+impl<'a, R: SpannedEventReceiver> Receive for Spans<'a, R> {
+    type Error = ScanError;
+
+    fn receive(&mut self, ev: Event, span: Span) -> Result<(), ScanError> {
+        self.0.on_spanned_event(ev, span)
+    }
+}
+
 pub type ParseResult = Result<(Event, Marker), ScanError>;
 
 impl<T: Iterator<Item = char>> Parser<T> {
@@ -79,33 +319,162 @@ impl<T: Iterator<Item = char>> Parser<T> {
             state: State::StreamStart,
             token: None,
             current: None,
+            end: Marker::new(0, 1, 0),
+            token_end: None,
+            open: Vec::new(),
+            aliases: None,
+            anchors_as_text: false,
         }
     }
 
+    /// Accepts `&anchor` and `*alias`, which are not part of StrictYAML, for
+    /// reading legacy YAML. Each alias is replaced by a copy of the events of
+    /// its anchored node. At most `budget` events are replayed in total, which
+    /// bounds the size of documents built from nested aliases.
+    ///
+    /// Must be called before the first event is parsed.
+    pub fn expand_aliases(&mut self, budget: usize) {
+        self.scanner.enable_anchors();
+        self.aliases = Some(Aliases::new(budget));
+    }
+
+    /// Reads `&anchor` and `*alias` at the start of plain scalars as part of
+    /// their text, e.g. `&x a` as `"&x a"`, rather than failing on them.
+    ///
+    /// Must be called before the first event is parsed.
+    pub fn anchors_as_text(&mut self) {
+        self.anchors_as_text = true;
+    }
+
+    /// Keeps the comments of the input, which are otherwise skipped, for
+    /// `take_comments`.
+    ///
+    /// Must be called before the first event is parsed.
+    pub fn keep_comments(&mut self) {
+        self.scanner.keep_comments();
+    }
+
+    /// Returns the comments scanned since the last call, with the position of
+    /// their `#` and their text after it. The scanner reads ahead of the
+    /// events, so these may include comments following the last event.
+    pub fn take_comments(&mut self) -> Vec<(Marker, String)> {
+        self.scanner.take_comments()
+    }
+
+    /// Parses flow collections (`[a, b]` and `{k: v}`), which are not part of
+    /// StrictYAML, into sequence and mapping events instead of scalars, for
+    /// reading legacy YAML.
+    ///
+    /// Must be called before the first event is parsed.
+    pub fn parse_flow_collections(&mut self) {
+        self.scanner.enable_flow();
+    }
+
+    /// Keeps the spaces and tabs ending the last line of plain scalars, which
+    /// YAML drops, e.g. `a: b  ` reads as `"b  "`. Blanks ending a line
+    /// followed by more of the scalar are still folded away.
+    ///
+    /// Must be called before the first event is parsed.
+    pub fn keep_trailing_blanks(&mut self) {
+        self.scanner.keep_trailing_blanks();
+    }
+
+    pub(crate) fn scanner(&self) -> &Scanner<T> {
+        &self.scanner
+    }
+
+    /// Returns a reference to the next event without consuming it.
+    ///
+    /// The peeked event is moved out (not cloned) by the following `next` call.
     pub fn peek(&mut self) -> Result<&(Event, Marker), ScanError> {
         match self.current {
             Some(ref x) => Ok(x),
             None => {
-                self.current = Some(self.next()?);
-                self.peek()
+                let next = self.parse()?;
+                Ok(self.current.get_or_insert(next))
             }
         }
     }
 
     pub fn next(&mut self) -> ParseResult {
-        match self.current {
+        match self.current.take() {
+            Some(x) => Ok(x),
             None => self.parse(),
-            Some(_) => Ok(self.current.take().unwrap()),
         }
     }
 
+    /// Like `next`, with the span of the source the event was parsed from.
+    /// The span of a scalar covers its content, from its first character to
+    /// its last, including quotes, and the span of a flow collection start
+    /// covers its `[` or `{`; other events and empty scalars have an empty
+    /// span at their position.
+    pub fn next_spanned(&mut self) -> Result<(Event, Span), ScanError> {
+        // `end` is still that of the event returned, peeked or not
+        let (ev, mark) = self.next()?;
+        Ok((ev, Span::new(mark, self.end)))
+    }
+
+    /// Consumes the next node, including all of its children when it is a
+    /// sequence or a mapping, without building anything.
+    ///
+    /// Memory use is constant regardless of the size of the skipped node, which
+    /// makes this the building block for extracting a few values out of very
+    /// large documents. Returns the marker of the skipped node.
+    pub fn skip_node(&mut self) -> Result<Marker, ScanError> {
+        let (ev, start) = self.next()?;
+        let mut depth = match ev {
+            Event::SequenceStart(_) | Event::MappingStart(_) => 1usize,
+            Event::Scalar(..) => 0,
+            _ => {
+                return Err(ScanError::parser(
+                    start,
+                    &format!("expected a node, found {:?}", ev),
+                ))
+            }
+        };
+        while depth > 0 {
+            match self.next()?.0 {
+                Event::SequenceStart(_) | Event::MappingStart(_) => depth += 1,
+                Event::SequenceEnd | Event::MappingEnd => depth -= 1,
+                Event::StreamEnd => {
+                    return Err(ScanError::parser(start, "unexpected end of stream in node"))
+                }
+                _ => {}
+            }
+        }
+        Ok(start)
+    }
+
+    /// Discards the remainder of the current (failed) document so that parsing
+    /// can resume with the next explicit document of the stream.
+    pub fn skip_to_next_document(&mut self) {
+        self.current = None;
+        self.token_end = None;
+        self.states.clear();
+        self.open.clear();
+        if let Some(ref mut aliases) = self.aliases {
+            aliases.reset();
+        }
+        if self.state == State::End {
+            return;
+        }
+        match self.token {
+            Some(Token(_, TokenType::DocumentStart)) | Some(Token(_, TokenType::StreamEnd)) => {}
+            _ => {
+                self.token = None;
+                self.scanner.skip_to_next_document();
+            }
+        }
+        self.state = State::DocumentStart;
+    }
+
     fn peek_token(&mut self) -> Result<&Token, ScanError> {
         match self.token {
+            Some(ref tok) => Ok(tok),
             None => {
-                self.token = Some(self.scan_next_token()?);
-                Ok(self.token.as_ref().unwrap())
+                let tok = self.scan_next_token()?;
+                Ok(self.token.get_or_insert(tok))
             }
-            Some(ref tok) => Ok(tok),
         }
     }
 
@@ -113,13 +482,19 @@ impl<T: Iterator<Item = char>> Parser<T> {
         let token = self.scanner.next();
         match token {
             None => match self.scanner.get_error() {
-                None => Err(ScanError::new(self.scanner.mark(), "unexpected eof")),
+                None => Err(ScanError::parser(
+                    self.scanner.mark(),
+                    "unexpected end of input",
+                )),
                 Some(e) => Err(e),
             },
             Some(tok) => Ok(tok),
         }
     }
 
+    /// Takes the token that `peek_token` returned. Each call follows a
+    /// successful `peek_token` in the same function, with nothing taking the
+    /// token in between.
     fn fetch_token(&mut self) -> Token {
         self.token
             .take()
@@ -130,8 +505,17 @@ impl<T: Iterator<Item = char>> Parser<T> {
         self.token = None;
         //self.peek_token();
     }
-    fn pop_state(&mut self) {
-        self.state = self.states.pop().unwrap()
+    fn pop_state(&mut self) -> Result<(), ScanError> {
+        match self.states.pop() {
+            Some(state) => {
+                self.state = state;
+                Ok(())
+            }
+            None => Err(ScanError::parser(
+                self.scanner.mark(),
+                "unexpected end of a node, with nothing to return to",
+            )),
+        }
     }
     fn push_state(&mut self, state: State) {
         self.states.push(state);
@@ -139,11 +523,55 @@ impl<T: Iterator<Item = char>> Parser<T> {
 
     fn parse(&mut self) -> ParseResult {
         if self.state == State::End {
-            return Ok((Event::StreamEnd, self.scanner.mark()));
+            self.end = self.scanner.mark();
+            return Ok((Event::StreamEnd, self.end));
+        }
+        let replayed = self.aliases.as_mut().and_then(|a| a.replay.pop_front());
+        let res = match replayed {
+            Some(replayed) => Ok(replayed),
+            None => self.state_machine(),
+        };
+        #[cfg(feature = "tracing")]
+        if let Err(ref e) = res {
+            tracing::debug!(
+                line = e.marker().line(),
+                column = e.marker().col() + 1,
+                error = e.info(),
+                "parse error"
+            );
+        }
+        match res {
+            Ok((ev, mark)) => {
+                self.end = self.token_end.take().unwrap_or(mark);
+                if let Some(ref mut aliases) = self.aliases {
+                    aliases.record(&ev);
+                }
+                match ev {
+                    Event::SequenceStart(_) => self.open.push(("sequence", mark)),
+                    Event::MappingStart(_) => self.open.push(("mapping", mark)),
+                    Event::SequenceEnd | Event::MappingEnd => {
+                        self.open.pop();
+                    }
+                    _ => {}
+                }
+                Ok((ev, mark))
+            }
+            Err(e) => match self.open.last() {
+                // name the construct the input ended in
+                Some(&(kind, start)) if self.scanner.ended_early() => Err(ScanError::with_origin(
+                    e.origin(),
+                    *e.marker(),
+                    &format!(
+                        "{}, inside the {} started at line {} column {}",
+                        e.info(),
+                        kind,
+                        start.line(),
+                        start.col() + 1
+                    ),
+                )),
+                _ => Err(e),
+            },
         }
-        let (ev, mark) = self.state_machine()?;
-        // println!("EV {:?}", ev);
-        Ok((ev, mark))
     }
 
     pub fn load<R: MarkedEventReceiver>(
@@ -151,24 +579,61 @@ impl<T: Iterator<Item = char>> Parser<T> {
         recv: &mut R,
         multi: bool,
     ) -> Result<(), ScanError> {
+        match self.try_load(&mut Infallible(recv), multi) {
+            Ok(()) => Ok(()),
+            Err(ReceiveError::ScanError(e)) | Err(ReceiveError::Receiver(e)) => Err(e),
+        }
+    }
+
+    /// Like `load`, for receivers with their own error type. Errors of the
+    /// receiver stop the load and are returned as `ReceiveError::Receiver`.
+    pub fn try_load<R: TryMarkedEventReceiver>(
+        &mut self,
+        recv: &mut R,
+        multi: bool,
+    ) -> Result<(), ReceiveError<R::Error>> {
+        self.load_events(&mut Starts(recv), multi)
+    }
+
+    /// Like `load`, giving the receiver the span of each event, see
+    /// `next_spanned`.
+    pub fn load_spanned<R: SpannedEventReceiver>(
+        &mut self,
+        recv: &mut R,
+        multi: bool,
+    ) -> Result<(), ScanError> {
+        match self.load_events(&mut Spans(recv), multi) {
+            Ok(()) => Ok(()),
+            Err(ReceiveError::ScanError(e)) | Err(ReceiveError::Receiver(e)) => Err(e),
+        }
+    }
+
+    fn load_events<R: Receive>(
+        &mut self,
+        recv: &mut R,
+        multi: bool,
+    ) -> Result<(), ReceiveError<R::Error>> {
         if !self.scanner.stream_started() {
-            let (ev, mark) = self.next()?;
-            assert_eq!(ev, Event::StreamStart);
-            recv.on_event(ev, mark)?;
+            let (ev, span) = self.next_spanned()?;
+            if ev != Event::StreamStart {
+                return Err(unexpected(&ev, span.start()));
+            }
+            recv.receive(ev, span).map_err(ReceiveError::Receiver)?;
         }
 
         if self.scanner.stream_ended() {
             // XXX has parsed?
-            recv.on_event(Event::StreamEnd, self.scanner.mark())?;
+            recv.receive(Event::StreamEnd, Span::empty(self.scanner.mark()))
+                .map_err(ReceiveError::Receiver)?;
             return Ok(());
         }
         loop {
-            let (ev, mark) = self.next()?;
+            let (ev, span) = self.next_spanned()?;
             if ev == Event::StreamEnd {
-                recv.on_event(ev, mark)?;
+                recv.receive(ev, span).map_err(ReceiveError::Receiver)?;
                 return Ok(());
             }
-            self.load_document(ev, mark, recv)?;
+            self.load_document(ev, span, recv)?;
             if !multi {
                 break;
             }
@@ -176,83 +641,90 @@ impl<T: Iterator<Item = char>> Parser<T> {
         Ok(())
     }
 
-    fn load_document<R: MarkedEventReceiver>(
+    fn load_document<R: Receive>(
         &mut self,
         first_ev: Event,
-        mark: Marker,
+        span: Span,
         recv: &mut R,
-    ) -> Result<(), ScanError> {
-        assert_eq!(first_ev, Event::DocumentStart);
-        recv.on_event(first_ev, mark)?;
+    ) -> Result<(), ReceiveError<R::Error>> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("document", line = span.start().line()).entered();
+        let (mut first_ev, mut span) = (first_ev, span);
+        while let Event::Directive(_) = first_ev {
+            recv.receive(first_ev, span)
+                .map_err(ReceiveError::Receiver)?;
+            let (ev, next_span) = self.next_spanned()?;
+            first_ev = ev;
+            span = next_span;
+        }
+        if first_ev != Event::DocumentStart {
+            return Err(unexpected(&first_ev, span.start()));
+        }
+        #[cfg(feature = "tracing")]
+        tracing::debug!(line = span.start().line(), "document start");
+        recv.receive(first_ev, span)
+            .map_err(ReceiveError::Receiver)?;
 
-        let (ev, mark) = self.next()?;
-        self.load_node(ev, mark, recv)?;
+        let (ev, span) = self.next_spanned()?;
+        self.load_node(ev, span, recv)?;
 
         // DOCUMENT-END is expected.
-        let (ev, mark) = self.next()?;
-        assert_eq!(ev, Event::DocumentEnd);
-        recv.on_event(ev, mark)?;
+        let (ev, span) = self.next_spanned()?;
+        if ev != Event::DocumentEnd {
+            return Err(unexpected(&ev, span.start()));
+        }
+        #[cfg(feature = "tracing")]
+        tracing::debug!(line = span.start().line(), "document end");
+        recv.receive(ev, span).map_err(ReceiveError::Receiver)?;
 
         Ok(())
     }
 
-    fn load_node<R: MarkedEventReceiver>(
+    /// Loads the node starting with `first_ev` and its children. Nesting is
+    /// tracked on the heap rather than by recursion, so that deep documents
+    /// cannot overflow the stack.
+    fn load_node<R: Receive>(
         &mut self,
         first_ev: Event,
-        mark: Marker,
+        span: Span,
         recv: &mut R,
-    ) -> Result<(), ScanError> {
-        match first_ev {
-            Event::Scalar(..) => {
-                recv.on_event(first_ev, mark)?;
-                Ok(())
-            }
-            Event::SequenceStart(_) => {
-                recv.on_event(first_ev, mark)?;
-                self.load_sequence(recv)
-            }
-            Event::MappingStart(_) => {
-                recv.on_event(first_ev, mark)?;
-                self.load_mapping(recv)
+    ) -> Result<(), ReceiveError<R::Error>> {
+        // the collections being loaded, innermost last: whether each is a
+        // mapping, and its number of nodes so far
+        let mut open: Vec<(bool, usize)> = Vec::new();
+        let (mut ev, mut span) = (first_ev, span);
+        loop {
+            match ev {
+                Event::Scalar(..) => {}
+                Event::SequenceStart(_) => open.push((false, 0)),
+                Event::MappingStart(_) => open.push((true, 0)),
+                Event::SequenceEnd if matches!(open.last(), Some(&(false, _))) => {
+                    open.pop();
+                }
+                // a mapping ends after the value of its last key
+                Event::MappingEnd if matches!(open.last(), Some(&(true, n)) if n % 2 == 0) => {
+                    open.pop();
+                }
+                _ => return Err(unexpected(&ev, span.start())),
             }
-            _ => {
-                println!("UNREACHABLE EVENT: {:?}", first_ev);
-                unreachable!();
+            let done = match ev {
+                Event::SequenceStart(_) | Event::MappingStart(_) => false,
+                _ => match open.last_mut() {
+                    Some(parent) => {
+                        parent.1 += 1;
+                        false
+                    }
+                    None => true,
+                },
+            };
+            recv.receive(ev, span).map_err(ReceiveError::Receiver)?;
+            if done {
+                return Ok(());
             }
-        }
-    }
-
-    fn load_mapping<R: MarkedEventReceiver>(&mut self, recv: &mut R) -> Result<(), ScanError> {
-        let (mut key_ev, mut key_mark) = self.next()?;
-        while key_ev != Event::MappingEnd {
-            // key
-            self.load_node(key_ev, key_mark, recv)?;
-
-            // value
-            let (ev, mark) = self.next()?;
-            self.load_node(ev, mark, recv)?;
-
-            // next event
-            let (ev, mark) = self.next()?;
-            key_ev = ev;
-            key_mark = mark;
-        }
-        recv.on_event(key_ev, key_mark)?;
-        Ok(())
-    }
-
-    fn load_sequence<R: MarkedEventReceiver>(&mut self, recv: &mut R) -> Result<(), ScanError> {
-        let (mut ev, mut mark) = self.next()?;
-        while ev != Event::SequenceEnd {
-            self.load_node(ev, mark, recv)?;
-
-            // next event
-            let (next_ev, next_mark) = self.next()?;
+            let (next_ev, next_span) = self.next_spanned()?;
             ev = next_ev;
-            mark = next_mark;
+            span = next_span;
         }
-        recv.on_event(ev, mark)?;
-        Ok(())
     }
 
     fn state_machine(&mut self) -> ParseResult {
@@ -262,6 +734,7 @@ impl<T: Iterator<Item = char>> Parser<T> {
             State::StreamStart => self.stream_start(),
 
             State::ImplicitDocumentStart => self.document_start(true),
+            State::ExplicitDocumentStart => self._explict_document_start(),
             State::DocumentStart => self.document_start(false),
             State::DocumentContent => self.document_content(),
             State::DocumentEnd => self.document_end(),
@@ -277,7 +750,18 @@ impl<T: Iterator<Item = char>> Parser<T> {
 
             State::IndentlessSequenceEntry => self.indentless_sequence_entry(),
 
-            /* impossible */
+            State::FlowSequenceFirstEntry => self.flow_sequence_entry(true),
+            State::FlowSequenceEntry => self.flow_sequence_entry(false),
+            State::FlowSequenceEntryMappingKey => self.flow_sequence_entry_mapping_key(),
+            State::FlowSequenceEntryMappingValue => self.flow_sequence_entry_mapping_value(),
+            State::FlowSequenceEntryMappingEnd => self.flow_sequence_entry_mapping_end(),
+
+            State::FlowMappingFirstKey => self.flow_mapping_key(true),
+            State::FlowMappingKey => self.flow_mapping_key(false),
+            State::FlowMappingValue => self.flow_mapping_value(false),
+            State::FlowMappingEmptyValue => self.flow_mapping_value(true),
+
+            // `parse` returns the stream end without coming here
             State::End => unreachable!(),
         }
     }
@@ -289,7 +773,10 @@ impl<T: Iterator<Item = char>> Parser<T> {
                 self.skip();
                 Ok((Event::StreamStart, mark))
             }
-            Token(mark, _) => Err(ScanError::new(mark, "did not find expected <stream-start>")),
+            Token(mark, _) => Err(ScanError::parser(
+                mark,
+                "did not find expected <stream-start>",
+            )),
         }
     }
 
@@ -308,12 +795,12 @@ impl<T: Iterator<Item = char>> Parser<T> {
             }
             Token(_, TokenType::VersionDirective(..))
             | Token(_, TokenType::TagDirective(..))
+            | Token(_, TokenType::ReservedDirective(..))
             | Token(_, TokenType::DocumentStart) => {
                 // explicit document
                 self._explict_document_start()
             }
             Token(mark, _) if implicit => {
-                self.parser_process_directives()?;
                 self.push_state(State::DocumentEnd);
                 self.state = State::BlockNode;
                 Ok((Event::DocumentStart, mark))
@@ -325,29 +812,25 @@ impl<T: Iterator<Item = char>> Parser<T> {
         }
     }
 
-    fn parser_process_directives(&mut self) -> Result<(), ScanError> {
-        loop {
-            match self.peek_token()?.1 {
-                TokenType::VersionDirective(_, _) => {
-                    // XXX parsing with warning according to spec
-                    //if major != 1 || minor > 2 {
-                    //    return Err(ScanError::new(tok.0,
-                    //        "found incompatible YAML document"));
-                    //}
-                }
-                TokenType::TagDirective(..) => {
-                    // TODO add tag directive
-                }
-                _ => break,
-            }
-            self.skip();
-        }
-        // TODO tag directive
-        Ok(())
-    }
-
     fn _explict_document_start(&mut self) -> ParseResult {
-        self.parser_process_directives()?;
+        // directives are reported one by one, until the document starts
+        if matches!(
+            self.peek_token()?.1,
+            TokenType::VersionDirective(..)
+                | TokenType::TagDirective(..)
+                | TokenType::ReservedDirective(..)
+        ) {
+            let Token(mark, tok) = self.fetch_token();
+            let directive = match tok {
+                TokenType::VersionDirective(major, minor) => Directive::Version(major, minor),
+                TokenType::TagDirective(handle, prefix) => Directive::Tag(handle, prefix),
+                TokenType::ReservedDirective(name) => Directive::Reserved(name),
+                // the token peeked above is a directive
+                _ => unreachable!(),
+            };
+            self.state = State::ExplicitDocumentStart;
+            return Ok((Event::Directive(directive), mark));
+        }
         match *self.peek_token()? {
             Token(mark, TokenType::DocumentStart) => {
                 self.push_state(State::DocumentEnd);
@@ -355,7 +838,7 @@ impl<T: Iterator<Item = char>> Parser<T> {
                 self.skip();
                 Ok((Event::DocumentStart, mark))
             }
-            Token(mark, _) => Err(ScanError::new(
+            Token(mark, _) => Err(ScanError::parser(
                 mark,
                 "did not find expected <document start>",
             )),
@@ -366,10 +849,11 @@ impl<T: Iterator<Item = char>> Parser<T> {
         match *self.peek_token()? {
             Token(mark, TokenType::VersionDirective(..))
             | Token(mark, TokenType::TagDirective(..))
+            | Token(mark, TokenType::ReservedDirective(..))
             | Token(mark, TokenType::DocumentStart)
             | Token(mark, TokenType::DocumentEnd)
             | Token(mark, TokenType::StreamEnd) => {
-                self.pop_state();
+                self.pop_state()?;
                 // empty scalar
                 Ok((Event::empty_scalar(), mark))
             }
@@ -394,17 +878,54 @@ impl<T: Iterator<Item = char>> Parser<T> {
     }
 
     fn parse_node(&mut self, block: bool, indentless_sequence: bool) -> ParseResult {
-        let anchor_id = 0;
+        let mut anchor_id = 0;
+        if let Token(mark, TokenType::Anchor(_)) = *self.peek_token()? {
+            if let Token(_, TokenType::Anchor(name)) = self.fetch_token() {
+                anchor_id = match self.aliases {
+                    Some(ref mut aliases) => aliases.anchor(name),
+                    None => return Err(ScanError::parser(mark, "unexpected anchor")),
+                };
+            }
+        }
         match *self.peek_token()? {
+            Token(mark, TokenType::Alias(_)) => {
+                if anchor_id > 0 {
+                    return Err(ScanError::parser(mark, "an alias cannot have an anchor"));
+                }
+                self.pop_state()?;
+                let name = match self.fetch_token() {
+                    Token(_, TokenType::Alias(name)) => name,
+                    // the token peeked above is an alias
+                    _ => unreachable!(),
+                };
+                match self.aliases {
+                    Some(ref mut aliases) => aliases.expand(&name, mark),
+                    None => Err(ScanError::parser(mark, "unexpected alias")),
+                }
+            }
             Token(mark, TokenType::BlockEntry) if indentless_sequence => {
                 self.state = State::IndentlessSequenceEntry;
                 Ok((Event::SequenceStart(anchor_id), mark))
             }
             Token(_, TokenType::Scalar(..)) => {
-                self.pop_state();
+                self.pop_state()?;
                 if let Token(mark, TokenType::Scalar(style, v)) = self.fetch_token() {
+                    if style == TScalarStyle::Plain && !self.anchors_as_text {
+                        if let Some(found) = leading_anchor(&v) {
+                            let what = if found.starts_with('&') {
+                                "anchors"
+                            } else {
+                                "aliases"
+                            };
+                            let info =
+                                format!("{} are not part of StrictYAML, found {}", what, found);
+                            return Err(ScanError::parser(mark, &info));
+                        }
+                    }
+                    self.token_end = self.scanner.scalar_end();
                     Ok((Event::Scalar(v, style, anchor_id), mark))
                 } else {
+                    // the token peeked above is a scalar
                     unreachable!()
                 }
             }
@@ -416,7 +937,25 @@ impl<T: Iterator<Item = char>> Parser<T> {
                 self.state = State::BlockMappingFirstKey;
                 Ok((Event::MappingStart(anchor_id), mark))
             }
-            Token(mark, _) => Err(ScanError::new(
+            Token(mark, TokenType::FlowSequenceStart) => {
+                self.state = State::FlowSequenceFirstEntry;
+                self.token_end = Some(past_indicator(mark));
+                Ok((Event::SequenceStart(anchor_id), mark))
+            }
+            Token(mark, TokenType::FlowMappingStart) => {
+                self.state = State::FlowMappingFirstKey;
+                self.token_end = Some(past_indicator(mark));
+                Ok((Event::MappingStart(anchor_id), mark))
+            }
+            // an anchored empty node
+            Token(mark, _) if anchor_id > 0 => {
+                self.pop_state()?;
+                Ok((
+                    Event::Scalar(String::new(), TScalarStyle::Plain, anchor_id),
+                    mark,
+                ))
+            }
+            Token(mark, _) => Err(ScanError::parser(
                 mark,
                 "while parsing a node, did not find expected node content",
             )),
@@ -453,11 +992,11 @@ impl<T: Iterator<Item = char>> Parser<T> {
                 Ok((Event::empty_scalar(), mark))
             }
             Token(mark, TokenType::BlockEnd) => {
-                self.pop_state();
+                self.pop_state()?;
                 self.skip();
                 Ok((Event::MappingEnd, mark))
             }
-            Token(mark, _) => Err(ScanError::new(
+            Token(mark, _) => Err(ScanError::parser(
                 mark,
                 "while parsing a block mapping, did not find expected key",
             )),
@@ -494,7 +1033,7 @@ impl<T: Iterator<Item = char>> Parser<T> {
         match *self.peek_token()? {
             Token(_, TokenType::BlockEntry) => (),
             Token(mark, _) => {
-                self.pop_state();
+                self.pop_state()?;
                 return Ok((Event::SequenceEnd, mark));
             }
         }
@@ -514,6 +1053,163 @@ impl<T: Iterator<Item = char>> Parser<T> {
         }
     }
 
+    fn flow_sequence_entry(&mut self, first: bool) -> ParseResult {
+        // FLOW-SEQUENCE-START
+        if first {
+            let _ = self.peek_token()?;
+            self.skip();
+        }
+        match *self.peek_token()? {
+            Token(mark, TokenType::FlowSequenceEnd) => {
+                self.pop_state()?;
+                self.skip();
+                return Ok((Event::SequenceEnd, mark));
+            }
+            Token(_, TokenType::FlowEntry) if !first => self.skip(),
+            Token(mark, _) if !first => {
+                return Err(ScanError::parser(
+                    mark,
+                    "while parsing a flow sequence, did not find expected ',' or ']'",
+                ));
+            }
+            _ => {}
+        }
+        match *self.peek_token()? {
+            Token(mark, TokenType::FlowSequenceEnd) => {
+                self.pop_state()?;
+                self.skip();
+                Ok((Event::SequenceEnd, mark))
+            }
+            // a single pair mapping, as in `[a: b]`
+            Token(mark, TokenType::Key) => {
+                self.state = State::FlowSequenceEntryMappingKey;
+                self.skip();
+                Ok((Event::MappingStart(0), mark))
+            }
+            _ => {
+                self.push_state(State::FlowSequenceEntry);
+                self.parse_node(false, false)
+            }
+        }
+    }
+
+    fn flow_sequence_entry_mapping_key(&mut self) -> ParseResult {
+        match *self.peek_token()? {
+            Token(mark, TokenType::Value)
+            | Token(mark, TokenType::FlowEntry)
+            | Token(mark, TokenType::FlowSequenceEnd) => {
+                self.state = State::FlowSequenceEntryMappingValue;
+                Ok((Event::empty_scalar(), mark))
+            }
+            _ => {
+                self.push_state(State::FlowSequenceEntryMappingValue);
+                self.parse_node(false, false)
+            }
+        }
+    }
+
+    fn flow_sequence_entry_mapping_value(&mut self) -> ParseResult {
+        match *self.peek_token()? {
+            Token(_, TokenType::Value) => {
+                self.skip();
+                self.state = State::FlowSequenceEntryMappingEnd;
+                match *self.peek_token()? {
+                    Token(mark, TokenType::FlowEntry) | Token(mark, TokenType::FlowSequenceEnd) => {
+                        Ok((Event::empty_scalar(), mark))
+                    }
+                    _ => {
+                        self.push_state(State::FlowSequenceEntryMappingEnd);
+                        self.parse_node(false, false)
+                    }
+                }
+            }
+            Token(mark, _) => {
+                self.state = State::FlowSequenceEntryMappingEnd;
+                Ok((Event::empty_scalar(), mark))
+            }
+        }
+    }
+
+    fn flow_sequence_entry_mapping_end(&mut self) -> ParseResult {
+        let mark = self.peek_token()?.0;
+        self.state = State::FlowSequenceEntry;
+        Ok((Event::MappingEnd, mark))
+    }
+
+    fn flow_mapping_key(&mut self, first: bool) -> ParseResult {
+        // FLOW-MAPPING-START
+        if first {
+            let _ = self.peek_token()?;
+            self.skip();
+        }
+        match *self.peek_token()? {
+            Token(mark, TokenType::FlowMappingEnd) => {
+                self.pop_state()?;
+                self.skip();
+                return Ok((Event::MappingEnd, mark));
+            }
+            Token(_, TokenType::FlowEntry) if !first => self.skip(),
+            Token(mark, _) if !first => {
+                return Err(ScanError::parser(
+                    mark,
+                    "while parsing a flow mapping, did not find expected ',' or '}'",
+                ));
+            }
+            _ => {}
+        }
+        match *self.peek_token()? {
+            Token(mark, TokenType::FlowMappingEnd) => {
+                self.pop_state()?;
+                self.skip();
+                Ok((Event::MappingEnd, mark))
+            }
+            Token(_, TokenType::Key) => {
+                self.skip();
+                match *self.peek_token()? {
+                    Token(mark, TokenType::Value)
+                    | Token(mark, TokenType::FlowEntry)
+                    | Token(mark, TokenType::FlowMappingEnd) => {
+                        self.state = State::FlowMappingValue;
+                        Ok((Event::empty_scalar(), mark))
+                    }
+                    _ => {
+                        self.push_state(State::FlowMappingValue);
+                        self.parse_node(false, false)
+                    }
+                }
+            }
+            Token(mark, TokenType::Value) => {
+                self.state = State::FlowMappingValue;
+                Ok((Event::empty_scalar(), mark))
+            }
+            // a key without value, as in `{a, b: c}`
+            _ => {
+                self.push_state(State::FlowMappingEmptyValue);
+                self.parse_node(false, false)
+            }
+        }
+    }
+
+    fn flow_mapping_value(&mut self, empty: bool) -> ParseResult {
+        self.state = State::FlowMappingKey;
+        match *self.peek_token()? {
+            Token(mark, _) if empty => Ok((Event::empty_scalar(), mark)),
+            Token(_, TokenType::Value) => {
+                self.skip();
+                match *self.peek_token()? {
+                    Token(mark, TokenType::FlowEntry) | Token(mark, TokenType::FlowMappingEnd) => {
+                        Ok((Event::empty_scalar(), mark))
+                    }
+                    _ => {
+                        self.push_state(State::FlowMappingKey);
+                        self.parse_node(false, false)
+                    }
+                }
+            }
+            Token(mark, _) => Ok((Event::empty_scalar(), mark)),
+        }
+    }
+
     fn block_sequence_entry(&mut self, first: bool) -> ParseResult {
         // BLOCK-SEQUENCE-START
         if first {
@@ -523,7 +1219,7 @@ impl<T: Iterator<Item = char>> Parser<T> {
         }
         match *self.peek_token()? {
             Token(mark, TokenType::BlockEnd) => {
-                self.pop_state();
+                self.pop_state()?;
                 self.skip();
                 Ok((Event::SequenceEnd, mark))
             }
@@ -540,7 +1236,7 @@ impl<T: Iterator<Item = char>> Parser<T> {
                     }
                 }
             }
-            Token(mark, _) => Err(ScanError::new(
+            Token(mark, _) => Err(ScanError::parser(
                 mark,
                 "while parsing a block collection, did not find expected '-' indicator",
             )),
@@ -548,9 +1244,30 @@ impl<T: Iterator<Item = char>> Parser<T> {
     }
 }
 
+/// Returns the `&anchor` or `*alias` starting `value`, if followed by a space
+/// or nothing.
+pub(crate) fn leading_anchor(value: &str) -> Option<&str> {
+    if !value.starts_with(['&', '*']) {
+        return None;
+    }
+    let end = value[1..]
+        .find(|c: char| !matches!(c, '0'..='9' | 'a'..='z' | 'A'..='Z' | '_' | '-'))
+        .map_or(value.len(), |i| i + 1);
+    if end == 1 || !(value[end..].is_empty() || value[end..].starts_with(' ')) {
+        return None;
+    }
+    Some(&value[..end])
+}
+
 #[cfg(test)]
 mod test {
-    use super::{Event, Parser};
+    use super::{
+        Directive, Event, EventReceiver, Parser, ReceiveError, SpannedEventReceiver,
+        TryMarkedEventReceiver,
+    };
+    use emitter::StrictYamlEmitter;
+    use scanner::{Marker, Origin, ScanError, Span, TScalarStyle};
+    use strict_yaml::{StrictYaml, StrictYamlLoader};
 
     #[test]
     fn test_peek_eq_parse() {
@@ -567,6 +1284,7 @@ a4:
 a5: *x
 "#;
         let mut p = Parser::new(s.chars());
+        p.anchors_as_text();
         while {
             let event_peek = p.peek().unwrap().clone();
             let event = p.next().unwrap();
@@ -574,4 +1292,367 @@ a5: *x
             event.0 != Event::StreamEnd
         } {}
     }
+
+    #[test]
+    fn test_load_deep_nesting() {
+        struct Depth {
+            depth: usize,
+            max: usize,
+        }
+
+        impl EventReceiver for Depth {
+            fn on_event(&mut self, ev: Event) -> Result<(), ScanError> {
+                match ev {
+                    Event::SequenceStart(_) => {
+                        self.depth += 1;
+                        self.max = self.max.max(self.depth);
+                    }
+                    Event::SequenceEnd => self.depth -= 1,
+                    _ => {}
+                }
+                Ok(())
+            }
+        }
+
+        // far deeper than a recursive load could go on a test thread stack
+        let s = format!("{}x", "- ".repeat(100_000));
+        let mut depth = Depth { depth: 0, max: 0 };
+        Parser::new(s.chars()).load(&mut depth, true).unwrap();
+        assert_eq!((depth.depth, depth.max), (0, 100_000));
+
+        // the tree would be, so the loader stops at its nesting limit
+        let err = StrictYamlLoader::load_from_str(&s).unwrap_err();
+        assert_eq!(err.origin(), Origin::Limit);
+        assert_eq!(
+            err.to_string(),
+            "collections nested deeper than 256 levels at line 1 column 513"
+        );
+
+        let s = "a:\n  - b\n  - c: d\n    e:\n      - f\ng: h\n";
+        let mut loader = StrictYamlLoader::new();
+        Parser::new(s.chars()).load(&mut loader, true).unwrap();
+        assert_eq!(
+            loader.take_documents()[0]["a"][1]["e"][0].as_str(),
+            Some("f")
+        );
+    }
+
+    #[test]
+    fn test_try_load() {
+        #[derive(Debug, PartialEq)]
+        struct TooDeep(usize);
+
+        struct DepthLimit {
+            depth: usize,
+            max: usize,
+        }
+
+        impl TryMarkedEventReceiver for DepthLimit {
+            type Error = TooDeep;
+
+            fn try_on_event(&mut self, ev: Event, mark: Marker) -> Result<(), TooDeep> {
+                match ev {
+                    Event::SequenceStart(_) | Event::MappingStart(_) => self.depth += 1,
+                    Event::SequenceEnd | Event::MappingEnd => self.depth -= 1,
+                    _ => {}
+                }
+                if self.depth > self.max {
+                    return Err(TooDeep(mark.line()));
+                }
+                Ok(())
+            }
+        }
+
+        let mut recv = DepthLimit { depth: 0, max: 2 };
+        let res = Parser::new("a:\n  b:\n    c: d\n".chars()).try_load(&mut recv, true);
+        assert_eq!(res, Err(ReceiveError::Receiver(TooDeep(3))));
+
+        let mut recv = DepthLimit { depth: 0, max: 2 };
+        let res = Parser::new("a: 'open".chars()).try_load(&mut recv, true);
+        assert!(matches!(res, Err(ReceiveError::ScanError(_))));
+    }
+
+    #[test]
+    fn test_spans() {
+        struct Texts<'a>(&'a str, Vec<String>);
+        impl<'a> SpannedEventReceiver for Texts<'a> {
+            fn on_spanned_event(&mut self, ev: Event, span: Span) -> Result<(), ScanError> {
+                if let Event::Scalar(..) = ev {
+                    let text: String = self
+                        .0
+                        .chars()
+                        .skip(span.start().index())
+                        .take(span.end().index() - span.start().index())
+                        .collect();
+                    self.1.push(text);
+                } else {
+                    assert!(span.is_empty());
+                }
+                Ok(())
+            }
+        }
+        let s = "a: 1\nb c: 'd'  # e\nf:\ng: two\n  lines \nh: |\n  one\n\n  two\n\n\"ü\": \"x\"\n";
+        let mut texts = Texts(s, Vec::new());
+        Parser::new(s.chars())
+            .load_spanned(&mut texts, true)
+            .unwrap();
+        assert_eq!(
+            texts.1,
+            [
+                "a",
+                "1",
+                "b c",
+                "'d'",
+                "f",
+                "",
+                "g",
+                "two\n  lines",
+                "h",
+                "one\n\n  two",
+                "\"ü\"",
+                "\"x\""
+            ]
+        );
+
+        let mut p = Parser::new("- x\n- yy".chars());
+        let mut spans = Vec::new();
+        loop {
+            let (ev, span) = p.next_spanned().unwrap();
+            if ev == Event::StreamEnd {
+                break;
+            }
+            spans.push((span.start().index(), span.end().index()));
+        }
+        assert_eq!(
+            spans,
+            [(0, 0), (0, 0), (0, 0), (2, 3), (6, 8), (8, 8), (8, 8)]
+        );
+
+        let mut p = Parser::new("- [x, {y: z}]".chars());
+        p.parse_flow_collections();
+        let mut spans = Vec::new();
+        loop {
+            let (ev, span) = p.next_spanned().unwrap();
+            if ev == Event::StreamEnd {
+                break;
+            }
+            spans.push((span.start().index(), span.end().index()));
+        }
+        assert_eq!(
+            spans,
+            [
+                (0, 0),
+                (0, 0),
+                (0, 0),
+                (2, 3),
+                (3, 4),
+                (6, 7),
+                (7, 8),
+                (10, 11),
+                (11, 11),
+                (12, 12),
+                (13, 13),
+                (13, 13)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_eof_errors() {
+        fn error(s: &str) -> String {
+            let mut p = Parser::new(s.chars());
+            loop {
+                match p.next() {
+                    Ok((Event::StreamEnd, _)) => panic!("no error in {:?}", s),
+                    Ok(_) => {}
+                    Err(e) => return e.to_string(),
+                }
+            }
+        }
+
+        assert_eq!(
+            error("\"abc"),
+            "unexpected end of input while scanning a quoted scalar \
+             started at line 1 column 1 at line 1 column 5"
+        );
+        assert_eq!(
+            error("a:\n  - x\n  - 'y\n"),
+            "unexpected end of input while scanning a quoted scalar started at line 3 column 5, \
+             inside the sequence started at line 2 column 3 at line 4 column 1"
+        );
+        assert_eq!(
+            error("a: b\nc: d\ne: \"f\\"),
+            "unexpected end of input while scanning a quoted scalar started at line 3 column 4, \
+             inside the mapping started at line 1 column 2 at line 3 column 6"
+        );
+        // errors before the end of input are left alone
+        assert_eq!(
+            error("a:\n  - 'y\n---\n"),
+            "while scanning a quoted scalar, found unexpected document indicator at line 2 column 5"
+        );
+    }
+
+    #[test]
+    fn test_expand_aliases() {
+        fn events(s: &str, budget: usize) -> Result<Vec<Event>, ScanError> {
+            let mut p = Parser::new(s.chars());
+            p.expand_aliases(budget);
+            let mut events = Vec::new();
+            loop {
+                match p.next()?.0 {
+                    Event::StreamEnd => return Ok(events),
+                    Event::StreamStart | Event::DocumentStart | Event::DocumentEnd => {}
+                    ev => events.push(ev),
+                }
+            }
+        }
+        let scalar = |v: &str, aid| Event::Scalar(v.to_owned(), TScalarStyle::Plain, aid);
+
+        let s = "a: &x\n  - &y b\n  - c\nd: *y\n*x : &z\ne: *z";
+        assert_eq!(
+            events(s, 100).unwrap(),
+            vec![
+                Event::MappingStart(0),
+                scalar("a", 0),
+                Event::SequenceStart(1),
+                scalar("b", 2),
+                scalar("c", 0),
+                Event::SequenceEnd,
+                scalar("d", 0),
+                scalar("b", 2),
+                Event::SequenceStart(1),
+                scalar("b", 2),
+                scalar("c", 0),
+                Event::SequenceEnd,
+                scalar("", 3),
+                scalar("e", 0),
+                scalar("", 3),
+                Event::MappingEnd,
+            ]
+        );
+
+        // without expansion, anchors and aliases are errors, or scalar content
+        let mut p = Parser::new("- &x a\n- *x".chars());
+        p.next().unwrap();
+        p.next().unwrap();
+        p.next().unwrap();
+        assert_eq!(
+            p.next().unwrap_err().to_string(),
+            "anchors are not part of StrictYAML, found &x at line 1 column 3"
+        );
+        let mut p = Parser::new("- *x\n".chars());
+        p.next().unwrap();
+        p.next().unwrap();
+        p.next().unwrap();
+        assert_eq!(
+            p.next().unwrap_err().to_string(),
+            "aliases are not part of StrictYAML, found *x at line 1 column 3"
+        );
+        let mut p = Parser::new("- &x a\n- *x".chars());
+        p.anchors_as_text();
+        p.next().unwrap();
+        p.next().unwrap();
+        p.next().unwrap();
+        assert_eq!(p.next().unwrap().0, scalar("&x a", 0));
+        assert_eq!(p.next().unwrap().0, scalar("*x", 0));
+        for s in ["- *.txt", "- a &b", "- &", "- '&x'", "- &x!", "- Q&A"] {
+            let mut p = Parser::new(s.chars());
+            assert!(p.load(&mut StrictYamlLoader::new(), true).is_ok(), "{}", s);
+        }
+
+        let error = |s, budget| events(s, budget).unwrap_err().to_string();
+        assert_eq!(
+            error("- *x", 100),
+            "alias *x refers to an unknown anchor at line 1 column 3"
+        );
+        assert_eq!(
+            error("a: &x\n  b: *x", 100),
+            "alias *x refers to a node containing it at line 2 column 6"
+        );
+        assert_eq!(
+            error("&x a\n--- *x", 100),
+            "alias *x refers to an unknown anchor at line 2 column 5"
+        );
+        assert_eq!(
+            error("- &x *y", 100),
+            "an alias cannot have an anchor at line 1 column 6"
+        );
+        let laughs =
+            "a: &a [lol]\nb: &b\n  - *a\n  - *a\nc: &c\n  - *b\n  - *b\nd:\n  - *c\n  - *c";
+        assert!(events(laughs, 100).is_ok());
+        assert_eq!(
+            error(laughs, 20),
+            "expanding alias *c exceeds the alias budget at line 10 column 5"
+        );
+    }
+
+    #[test]
+    fn test_flow_collections() {
+        fn load(s: &str) -> Result<Vec<StrictYaml>, ScanError> {
+            let mut loader = StrictYamlLoader::new();
+            let mut p = Parser::new(s.chars());
+            p.parse_flow_collections();
+            p.load(&mut loader, true)?;
+            Ok(loader.take_documents())
+        }
+
+        let s = "a: [1, 'b c', [], {}]\nd: {e: f, g: [h], i, j: }\nk: [l: m, n]\n\
+                 o: {p: [q,\n  r], s: {t: u}}\nv: w]x, y\n[z]: 1\n";
+        let expected = "a:\n  - \"1\"\n  - b c\n  - []\n  - {}\n\
+                        d:\n  e: f\n  g:\n    - h\n  i: \"\"\n  j: \"\"\n\
+                        k:\n  - l: m\n  - n\n\
+                        o:\n  p:\n    - q\n    - r\n  s:\n    t: u\n\
+                        v: \"w]x, y\"\n? - z\n: \"1\"";
+        let docs = load(s).unwrap();
+        let mut out = String::new();
+        StrictYamlEmitter::new(&mut out).dump(&docs[0]).unwrap();
+        assert_eq!(out, format!("---\n{}", expected));
+
+        assert!(load("[a, b").is_err());
+        assert!(load("{a: b c: d}").is_err());
+        assert!(load("[- a]").is_err());
+        assert!(load("a]").is_ok());
+        // without the option, flow collections are scalars
+        assert_eq!(
+            StrictYamlLoader::load_from_str("a: [b, c]").unwrap()[0]["a"].as_str(),
+            Some("[b, c]")
+        );
+    }
+
+    #[test]
+    fn test_directives() {
+        let s = "%YAML 1.2\n%TAG !e! tag:example.com,2000: # comment\n%FOO bar baz\n--- a\n\
+                 ...\n%YAML 1.1\n---\nb\n";
+        let mut p = Parser::new(s.chars());
+        let mut events = Vec::new();
+        loop {
+            let (ev, mark) = p.next().unwrap();
+            if let Event::Directive(..) = ev {
+                events.push((ev, mark.line()));
+            } else if ev == Event::StreamEnd {
+                break;
+            }
+        }
+        let directive = |d| Event::Directive(d);
+        assert_eq!(
+            events,
+            vec![
+                (directive(Directive::Version(1, 2)), 1),
+                (
+                    directive(Directive::Tag(
+                        "!e!".to_owned(),
+                        "tag:example.com,2000:".to_owned()
+                    )),
+                    2
+                ),
+                (directive(Directive::Reserved("FOO".to_owned())), 3),
+                (directive(Directive::Version(1, 1)), 6),
+            ]
+        );
+
+        let load = |s: &str| StrictYamlLoader::load_from_str(s).map(|docs| docs.len());
+        assert_eq!(load(s), Ok(2));
+        assert!(load("%YAML 1.2\na: b").is_err());
+        assert!(load("%TAG !e!\n---").is_err());
+    }
 }
@@ -0,0 +1,341 @@
+use error::code;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// A single step of a `YamlPath`: either a mapping key or a sequence index.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+impl fmt::Display for PathSegment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PathSegment::Key(ref k) => {
+                for c in k.chars() {
+                    if matches!(c, '.' | '[' | ']' | '\\') {
+                        f.write_str("\\")?;
+                    }
+                    write!(f, "{}", c)?;
+                }
+                Ok(())
+            }
+            PathSegment::Index(i) => write!(f, "[{}]", i),
+        }
+    }
+}
+
+/// Location of a node inside a document, from the root down.
+///
+/// Paths display in dotted notation, with sequence indices in brackets:
+/// `servers[0].host`. Key characters that are part of the notation (`.`, `[`, `]`
+/// and `\`) are escaped with a backslash. The root path displays as an empty string.
+/// Paths parse from the same notation.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Default)]
+pub struct YamlPath {
+    segments: Vec<PathSegment>,
+}
+
+impl YamlPath {
+    /// Creates the root path.
+    pub fn new() -> YamlPath {
+        YamlPath::default()
+    }
+
+    pub fn segments(&self) -> &[PathSegment] {
+        &self.segments
+    }
+
+    pub fn len(&self) -> usize {
+        self.segments.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    pub fn push(&mut self, segment: PathSegment) {
+        self.segments.push(segment);
+    }
+
+    pub fn pop(&mut self) -> Option<PathSegment> {
+        self.segments.pop()
+    }
+
+    /// Returns a new path extended by `segment`.
+    pub fn child(&self, segment: PathSegment) -> YamlPath {
+        let mut path = self.clone();
+        path.push(segment);
+        path
+    }
+}
+
+impl From<Vec<PathSegment>> for YamlPath {
+    fn from(segments: Vec<PathSegment>) -> YamlPath {
+        YamlPath { segments }
+    }
+}
+
+impl fmt::Display for YamlPath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, segment) in self.segments.iter().enumerate() {
+            if i > 0 {
+                if let PathSegment::Key(_) = *segment {
+                    f.write_str(".")?;
+                }
+            }
+            write!(f, "{}", segment)?;
+        }
+        Ok(())
+    }
+}
+
+/// One step of a `PathPattern`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+enum PatternSegment {
+    Key(String),
+    Index(usize),
+    /// `*`, any key
+    AnyKey,
+    /// `[*]`, any index
+    AnyIndex,
+    /// `**`, any number of segments, including none
+    AnyPath,
+}
+
+/// Error of parsing a `PathPattern`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct PatternError(String);
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl PatternError {
+    pub fn code(&self) -> &'static str {
+        code::PATTERN
+    }
+}
+
+impl Error for PatternError {}
+
+/// A glob over paths, in the notation of `YamlPath` where a `*` key matches
+/// any key, `[*]` any index and a `**` key any number of segments:
+/// `**.script` matches `script` at any depth, `servers[*].host` the host of
+/// every server. A literal `*` key is escaped as `\*`.
+///
+/// ```
+/// use strict_yaml_rust::path::PathPattern;
+/// use strict_yaml_rust::{PathSegment, YamlPath};
+///
+/// let pattern: PathPattern = "**.version".parse().unwrap();
+/// let path = YamlPath::from(vec![
+///     PathSegment::Key("deps".to_owned()),
+///     PathSegment::Index(0),
+///     PathSegment::Key("version".to_owned()),
+/// ]);
+/// assert!(pattern.matches(&path));
+/// ```
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct PathPattern {
+    segments: Vec<PatternSegment>,
+}
+
+impl PathPattern {
+    pub fn matches(&self, path: &YamlPath) -> bool {
+        matches_from(&self.segments, path.segments())
+    }
+}
+
+fn matches_from(pattern: &[PatternSegment], path: &[PathSegment]) -> bool {
+    match (pattern.first(), path.first()) {
+        (None, _) => path.is_empty(),
+        (Some(&PatternSegment::AnyPath), _) => {
+            (0..=path.len()).any(|skip| matches_from(&pattern[1..], &path[skip..]))
+        }
+        (Some(_), None) => false,
+        (Some(p), Some(s)) => {
+            let matched = match (p, s) {
+                (PatternSegment::Key(p), PathSegment::Key(k)) => p == k,
+                (&PatternSegment::Index(p), &PathSegment::Index(i)) => p == i,
+                (&PatternSegment::AnyKey, &PathSegment::Key(_))
+                | (&PatternSegment::AnyIndex, &PathSegment::Index(_)) => true,
+                _ => false,
+            };
+            matched && matches_from(&pattern[1..], &path[1..])
+        }
+    }
+}
+
+impl FromStr for YamlPath {
+    type Err = PatternError;
+
+    /// Parses the notation paths display in, where `*` and `**` are plain
+    /// keys and `[*]` is not allowed.
+    fn from_str(s: &str) -> Result<YamlPath, PatternError> {
+        let segments = parse_segments(s, "path")?
+            .into_iter()
+            .map(|segment| match segment {
+                PatternSegment::Key(k) => Ok(PathSegment::Key(k)),
+                PatternSegment::Index(i) => Ok(PathSegment::Index(i)),
+                PatternSegment::AnyKey => Ok(PathSegment::Key("*".to_owned())),
+                PatternSegment::AnyPath => Ok(PathSegment::Key("**".to_owned())),
+                PatternSegment::AnyIndex => {
+                    Err(PatternError(format!("invalid index in path `{}`", s)))
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(YamlPath { segments })
+    }
+}
+
+impl FromStr for PathPattern {
+    type Err = PatternError;
+
+    fn from_str(s: &str) -> Result<PathPattern, PatternError> {
+        let segments = parse_segments(s, "path pattern")?;
+        Ok(PathPattern { segments })
+    }
+}
+
+/// Parses `s`, a path or a pattern according to `what`, into pattern segments.
+fn parse_segments(s: &str, what: &str) -> Result<Vec<PatternSegment>, PatternError> {
+    let error = |e: &str| Err(PatternError(format!("{} in {} `{}`", e, what, s)));
+    let mut segments = Vec::new();
+    let mut chars = s.chars();
+    let mut key = String::new();
+    // whether the key has escapes, which makes `*` literal
+    let mut escaped = false;
+    // whether a key must follow, after a `.`
+    let mut dot = false;
+    // whether an index was just closed, which a key cannot follow
+    let mut closed = false;
+    loop {
+        let c = chars.next();
+        match c {
+            Some('.') | Some('[') | None => {
+                if !key.is_empty() || escaped {
+                    segments.push(match key.as_str() {
+                        "*" if !escaped => PatternSegment::AnyKey,
+                        "**" if !escaped => PatternSegment::AnyPath,
+                        _ => PatternSegment::Key(key.clone()),
+                    });
+                } else if dot || (c == Some('.') && segments.is_empty()) {
+                    return error("empty key");
+                }
+                key.clear();
+                escaped = false;
+                dot = c == Some('.');
+                closed = false;
+            }
+            Some(']') => return error("unbalanced `]`"),
+            Some(_) if closed => return error("missing `.` after an index"),
+            Some('\\') => match chars.next() {
+                Some(c) => {
+                    key.push(c);
+                    escaped = true;
+                }
+                None => return error("dangling escape"),
+            },
+            Some(c) => key.push(c),
+        }
+        match c {
+            None => break,
+            Some('[') => {
+                let mut index = String::new();
+                loop {
+                    match chars.next() {
+                        Some(']') => break,
+                        Some(c) => index.push(c),
+                        None => return error("unclosed `[`"),
+                    }
+                }
+                segments.push(match index.as_str() {
+                    "*" => PatternSegment::AnyIndex,
+                    _ => match index.parse() {
+                        Ok(i) => PatternSegment::Index(i),
+                        Err(_) => return error("invalid index"),
+                    },
+                });
+                closed = true;
+            }
+            _ => {}
+        }
+    }
+    Ok(segments)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        let path = YamlPath::from(vec![
+            PathSegment::Key("servers".to_owned()),
+            PathSegment::Index(0),
+            PathSegment::Key("host".to_owned()),
+        ]);
+        assert_eq!(path.to_string(), "servers[0].host");
+        assert_eq!(YamlPath::new().to_string(), "");
+
+        let path = YamlPath::new()
+            .child(PathSegment::Index(3))
+            .child(PathSegment::Key("a.b[c]\\".to_owned()));
+        assert_eq!(path.to_string(), "[3].a\\.b\\[c\\]\\\\");
+    }
+
+    #[test]
+    fn test_pattern() {
+        let path = |s: &[&str]| {
+            YamlPath::from(
+                s.iter()
+                    .map(|s| match s.parse() {
+                        Ok(i) => PathSegment::Index(i),
+                        Err(_) => PathSegment::Key(s.to_string()),
+                    })
+                    .collect::<Vec<_>>(),
+            )
+        };
+        let matches =
+            |pattern: &str, p: &[&str]| pattern.parse::<PathPattern>().unwrap().matches(&path(p));
+        assert!(matches("**.script", &["script"]));
+        assert!(matches("**.script", &["jobs", "build", "script"]));
+        assert!(!matches("**.script", &["jobs", "script", "x"]));
+        assert!(matches("servers[*].host", &["servers", "3", "host"]));
+        assert!(!matches("servers[*].host", &["servers", "a", "host"]));
+        assert!(matches("servers[1].*", &["servers", "1", "port"]));
+        assert!(!matches("servers[1].*", &["servers", "1"]));
+        assert!(matches("[0][*]", &["0", "5"]));
+        assert!(matches("a.**", &["a"]));
+        assert!(matches("a.**", &["a", "b", "1"]));
+        assert!(matches("a\\.b.\\*", &["a.b", "*"]));
+        assert!(!matches("a\\.b.\\*", &["a.b", "c"]));
+        assert!(matches("", &[]));
+
+        for bad in &["a..b", "a.", ".a", "a[x]", "a[1", "a]", "a[0]b", "a\\"] {
+            assert!(bad.parse::<PathPattern>().is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        for s in &["servers[0].host", "", "[3].a\\.b\\[c\\]\\\\", "a.*.**"] {
+            let path: YamlPath = s.parse().unwrap();
+            assert_eq!(path.to_string(), *s);
+        }
+        let path: YamlPath = "a\\.b[1]".parse().unwrap();
+        assert_eq!(
+            path.segments(),
+            [PathSegment::Key("a.b".to_owned()), PathSegment::Index(1)]
+        );
+        let err = "a[*]".parse::<YamlPath>().unwrap_err();
+        assert_eq!(err.to_string(), "invalid index in path `a[*]`");
+        let err = "a..b".parse::<YamlPath>().unwrap_err();
+        assert_eq!(err.to_string(), "empty key in path `a..b`");
+    }
+}
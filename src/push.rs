@@ -0,0 +1,387 @@
+//! Push-style parsing of input that arrives in chunks.
+//!
+//! `PushParser` accepts raw UTF-8 bytes through `feed` as they become available
+//! (from a socket, a chunked HTTP body, ...) and hands every event that can be
+//! fully determined from the data seen so far to a `MarkedEventReceiver`.
+//! `finish` signals the end of the input and flushes the remaining events.
+//!
+//! ```
+//! use strict_yaml_rust::{Event, PushParser};
+//! use strict_yaml_rust::parser::EventReceiver;
+//!
+//! struct Collect(Vec<Event>);
+//! impl EventReceiver for Collect {
+//!     fn on_event(&mut self, ev: Event) -> Result<(), strict_yaml_rust::ScanError> {
+//!         self.0.push(ev);
+//!         Ok(())
+//!     }
+//! }
+//!
+//! let mut events = Collect(Vec::new());
+//! let mut parser = PushParser::new();
+//! parser.feed(b"name: de", &mut events).unwrap();
+//! parser.feed(b"mo\nsize: 3\n", &mut events).unwrap();
+//! assert!(events.0.contains(&Event::MappingStart(0)));
+//! parser.finish(&mut events).unwrap();
+//! assert_eq!(events.0.last(), Some(&Event::StreamEnd));
+//! ```
+
+#[cfg(feature = "futures-io")]
+use options::LoadOptions;
+use parser::{Event, Parser, SpannedEventReceiver};
+use scanner::{InvalidSequence, Marker, ScanError};
+use std::collections::VecDeque;
+use std::str;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// Characters fed so far but not yet consumed by the parser.
+#[derive(Debug, Default)]
+struct Input {
+    chars: VecDeque<char>,
+    /// Absolute position of `chars[0]` in the stream.
+    base: usize,
+    /// Set once `finish` has been called.
+    closed: bool,
+    /// Set when a read went past the available data before the input was closed.
+    starved: bool,
+}
+
+/// The parser's view of the shared input.
+///
+/// Cloning it (along with the parser that owns it) snapshots the read position
+/// without copying any data.
+#[derive(Clone, Debug)]
+struct Source {
+    input: Arc<Mutex<Input>>,
+    pos: usize,
+}
+
+fn lock<'a>(input: &'a Mutex<Input>) -> MutexGuard<'a, Input> {
+    match input.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
+
+impl Iterator for Source {
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        let mut input = lock(&self.input);
+        match input.chars.get(self.pos - input.base).cloned() {
+            Some(c) => {
+                self.pos += 1;
+                Some(c)
+            }
+            None => {
+                if !input.closed {
+                    input.starved = true;
+                }
+                None
+            }
+        }
+    }
+}
+
+/// An incremental parser fed with chunks of UTF-8 encoded bytes.
+///
+/// Events are produced once enough input is available to determine them,
+/// exactly as `Parser` would produce them from the complete input. An event
+/// waiting for more input is retried once the input it has to read has
+/// doubled, which keeps the cost of large scalars linear. Only the input not
+/// yet consumed by the parser is kept in memory.
+#[derive(Debug)]
+pub struct PushParser {
+    parser: Parser<Source>,
+    input: Arc<Mutex<Input>>,
+    /// Trailing bytes of an incomplete UTF-8 sequence.
+    pending: Vec<u8>,
+    /// Position of the next decoded character, for error reporting.
+    decoded: Marker,
+    /// Number of bytes decoded so far.
+    offset: usize,
+    /// Number of characters to have decoded before parsing again, after the
+    /// last event ran out of input.
+    retry_at: usize,
+    /// The input checks of `LoadOptions`, see `with_options`.
+    reject_bom: bool,
+    reject_nulls: bool,
+    ended: bool,
+}
+
+impl Default for PushParser {
+    fn default() -> PushParser {
+        PushParser::new()
+    }
+}
+
+impl PushParser {
+    pub fn new() -> PushParser {
+        let input = Arc::new(Mutex::new(Input::default()));
+        PushParser {
+            parser: Parser::new(Source {
+                input: input.clone(),
+                pos: 0,
+            }),
+            input,
+            pending: Vec::new(),
+            decoded: Marker::new(0, 1, 0),
+            offset: 0,
+            retry_at: 0,
+            reject_bom: false,
+            reject_nulls: false,
+            ended: false,
+        }
+    }
+
+    /// A parser set up for `options`, as far as the parser and the input are
+    /// concerned. The options applying to events take an `OptionsReceiver`.
+    #[cfg(feature = "futures-io")]
+    pub(crate) fn with_options(options: &LoadOptions) -> PushParser {
+        let mut parser = PushParser::new();
+        options.configure(&mut parser.parser);
+        parser.reject_bom = options.is_bom_rejected();
+        parser.reject_nulls = options.are_control_chars_rejected();
+        parser
+    }
+
+    /// Appends `data` to the input and delivers every event that can now be
+    /// determined to `recv`. `data` may end in the middle of a UTF-8 sequence.
+    pub fn feed<R: SpannedEventReceiver>(
+        &mut self,
+        data: &[u8],
+        recv: &mut R,
+    ) -> Result<(), ScanError> {
+        self.pending.extend_from_slice(data);
+        let valid = match str::from_utf8(&self.pending) {
+            Ok(s) => s.len(),
+            Err(e) => match e.error_len() {
+                None => e.valid_up_to(),
+                Some(len) => {
+                    self.decode(e.valid_up_to())?;
+                    let seq = InvalidSequence::new("UTF-8", self.offset, &self.pending[..len]);
+                    return Err(ScanError::from_invalid_sequence(self.decoded, seq));
+                }
+            },
+        };
+        self.decode(valid)?;
+        self.drive(recv)
+    }
+
+    /// Signals the end of the input and delivers all remaining events, up to and
+    /// including `Event::StreamEnd`.
+    pub fn finish<R: SpannedEventReceiver>(&mut self, recv: &mut R) -> Result<(), ScanError> {
+        if !self.pending.is_empty() {
+            let seq = InvalidSequence::new("UTF-8", self.offset, &self.pending);
+            return Err(ScanError::from_invalid_sequence(self.decoded, seq));
+        }
+        lock(&self.input).closed = true;
+        self.drive(recv)
+    }
+
+    /// Returns true once `Event::StreamEnd` has been delivered.
+    pub fn is_finished(&self) -> bool {
+        self.ended
+    }
+
+    /// Moves the first `len` (valid) pending bytes into the shared input.
+    fn decode(&mut self, len: usize) -> Result<(), ScanError> {
+        let text = match str::from_utf8(&self.pending[..len]) {
+            Ok(text) => text,
+            Err(_) => return Ok(()),
+        };
+        let (mut index, mut line, mut col) = (
+            self.decoded.index(),
+            self.decoded.line(),
+            self.decoded.col(),
+        );
+        let mut input = lock(&self.input);
+        for c in text.chars() {
+            if c == '\u{feff}' && index == 0 && self.reject_bom {
+                return Err(ScanError::limit(
+                    Marker::new(0, 1, 0),
+                    "byte order mark at the start of the input",
+                ));
+            }
+            if c == '\0' && self.reject_nulls {
+                let mark = Marker::new(index, line, col);
+                return Err(ScanError::scanner(mark, "null character in input"));
+            }
+            input.chars.push_back(c);
+            index += 1;
+            if c == '\n' {
+                line += 1;
+                col = 0;
+            } else {
+                col += 1;
+            }
+        }
+        drop(input);
+        self.decoded = Marker::new(index, line, col);
+        self.offset += len;
+        self.pending.drain(..len);
+        Ok(())
+    }
+
+    /// Delivers the events the input holds. An event that runs out of input
+    /// is parsed again from its start once more input arrives, so it is only
+    /// retried once the input not consumed yet has doubled: a token spread
+    /// over many chunks is then scanned a bounded number of times over.
+    fn drive<R: SpannedEventReceiver>(&mut self, recv: &mut R) -> Result<(), ScanError> {
+        {
+            let input = lock(&self.input);
+            if !input.closed && self.decoded.index() < self.retry_at {
+                return Ok(());
+            }
+        }
+        while !self.ended {
+            let snapshot = self.parser.clone();
+            lock(&self.input).starved = false;
+            let res = self.parser.next_spanned();
+            if lock(&self.input).starved {
+                // the event depends on input that has not arrived yet
+                self.parser = snapshot;
+                let pending = self.decoded.index() - self.parser.scanner().source().pos;
+                self.retry_at = self.decoded.index() + pending.max(1);
+                break;
+            }
+            let (ev, span) = res?;
+            if ev == Event::StreamEnd {
+                self.ended = true;
+            }
+            recv.on_spanned_event(ev, span)?;
+        }
+
+        // release the characters the parser is done with
+        let pos = self.parser.scanner().source().pos;
+        let mut input = lock(&self.input);
+        let consumed = pos - input.base;
+        input.chars.drain(..consumed);
+        input.base = pos;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use parser::EventReceiver;
+    use std::time::{Duration, Instant};
+
+    struct Collect(Vec<Event>);
+
+    impl EventReceiver for Collect {
+        fn on_event(&mut self, ev: Event) -> Result<(), ScanError> {
+            self.0.push(ev);
+            Ok(())
+        }
+    }
+
+    fn pull_events(s: &str) -> Vec<Event> {
+        let mut parser = Parser::new(s.chars());
+        let mut events = Vec::new();
+        loop {
+            let (ev, _) = parser.next().unwrap();
+            events.push(ev.clone());
+            if ev == Event::StreamEnd {
+                return events;
+            }
+        }
+    }
+
+    const DOC: &str = "
+# comment
+a0 bb: val
+a1:
+    b1: 4
+    b2: d
+a2: 'quoted
+  over lines'
+a3: |
+  literal
+    text
+a4:
+    - - a1
+      - a2
+    - 2
+a7: 你好
+---
+second: doc
+";
+
+    #[test]
+    fn test_byte_by_byte() {
+        let mut parser = PushParser::new();
+        let mut events = Collect(Vec::new());
+        for b in DOC.as_bytes() {
+            parser.feed(&[*b], &mut events).unwrap();
+        }
+        assert!(!parser.is_finished());
+        parser.finish(&mut events).unwrap();
+        assert!(parser.is_finished());
+        assert_eq!(events.0, pull_events(DOC));
+    }
+
+    #[test]
+    fn test_events_before_finish() {
+        let mut parser = PushParser::new();
+        let mut events = Collect(Vec::new());
+        parser.feed(b"a: 1\nb: [x", &mut events).unwrap();
+        assert_eq!(
+            events.0,
+            vec![
+                Event::StreamStart,
+                Event::DocumentStart,
+                Event::MappingStart(0),
+                Event::Scalar("a".to_owned(), ::scanner::TScalarStyle::Plain, 0),
+                Event::Scalar("1".to_owned(), ::scanner::TScalarStyle::Plain, 0),
+                Event::Scalar("b".to_owned(), ::scanner::TScalarStyle::Plain, 0),
+            ]
+        );
+        parser.feed(b"]\n", &mut events).unwrap();
+        parser.finish(&mut events).unwrap();
+        assert_eq!(events.0, pull_events("a: 1\nb: [x]\n"));
+        assert!(lock(&parser.input).chars.is_empty());
+    }
+
+    #[test]
+    fn test_utf8_errors() {
+        let mut events = Collect(Vec::new());
+        let mut parser = PushParser::new();
+        parser.feed(b"a: \xe4\xbd", &mut events).unwrap();
+        let err = parser.finish(&mut events).unwrap_err();
+        let seq = err.invalid_sequence().unwrap();
+        assert_eq!((seq.offset(), seq.bytes()), (3, &b"\xe4\xbd"[..]));
+
+        let mut parser = PushParser::new();
+        parser.feed(b"a: b\nc", &mut events).unwrap();
+        let err = parser.feed(b": \xff", &mut events).unwrap_err();
+        assert_eq!(err.marker().line(), 2);
+        assert_eq!(err.marker().col(), 3);
+        let seq = err.invalid_sequence().unwrap();
+        assert_eq!((seq.offset(), seq.bytes()), (8, &b"\xff"[..]));
+    }
+
+    #[test]
+    fn test_large_scalar_in_chunks() {
+        let doc = format!("a: {}\nb: '{}'\n", "x".repeat(1 << 21), "y".repeat(1 << 21));
+        let start = Instant::now();
+        let mut parser = PushParser::new();
+        let mut events = Collect(Vec::new());
+        for chunk in doc.as_bytes().chunks(8 << 10) {
+            parser.feed(chunk, &mut events).unwrap();
+        }
+        parser.finish(&mut events).unwrap();
+        // rescanning the scalars from their start on every chunk takes minutes
+        assert!(start.elapsed() < Duration::from_secs(10));
+        assert_eq!(events.0, pull_events(&doc));
+    }
+
+    #[test]
+    fn test_scan_error() {
+        let mut events = Collect(Vec::new());
+        let mut parser = PushParser::new();
+        parser.feed(b"key: 'unterminated", &mut events).unwrap();
+        assert!(parser.finish(&mut events).is_err());
+    }
+}
@@ -0,0 +1,538 @@
+//! Selectors in the style of yq, evaluated against `StrictYaml` documents.
+//!
+//! A query is a pipeline of stages separated by `|`, each applied to every
+//! node matched by the stage before it:
+//!
+//! - `.` matches the node itself;
+//! - `.key` or `."key"` matches the value of a key, `[n]` an item of a
+//!   sequence and `[]` every item of a sequence or value of a mapping. These
+//!   chain, as in `.servers[0].host` or `.servers[].ports[]`;
+//! - `select(condition)` keeps the nodes the condition holds for.
+//!
+//! A condition is a path from the node compared to a double-quoted string
+//! with `==` or `!=`, or a path alone, which holds when it matches a node.
+//! Conditions combine with `and`, `or`, `not` and parentheses, nested up to
+//! 64 levels. Scalars are strings, so they compare to strings only:
+//! `.enabled == "true"`.
+//!
+//! Paths that do not match, such as a key of a sequence, match nothing rather
+//! than failing.
+//!
+//! ```
+//! use strict_yaml_rust::query::query;
+//! use strict_yaml_rust::StrictYamlLoader;
+//!
+//! let source = "
+//! servers:
+//!   - host: a
+//!     enabled: true
+//!   - host: b
+//!     enabled: false
+//! ";
+//! let doc = &StrictYamlLoader::load_from_str(source).unwrap()[0];
+//! let hosts = query(doc, r#".servers[] | select(.enabled == "true") | .host"#).unwrap();
+//! assert_eq!(hosts.len(), 1);
+//! assert_eq!(hosts[0].as_str(), Some("a"));
+//! ```
+
+use error::code;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+use strict_yaml::StrictYaml;
+
+/// Error of parsing a `Query`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct QueryError(String);
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl QueryError {
+    pub fn code(&self) -> &'static str {
+        code::QUERY
+    }
+}
+
+impl Error for QueryError {}
+
+/// One step of a path.
+#[derive(Clone, PartialEq, Eq, Debug)]
+enum Step {
+    Key(String),
+    Index(usize),
+    /// `[]`, every item or value
+    Each,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+enum Condition {
+    Matches(Vec<Step>),
+    Equals(Vec<Step>, String),
+    Not(Box<Condition>),
+    /// A chain of `and`, kept flat so that long chains do not nest
+    All(Vec<Condition>),
+    /// A chain of `or`
+    Any(Vec<Condition>),
+}
+
+impl Condition {
+    fn holds(&self, node: &StrictYaml) -> bool {
+        match *self {
+            Condition::Matches(ref steps) => !follow(node, steps).is_empty(),
+            Condition::Equals(ref steps, ref value) => follow(node, steps)
+                .iter()
+                .any(|n| n.as_str() == Some(value.as_str())),
+            Condition::Not(ref c) => !c.holds(node),
+            Condition::All(ref cs) => cs.iter().all(|c| c.holds(node)),
+            Condition::Any(ref cs) => cs.iter().any(|c| c.holds(node)),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+enum Stage {
+    Path(Vec<Step>),
+    Select(Condition),
+}
+
+/// A parsed query, to run against any number of documents.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Query {
+    stages: Vec<Stage>,
+}
+
+impl Query {
+    /// The nodes the query matches in `node`, in document order.
+    pub fn run<'a>(&self, node: &'a StrictYaml) -> Vec<&'a StrictYaml> {
+        let mut nodes = vec![node];
+        for stage in &self.stages {
+            nodes = match *stage {
+                Stage::Path(ref steps) => {
+                    nodes.into_iter().flat_map(|n| follow(n, steps)).collect()
+                }
+                Stage::Select(ref c) => nodes.into_iter().filter(|n| c.holds(n)).collect(),
+            };
+        }
+        nodes
+    }
+}
+
+/// Parses `selector` and runs it against `node`.
+pub fn query<'a>(node: &'a StrictYaml, selector: &str) -> Result<Vec<&'a StrictYaml>, QueryError> {
+    let query: Query = selector.parse()?;
+    Ok(query.run(node))
+}
+
+fn follow<'a>(node: &'a StrictYaml, steps: &[Step]) -> Vec<&'a StrictYaml> {
+    let mut nodes = vec![node];
+    for step in steps {
+        nodes = nodes
+            .into_iter()
+            .flat_map(|n| -> Vec<&StrictYaml> {
+                match (step, n) {
+                    (Step::Key(k), StrictYaml::Hash(h)) => {
+                        h.get(&StrictYaml::String(k.clone())).into_iter().collect()
+                    }
+                    (&Step::Index(i), StrictYaml::Array(v)) => v.get(i).into_iter().collect(),
+                    (Step::Each, StrictYaml::Array(v)) => v.iter().collect(),
+                    (Step::Each, StrictYaml::Hash(h)) => h.values().collect(),
+                    _ => Vec::new(),
+                }
+            })
+            .collect();
+    }
+    nodes
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+enum Token {
+    Dot,
+    /// A bare word: a key, an index or a keyword.
+    Word(String),
+    Str(String),
+    LBracket,
+    RBracket,
+    LParen,
+    RParen,
+    Pipe,
+    Eq,
+    Ne,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Token::Dot => f.write_str("`.`"),
+            Token::Word(ref w) => write!(f, "`{}`", w),
+            Token::Str(ref s) => write!(f, "{:?}", s),
+            Token::LBracket => f.write_str("`[`"),
+            Token::RBracket => f.write_str("`]`"),
+            Token::LParen => f.write_str("`(`"),
+            Token::RParen => f.write_str("`)`"),
+            Token::Pipe => f.write_str("`|`"),
+            Token::Eq => f.write_str("`==`"),
+            Token::Ne => f.write_str("`!=`"),
+        }
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-'
+}
+
+/// How deep `not` and parentheses nest in a condition, which keeps the
+/// recursive descent off the end of the stack.
+const MAX_NESTING: usize = 64;
+
+struct QueryParser<'a> {
+    source: &'a str,
+    tokens: Vec<Token>,
+    pos: usize,
+    /// The `not`s and parentheses around the current condition.
+    depth: usize,
+}
+
+impl<'a> QueryParser<'a> {
+    fn error<T>(&self, what: &str) -> Result<T, QueryError> {
+        Err(QueryError(format!("{} in query `{}`", what, self.source)))
+    }
+
+    fn tokenize(&mut self) -> Result<(), QueryError> {
+        let mut chars = self.source.chars().peekable();
+        while let Some(c) = chars.next() {
+            let token = match c {
+                _ if c.is_whitespace() => continue,
+                '.' => Token::Dot,
+                '[' => Token::LBracket,
+                ']' => Token::RBracket,
+                '(' => Token::LParen,
+                ')' => Token::RParen,
+                '|' => Token::Pipe,
+                '=' | '!' if chars.peek() == Some(&'=') => {
+                    chars.next();
+                    if c == '=' {
+                        Token::Eq
+                    } else {
+                        Token::Ne
+                    }
+                }
+                '"' => {
+                    let mut s = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('"') => break,
+                            Some('\\') => match chars.next() {
+                                Some(c @ '"') | Some(c @ '\\') => s.push(c),
+                                _ => return self.error("invalid escape"),
+                            },
+                            Some(c) => s.push(c),
+                            None => return self.error("unterminated string"),
+                        }
+                    }
+                    Token::Str(s)
+                }
+                _ if is_word_char(c) => {
+                    let mut word = c.to_string();
+                    while let Some(&c) = chars.peek() {
+                        if !is_word_char(c) {
+                            break;
+                        }
+                        word.push(c);
+                        chars.next();
+                    }
+                    Token::Word(word)
+                }
+                _ => return self.error(&format!("unexpected `{}`", c)),
+            };
+            self.tokens.push(token);
+        }
+        Ok(())
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn is_word(&self, word: &str) -> bool {
+        matches!(self.peek(), Some(Token::Word(w)) if w == word)
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.peek() == Some(token) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: &Token) -> Result<(), QueryError> {
+        if self.eat(token) {
+            Ok(())
+        } else {
+            self.unexpected(&format!("expected {}", token))
+        }
+    }
+
+    fn unexpected<T>(&self, what: &str) -> Result<T, QueryError> {
+        match self.peek() {
+            Some(token) => self.error(&format!("{}, found {}", what, token)),
+            None => self.error(&format!("{} at the end", what)),
+        }
+    }
+
+    fn pipeline(&mut self) -> Result<Vec<Stage>, QueryError> {
+        let mut stages = vec![self.stage()?];
+        while self.eat(&Token::Pipe) {
+            stages.push(self.stage()?);
+        }
+        match self.peek() {
+            None => Ok(stages),
+            Some(_) => self.unexpected("expected `|`"),
+        }
+    }
+
+    fn stage(&mut self) -> Result<Stage, QueryError> {
+        if self.is_word("select") {
+            self.pos += 1;
+            self.expect(&Token::LParen)?;
+            let condition = self.or()?;
+            self.expect(&Token::RParen)?;
+            Ok(Stage::Select(condition))
+        } else {
+            Ok(Stage::Path(self.path()?))
+        }
+    }
+
+    fn path(&mut self) -> Result<Vec<Step>, QueryError> {
+        if self.peek() != Some(&Token::Dot) {
+            return self.unexpected("expected a path");
+        }
+        let mut steps = Vec::new();
+        loop {
+            if self.eat(&Token::Dot) {
+                match self.peek().cloned() {
+                    Some(Token::Word(key)) | Some(Token::Str(key)) => {
+                        self.pos += 1;
+                        steps.push(Step::Key(key));
+                    }
+                    Some(Token::Dot) => return self.error("empty key"),
+                    _ => {}
+                }
+            } else if self.eat(&Token::LBracket) {
+                if self.eat(&Token::RBracket) {
+                    steps.push(Step::Each);
+                    continue;
+                }
+                let index = match self.peek() {
+                    Some(Token::Word(w)) if w.bytes().all(|b| b.is_ascii_digit()) => w.parse().ok(),
+                    _ => None,
+                };
+                match index {
+                    Some(i) => {
+                        self.pos += 1;
+                        steps.push(Step::Index(i));
+                    }
+                    None => return self.unexpected("expected an index"),
+                }
+                self.expect(&Token::RBracket)?;
+            } else {
+                return Ok(steps);
+            }
+        }
+    }
+
+    fn or(&mut self) -> Result<Condition, QueryError> {
+        let mut conditions = vec![self.and()?];
+        while self.is_word("or") {
+            self.pos += 1;
+            conditions.push(self.and()?);
+        }
+        Ok(if conditions.len() == 1 {
+            conditions.remove(0)
+        } else {
+            Condition::Any(conditions)
+        })
+    }
+
+    fn and(&mut self) -> Result<Condition, QueryError> {
+        let mut conditions = vec![self.not()?];
+        while self.is_word("and") {
+            self.pos += 1;
+            conditions.push(self.not()?);
+        }
+        Ok(if conditions.len() == 1 {
+            conditions.remove(0)
+        } else {
+            Condition::All(conditions)
+        })
+    }
+
+    fn nested<T>(
+        &mut self,
+        parse: impl FnOnce(&mut Self) -> Result<T, QueryError>,
+    ) -> Result<T, QueryError> {
+        if self.depth == MAX_NESTING {
+            let what = format!("conditions nested deeper than {} levels", MAX_NESTING);
+            return self.error(&what);
+        }
+        self.depth += 1;
+        let parsed = parse(self);
+        self.depth -= 1;
+        parsed
+    }
+
+    fn not(&mut self) -> Result<Condition, QueryError> {
+        if self.is_word("not") {
+            self.pos += 1;
+            let condition = self.nested(|p| p.not())?;
+            return Ok(Condition::Not(Box::new(condition)));
+        }
+        if self.eat(&Token::LParen) {
+            let condition = self.nested(|p| p.or())?;
+            self.expect(&Token::RParen)?;
+            return Ok(condition);
+        }
+        let path = self.path()?;
+        let negated = match self.peek() {
+            Some(Token::Eq) => false,
+            Some(Token::Ne) => true,
+            _ => return Ok(Condition::Matches(path)),
+        };
+        self.pos += 1;
+        let value = match self.peek().cloned() {
+            Some(Token::Str(s)) => s,
+            _ => return self.unexpected("expected a string"),
+        };
+        self.pos += 1;
+        let condition = Condition::Equals(path, value);
+        Ok(if negated {
+            Condition::Not(Box::new(condition))
+        } else {
+            condition
+        })
+    }
+}
+
+impl FromStr for Query {
+    type Err = QueryError;
+
+    fn from_str(s: &str) -> Result<Query, QueryError> {
+        let mut parser = QueryParser {
+            source: s,
+            tokens: Vec::new(),
+            pos: 0,
+            depth: 0,
+        };
+        parser.tokenize()?;
+        let stages = parser.pipeline()?;
+        Ok(Query { stages })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use strict_yaml::StrictYamlLoader;
+
+    const SOURCE: &str = "
+servers:
+  - host: a
+    enabled: true
+    ports:
+      - 80
+      - 443
+  - host: b
+    enabled: false
+    ports:
+      - 8080
+  - host: c
+    tls:
+      cert: c.pem
+owner: ops
+'dotted.key': x
+";
+
+    fn run(selector: &str) -> Vec<String> {
+        let doc = &StrictYamlLoader::load_from_str(SOURCE).unwrap()[0];
+        query(doc, selector)
+            .unwrap()
+            .into_iter()
+            .map(|n| n.as_str().unwrap_or("<collection>").to_owned())
+            .collect()
+    }
+
+    #[test]
+    fn test_paths() {
+        assert_eq!(run(".owner"), ["ops"]);
+        assert_eq!(run("."), ["<collection>"]);
+        assert_eq!(run(".servers[1].host"), ["b"]);
+        assert_eq!(run(".servers[].host"), ["a", "b", "c"]);
+        assert_eq!(run(".servers[].ports[]"), ["80", "443", "8080"]);
+        assert_eq!(run(".servers | .[2] | .tls.cert"), ["c.pem"]);
+        assert_eq!(run(".\"dotted.key\""), ["x"]);
+        assert_eq!(run(".servers[0][]").len(), 3);
+        assert!(run(".owner.x").is_empty());
+        assert!(run(".servers[9]").is_empty());
+        assert!(run(".servers.host").is_empty());
+    }
+
+    #[test]
+    fn test_select() {
+        let hosts = |condition: &str| run(&format!(".servers[] | select({}) | .host", condition));
+        assert_eq!(hosts(".enabled == \"true\""), ["a"]);
+        assert_eq!(hosts(".enabled != \"true\""), ["b", "c"]);
+        assert_eq!(hosts(".tls"), ["c"]);
+        assert_eq!(hosts("not .enabled"), ["c"]);
+        assert_eq!(
+            hosts(".ports[] == \"8080\" or .tls.cert == \"c.pem\""),
+            ["b", "c"]
+        );
+        assert_eq!(hosts(".enabled and not (.host == \"a\")"), ["b"]);
+        assert_eq!(run(".owner | select(. == \"ops\")"), ["ops"]);
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = |selector: &str| selector.parse::<Query>().unwrap_err().to_string();
+        assert_eq!(error(""), "expected a path at the end in query ``");
+        assert_eq!(
+            error("owner"),
+            "expected a path, found `owner` in query `owner`"
+        );
+        assert_eq!(error(".a..b"), "empty key in query `.a..b`");
+        assert_eq!(
+            error(".a[x]"),
+            "expected an index, found `x` in query `.a[x]`"
+        );
+        assert_eq!(error(".a[1"), "expected `]` at the end in query `.a[1`");
+        assert_eq!(
+            error(".a \"b\""),
+            "expected `|`, found \"b\" in query `.a \"b\"`"
+        );
+        assert_eq!(
+            error("select(.a == b)"),
+            "expected a string, found `b` in query `select(.a == b)`"
+        );
+        assert_eq!(error(".\"a"), "unterminated string in query `.\"a`");
+        assert_eq!(error(".a = \"b\""), "unexpected `=` in query `.a = \"b\"`");
+
+        let deep = format!("select({}.a{})", "(".repeat(200_000), ")".repeat(200_000));
+        assert!(error(&deep).starts_with("conditions nested deeper than 64 levels in query"));
+        let deep = format!("select({}.a)", "not ".repeat(200_000));
+        assert!(error(&deep).starts_with("conditions nested deeper than 64 levels in query"));
+        let nested = format!("select({}.a{})", "(not ".repeat(32), ")".repeat(32));
+        assert!(nested.parse::<Query>().is_ok());
+
+        let doc = &StrictYamlLoader::load_from_str("- a: x\n- a: y").unwrap()[0];
+        let chain = vec![".a == \"x\""; 100_000];
+        let all = format!(".[] | select({})", chain.join(" and "));
+        assert_eq!(query(doc, &all).unwrap().len(), 1);
+        let any = format!(".[] | select({})", chain.join(" or "));
+        assert_eq!(query(doc, &any).unwrap().len(), 1);
+    }
+}
@@ -1,6 +1,7 @@
+use error::code;
 use std::collections::VecDeque;
 use std::error::Error;
-use std::{char, fmt};
+use std::{char, fmt, mem};
 
 #[derive(Clone, Copy, PartialEq, Debug, Eq)]
 pub enum TEncoding {
@@ -26,7 +27,7 @@ pub struct Marker {
 }
 
 impl Marker {
-    fn new(index: usize, line: usize, col: usize) -> Marker {
+    pub(crate) fn new(index: usize, line: usize, col: usize) -> Marker {
         Marker { index, line, col }
     }
 
@@ -43,33 +44,177 @@ impl Marker {
     }
 }
 
+/// The part of the source an event was parsed from, from `start` up to but
+/// not including `end`.
+#[derive(Clone, Copy, PartialEq, Debug, Eq)]
+pub struct Span {
+    start: Marker,
+    end: Marker,
+}
+
+impl Span {
+    pub fn new(start: Marker, end: Marker) -> Span {
+        Span { start, end }
+    }
+
+    /// An empty span at `mark`.
+    pub fn empty(mark: Marker) -> Span {
+        Span::new(mark, mark)
+    }
+
+    pub fn start(&self) -> Marker {
+        self.start
+    }
+
+    pub fn end(&self) -> Marker {
+        self.end
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start.index == self.end.index
+    }
+}
+
+/// A sequence of bytes that is not valid in the encoding of the input.
+#[derive(Clone, PartialEq, Debug, Eq)]
+pub struct InvalidSequence {
+    encoding: &'static str,
+    offset: usize,
+    bytes: Vec<u8>,
+}
+
+impl InvalidSequence {
+    pub(crate) fn new(encoding: &'static str, offset: usize, bytes: &[u8]) -> InvalidSequence {
+        InvalidSequence {
+            encoding,
+            offset,
+            bytes: bytes.to_owned(),
+        }
+    }
+
+    /// Name of the encoding, e.g. "UTF-8".
+    pub fn encoding(&self) -> &str {
+        self.encoding
+    }
+
+    /// Offset of the sequence in the input, in bytes.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+/// The stage of loading that raised a `ScanError`, which `LoadError` tells
+/// apart.
+#[derive(Clone, Copy, PartialEq, Debug, Eq)]
+pub(crate) enum Origin {
+    Scanner,
+    Parser,
+    DuplicateKey,
+    Limit,
+    /// A secret that could not be decrypted.
+    Decrypt,
+    /// Raised by an event receiver outside the crate.
+    Other,
+}
+
 #[derive(Clone, PartialEq, Debug, Eq)]
 pub struct ScanError {
     mark: Marker,
     info: String,
+    invalid_sequence: Option<Box<InvalidSequence>>,
+    origin: Origin,
 }
 
 impl ScanError {
     pub fn new(loc: Marker, info: &str) -> ScanError {
+        ScanError::with_origin(Origin::Other, loc, info)
+    }
+
+    pub(crate) fn with_origin(origin: Origin, loc: Marker, info: &str) -> ScanError {
         ScanError {
             mark: loc,
             info: info.to_owned(),
+            invalid_sequence: None,
+            origin,
         }
     }
 
+    /// An error for malformed input, found while scanning tokens.
+    pub(crate) fn scanner(loc: Marker, info: &str) -> ScanError {
+        ScanError::with_origin(Origin::Scanner, loc, info)
+    }
+
+    /// An error for tokens or events out of place.
+    pub(crate) fn parser(loc: Marker, info: &str) -> ScanError {
+        ScanError::with_origin(Origin::Parser, loc, info)
+    }
+
+    /// An error for a key repeated in a mapping.
+    pub(crate) fn duplicate_key(loc: Marker, info: &str) -> ScanError {
+        ScanError::with_origin(Origin::DuplicateKey, loc, info)
+    }
+
+    /// An error for input rejected by `LoadOptions`.
+    pub(crate) fn limit(loc: Marker, info: &str) -> ScanError {
+        ScanError::with_origin(Origin::Limit, loc, info)
+    }
+
+    /// An error for a secret that could not be decrypted.
+    pub(crate) fn decrypt(loc: Marker, info: &str) -> ScanError {
+        ScanError::with_origin(Origin::Decrypt, loc, info)
+    }
+
+    /// An error for an invalid sequence in byte input, decoded up to `loc`.
+    pub(crate) fn from_invalid_sequence(loc: Marker, seq: InvalidSequence) -> ScanError {
+        let info = format!("invalid {} sequence at byte {}", seq.encoding, seq.offset);
+        ScanError {
+            mark: loc,
+            info,
+            invalid_sequence: Some(Box::new(seq)),
+            origin: Origin::Scanner,
+        }
+    }
+
+    pub(crate) fn origin(&self) -> Origin {
+        self.origin
+    }
+
+    /// The offending bytes, if the error is due to byte input that could not be
+    /// decoded.
+    pub fn invalid_sequence(&self) -> Option<&InvalidSequence> {
+        self.invalid_sequence.as_deref()
+    }
+
     pub fn marker(&self) -> &Marker {
         &self.mark
     }
+
+    pub fn info(&self) -> &str {
+        &self.info
+    }
+
+    /// The code of the `LoadError` this error converts into, see
+    /// `error::code`.
+    pub fn code(&self) -> &'static str {
+        match self.origin {
+            Origin::Scanner => code::SCAN,
+            Origin::Parser => code::PARSE,
+            Origin::DuplicateKey => code::DUPLICATE_KEY,
+            Origin::Limit => code::LIMIT,
+            Origin::Decrypt => code::DECRYPT,
+            Origin::Other => code::OTHER,
+        }
+    }
 }
 
 impl Error for ScanError {
     fn description(&self) -> &str {
         self.info.as_ref()
     }
-
-    fn cause(&self) -> Option<&dyn Error> {
-        None
-    }
 }
 
 impl fmt::Display for ScanError {
@@ -94,6 +239,8 @@ pub enum TokenType {
     VersionDirective(u32, u32),
     /// handle, prefix
     TagDirective(String, String),
+    /// name of a directive other than `%YAML` and `%TAG`
+    ReservedDirective(String),
     DocumentStart,
     DocumentEnd,
     BlockSequenceStart,
@@ -103,6 +250,16 @@ pub enum TokenType {
     Key,
     Value,
     Scalar(TScalarStyle, String),
+    /// Only produced when anchors are enabled, see `Parser::expand_aliases`.
+    Anchor(String),
+    Alias(String),
+    /// Only produced when flow collections are enabled, see
+    /// `Parser::parse_flow_collections`.
+    FlowSequenceStart,
+    FlowSequenceEnd,
+    FlowMappingStart,
+    FlowMappingEnd,
+    FlowEntry,
 }
 
 #[derive(Clone, PartialEq, Debug, Eq)]
@@ -127,13 +284,29 @@ impl SimpleKey {
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Scanner<T> {
     rdr: T,
     mark: Marker,
     tokens: VecDeque<Token>,
     buffer: VecDeque<char>,
     error: Option<ScanError>,
+    /// Whether scanning failed because the input ended inside a token.
+    ended_early: bool,
+    /// Whether `&anchor` and `*alias` are tokens rather than scalar content.
+    anchors: bool,
+    /// Whether `[`, `]`, `{`, `}` and `,` are tokens rather than scalar content.
+    flow: bool,
+    flow_level: usize,
+    /// Whether plain scalars keep the blanks ending their last line.
+    trailing_blanks: bool,
+    /// The comments scanned so far, with the position of their `#`, when
+    /// they are kept.
+    comments: Option<Vec<(Marker, String)>>,
+    /// The end of each scalar token not returned yet, in order.
+    scalar_ends: VecDeque<Marker>,
+    /// The end of the last scalar token returned.
+    scalar_end: Option<Marker>,
 
     stream_start_produced: bool,
     stream_end_produced: bool,
@@ -182,8 +355,12 @@ fn is_blankz(c: char) -> bool {
     is_blank(c) || is_breakz(c)
 }
 #[inline]
+fn is_flow(c: char) -> bool {
+    matches!(c, ',' | '[' | ']' | '{' | '}')
+}
+#[inline]
 fn is_digit(c: char) -> bool {
-    ('0'..='9').contains(&c)
+    c.is_ascii_digit()
 }
 #[inline]
 fn is_alpha(c: char) -> bool {
@@ -191,7 +368,7 @@ fn is_alpha(c: char) -> bool {
 }
 #[inline]
 fn is_hex(c: char) -> bool {
-    ('0'..='9').contains(&c) || ('a'..='f').contains(&c) || ('A'..='F').contains(&c)
+    c.is_ascii_hexdigit()
 }
 #[inline]
 fn as_hex(c: char) -> u32 {
@@ -199,6 +376,7 @@ fn as_hex(c: char) -> u32 {
         '0'..='9' => (c as u32) - ('0' as u32),
         'a'..='f' => (c as u32) - ('a' as u32) + 10,
         'A'..='F' => (c as u32) - ('A' as u32) + 10,
+        // only called on characters that `is_hex` accepts
         _ => unreachable!(),
     }
 }
@@ -214,6 +392,14 @@ impl<T: Iterator<Item = char>> Scanner<T> {
             mark: Marker::new(0, 1, 0),
             tokens: VecDeque::new(),
             error: None,
+            ended_early: false,
+            anchors: false,
+            flow: false,
+            trailing_blanks: false,
+            comments: None,
+            scalar_ends: VecDeque::new(),
+            scalar_end: None,
+            flow_level: 0,
 
             stream_start_produced: false,
             stream_end_produced: false,
@@ -225,6 +411,17 @@ impl<T: Iterator<Item = char>> Scanner<T> {
             token_available: false,
         }
     }
+
+    pub(crate) fn source(&self) -> &T {
+        &self.rdr
+    }
+
+    /// The end of the last scalar token returned, past its last character:
+    /// its closing quote, or the end of its last line for a block scalar.
+    pub fn scalar_end(&self) -> Option<Marker> {
+        self.scalar_end
+    }
+
     #[inline]
     pub fn get_error(&self) -> Option<ScanError> {
         self.error.as_ref().cloned()
@@ -241,7 +438,12 @@ impl<T: Iterator<Item = char>> Scanner<T> {
     }
     #[inline]
     fn skip_char(&mut self) {
-        let c = self.buffer.pop_front().unwrap();
+        let c = match self.buffer.pop_front() {
+            Some(c) => c,
+            // callers look ahead first; should one not, read on so that the
+            // marker still follows the input
+            None => self.rdr.next().unwrap_or('\0'),
+        };
 
         self.mark.index += 1;
         if c == '\n' {
@@ -283,6 +485,43 @@ impl<T: Iterator<Item = char>> Scanner<T> {
     pub fn stream_ended(&self) -> bool {
         self.stream_end_produced
     }
+    /// Makes `&anchor` and `*alias` at the start of a node tokens of their own.
+    pub(crate) fn enable_anchors(&mut self) {
+        self.anchors = true;
+    }
+    /// Makes flow collections tokens of their own.
+    pub(crate) fn enable_flow(&mut self) {
+        self.flow = true;
+    }
+    /// Makes plain scalars keep the spaces and tabs between their content and
+    /// the end of their last line.
+    pub(crate) fn keep_trailing_blanks(&mut self) {
+        self.trailing_blanks = true;
+    }
+    /// Keeps the comments, for `take_comments`.
+    pub(crate) fn keep_comments(&mut self) {
+        self.comments = Some(Vec::new());
+    }
+    /// Returns the comments scanned since the last call, see `keep_comments`.
+    pub(crate) fn take_comments(&mut self) -> Vec<(Marker, String)> {
+        self.comments.as_mut().map(mem::take).unwrap_or_default()
+    }
+    /// Whether the last error was caused by the input ending inside a token.
+    pub(crate) fn ended_early(&self) -> bool {
+        self.ended_early
+    }
+    fn unexpected_end(&mut self, what: &str, start: Marker) -> ScanError {
+        self.ended_early = true;
+        ScanError::scanner(
+            self.mark,
+            &format!(
+                "unexpected end of input while scanning {} started at line {} column {}",
+                what,
+                start.line,
+                start.col + 1
+            ),
+        )
+    }
     #[inline]
     pub fn mark(&self) -> Marker {
         self.mark
@@ -298,6 +537,8 @@ impl<T: Iterator<Item = char>> Scanner<T> {
             s.push('\n');
             self.skip_char();
         } else {
+            // every caller checks `is_break` on the first character, after
+            // looking ahead by two
             unreachable!();
         }
     }
@@ -368,19 +609,26 @@ impl<T: Iterator<Item = char>> Scanner<T> {
         let c = self.buffer[0];
         let nc = self.buffer[1];
         match c {
+            '[' if self.flow => self.fetch_flow_collection_start(TokenType::FlowSequenceStart),
+            '{' if self.flow => self.fetch_flow_collection_start(TokenType::FlowMappingStart),
+            ']' if self.flow => self.fetch_flow_collection_end(TokenType::FlowSequenceEnd),
+            '}' if self.flow => self.fetch_flow_collection_end(TokenType::FlowMappingEnd),
+            ',' if self.flow_level > 0 => self.fetch_flow_entry(),
             '-' if is_blankz(nc) => self.fetch_block_entry(),
             '?' if is_blankz(nc) => self.fetch_key(),
-            ':' if is_blankz(nc) => self.fetch_value(),
+            ':' if is_blankz(nc) || (self.flow_level > 0 && is_flow(nc)) => self.fetch_value(),
             // Is it a literal scalar?
             '|' => self.fetch_block_scalar(true),
             // Is it a folded scalar?
             '>' => self.fetch_block_scalar(false),
             '\'' => self.fetch_flow_scalar(true),
             '"' => self.fetch_flow_scalar(false),
+            '&' if self.anchors => self.fetch_anchor(false),
+            '*' if self.anchors => self.fetch_anchor(true),
             // plain scalar
             '-' if !is_blankz(nc) => self.fetch_plain_scalar(),
             ':' | '?' if !is_blankz(nc) => self.fetch_plain_scalar(),
-            '%' | '@' | '`' => Err(ScanError::new(
+            '%' | '@' | '`' => Err(ScanError::scanner(
                 self.mark,
                 &format!("unexpected character: `{}'", c),
             )),
@@ -396,9 +644,20 @@ impl<T: Iterator<Item = char>> Scanner<T> {
         if !self.token_available {
             self.fetch_more_tokens()?;
         }
-        let t = self.tokens.pop_front().unwrap();
+        // `fetch_more_tokens` only returns once there is a token, and
+        // `skip_to_next_document` only sets `token_available` after putting
+        // one back
+        let t = match self.tokens.pop_front() {
+            Some(t) => t,
+            None => return Err(ScanError::scanner(self.mark, "no token to return")),
+        };
+        if let TokenType::Scalar(..) = t.1 {
+            self.scalar_end = self.scalar_ends.pop_front();
+        }
         self.token_available = false;
         self.tokens_parsed += 1;
+        #[cfg(feature = "tracing")]
+        tracing::trace!(line = t.0.line, column = t.0.col + 1, token = ?t.1, "token");
 
         if let TokenType::StreamEnd = t.1 {
             self.stream_end_produced = true;
@@ -406,6 +665,54 @@ impl<T: Iterator<Item = char>> Scanner<T> {
         Ok(Some(t))
     }
 
+    /// Resynchronizes the scanner after an error so that scanning can resume
+    /// at the next document start marker (`---` in the first column).
+    ///
+    /// Already scanned tokens are kept from the first document start (or stream
+    /// end) onwards; everything before it is discarded, as is the input up to
+    /// the next marker. Indentation and simple key state are reset.
+    pub fn skip_to_next_document(&mut self) {
+        self.error = None;
+        if self.stream_end_produced {
+            return;
+        }
+        while let Some(tok) = self.tokens.pop_front() {
+            match tok.1 {
+                TokenType::DocumentStart | TokenType::StreamEnd => {
+                    self.tokens.push_front(tok);
+                    self.token_available = true;
+                    return;
+                }
+                TokenType::Scalar(..) => {
+                    self.scalar_ends.pop_front();
+                }
+                _ => {}
+            }
+            self.tokens_parsed += 1;
+        }
+        self.token_available = false;
+        self.indent = -1;
+        self.indents.clear();
+        self.allow_simple_key();
+        for sk in &mut self.simple_keys {
+            sk.possible = false;
+        }
+
+        loop {
+            self.lookahead(4);
+            if is_z(self.ch())
+                || (self.mark.col == 0
+                    && self.buffer[0] == '-'
+                    && self.buffer[1] == '-'
+                    && self.buffer[2] == '-'
+                    && is_blankz(self.buffer[3]))
+            {
+                break;
+            }
+            self.skip_char();
+        }
+    }
+
     pub fn fetch_more_tokens(&mut self) -> ScanResult {
         let mut need_more;
         loop {
@@ -438,7 +745,7 @@ impl<T: Iterator<Item = char>> Scanner<T> {
                 && (sk.mark.line < self.mark.line || sk.mark.index + 1024 < self.mark.index)
             {
                 if sk.required {
-                    return Err(ScanError::new(self.mark, "simple key expect ':'"));
+                    return Err(ScanError::scanner(self.mark, "simple key expect ':'"));
                 }
                 sk.possible = false;
             }
@@ -449,26 +756,47 @@ impl<T: Iterator<Item = char>> Scanner<T> {
     fn skip_to_next_token(&mut self) {
         loop {
             self.lookahead(1);
-            // TODO(chenyh) BOM
             match self.ch() {
+                // a byte order mark is only allowed at the start of the stream, and
+                // takes no column
+                '\u{feff}' if self.mark.index == 0 => {
+                    self.buffer.pop_front();
+                    self.mark.index += 1;
+                }
                 ' ' => self.skip_char(),
-                '\t' if !self.simple_key_allowed => self.skip_char(),
+                '\t' if self.flow_level > 0 || !self.simple_key_allowed => self.skip_char(),
                 '\n' | '\r' => {
                     self.lookahead(2);
                     self.skip_line();
-                    self.allow_simple_key();
-                }
-                '#' => {
-                    while !is_breakz(self.ch()) {
-                        self.skip_char();
-                        self.lookahead(1);
+                    if self.flow_level == 0 {
+                        self.allow_simple_key();
                     }
                 }
+                '#' => self.skip_comment(),
                 _ => break,
             }
         }
     }
 
+    /// Skips a comment up to the end of its line, keeping it if comments are
+    /// kept.
+    fn skip_comment(&mut self) {
+        let mark = self.mark;
+        let mut text = String::new();
+        self.skip_char();
+        self.lookahead(1);
+        while !is_breakz(self.ch()) {
+            if self.comments.is_some() {
+                text.push(self.ch());
+            }
+            self.skip_char();
+            self.lookahead(1);
+        }
+        if let Some(ref mut comments) = self.comments {
+            comments.push((mark, text));
+        }
+    }
+
     fn fetch_stream_start(&mut self) {
         let mark = self.mark;
         self.indent = -1;
@@ -515,7 +843,11 @@ impl<T: Iterator<Item = char>> Scanner<T> {
         let name = self.scan_directive_name()?;
         let tok = match name.as_ref() {
             "YAML" => self.scan_version_directive_value(&start_mark)?,
-            // XXX This should be a warning instead of an error
+            "TAG" => {
+                let handle = self.scan_directive_word(&start_mark, "handle")?;
+                let prefix = self.scan_directive_word(&start_mark, "prefix")?;
+                Token(start_mark, TokenType::TagDirective(handle, prefix))
+            }
             _ => {
                 // skip current line
                 self.lookahead(1);
@@ -523,13 +855,7 @@ impl<T: Iterator<Item = char>> Scanner<T> {
                     self.skip_char();
                     self.lookahead(1);
                 }
-                // XXX return an empty TagDirective token
-                Token(
-                    start_mark,
-                    TokenType::TagDirective(String::new(), String::new()),
-                )
-                // return Err(ScanError::new(start_mark,
-                //     "while scanning a directive, found unknown directive name"))
+                Token(start_mark, TokenType::ReservedDirective(name))
             }
         };
         self.lookahead(1);
@@ -540,14 +866,11 @@ impl<T: Iterator<Item = char>> Scanner<T> {
         }
 
         if self.ch() == '#' {
-            while !is_breakz(self.ch()) {
-                self.skip_char();
-                self.lookahead(1);
-            }
+            self.skip_comment();
         }
 
         if !is_breakz(self.ch()) {
-            return Err(ScanError::new(
+            return Err(ScanError::scanner(
                 start_mark,
                 "while scanning a directive, did not find expected comment or line break",
             ));
@@ -562,6 +885,31 @@ impl<T: Iterator<Item = char>> Scanner<T> {
         Ok(tok)
     }
 
+    /// Scans a blank-separated parameter of a `%TAG` directive.
+    fn scan_directive_word(&mut self, mark: &Marker, what: &str) -> Result<String, ScanError> {
+        self.lookahead(1);
+        while is_blank(self.ch()) {
+            self.skip_char();
+            self.lookahead(1);
+        }
+        let mut word = String::new();
+        while !is_blankz(self.ch()) {
+            word.push(self.ch());
+            self.skip_char();
+            self.lookahead(1);
+        }
+        if word.is_empty() {
+            return Err(ScanError::scanner(
+                *mark,
+                &format!(
+                    "while scanning a TAG directive, did not find expected {}",
+                    what
+                ),
+            ));
+        }
+        Ok(word)
+    }
+
     fn scan_version_directive_value(&mut self, mark: &Marker) -> Result<Token, ScanError> {
         self.lookahead(1);
 
@@ -573,7 +921,7 @@ impl<T: Iterator<Item = char>> Scanner<T> {
         let major = self.scan_version_directive_number(mark)?;
 
         if self.ch() != '.' {
-            return Err(ScanError::new(
+            return Err(ScanError::scanner(
                 *mark,
                 "while scanning a YAML directive, did not find expected digit or '.' character",
             ));
@@ -597,14 +945,14 @@ impl<T: Iterator<Item = char>> Scanner<T> {
         }
 
         if string.is_empty() {
-            return Err(ScanError::new(
+            return Err(ScanError::scanner(
                 start_mark,
                 "while scanning a directive, could not find expected directive name",
             ));
         }
 
         if !is_blankz(self.ch()) {
-            return Err(ScanError::new(
+            return Err(ScanError::scanner(
                 start_mark,
                 "while scanning a directive, found unexpected non-alphabetical character",
             ));
@@ -619,7 +967,7 @@ impl<T: Iterator<Item = char>> Scanner<T> {
         self.lookahead(1);
         while is_digit(self.ch()) {
             if length + 1 > 9 {
-                return Err(ScanError::new(
+                return Err(ScanError::scanner(
                     *mark,
                     "while scanning a YAML directive, found extremely long version number",
                 ));
@@ -631,7 +979,7 @@ impl<T: Iterator<Item = char>> Scanner<T> {
         }
 
         if length == 0 {
-            return Err(ScanError::new(
+            return Err(ScanError::scanner(
                 *mark,
                 "while scanning a YAML directive, did not find expected version number",
             ));
@@ -640,10 +988,64 @@ impl<T: Iterator<Item = char>> Scanner<T> {
         Ok(val)
     }
 
+    fn fetch_flow_collection_start(&mut self, tok: TokenType) -> ScanResult {
+        // The indicators '[' and '{' may start a simple key.
+        self.save_simple_key()?;
+
+        self.simple_keys.push(SimpleKey::new(Marker::new(0, 0, 0)));
+        self.flow_level += 1;
+
+        self.allow_simple_key();
+
+        let start_mark = self.mark;
+        self.skip_char();
+
+        self.tokens.push_back(Token(start_mark, tok));
+        Ok(())
+    }
+
+    fn fetch_flow_collection_end(&mut self, tok: TokenType) -> ScanResult {
+        if self.flow_level == 0 {
+            return Err(ScanError::scanner(
+                self.mark,
+                "unexpected end of a flow collection",
+            ));
+        }
+        self.remove_simple_key()?;
+        self.simple_keys.pop();
+        self.flow_level -= 1;
+
+        self.disallow_simple_key();
+
+        let start_mark = self.mark;
+        self.skip_char();
+
+        self.tokens.push_back(Token(start_mark, tok));
+        Ok(())
+    }
+
+    fn fetch_flow_entry(&mut self) -> ScanResult {
+        self.remove_simple_key()?;
+        self.allow_simple_key();
+
+        let start_mark = self.mark;
+        self.skip_char();
+
+        self.tokens
+            .push_back(Token(start_mark, TokenType::FlowEntry));
+        Ok(())
+    }
+
     fn fetch_block_entry(&mut self) -> ScanResult {
+        if self.flow_level > 0 {
+            return Err(ScanError::scanner(
+                self.mark,
+                "block sequence entries are not allowed in flow collections",
+            ));
+        }
         // Check if we are allowed to start a new entry.
         if !self.simple_key_allowed {
-            return Err(ScanError::new(
+            return Err(ScanError::scanner(
                 self.mark,
                 "block sequence entries are not allowed in this context",
             ));
@@ -681,13 +1083,15 @@ impl<T: Iterator<Item = char>> Scanner<T> {
     fn fetch_block_scalar(&mut self, literal: bool) -> ScanResult {
         self.save_simple_key()?;
         self.allow_simple_key();
-        let tok = self.scan_block_scalar(literal)?;
+        let (tok, end) = self.scan_block_scalar(literal)?;
 
+        self.scalar_ends.push_back(end);
         self.tokens.push_back(tok);
         Ok(())
     }
 
-    fn scan_block_scalar(&mut self, literal: bool) -> Result<Token, ScanError> {
+    /// Scans a block scalar, returning it with the end of its last line.
+    fn scan_block_scalar(&mut self, literal: bool) -> Result<(Token, Marker), ScanError> {
         let start_mark = self.mark;
         let mut chomping: i32 = 0;
         let mut increment: usize = 0;
@@ -713,7 +1117,7 @@ impl<T: Iterator<Item = char>> Scanner<T> {
             self.lookahead(1);
             if is_digit(self.ch()) {
                 if self.ch() == '0' {
-                    return Err(ScanError::new(
+                    return Err(ScanError::scanner(
                         start_mark,
                         "while scanning a block scalar, found an intendation indicator equal to 0",
                     ));
@@ -723,7 +1127,7 @@ impl<T: Iterator<Item = char>> Scanner<T> {
             }
         } else if is_digit(self.ch()) {
             if self.ch() == '0' {
-                return Err(ScanError::new(
+                return Err(ScanError::scanner(
                     start_mark,
                     "while scanning a block scalar, found an intendation indicator equal to 0",
                 ));
@@ -751,15 +1155,12 @@ impl<T: Iterator<Item = char>> Scanner<T> {
         }
 
         if self.ch() == '#' {
-            while !is_breakz(self.ch()) {
-                self.skip_char();
-                self.lookahead(1);
-            }
+            self.skip_comment();
         }
 
         // Check if we are at the end of the line.
         if !is_breakz(self.ch()) {
-            return Err(ScanError::new(
+            return Err(ScanError::scanner(
                 start_mark,
                 "while scanning a block scalar, did not find expected comment or line break",
             ));
@@ -783,6 +1184,7 @@ impl<T: Iterator<Item = char>> Scanner<T> {
         self.lookahead(1);
 
         let start_mark = self.mark;
+        let mut end_mark = start_mark;
 
         while self.mark.col == indent && !is_z(self.ch()) {
             // We are at the beginning of a non-empty line.
@@ -807,6 +1209,7 @@ impl<T: Iterator<Item = char>> Scanner<T> {
                 self.skip_char();
                 self.lookahead(1);
             }
+            end_mark = self.mark;
             // break on EOF
             if is_z(self.ch()) {
                 break;
@@ -828,17 +1231,15 @@ impl<T: Iterator<Item = char>> Scanner<T> {
             string.push_str(&trailing_breaks);
         }
 
-        if literal {
-            Ok(Token(
-                start_mark,
-                TokenType::Scalar(TScalarStyle::Literal, string),
-            ))
+        let style = if literal {
+            TScalarStyle::Literal
         } else {
-            Ok(Token(
-                start_mark,
-                TokenType::Scalar(TScalarStyle::Foled, string),
-            ))
-        }
+            TScalarStyle::Foled
+        };
+        Ok((
+            Token(start_mark, TokenType::Scalar(style, string)),
+            end_mark,
+        ))
     }
 
     fn block_scalar_breaks(&mut self, indent: &mut usize, breaks: &mut String) -> ScanResult {
@@ -856,7 +1257,7 @@ impl<T: Iterator<Item = char>> Scanner<T> {
 
             // Check for a tab character messing the intendation.
             if (*indent == 0 || self.mark.col < *indent) && self.buffer[0] == '\t' {
-                return Err(ScanError::new(self.mark,
+                return Err(ScanError::scanner(self.mark,
                         "while scanning a block scalar, found a tab character where an intendation space is expected"));
             }
 
@@ -887,6 +1288,8 @@ impl<T: Iterator<Item = char>> Scanner<T> {
 
         let tok = self.scan_flow_scalar(single)?;
 
+        // past the closing quote
+        self.scalar_ends.push_back(self.mark);
         self.tokens.push_back(tok);
         Ok(())
     }
@@ -914,17 +1317,14 @@ impl<T: Iterator<Item = char>> Scanner<T> {
                         && (self.buffer[2] == '.')))
                 && is_blankz(self.buffer[3])
             {
-                return Err(ScanError::new(
+                return Err(ScanError::scanner(
                     start_mark,
                     "while scanning a quoted scalar, found unexpected document indicator",
                 ));
             }
 
             if is_z(self.ch()) {
-                return Err(ScanError::new(
-                    start_mark,
-                    "while scanning a quoted scalar, found unexpected end of stream",
-                ));
+                return Err(self.unexpected_end("a quoted scalar", start_mark));
             }
 
             self.lookahead(2);
@@ -953,6 +1353,9 @@ impl<T: Iterator<Item = char>> Scanner<T> {
                     }
                     // Check for an escape sequence.
                     '\\' if !single => {
+                        if is_z(self.buffer[1]) {
+                            return Err(self.unexpected_end("a quoted scalar", start_mark));
+                        }
                         let mut code_length = 0usize;
                         match self.buffer[1] {
                             '0' => string.push('\0'),
@@ -969,18 +1372,18 @@ impl<T: Iterator<Item = char>> Scanner<T> {
                             '\'' => string.push('\''),
                             '\\' => string.push('\\'),
                             // NEL (#x85)
-                            'N' => string.push(char::from_u32(0x85).unwrap()),
+                            'N' => string.push('\u{85}'),
                             // #xA0
-                            '_' => string.push(char::from_u32(0xA0).unwrap()),
+                            '_' => string.push('\u{A0}'),
                             // LS (#x2028)
-                            'L' => string.push(char::from_u32(0x2028).unwrap()),
+                            'L' => string.push('\u{2028}'),
                             // PS (#x2029)
-                            'P' => string.push(char::from_u32(0x2029).unwrap()),
+                            'P' => string.push('\u{2029}'),
                             'x' => code_length = 2,
                             'u' => code_length = 4,
                             'U' => code_length = 8,
                             _ => {
-                                return Err(ScanError::new(
+                                return Err(ScanError::scanner(
                                     start_mark,
                                     "while parsing a quoted scalar, found unknown escape character",
                                 ))
@@ -994,7 +1397,7 @@ impl<T: Iterator<Item = char>> Scanner<T> {
                             let mut value = 0u32;
                             for i in 0..code_length {
                                 if !is_hex(self.buffer[i]) {
-                                    return Err(ScanError::new(start_mark,
+                                    return Err(ScanError::scanner(start_mark,
                                                               "while parsing a quoted scalar, did not find expected hexdecimal number"));
                                 }
                                 value = (value << 4) + as_hex(self.buffer[i]);
@@ -1003,7 +1406,7 @@ impl<T: Iterator<Item = char>> Scanner<T> {
                             let ch = match char::from_u32(value) {
                                 Some(v) => v,
                                 None => {
-                                    return Err(ScanError::new(start_mark,
+                                    return Err(ScanError::scanner(start_mark,
                                                               "while parsing a quoted scalar, found invalid Unicode character escape code"));
                                 }
                             };
@@ -1089,24 +1492,66 @@ impl<T: Iterator<Item = char>> Scanner<T> {
         }
     }
 
+    fn fetch_anchor(&mut self, alias: bool) -> ScanResult {
+        self.save_simple_key()?;
+        self.disallow_simple_key();
+
+        let tok = self.scan_anchor(alias)?;
+
+        self.tokens.push_back(tok);
+        Ok(())
+    }
+
+    fn scan_anchor(&mut self, alias: bool) -> Result<Token, ScanError> {
+        let start_mark = self.mark;
+        let mut name = String::new();
+
+        self.skip_char();
+        self.lookahead(1);
+        while is_alpha(self.ch()) {
+            name.push(self.ch());
+            self.skip_char();
+            self.lookahead(1);
+        }
+
+        if name.is_empty() || !(is_blankz(self.ch()) || self.ch_is(':')) {
+            return Err(ScanError::scanner(
+                start_mark,
+                "while scanning an anchor or alias, did not find expected alphabetic or numeric character",
+            ));
+        }
+
+        if alias {
+            Ok(Token(start_mark, TokenType::Alias(name)))
+        } else {
+            Ok(Token(start_mark, TokenType::Anchor(name)))
+        }
+    }
+
     fn fetch_plain_scalar(&mut self) -> ScanResult {
         self.save_simple_key()?;
         self.disallow_simple_key();
 
-        let tok = self.scan_plain_scalar()?;
+        let (tok, end) = self.scan_plain_scalar()?;
 
+        self.scalar_ends.push_back(end);
         self.tokens.push_back(tok);
         Ok(())
     }
 
-    fn scan_plain_scalar(&mut self) -> Result<Token, ScanError> {
+    /// Scans a plain scalar, returning it with the end of its last
+    /// character.
+    fn scan_plain_scalar(&mut self) -> Result<(Token, Marker), ScanError> {
         let indent = self.indent + 1;
         let start_mark = self.mark;
+        let mut end_mark = start_mark;
 
         let mut string = String::new();
         let mut leading_break = String::new();
         let mut trailing_breaks = String::new();
         let mut whitespaces = String::new();
+        // blanks ending the last line with content
+        let mut trailing = String::new();
         let mut leading_blanks = false;
 
         loop {
@@ -1130,6 +1575,8 @@ impl<T: Iterator<Item = char>> Scanner<T> {
                 // indicators ends a plain scalar
                 match self.ch() {
                     ':' if is_blankz(self.buffer[1]) => break,
+                    ':' if self.flow_level > 0 && is_flow(self.buffer[1]) => break,
+                    ',' | '[' | ']' | '{' | '}' if self.flow_level > 0 => break,
                     _ => {}
                 }
 
@@ -1150,6 +1597,7 @@ impl<T: Iterator<Item = char>> Scanner<T> {
                             leading_break.clear();
                         }
                         leading_blanks = false;
+                        trailing.clear();
                     } else {
                         string.push_str(&whitespaces);
                         whitespaces.clear();
@@ -1159,6 +1607,7 @@ impl<T: Iterator<Item = char>> Scanner<T> {
                 string.push(self.ch());
                 self.skip_char();
                 self.lookahead(2);
+                end_mark = self.mark;
             }
             // is the end?
             if !(is_blank(self.ch()) || is_break(self.ch())) {
@@ -1169,7 +1618,7 @@ impl<T: Iterator<Item = char>> Scanner<T> {
             while is_blank(self.ch()) || is_break(self.ch()) {
                 if is_blank(self.ch()) {
                     if leading_blanks && (self.mark.col as isize) < indent && self.ch() == '\t' {
-                        return Err(ScanError::new(
+                        return Err(ScanError::scanner(
                             start_mark,
                             "while scanning a plain scalar, found a tab",
                         ));
@@ -1187,7 +1636,7 @@ impl<T: Iterator<Item = char>> Scanner<T> {
                     if leading_blanks {
                         self.read_break(&mut trailing_breaks);
                     } else {
-                        whitespaces.clear();
+                        trailing = mem::take(&mut whitespaces);
                         self.read_break(&mut leading_break);
                         leading_blanks = true;
                     }
@@ -1196,7 +1645,7 @@ impl<T: Iterator<Item = char>> Scanner<T> {
             }
 
             // check intendation level
-            if (self.mark.col as isize) < indent {
+            if self.flow_level == 0 && (self.mark.col as isize) < indent {
                 break;
             }
         }
@@ -1204,28 +1653,37 @@ impl<T: Iterator<Item = char>> Scanner<T> {
         if leading_blanks {
             self.allow_simple_key();
         }
+        if self.trailing_blanks {
+            if leading_blanks {
+                string.push_str(&trailing);
+            } else if is_z(self.ch()) {
+                string.push_str(&whitespaces);
+            }
+        }
 
-        Ok(Token(
-            start_mark,
-            TokenType::Scalar(TScalarStyle::Plain, string),
+        Ok((
+            Token(start_mark, TokenType::Scalar(TScalarStyle::Plain, string)),
+            end_mark,
         ))
     }
 
     fn fetch_key(&mut self) -> ScanResult {
         let start_mark = self.mark;
-        // Check if we are allowed to start a new key (not nessesary simple).
-        if !self.simple_key_allowed {
-            return Err(ScanError::new(
-                self.mark,
-                "mapping keys are not allowed in this context",
-            ));
+        if self.flow_level == 0 {
+            // Check if we are allowed to start a new key (not nessesary simple).
+            if !self.simple_key_allowed {
+                return Err(ScanError::scanner(
+                    self.mark,
+                    "mapping keys are not allowed in this context",
+                ));
+            }
+            self.roll_indent(
+                start_mark.col,
+                None,
+                TokenType::BlockMappingStart,
+                start_mark,
+            );
         }
-        self.roll_indent(
-            start_mark.col,
-            None,
-            TokenType::BlockMappingStart,
-            start_mark,
-        );
 
         self.remove_simple_key()?;
 
@@ -1236,8 +1694,18 @@ impl<T: Iterator<Item = char>> Scanner<T> {
         Ok(())
     }
 
+    /// The simple key of the current flow level. There is always one: the
+    /// stream start pushes the one of the block level, and each flow level
+    /// pushes its own, popped when the level ends.
+    fn last_simple_key(&mut self) -> Result<&mut SimpleKey, ScanError> {
+        let mark = self.mark;
+        self.simple_keys
+            .last_mut()
+            .ok_or_else(|| ScanError::scanner(mark, "no simple key level"))
+    }
+
     fn fetch_value(&mut self) -> ScanResult {
-        let sk = self.simple_keys.last().unwrap().clone();
+        let sk = self.last_simple_key()?.clone();
         let start_mark = self.mark;
         if sk.possible {
             // insert simple key
@@ -1253,25 +1721,31 @@ impl<T: Iterator<Item = char>> Scanner<T> {
                 start_mark,
             );
 
-            self.simple_keys.last_mut().unwrap().possible = false;
+            self.last_simple_key()?.possible = false;
             self.disallow_simple_key();
         } else {
             // The ':' indicator follows a complex key.
-            if !self.simple_key_allowed {
-                return Err(ScanError::new(
+            if self.flow_level == 0 {
+                if !self.simple_key_allowed {
+                    return Err(ScanError::scanner(
+                        start_mark,
+                        "mapping values are not allowed in this context",
+                    ));
+                }
+
+                self.roll_indent(
+                    start_mark.col,
+                    None,
+                    TokenType::BlockMappingStart,
                     start_mark,
-                    "mapping values are not allowed in this context",
-                ));
+                );
             }
 
-            self.roll_indent(
-                start_mark.col,
-                None,
-                TokenType::BlockMappingStart,
-                start_mark,
-            );
-
-            self.allow_simple_key();
+            if self.flow_level == 0 {
+                self.allow_simple_key();
+            } else {
+                self.disallow_simple_key();
+            }
         }
         self.skip_char();
         self.tokens.push_back(Token(start_mark, TokenType::Value));
@@ -1280,6 +1754,9 @@ impl<T: Iterator<Item = char>> Scanner<T> {
     }
 
     fn roll_indent(&mut self, col: usize, number: Option<usize>, tok: TokenType, mark: Marker) {
+        if self.flow_level > 0 {
+            return;
+        }
         if self.indent < col as isize {
             self.indents.push(self.indent);
             self.indent = col as isize;
@@ -1292,9 +1769,14 @@ impl<T: Iterator<Item = char>> Scanner<T> {
     }
 
     fn unroll_indent(&mut self, col: isize) {
+        if self.flow_level > 0 {
+            return;
+        }
         while self.indent > col {
             self.tokens.push_back(Token(self.mark, TokenType::BlockEnd));
-            self.indent = self.indents.pop().unwrap();
+            // an indentation above -1 was set by `roll_indent`, which saved
+            // the previous one; -1 is that of the stream
+            self.indent = self.indents.pop().unwrap_or(-1);
         }
     }
 
@@ -1314,9 +1796,10 @@ impl<T: Iterator<Item = char>> Scanner<T> {
     }
 
     fn remove_simple_key(&mut self) -> ScanResult {
-        let last = self.simple_keys.last_mut().unwrap();
+        let mark = self.mark;
+        let last = self.last_simple_key()?;
         if last.possible && last.required {
-            return Err(ScanError::new(self.mark, "simple key expected"));
+            return Err(ScanError::scanner(mark, "simple key expected"));
         }
 
         last.possible = false;
@@ -1615,6 +2098,28 @@ key:
         end!(p);
     }
 
+    #[test]
+    fn test_bom() {
+        let s = "\u{feff}a: b";
+        let mut p = Scanner::new(s.chars());
+        next!(p, StreamStart(..));
+        next!(p, BlockMappingStart);
+        next!(p, Key);
+        let tok = p.next().unwrap();
+        assert_eq!(tok.1, Scalar(TScalarStyle::Plain, "a".to_owned()));
+        assert_eq!((tok.0.index, tok.0.col), (1, 0));
+        next!(p, Value);
+        next_scalar!(p, TScalarStyle::Plain, "b");
+        next!(p, BlockEnd);
+        next!(p, StreamEnd);
+        end!(p);
+
+        // anywhere else, it is content
+        let mut p = Scanner::new("a\u{feff}".chars());
+        next!(p, StreamStart(..));
+        next_scalar!(p, TScalarStyle::Plain, "a\u{feff}");
+    }
+
     #[test]
     fn test_uri() {
         // TODO
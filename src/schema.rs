@@ -0,0 +1,1774 @@
+//! Validating documents against a schema built from composable validators.
+//!
+//! A schema says what each node must be: `Str`, `Int`, `IntRange`, `Bool`,
+//! `Decimal` and `Datetime` check scalars, `Seq`, `UniqueSeq`, `FixedSeq`, `Map` and
+//! `MapPattern` check collections and the validators of their content, `Or`
+//! accepts what either of two validators accepts. `EmptyNone`, `EmptyDict`
+//! and `EmptyList` say what an empty value, as in `key:`, stands for in the
+//! validated view of the document. Validation reports every problem found, each with the
+//! path of the node and, when it comes from loaded text, its position. The
+//! `schema!` macro declares a schema in a shorter form, and `StrictYamlConfig`,
+//! derived by the `strict-yaml-rust-derive` crate, reads a struct through one.
+//!
+//! ```
+//! use strict_yaml_rust::schema::{load_with_schema, Bool, Int, Map, Seq, Str};
+//!
+//! let schema = Map::new()
+//!     .required("name", Str)
+//!     .required("ports", Seq::new(Int))
+//!     .optional("debug", Bool);
+//!
+//! let docs = load_with_schema("name: demo\nports:\n  - 80\n  - 443", &schema).unwrap();
+//! assert_eq!(docs[0]["ports"][1].as_str(), Some("443"));
+//!
+//! let err = load_with_schema("name: demo\nports:\n  - 80\n  - http\nlog: on", &schema).unwrap_err();
+//! assert_eq!(
+//!     err.to_string(),
+//!     "expected an integer, found \"http\" at ports[1] (line 4 column 5)\n\
+//!      unexpected key \"log\" at log (line 5 column 1)"
+//! );
+//! ```
+
+use error::{code, LoadError};
+use marked::{MarkedNode, MarkedStrictYaml};
+use path::{PathSegment, YamlPath};
+use scanner::{Marker, ScanError};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+use std::ops::Range;
+use strict_yaml::{Hash, StrictYaml};
+
+/// What is wrong with a node.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum SchemaErrorKind {
+    /// The node is not what the validator accepts, e.g. `an integer`. The
+    /// scalar found is quoted, collections are described.
+    Expected { expected: String, found: String },
+    /// A required key of a mapping is missing.
+    MissingKey(String),
+    /// A mapping has a key the schema does not list.
+    UnexpectedKey(String),
+    /// A sequence has more items than a `FixedSeq` lists.
+    UnexpectedItem,
+    /// An item of a `UniqueSeq` equals the item at the given index.
+    Duplicate(usize),
+    /// A scalar is not one of the values an `Enum` allows.
+    NotAllowed { found: String, allowed: Vec<String> },
+    /// A mapping has too few or too many entries.
+    Entries {
+        min: usize,
+        max: Option<usize>,
+        found: usize,
+    },
+}
+
+impl SchemaErrorKind {
+    pub fn code(&self) -> &'static str {
+        match *self {
+            SchemaErrorKind::Expected { .. } => code::EXPECTED,
+            SchemaErrorKind::MissingKey(_) => code::MISSING_KEY,
+            SchemaErrorKind::UnexpectedKey(_) => code::UNEXPECTED_KEY,
+            SchemaErrorKind::UnexpectedItem => code::UNEXPECTED_ITEM,
+            SchemaErrorKind::Duplicate(_) => code::DUPLICATE_ITEM,
+            SchemaErrorKind::NotAllowed { .. } => code::NOT_ALLOWED,
+            SchemaErrorKind::Entries { .. } => code::ENTRIES,
+        }
+    }
+}
+
+impl fmt::Display for SchemaErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SchemaErrorKind::Expected {
+                ref expected,
+                ref found,
+            } => write!(f, "expected {}, found {}", expected, found),
+            SchemaErrorKind::MissingKey(ref key) => write!(f, "missing key {:?}", key),
+            SchemaErrorKind::UnexpectedKey(ref key) => write!(f, "unexpected key {:?}", key),
+            SchemaErrorKind::UnexpectedItem => f.write_str("unexpected item"),
+            SchemaErrorKind::Duplicate(first) => write!(f, "duplicate of item {}", first),
+            SchemaErrorKind::NotAllowed {
+                ref found,
+                ref allowed,
+            } => write!(
+                f,
+                "expected one of {}, found {:?}",
+                quoted_list(allowed),
+                found
+            ),
+            SchemaErrorKind::Entries { min, max, found } => {
+                match max {
+                    Some(max) if max == min => write!(f, "expected {} entries", min)?,
+                    Some(max) => write!(f, "expected {} to {} entries", min, max)?,
+                    None => write!(f, "expected at least {} entries", min)?,
+                }
+                write!(f, ", found {}", found)
+            }
+        }
+    }
+}
+
+/// A node not matching its schema.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct SchemaError {
+    kind: SchemaErrorKind,
+    path: YamlPath,
+    marker: Option<Marker>,
+}
+
+impl SchemaError {
+    pub fn new(kind: SchemaErrorKind, path: YamlPath, marker: Option<Marker>) -> SchemaError {
+        SchemaError { kind, path, marker }
+    }
+
+    pub fn kind(&self) -> &SchemaErrorKind {
+        &self.kind
+    }
+
+    pub fn code(&self) -> &'static str {
+        self.kind.code()
+    }
+
+    /// The path of the node, or of the unexpected key.
+    pub fn path(&self) -> &YamlPath {
+        &self.path
+    }
+
+    /// Where the node starts in the source, unless the tree was built
+    /// without positions.
+    pub fn marker(&self) -> Option<Marker> {
+        self.marker
+    }
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.kind)?;
+        if !self.path.is_empty() {
+            write!(f, " at {}", self.path)?;
+        }
+        if let Some(marker) = self.marker {
+            write!(f, " (line {} column {})", marker.line(), marker.col() + 1)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for SchemaError {}
+
+/// Why `load_with_schema` failed.
+#[derive(Debug)]
+pub enum SchemaLoadError {
+    /// The source could not be loaded.
+    Load(LoadError),
+    /// All the problems found, in document order.
+    Schema(Vec<SchemaError>),
+}
+
+impl fmt::Display for SchemaLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SchemaLoadError::Load(ref e) => e.fmt(f),
+            SchemaLoadError::Schema(ref errors) => {
+                for (i, e) in errors.iter().enumerate() {
+                    if i > 0 {
+                        writeln!(f)?;
+                    }
+                    e.fmt(f)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl SchemaLoadError {
+    pub fn code(&self) -> &'static str {
+        match *self {
+            SchemaLoadError::Load(ref e) => e.code(),
+            SchemaLoadError::Schema(_) => code::SCHEMA,
+        }
+    }
+}
+
+impl Error for SchemaLoadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            SchemaLoadError::Load(ref e) => Some(e),
+            SchemaLoadError::Schema(_) => None,
+        }
+    }
+}
+
+impl From<ScanError> for SchemaLoadError {
+    fn from(e: ScanError) -> SchemaLoadError {
+        SchemaLoadError::Load(LoadError::from(e))
+    }
+}
+
+impl From<LoadError> for SchemaLoadError {
+    fn from(e: LoadError) -> SchemaLoadError {
+        SchemaLoadError::Load(e)
+    }
+}
+
+/// A check of a node and, for collections, of their content.
+pub trait Validator {
+    /// What the validator accepts, to complete "expected ...".
+    fn expected(&self) -> String;
+
+    /// Checks `node`, found at `path`, adding what is wrong to `errors`.
+    fn check(&self, node: &MarkedStrictYaml, path: &YamlPath, errors: &mut Vec<SchemaError>);
+
+    /// The validated view of a node `check` accepted. Most validators keep
+    /// the node as it is, `EmptyList` for one turns an empty scalar into an
+    /// empty sequence. `BadValue` leaves a mapping entry out.
+    fn view(&self, node: &MarkedStrictYaml) -> StrictYaml {
+        node.to_strict_yaml()
+    }
+}
+
+impl Validator for Box<dyn Validator> {
+    fn expected(&self) -> String {
+        (**self).expected()
+    }
+
+    fn check(&self, node: &MarkedStrictYaml, path: &YamlPath, errors: &mut Vec<SchemaError>) {
+        (**self).check(node, path, errors)
+    }
+
+    fn view(&self, node: &MarkedStrictYaml) -> StrictYaml {
+        (**self).view(node)
+    }
+}
+
+/// Loads all the documents of `source` and checks each against `schema`,
+/// returning their validated views.
+pub fn load_with_schema(
+    source: &str,
+    schema: &dyn Validator,
+) -> Result<Vec<StrictYaml>, SchemaLoadError> {
+    let docs = MarkedStrictYaml::load_from_str(source)?;
+    let mut errors = Vec::new();
+    for doc in &docs {
+        schema.check(doc, &YamlPath::new(), &mut errors);
+    }
+    if !errors.is_empty() {
+        sort(&mut errors);
+        return Err(SchemaLoadError::Schema(errors));
+    }
+    Ok(docs.into_iter().map(|doc| schema.view(&doc)).collect())
+}
+
+/// Checks a node loaded with its positions against `schema`, returning its
+/// validated view.
+pub fn validate_marked(
+    node: &MarkedStrictYaml,
+    schema: &dyn Validator,
+) -> Result<StrictYaml, Vec<SchemaError>> {
+    let mut errors = Vec::new();
+    schema.check(node, &YamlPath::new(), &mut errors);
+    if errors.is_empty() {
+        Ok(schema.view(node))
+    } else {
+        sort(&mut errors);
+        Err(errors)
+    }
+}
+
+/// Puts `errors` in document order. Validators report the missing keys of a
+/// mapping after the errors of its entries, and without positions the order
+/// of the checks stays.
+fn sort(errors: &mut [SchemaError]) {
+    errors.sort_by_key(|e| e.marker.map(|m| m.index()));
+}
+
+/// Checks `node` against `schema`, returning its validated view. The errors
+/// have no position.
+pub fn validate(node: &StrictYaml, schema: &dyn Validator) -> Result<StrictYaml, Vec<SchemaError>> {
+    validate_marked(&unmarked(node), schema).map_err(|errors| {
+        errors
+            .into_iter()
+            .map(|e| SchemaError { marker: None, ..e })
+            .collect()
+    })
+}
+
+/// Whether `v` accepts `node`.
+fn accepts(v: &dyn Validator, node: &MarkedStrictYaml) -> bool {
+    let mut errors = Vec::new();
+    v.check(node, &YamlPath::new(), &mut errors);
+    errors.is_empty()
+}
+
+/// The views of the items of a sequence, by the validator of each.
+fn view_items<'v, I>(node: &MarkedStrictYaml, validators: I) -> StrictYaml
+where
+    I: Iterator<Item = &'v dyn Validator>,
+{
+    match *node.node() {
+        MarkedNode::Array(ref items) => StrictYaml::Array(
+            items
+                .iter()
+                .zip(validators)
+                .map(|(item, v)| v.view(item))
+                .collect(),
+        ),
+        _ => node.to_strict_yaml(),
+    }
+}
+
+/// The views of the values of a mapping, by the validator `validator`
+/// returns for each key, leaving out `BadValue` views.
+fn view_entries<'v, F>(node: &MarkedStrictYaml, validator: F) -> StrictYaml
+where
+    F: Fn(&MarkedStrictYaml) -> Option<&'v dyn Validator>,
+{
+    match *node.node() {
+        MarkedNode::Hash(ref entries) => StrictYaml::Hash(
+            entries
+                .iter()
+                .filter_map(|(k, v)| {
+                    let v = match validator(k) {
+                        Some(validator) => validator.view(v),
+                        None => v.to_strict_yaml(),
+                    };
+                    (v != StrictYaml::BadValue).then_some((k.to_strict_yaml(), v))
+                })
+                .collect(),
+        ),
+        _ => node.to_strict_yaml(),
+    }
+}
+
+fn unmarked(node: &StrictYaml) -> MarkedStrictYaml {
+    let content = match *node {
+        StrictYaml::String(ref s) => MarkedNode::String(s.clone()),
+        StrictYaml::Array(ref v) => MarkedNode::Array(v.iter().map(unmarked).collect()),
+        StrictYaml::Hash(ref h) => {
+            MarkedNode::Hash(h.iter().map(|(k, v)| (unmarked(k), unmarked(v))).collect())
+        }
+        StrictYaml::BadValue => MarkedNode::BadValue,
+    };
+    MarkedStrictYaml::new(content, Marker::new(0, 1, 0))
+}
+
+fn found(node: &MarkedStrictYaml) -> String {
+    match *node.node() {
+        MarkedNode::String(ref s) => format!("{:?}", s),
+        MarkedNode::Array(_) => "a sequence".to_owned(),
+        MarkedNode::Hash(_) => "a mapping".to_owned(),
+        MarkedNode::BadValue => "nothing".to_owned(),
+    }
+}
+
+fn mismatch(v: &dyn Validator, node: &MarkedStrictYaml, path: &YamlPath) -> SchemaError {
+    SchemaError::new(
+        SchemaErrorKind::Expected {
+            expected: v.expected(),
+            found: found(node),
+        },
+        path.clone(),
+        Some(node.marker()),
+    )
+}
+
+/// Checks that `node` is a scalar accepted by `accepts`.
+fn check_scalar<F: Fn(&str) -> bool>(
+    v: &dyn Validator,
+    node: &MarkedStrictYaml,
+    path: &YamlPath,
+    errors: &mut Vec<SchemaError>,
+    accepts: F,
+) {
+    if !node.as_str().is_some_and(accepts) {
+        errors.push(mismatch(v, node, path));
+    }
+}
+
+/// Any node.
+pub struct Any;
+
+impl Validator for Any {
+    fn expected(&self) -> String {
+        "anything".to_owned()
+    }
+
+    fn check(&self, _: &MarkedStrictYaml, _: &YamlPath, _: &mut Vec<SchemaError>) {}
+}
+
+/// A validator of scalars, reading the scalars it accepts as a value. The
+/// tree keeps the text, `value` converts it when needed.
+///
+/// ```
+/// use strict_yaml_rust::schema::{Bool, Int, ScalarValidator};
+/// use strict_yaml_rust::StrictYamlLoader;
+///
+/// let doc = &StrictYamlLoader::load_from_str("port: 8080\ndebug: off").unwrap()[0];
+/// assert_eq!(Int.value(&doc["port"]), Some(8080));
+/// assert_eq!(Bool.value(&doc["debug"]), Some(false));
+/// assert_eq!(Int.value(&doc["debug"]), None);
+/// ```
+pub trait ScalarValidator: Validator {
+    type Value;
+
+    /// The value of `s`, if the validator accepts it.
+    fn parse(&self, s: &str) -> Option<Self::Value>;
+
+    /// The value of `node`, if it is a scalar the validator accepts.
+    fn value(&self, node: &StrictYaml) -> Option<Self::Value> {
+        node.as_str().and_then(|s| self.parse(s))
+    }
+}
+
+/// Implements `Validator` for a `ScalarValidator`.
+macro_rules! scalar_validator {
+    ($t:ty, $expected:expr) => {
+        impl Validator for $t {
+            fn expected(&self) -> String {
+                $expected.to_owned()
+            }
+
+            fn check(
+                &self,
+                node: &MarkedStrictYaml,
+                path: &YamlPath,
+                errors: &mut Vec<SchemaError>,
+            ) {
+                check_scalar(self, node, path, errors, |s| self.parse(s).is_some());
+            }
+        }
+    };
+}
+
+/// Any scalar.
+pub struct Str;
+
+impl ScalarValidator for Str {
+    type Value = String;
+
+    fn parse(&self, s: &str) -> Option<String> {
+        Some(s.to_owned())
+    }
+}
+
+scalar_validator!(Str, "a string");
+
+/// A scalar parsing as an `i64`.
+pub struct Int;
+
+impl ScalarValidator for Int {
+    type Value = i64;
+
+    fn parse(&self, s: &str) -> Option<i64> {
+        s.parse().ok()
+    }
+}
+
+scalar_validator!(Int, "an integer");
+
+/// An integer within a range, such as a port in `1..65536`.
+pub struct IntRange(Range<i64>);
+
+impl IntRange {
+    pub fn new(range: Range<i64>) -> IntRange {
+        IntRange(range)
+    }
+}
+
+impl ScalarValidator for IntRange {
+    type Value = i64;
+
+    fn parse(&self, s: &str) -> Option<i64> {
+        Int.parse(s).filter(|i| self.0.contains(i))
+    }
+}
+
+impl Validator for IntRange {
+    fn expected(&self) -> String {
+        format!("an integer from {} to {}", self.0.start, self.0.end - 1)
+    }
+
+    fn check(&self, node: &MarkedStrictYaml, path: &YamlPath, errors: &mut Vec<SchemaError>) {
+        check_scalar(self, node, path, errors, |s| self.parse(s).is_some());
+    }
+}
+
+/// A finite number such as `1.5`, `-3` or `2e10`.
+pub struct Decimal;
+
+impl ScalarValidator for Decimal {
+    type Value = f64;
+
+    fn parse(&self, s: &str) -> Option<f64> {
+        s.parse::<f64>().ok().filter(|v| v.is_finite())
+    }
+}
+
+scalar_validator!(Decimal, "a decimal");
+
+/// `true`, `false`, `yes`, `no`, `on` or `off`, in any case.
+pub struct Bool;
+
+impl ScalarValidator for Bool {
+    type Value = bool;
+
+    fn parse(&self, s: &str) -> Option<bool> {
+        let is = |words: [&str; 3]| words.iter().any(|w| w.eq_ignore_ascii_case(s));
+        if is(["true", "yes", "on"]) {
+            Some(true)
+        } else if is(["false", "no", "off"]) {
+            Some(false)
+        } else {
+            None
+        }
+    }
+}
+
+scalar_validator!(Bool, "a boolean");
+
+/// A point in time read by `Datetime`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Timestamp {
+    pub year: i32,
+    pub month: u32,
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+    pub nanosecond: u32,
+    /// The offset from UTC in minutes, if given.
+    pub offset: Option<i32>,
+}
+
+impl Timestamp {
+    /// Seconds since 1970-01-01T00:00:00Z, taking a missing offset as UTC.
+    pub fn unix_seconds(&self) -> i64 {
+        // days from civil, http://howardhinnant.github.io/date_algorithms.html
+        let y = i64::from(self.year) - i64::from(self.month <= 2);
+        let era = y.div_euclid(400);
+        let yoe = y - era * 400;
+        let m = i64::from(self.month);
+        let doy = (153 * (m + if m > 2 { -3 } else { 9 }) + 2) / 5 + i64::from(self.day) - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+        let days = era * 146_097 + doe - 719_468;
+        let seconds = i64::from(self.hour * 3600 + self.minute * 60 + self.second);
+        days * 86_400 + seconds - i64::from(self.offset.unwrap_or(0)) * 60
+    }
+}
+
+/// An ISO 8601 date, `2024-02-29`, or date and time, `2024-02-29T13:45:00`,
+/// with optional fractional seconds and offset: `Z`, `+01:00` or `-0500`.
+/// The time may be separated by a space instead of `T`.
+pub struct Datetime;
+
+impl ScalarValidator for Datetime {
+    type Value = Timestamp;
+
+    fn parse(&self, s: &str) -> Option<Timestamp> {
+        fn number(s: &str) -> Option<u32> {
+            if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+                return None;
+            }
+            s.parse().ok()
+        }
+        fn two_digits(s: &str) -> Option<(u32, &str)> {
+            let (digits, rest) = (s.get(..2)?, &s[2..]);
+            Some((number(digits)?, rest))
+        }
+
+        let (date, time) = match s.find(['T', 't', ' ']) {
+            Some(i) => (&s[..i], Some(&s[i + 1..])),
+            None => (s, None),
+        };
+        let mut parts = date.splitn(3, '-');
+        let year = parts.next().filter(|y| y.len() == 4).and_then(number)? as i32;
+        let month = parts.next().filter(|m| m.len() == 2).and_then(number)?;
+        let day = parts.next().filter(|d| d.len() == 2).and_then(number)?;
+        let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+        let days = match month {
+            1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+            4 | 6 | 9 | 11 => 30,
+            2 if leap => 29,
+            2 => 28,
+            _ => return None,
+        };
+        if day == 0 || day > days {
+            return None;
+        }
+        let mut stamp = Timestamp {
+            year,
+            month,
+            day,
+            hour: 0,
+            minute: 0,
+            second: 0,
+            nanosecond: 0,
+            offset: None,
+        };
+        let time = match time {
+            Some(time) => time,
+            None => return Some(stamp),
+        };
+
+        let (hour, rest) = two_digits(time)?;
+        let (minute, mut rest) = two_digits(rest.strip_prefix(':')?)?;
+        if let Some(seconds) = rest.strip_prefix(':') {
+            let (second, after) = two_digits(seconds)?;
+            stamp.second = second;
+            rest = after;
+            if let Some(fraction) = rest.strip_prefix('.') {
+                let digits = fraction.bytes().take_while(u8::is_ascii_digit).count();
+                if digits == 0 {
+                    return None;
+                }
+                let nanos: String = fraction[..digits]
+                    .chars()
+                    .chain("00000000".chars())
+                    .take(9)
+                    .collect();
+                stamp.nanosecond = number(&nanos)?;
+                rest = &fraction[digits..];
+            }
+        }
+        stamp.offset = match rest {
+            "" => None,
+            "Z" | "z" => Some(0),
+            _ => {
+                let sign = match rest.as_bytes()[0] {
+                    b'+' => 1,
+                    b'-' => -1,
+                    _ => return None,
+                };
+                let (hours, after) = two_digits(&rest[1..])?;
+                let minutes = match after.strip_prefix(':').unwrap_or(after) {
+                    "" => 0,
+                    m if m.len() == 2 => number(m)?,
+                    _ => return None,
+                };
+                if hours > 23 || minutes > 59 {
+                    return None;
+                }
+                Some(sign * (hours * 60 + minutes) as i32)
+            }
+        };
+        if hour > 23 || minute > 59 || stamp.second > 60 {
+            return None;
+        }
+        stamp.hour = hour;
+        stamp.minute = minute;
+        Some(stamp)
+    }
+}
+
+scalar_validator!(Datetime, "a datetime");
+
+/// A scalar matching a regular expression as a whole, with the `regex`
+/// feature.
+#[cfg(feature = "regex")]
+pub struct Regex(regex::Regex);
+
+#[cfg(feature = "regex")]
+impl Regex {
+    /// Compiles `pattern`, which has to match the whole scalar.
+    pub fn new(pattern: &str) -> Result<Regex, regex::Error> {
+        regex::Regex::new(&format!("^(?:{})$", pattern)).map(Regex)
+    }
+}
+
+#[cfg(feature = "regex")]
+impl Validator for Regex {
+    fn expected(&self) -> String {
+        let pattern = self.0.as_str();
+        format!("a match of {:?}", &pattern[4..pattern.len() - 2])
+    }
+
+    fn check(&self, node: &MarkedStrictYaml, path: &YamlPath, errors: &mut Vec<SchemaError>) {
+        check_scalar(self, node, path, errors, |s| self.0.is_match(s));
+    }
+}
+
+/// One of a list of scalars, such as log levels. A scalar not in the list is
+/// a `NotAllowed` error listing the permitted values.
+pub struct Enum(Vec<String>);
+
+impl Enum {
+    pub fn new(values: &[&str]) -> Enum {
+        Enum(values.iter().map(|&v| v.to_owned()).collect())
+    }
+
+    /// The permitted values.
+    pub fn values(&self) -> &[String] {
+        &self.0
+    }
+}
+
+impl ScalarValidator for Enum {
+    type Value = String;
+
+    fn parse(&self, s: &str) -> Option<String> {
+        self.0.iter().find(|v| *v == s).cloned()
+    }
+}
+
+impl Validator for Enum {
+    fn expected(&self) -> String {
+        format!("one of {}", quoted_list(&self.0))
+    }
+
+    fn check(&self, node: &MarkedStrictYaml, path: &YamlPath, errors: &mut Vec<SchemaError>) {
+        match node.as_str() {
+            Some(s) if self.parse(s).is_none() => errors.push(SchemaError::new(
+                SchemaErrorKind::NotAllowed {
+                    found: s.to_owned(),
+                    allowed: self.0.clone(),
+                },
+                path.clone(),
+                Some(node.marker()),
+            )),
+            Some(_) => {}
+            None => errors.push(mismatch(self, node, path)),
+        }
+    }
+}
+
+fn quoted_list(values: &[String]) -> String {
+    let values: Vec<String> = values.iter().map(|v| format!("{:?}", v)).collect();
+    values.join(", ")
+}
+
+/// A sequence whose items all match a validator.
+pub struct Seq(Box<dyn Validator>);
+
+impl Seq {
+    pub fn new<V: Validator + 'static>(item: V) -> Seq {
+        Seq(Box::new(item))
+    }
+}
+
+impl Validator for Seq {
+    fn expected(&self) -> String {
+        format!("a sequence of {}", self.0.expected())
+    }
+
+    fn check(&self, node: &MarkedStrictYaml, path: &YamlPath, errors: &mut Vec<SchemaError>) {
+        match *node.node() {
+            MarkedNode::Array(ref items) => {
+                for (i, item) in items.iter().enumerate() {
+                    self.0
+                        .check(item, &path.child(PathSegment::Index(i)), errors);
+                }
+            }
+            _ => errors.push(mismatch(self, node, path)),
+        }
+    }
+
+    fn view(&self, node: &MarkedStrictYaml) -> StrictYaml {
+        view_items(node, std::iter::repeat(&*self.0))
+    }
+}
+
+/// A sequence whose items all match a validator and are all different. A
+/// repeated item is an error at the repetition.
+pub struct UniqueSeq(Box<dyn Validator>);
+
+impl UniqueSeq {
+    pub fn new<V: Validator + 'static>(item: V) -> UniqueSeq {
+        UniqueSeq(Box::new(item))
+    }
+}
+
+impl Validator for UniqueSeq {
+    fn expected(&self) -> String {
+        format!("a sequence of unique {}", self.0.expected())
+    }
+
+    fn check(&self, node: &MarkedStrictYaml, path: &YamlPath, errors: &mut Vec<SchemaError>) {
+        let items = match *node.node() {
+            MarkedNode::Array(ref items) => items,
+            _ => return errors.push(mismatch(self, node, path)),
+        };
+        let mut seen = HashMap::new();
+        for (i, item) in items.iter().enumerate() {
+            let item_path = path.child(PathSegment::Index(i));
+            self.0.check(item, &item_path, errors);
+            if let Some(&first) = seen.get(&item.to_strict_yaml()) {
+                errors.push(SchemaError::new(
+                    SchemaErrorKind::Duplicate(first),
+                    item_path,
+                    Some(item.marker()),
+                ));
+            } else {
+                seen.insert(item.to_strict_yaml(), i);
+            }
+        }
+    }
+
+    fn view(&self, node: &MarkedStrictYaml) -> StrictYaml {
+        view_items(node, std::iter::repeat(&*self.0))
+    }
+}
+
+/// A scalar such as `a, b, c`, each comma-separated part matching a
+/// validator, viewed as the sequence of its parts. An empty scalar has no
+/// parts. Errors on a part give its index in the path and the position of
+/// the scalar.
+pub struct CommaSeparated(Box<dyn Validator>);
+
+impl CommaSeparated {
+    pub fn new<V: Validator + 'static>(item: V) -> CommaSeparated {
+        CommaSeparated(Box::new(item))
+    }
+
+    fn parts(s: &str) -> Vec<&str> {
+        if s.trim().is_empty() {
+            Vec::new()
+        } else {
+            s.split(',').map(str::trim).collect()
+        }
+    }
+}
+
+impl Validator for CommaSeparated {
+    fn expected(&self) -> String {
+        format!("a comma-separated list of {}", self.0.expected())
+    }
+
+    fn check(&self, node: &MarkedStrictYaml, path: &YamlPath, errors: &mut Vec<SchemaError>) {
+        let s = match node.as_str() {
+            Some(s) => s,
+            None => return errors.push(mismatch(self, node, path)),
+        };
+        for (i, part) in CommaSeparated::parts(s).into_iter().enumerate() {
+            let part = MarkedStrictYaml::new(MarkedNode::String(part.to_owned()), node.marker());
+            self.0
+                .check(&part, &path.child(PathSegment::Index(i)), errors);
+        }
+    }
+
+    fn view(&self, node: &MarkedStrictYaml) -> StrictYaml {
+        let parts = match node.as_str() {
+            Some(s) => CommaSeparated::parts(s)
+                .into_iter()
+                .map(|part| {
+                    let part = MarkedNode::String(part.to_owned());
+                    self.0.view(&MarkedStrictYaml::new(part, node.marker()))
+                })
+                .collect(),
+            None => return node.to_strict_yaml(),
+        };
+        StrictYaml::Array(parts)
+    }
+}
+
+/// A sequence of a given length, each item matching its own validator, such
+/// as a `[name, port, enabled]` triple.
+#[derive(Default)]
+pub struct FixedSeq(Vec<Box<dyn Validator>>);
+
+impl FixedSeq {
+    pub fn new() -> FixedSeq {
+        FixedSeq::default()
+    }
+
+    /// Adds an item matching `v`.
+    pub fn item<V: Validator + 'static>(mut self, v: V) -> FixedSeq {
+        self.0.push(Box::new(v));
+        self
+    }
+}
+
+impl Validator for FixedSeq {
+    fn expected(&self) -> String {
+        format!("a sequence of {}", count(self.0.len(), "item"))
+    }
+
+    /// Checks the items present, then reports each item too many, or the
+    /// sequence if it is too short.
+    fn check(&self, node: &MarkedStrictYaml, path: &YamlPath, errors: &mut Vec<SchemaError>) {
+        let items = match *node.node() {
+            MarkedNode::Array(ref items) => items,
+            _ => return errors.push(mismatch(self, node, path)),
+        };
+        for (i, item) in items.iter().enumerate() {
+            let item_path = path.child(PathSegment::Index(i));
+            match self.0.get(i) {
+                Some(v) => v.check(item, &item_path, errors),
+                None => errors.push(SchemaError::new(
+                    SchemaErrorKind::UnexpectedItem,
+                    item_path,
+                    Some(item.marker()),
+                )),
+            }
+        }
+        if items.len() < self.0.len() {
+            errors.push(SchemaError::new(
+                SchemaErrorKind::Expected {
+                    expected: self.expected(),
+                    found: count(items.len(), "item"),
+                },
+                path.clone(),
+                Some(node.marker()),
+            ));
+        }
+    }
+
+    fn view(&self, node: &MarkedStrictYaml) -> StrictYaml {
+        view_items(node, self.0.iter().map(|v| &**v))
+    }
+}
+
+/// `n` and `what`, plural unless `n` is 1.
+fn count(n: usize, what: &str) -> String {
+    if n == 1 {
+        format!("1 {}", what)
+    } else {
+        format!("{} {}s", n, what)
+    }
+}
+
+/// A key of a `Map`.
+struct MapKey {
+    name: String,
+    required: bool,
+    /// The value of the validated view when the key is left out.
+    default: Option<StrictYaml>,
+    validator: Box<dyn Validator>,
+}
+
+/// A mapping with known keys, each matching its own validator. Keys not
+/// listed are errors.
+#[derive(Default)]
+pub struct Map(Vec<MapKey>);
+
+impl Map {
+    pub fn new() -> Map {
+        Map::default()
+    }
+
+    /// Adds a key which must be present.
+    pub fn required<V: Validator + 'static>(self, key: &str, v: V) -> Map {
+        self.key(key, true, None, v)
+    }
+
+    /// Adds a key which may be left out.
+    pub fn optional<V: Validator + 'static>(self, key: &str, v: V) -> Map {
+        self.key(key, false, None, v)
+    }
+
+    /// Adds a key which may be left out, set to `default` in the validated
+    /// view when it is. `default` is not checked against `v`.
+    pub fn optional_with_default<V, D>(self, key: &str, v: V, default: D) -> Map
+    where
+        V: Validator + 'static,
+        D: Into<StrictYaml>,
+    {
+        self.key(key, false, Some(default.into()), v)
+    }
+
+    fn key<V: Validator + 'static>(
+        mut self,
+        name: &str,
+        required: bool,
+        default: Option<StrictYaml>,
+        v: V,
+    ) -> Map {
+        self.0.push(MapKey {
+            name: name.to_owned(),
+            required,
+            default,
+            validator: Box::new(v),
+        });
+        self
+    }
+
+    fn find(&self, name: &str) -> Option<&MapKey> {
+        self.0.iter().find(|key| key.name == name)
+    }
+}
+
+impl Validator for Map {
+    fn expected(&self) -> String {
+        "a mapping".to_owned()
+    }
+
+    fn check(&self, node: &MarkedStrictYaml, path: &YamlPath, errors: &mut Vec<SchemaError>) {
+        let entries = match *node.node() {
+            MarkedNode::Hash(ref entries) => entries,
+            _ => return errors.push(mismatch(self, node, path)),
+        };
+        for (k, value) in entries {
+            let key = k.as_str().unwrap_or_default();
+            let entry_path = path.child(PathSegment::Key(key.to_owned()));
+            match self.find(key) {
+                Some(key) => key.validator.check(value, &entry_path, errors),
+                None => errors.push(SchemaError::new(
+                    SchemaErrorKind::UnexpectedKey(key.to_owned()),
+                    entry_path,
+                    Some(k.marker()),
+                )),
+            }
+        }
+        for key in &self.0 {
+            if key.required && node.get(&key.name).is_none() {
+                errors.push(SchemaError::new(
+                    SchemaErrorKind::MissingKey(key.name.clone()),
+                    path.clone(),
+                    Some(node.marker()),
+                ));
+            }
+        }
+    }
+
+    fn view(&self, node: &MarkedStrictYaml) -> StrictYaml {
+        let mut view = view_entries(node, |k| {
+            k.as_str()
+                .and_then(|name| self.find(name))
+                .map(|key| &*key.validator)
+        });
+        if let StrictYaml::Hash(ref mut entries) = view {
+            for key in &self.0 {
+                if let Some(ref default) = key.default {
+                    entries
+                        .entry(StrictYaml::String(key.name.clone()))
+                        .or_insert_with(|| default.clone());
+                }
+            }
+        }
+        view
+    }
+}
+
+/// A mapping of any keys, the keys matching a validator and the values
+/// another, such as hostnames to settings with `Regex` keys.
+pub struct MapPattern {
+    key: Box<dyn Validator>,
+    value: Box<dyn Validator>,
+    min: usize,
+    max: Option<usize>,
+}
+
+impl MapPattern {
+    pub fn new<K: Validator + 'static, V: Validator + 'static>(key: K, value: V) -> MapPattern {
+        MapPattern {
+            key: Box::new(key),
+            value: Box::new(value),
+            min: 0,
+            max: None,
+        }
+    }
+
+    /// Requires at least `min` entries.
+    pub fn min_entries(mut self, min: usize) -> MapPattern {
+        self.min = min;
+        self
+    }
+
+    /// Allows at most `max` entries.
+    pub fn max_entries(mut self, max: usize) -> MapPattern {
+        self.max = Some(max);
+        self
+    }
+}
+
+impl Validator for MapPattern {
+    fn expected(&self) -> String {
+        format!(
+            "a mapping of {} to {}",
+            self.key.expected(),
+            self.value.expected()
+        )
+    }
+
+    fn check(&self, node: &MarkedStrictYaml, path: &YamlPath, errors: &mut Vec<SchemaError>) {
+        let entries = match *node.node() {
+            MarkedNode::Hash(ref entries) => entries,
+            _ => return errors.push(mismatch(self, node, path)),
+        };
+        if entries.len() < self.min || self.max.is_some_and(|max| entries.len() > max) {
+            errors.push(SchemaError::new(
+                SchemaErrorKind::Entries {
+                    min: self.min,
+                    max: self.max,
+                    found: entries.len(),
+                },
+                path.clone(),
+                Some(node.marker()),
+            ));
+        }
+        for (k, v) in entries {
+            let key = k.as_str().unwrap_or_default();
+            let entry_path = path.child(PathSegment::Key(key.to_owned()));
+            self.key.check(k, &entry_path, errors);
+            self.value.check(v, &entry_path, errors);
+        }
+    }
+
+    fn view(&self, node: &MarkedStrictYaml) -> StrictYaml {
+        view_entries(node, |_| Some(&*self.value))
+    }
+}
+
+/// What either of two validators accepts, such as `Or::new(Int, Enum::new(&["auto"]))`.
+pub struct Or(Box<dyn Validator>, Box<dyn Validator>);
+
+impl Or {
+    pub fn new<A: Validator + 'static, B: Validator + 'static>(a: A, b: B) -> Or {
+        Or(Box::new(a), Box::new(b))
+    }
+}
+
+impl Validator for Or {
+    fn expected(&self) -> String {
+        format!("{} or {}", self.0.expected(), self.1.expected())
+    }
+
+    fn check(&self, node: &MarkedStrictYaml, path: &YamlPath, errors: &mut Vec<SchemaError>) {
+        for v in [&self.0, &self.1] {
+            let mut own = Vec::new();
+            v.check(node, path, &mut own);
+            if own.is_empty() {
+                return;
+            }
+        }
+        errors.push(mismatch(self, node, path));
+    }
+
+    fn view(&self, node: &MarkedStrictYaml) -> StrictYaml {
+        if accepts(&*self.0, node) {
+            self.0.view(node)
+        } else {
+            self.1.view(node)
+        }
+    }
+}
+
+/// What another validator accepts, except an empty scalar.
+pub struct NonEmpty(Box<dyn Validator>);
+
+impl NonEmpty {
+    pub fn new<V: Validator + 'static>(v: V) -> NonEmpty {
+        NonEmpty(Box::new(v))
+    }
+}
+
+impl Validator for NonEmpty {
+    fn expected(&self) -> String {
+        format!("{}, not empty", self.0.expected())
+    }
+
+    fn check(&self, node: &MarkedStrictYaml, path: &YamlPath, errors: &mut Vec<SchemaError>) {
+        if node.as_str() == Some("") {
+            errors.push(mismatch(self, node, path));
+        } else {
+            self.0.check(node, path, errors);
+        }
+    }
+
+    fn view(&self, node: &MarkedStrictYaml) -> StrictYaml {
+        self.0.view(node)
+    }
+}
+
+/// Defines a validator accepting an empty scalar, as in `key:`, and viewing
+/// it as `$view`.
+macro_rules! empty_validator {
+    ($(#[$doc:meta])* $name:ident, $view:expr) => {
+        $(#[$doc])*
+        pub struct $name;
+
+        impl Validator for $name {
+            fn expected(&self) -> String {
+                "an empty value".to_owned()
+            }
+
+            fn check(
+                &self,
+                node: &MarkedStrictYaml,
+                path: &YamlPath,
+                errors: &mut Vec<SchemaError>,
+            ) {
+                check_scalar(self, node, path, errors, str::is_empty);
+            }
+
+            fn view(&self, _: &MarkedStrictYaml) -> StrictYaml {
+                $view
+            }
+        }
+    };
+}
+
+empty_validator!(
+    /// An empty value, left out of its mapping in the validated view as if
+    /// the key were missing. With `Or`, such as `Or::new(EmptyNone, Int)`,
+    /// makes a value optional while keeping its key.
+    EmptyNone,
+    StrictYaml::BadValue
+);
+
+empty_validator!(
+    /// An empty value, an empty mapping in the validated view.
+    EmptyDict,
+    StrictYaml::Hash(Hash::new())
+);
+
+empty_validator!(
+    /// An empty value, an empty sequence in the validated view.
+    EmptyList,
+    StrictYaml::Array(Vec::new())
+);
+
+/// A Rust type read from the nodes a validator accepts. The companion
+/// `strict-yaml-rust-derive` crate derives it for structs with named
+/// fields, each a key of a `Map`.
+///
+/// ```
+/// use strict_yaml_rust::schema::StrictYamlConfig;
+///
+/// let ports = Vec::<u16>::load("- 80\n- 443").unwrap();
+/// assert_eq!(ports, [80, 443]);
+/// let err = Vec::<u16>::load("- 80\n- -1").unwrap_err();
+/// assert_eq!(
+///     err.to_string(),
+///     "expected an integer from 0 to 65535, found \"-1\" at [1] (line 2 column 3)"
+/// );
+/// ```
+pub trait StrictYamlConfig: Sized {
+    /// The validator of the nodes the type is read from.
+    fn validator() -> Box<dyn Validator>;
+
+    /// Reads a node `validator` accepted, `None` for other nodes.
+    fn from_node(node: &StrictYaml) -> Option<Self>;
+
+    /// Whether the type must be present as the value of a mapping entry.
+    /// `Option` can be left out.
+    fn required() -> bool {
+        true
+    }
+
+    /// Loads the first document of `source`, checked against `validator`.
+    fn load(source: &str) -> Result<Self, SchemaLoadError> {
+        let validator = Self::validator();
+        let doc = MarkedStrictYaml::load_from_str(source)?
+            .into_iter()
+            .next()
+            .unwrap_or_else(|| MarkedStrictYaml::new(MarkedNode::BadValue, Marker::new(0, 1, 0)));
+        let view = validate_marked(&doc, &validator).map_err(SchemaLoadError::Schema)?;
+        Self::from_node(&view).ok_or_else(|| {
+            SchemaLoadError::Schema(vec![mismatch(&validator, &doc, &YamlPath::new())])
+        })
+    }
+}
+
+/// Implements `StrictYamlConfig` for scalars read by a `ScalarValidator`.
+macro_rules! scalar_config {
+    ($($t:ty => $validator:expr),*) => {
+        $(
+            impl StrictYamlConfig for $t {
+                fn validator() -> Box<dyn Validator> {
+                    Box::new($validator)
+                }
+
+                fn from_node(node: &StrictYaml) -> Option<$t> {
+                    $validator.value(node).and_then(|v| <$t>::try_from(v).ok())
+                }
+            }
+        )*
+    };
+}
+
+scalar_config!(
+    String => Str,
+    i64 => Int,
+    i32 => IntRange::new(i32::MIN as i64..i32::MAX as i64 + 1),
+    i16 => IntRange::new(i16::MIN as i64..i16::MAX as i64 + 1),
+    i8 => IntRange::new(i8::MIN as i64..i8::MAX as i64 + 1),
+    u32 => IntRange::new(0..u32::MAX as i64 + 1),
+    u16 => IntRange::new(0..u16::MAX as i64 + 1),
+    u8 => IntRange::new(0..u8::MAX as i64 + 1),
+    f64 => Decimal,
+    bool => Bool
+);
+
+impl<T: StrictYamlConfig> StrictYamlConfig for Vec<T> {
+    fn validator() -> Box<dyn Validator> {
+        Box::new(Seq::new(T::validator()))
+    }
+
+    fn from_node(node: &StrictYaml) -> Option<Vec<T>> {
+        node.as_vec()?.iter().map(T::from_node).collect()
+    }
+}
+
+impl<T: StrictYamlConfig> StrictYamlConfig for Option<T> {
+    fn validator() -> Box<dyn Validator> {
+        T::validator()
+    }
+
+    fn from_node(node: &StrictYaml) -> Option<Option<T>> {
+        match *node {
+            StrictYaml::BadValue => Some(None),
+            _ => T::from_node(node).map(Some),
+        }
+    }
+
+    fn required() -> bool {
+        false
+    }
+}
+
+/// Builds a schema from a declaration, for schemas that stay readable as
+/// they grow.
+///
+/// Validators are written `any`, `str`, `int`, `int(range)` for an
+/// `IntRange`, `decimal`, `bool`, `seq(validator)` and `map { entries }`.
+/// Entries are `"key" => validator`, or `optional "key" => validator` for
+/// keys that may be left out, with `(default = value)` in place of the
+/// arguments of the validator for the value of the validated view then.
+///
+/// ```
+/// #[macro_use]
+/// extern crate strict_yaml_rust;
+///
+/// use strict_yaml_rust::schema::load_with_schema;
+///
+/// # fn main() {
+/// let schema = schema! {
+///     map {
+///         "port" => int(1..65536),
+///         "hosts" => seq(str),
+///         optional "debug" => bool(default = false),
+///     }
+/// };
+/// let docs = load_with_schema("port: 8080\nhosts:\n  - a", &schema).unwrap();
+/// assert_eq!(docs[0]["debug"].as_str(), Some("false"));
+/// let err = load_with_schema("port: 0\nhosts:\n  - a", &schema).unwrap_err();
+/// assert_eq!(
+///     err.to_string(),
+///     "expected an integer from 1 to 65535, found \"0\" at port (line 1 column 7)"
+/// );
+/// # }
+/// ```
+#[macro_export]
+macro_rules! schema {
+    (map { $($entries:tt)* }) => {
+        $crate::schema!(@entries $crate::schema::Map::new(); $($entries)*)
+    };
+    (seq($($item:tt)+)) => {
+        $crate::schema::Seq::new($crate::schema!($($item)+))
+    };
+    (int($range:expr)) => {
+        $crate::schema::IntRange::new($range)
+    };
+    (any) => {
+        $crate::schema::Any
+    };
+    (str) => {
+        $crate::schema::Str
+    };
+    (int) => {
+        $crate::schema::Int
+    };
+    (decimal) => {
+        $crate::schema::Decimal
+    };
+    (bool) => {
+        $crate::schema::Bool
+    };
+    (@entries $map:expr;) => {
+        $map
+    };
+    (@entries $map:expr; optional $key:literal => $name:ident(default = $default:expr)
+        $(, $($rest:tt)*)?) => {
+        $crate::schema!(@entries
+            $map.optional_with_default($key, $crate::schema!($name), $default);
+            $($($rest)*)?)
+    };
+    (@entries $map:expr; optional $key:literal => $name:ident $(($($args:tt)*))?
+        $({$($fields:tt)*})? $(, $($rest:tt)*)?) => {
+        $crate::schema!(@entries
+            $map.optional($key, $crate::schema!($name $(($($args)*))? $({$($fields)*})?));
+            $($($rest)*)?)
+    };
+    (@entries $map:expr; $key:literal => $name:ident $(($($args:tt)*))?
+        $({$($fields:tt)*})? $(, $($rest:tt)*)?) => {
+        $crate::schema!(@entries
+            $map.required($key, $crate::schema!($name $(($($args)*))? $({$($fields)*})?));
+            $($($rest)*)?)
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use strict_yaml::StrictYamlLoader;
+
+    fn schema() -> Map {
+        Map::new()
+            .required("name", Str)
+            .required("replicas", Int)
+            .optional("ratio", Decimal)
+            .optional("debug", Bool)
+            .optional("mode", Enum::new(&["fast", "safe"]))
+            .optional("timeout", Or::new(Int, Enum::new(&["none"])))
+            .optional("range", FixedSeq::new().item(Int).item(Int))
+            .optional("labels", MapPattern::new(Str, Str))
+            .optional(
+                "hosts",
+                Seq::new(Map::new().required("host", Str).optional("port", Int)),
+            )
+            .optional("extra", Any)
+    }
+
+    #[test]
+    fn test_valid() {
+        let source = "name: web
+replicas: -3
+ratio: 2.5e3
+debug: Yes
+mode: safe
+timeout: none
+range:
+  - 1
+  - 2
+labels:
+  team: core
+hosts:
+  - host: a
+  - host: b
+    port: 80
+extra:
+  - anything: here
+";
+        let docs = load_with_schema(source, &schema()).unwrap();
+        assert_eq!(docs, StrictYamlLoader::load_from_str(source).unwrap());
+    }
+
+    #[test]
+    fn test_errors() {
+        let source = "replicas: many
+ratio: inf
+debug: maybe
+mode: slow
+timeout: never
+range:
+  - 1
+labels:
+  team:
+    - core
+hosts:
+  - port: 80x
+bogus: 1
+";
+        let errors = match load_with_schema(source, &schema()).unwrap_err() {
+            SchemaLoadError::Schema(errors) => errors,
+            e => panic!("unexpected error {}", e),
+        };
+        let messages: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+        assert_eq!(
+            messages,
+            [
+                "missing key \"name\" (line 1 column 1)",
+                "expected an integer, found \"many\" at replicas (line 1 column 11)",
+                "expected a decimal, found \"inf\" at ratio (line 2 column 8)",
+                "expected a boolean, found \"maybe\" at debug (line 3 column 8)",
+                "expected one of \"fast\", \"safe\", found \"slow\" at mode (line 4 column 7)",
+                "expected an integer or one of \"none\", found \"never\" at timeout (line 5 column 10)",
+                "expected a sequence of 2 items, found 1 item at range (line 7 column 3)",
+                "expected a string, found a sequence at labels.team (line 10 column 5)",
+                "missing key \"host\" at hosts[0] (line 12 column 5)",
+                "expected an integer, found \"80x\" at hosts[0].port (line 12 column 11)",
+                "unexpected key \"bogus\" at bogus (line 13 column 1)",
+            ]
+        );
+        assert_eq!(
+            errors[10].kind(),
+            &SchemaErrorKind::UnexpectedKey("bogus".to_owned())
+        );
+        assert_eq!(
+            errors[4].kind(),
+            &SchemaErrorKind::NotAllowed {
+                found: "slow".to_owned(),
+                allowed: vec!["fast".to_owned(), "safe".to_owned()],
+            }
+        );
+        assert_eq!(errors[4].code(), "E106_NOT_ALLOWED");
+        assert_eq!(errors[10].code(), "E103_UNEXPECTED_KEY");
+
+        // every document is checked
+        let source = "name: a\nreplicas: 1\n---\nname: b\nreplicas: x\n---\nreplicas: 2\n";
+        let err = load_with_schema(source, &schema()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "expected an integer, found \"x\" at replicas (line 5 column 11)\n\
+             missing key \"name\" (line 7 column 1)"
+        );
+
+        let err = load_with_schema("a: b: c", &Any).unwrap_err();
+        assert!(matches!(err, SchemaLoadError::Load(LoadError::Scan(_))));
+        assert_eq!(err.code(), "E002_SCAN");
+        assert!(err.source().is_some());
+    }
+
+    #[test]
+    fn test_map_pattern_entries() {
+        let schema = MapPattern::new(Str, Int).min_entries(1).max_entries(2);
+        let check = |source: &str| match load_with_schema(source, &schema) {
+            Ok(_) => String::new(),
+            Err(e) => e.to_string(),
+        };
+        assert_eq!(check("a: 1"), "");
+        assert_eq!(check("a: 1\nb: 2"), "");
+        assert_eq!(
+            check("a: 1\nb: 2\nc: x"),
+            "expected 1 to 2 entries, found 3 (line 1 column 1)\n\
+             expected an integer, found \"x\" at c (line 3 column 4)"
+        );
+        let doc = &StrictYamlLoader::load_from_str("a: 1").unwrap()[0];
+        let errors = validate(doc, &MapPattern::new(Str, Str).min_entries(2)).unwrap_err();
+        assert_eq!(
+            errors[0].to_string(),
+            "expected at least 2 entries, found 1"
+        );
+        let errors = validate(
+            doc,
+            &MapPattern::new(Str, Str).min_entries(3).max_entries(3),
+        );
+        assert_eq!(
+            errors.unwrap_err()[0].to_string(),
+            "expected 3 entries, found 1"
+        );
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn test_regex_keys() {
+        let host = Regex::new(r"[a-z0-9-]+(\.[a-z0-9-]+)*").unwrap();
+        let schema = MapPattern::new(host, Map::new().required("port", Int));
+        let source = "web.example.com:\n  port: 80\nBad_Host:\n  port: 81";
+        let err = load_with_schema(source, &schema).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "expected a match of \"[a-z0-9-]+(\\\\.[a-z0-9-]+)*\", found \"Bad_Host\" \
+             at Bad_Host (line 3 column 1)"
+        );
+        assert!(Regex::new("(").is_err());
+        let partial = Regex::new("a|b").unwrap();
+        let doc = &StrictYamlLoader::load_from_str("ab").unwrap()[0];
+        assert!(validate(doc, &partial).is_err());
+    }
+
+    #[test]
+    fn test_sequences() {
+        let check = |source: &str, schema: &dyn Validator| match load_with_schema(source, schema) {
+            Ok(_) => Vec::new(),
+            Err(e) => e.to_string().lines().map(str::to_owned).collect(),
+        };
+        let triple = FixedSeq::new().item(Str).item(Int).item(Bool);
+        assert!(check("- web\n- 80\n- yes", &triple).is_empty());
+        assert_eq!(
+            check("- web\n- http\n- yes\n- extra\n- more", &triple),
+            [
+                "expected an integer, found \"http\" at [1] (line 2 column 3)",
+                "unexpected item at [3] (line 4 column 3)",
+                "unexpected item at [4] (line 5 column 3)",
+            ]
+        );
+        assert_eq!(
+            check("- web", &triple),
+            ["expected a sequence of 3 items, found 1 item (line 1 column 1)"]
+        );
+        assert_eq!(
+            check("a: b", &triple),
+            ["expected a sequence of 3 items, found a mapping (line 1 column 1)"]
+        );
+
+        let unique = UniqueSeq::new(Int);
+        assert!(check("- 1\n- 2\n- 3", &unique).is_empty());
+        assert_eq!(
+            check("- 1\n- 2\n- 1\n- x\n- 2", &unique),
+            [
+                "duplicate of item 0 at [2] (line 3 column 3)",
+                "expected an integer, found \"x\" at [3] (line 4 column 3)",
+                "duplicate of item 1 at [4] (line 5 column 3)",
+            ]
+        );
+        let nested = UniqueSeq::new(Seq::new(Str));
+        assert_eq!(
+            check("- - a\n- - b\n- - a", &nested),
+            ["duplicate of item 0 at [2] (line 3 column 3)"]
+        );
+    }
+
+    #[test]
+    fn test_empty_values() {
+        let schema = Map::new()
+            .required("name", NonEmpty::new(Str))
+            .required("note", Str)
+            .required("port", Or::new(EmptyNone, Int))
+            .required("env", Or::new(EmptyDict, MapPattern::new(Str, Str)))
+            .required(
+                "hosts",
+                Or::new(EmptyList, Seq::new(Or::new(EmptyNone, Str))),
+            );
+        let source = "name: web\nnote:\nport:\nenv:\nhosts:\n";
+        let doc = &load_with_schema(source, &schema).unwrap()[0];
+        let expected = StrictYamlLoader::load_from_str("name: web\nnote: ''").unwrap();
+        let mut expected = expected[0].as_hash().unwrap().clone();
+        expected.insert(StrictYaml::from_str("env"), StrictYaml::Hash(Hash::new()));
+        expected.insert(StrictYaml::from_str("hosts"), StrictYaml::Array(Vec::new()));
+        assert_eq!(doc, &StrictYaml::Hash(expected));
+        assert!(doc["port"].is_badvalue());
+
+        let source = "name: web\nnote: n\nport: 80\nenv:\n  A: b\nhosts:\n  - a\n  -\n";
+        let doc = &load_with_schema(source, &schema).unwrap()[0];
+        assert_eq!(doc["port"].as_str(), Some("80"));
+        assert_eq!(doc["env"]["A"].as_str(), Some("b"));
+        assert_eq!(
+            doc["hosts"],
+            StrictYaml::Array(vec![StrictYaml::from_str("a"), StrictYaml::BadValue])
+        );
+
+        let err = load_with_schema("name:\nnote:\nport: x\nenv:\nhosts:", &schema).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "expected a string, not empty, found \"\" at name (line 1 column 1)\n\
+             expected an empty value or an integer, found \"x\" at port (line 3 column 7)"
+        );
+    }
+
+    #[test]
+    fn test_comma_separated() {
+        let schema = Map::new()
+            .required("ports", CommaSeparated::new(Int))
+            .optional("tags", CommaSeparated::new(Str));
+        let doc = &load_with_schema("ports: 80, 443 ,8080\ntags: ''", &schema).unwrap()[0];
+        let ports: Vec<&str> = doc["ports"]
+            .as_vec()
+            .unwrap()
+            .iter()
+            .filter_map(|p| p.as_str())
+            .collect();
+        assert_eq!(ports, ["80", "443", "8080"]);
+        assert_eq!(doc["tags"], StrictYaml::Array(Vec::new()));
+
+        let err = load_with_schema("ports: 80, http,\ntags:\n  - a", &schema).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "expected an integer, found \"http\" at ports[1] (line 1 column 8)\n\
+             expected an integer, found \"\" at ports[2] (line 1 column 8)\n\
+             expected a comma-separated list of a string, found a sequence at tags (line 3 column 3)"
+        );
+    }
+
+    #[test]
+    fn test_values() {
+        assert_eq!(Int.parse("-42"), Some(-42));
+        assert_eq!(Int.parse("4.2"), None);
+        assert_eq!(Decimal.parse("2.5e3"), Some(2500.0));
+        assert_eq!(Decimal.parse("NaN"), None);
+        assert_eq!(Bool.parse("YES"), Some(true));
+        assert_eq!(Bool.parse("Off"), Some(false));
+        assert_eq!(Bool.parse("1"), None);
+        assert_eq!(Str.parse(""), Some(String::new()));
+        let levels = Enum::new(&["debug", "info"]);
+        assert_eq!(levels.parse("info"), Some("info".to_owned()));
+        assert_eq!(levels.parse("Info"), None);
+        assert_eq!(levels.values(), ["debug", "info"]);
+
+        let date = Datetime.parse("2024-02-29").unwrap();
+        assert_eq!((date.year, date.month, date.day), (2024, 2, 29));
+        assert_eq!(date.unix_seconds(), 1_709_164_800);
+        let stamp = Datetime.parse("2001-12-14t21:59:43.10-05:00").unwrap();
+        assert_eq!((stamp.hour, stamp.minute, stamp.second), (21, 59, 43));
+        assert_eq!(stamp.nanosecond, 100_000_000);
+        assert_eq!(stamp.offset, Some(-300));
+        assert_eq!(stamp.unix_seconds(), 1_008_385_183);
+        assert_eq!(
+            Datetime.parse("1969-12-31 23:59Z").unwrap().unix_seconds(),
+            -60
+        );
+        assert_eq!(
+            Datetime.parse("2024-01-01T00:00:00+0130").unwrap().offset,
+            Some(90)
+        );
+        for bad in [
+            "2023-02-29",
+            "2024-13-01",
+            "24-01-01",
+            "2024-1-01",
+            "2024-01-01T",
+            "2024-01-01T24:00",
+            "2024-01-01T12:00:00.",
+            "2024-01-01T12:00+1",
+            "2024-01-01T12:00 UTC",
+            "tomorrow",
+        ] {
+            assert_eq!(Datetime.parse(bad), None, "{}", bad);
+        }
+
+        let doc = &StrictYamlLoader::load_from_str("at: 2024-06-01\nn: x").unwrap()[0];
+        assert_eq!(Datetime.value(&doc["at"]).map(|t| t.day), Some(1));
+        assert_eq!(Datetime.value(&doc["n"]), None);
+        let errors = validate(doc, &MapPattern::new(Str, Datetime)).unwrap_err();
+        assert_eq!(
+            errors[0].to_string(),
+            "expected a datetime, found \"x\" at n"
+        );
+    }
+
+    #[test]
+    fn test_validate() {
+        let doc = &StrictYamlLoader::load_from_str("- 1\n- x").unwrap()[0];
+        let errors = validate(doc, &Seq::new(Int)).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].marker(), None);
+        assert_eq!(
+            errors[0].to_string(),
+            "expected an integer, found \"x\" at [1]"
+        );
+        assert_eq!(validate(doc, &Seq::new(Str)).as_ref(), Ok(doc));
+        let errors = validate(&StrictYaml::BadValue, &Str).unwrap_err();
+        assert_eq!(errors[0].to_string(), "expected a string, found nothing");
+    }
+
+    #[test]
+    fn test_schema_macro() {
+        let schema = schema! {
+            map {
+                "name" => str,
+                optional "port" => int(1..65536),
+                optional "debug" => bool(default = false),
+                optional "hosts" => seq(map { "host" => str, optional "weight" => decimal }),
+                optional "extra" => any
+            }
+        };
+        let doc = &StrictYamlLoader::load_from_str("name: a\nhosts:\n  - host: b").unwrap()[0];
+        let view = validate(doc, &schema).unwrap();
+        assert_eq!(view["debug"].as_str(), Some("false"));
+        assert_eq!(view["hosts"][0]["host"].as_str(), Some("b"));
+        assert!(view["port"].is_badvalue());
+        let doc = &StrictYamlLoader::load_from_str("name: a\ndebug: on").unwrap()[0];
+        assert_eq!(
+            validate(doc, &schema).unwrap()["debug"].as_str(),
+            Some("on")
+        );
+
+        let source = "port: 65536\nhosts:\n  - weight: x";
+        let errors: Vec<String> = match load_with_schema(source, &schema).unwrap_err() {
+            SchemaLoadError::Schema(errors) => errors.iter().map(|e| e.to_string()).collect(),
+            e => panic!("unexpected error {}", e),
+        };
+        assert_eq!(
+            errors,
+            [
+                "missing key \"name\" (line 1 column 1)",
+                "expected an integer from 1 to 65535, found \"65536\" at port (line 1 column 7)",
+                "missing key \"host\" at hosts[0] (line 3 column 5)",
+                "expected a decimal, found \"x\" at hosts[0].weight (line 3 column 13)",
+            ]
+        );
+    }
+}
@@ -0,0 +1,194 @@
+//! Encrypted values, in the style of sops: a secret is stored as its
+//! ciphertext wrapped in an envelope such as `ENC[...]`, and the rest of the
+//! document stays readable.
+//!
+//! The cryptography is left to the caller. `Decrypt` is a filter calling a
+//! decryption function with the values found in an envelope while loading, and
+//! `StrictYamlEmitter::encrypt` calls an encryption function with the values to
+//! protect while emitting.
+//!
+//! ```
+//! use strict_yaml_rust::secret::{Decrypt, Envelope};
+//! use strict_yaml_rust::{StrictYamlEmitter, StrictYamlLoader};
+//!
+//! // stand-ins for actual encryption
+//! let encrypt = |_: &_, v: &str| v.chars().rev().collect::<String>();
+//! let decrypt = |_: &_, v: &str| Ok(v.chars().rev().collect::<String>());
+//!
+//! let doc = &StrictYamlLoader::load_from_str("user: bob\npassword: hunter2").unwrap()[0];
+//! let mut out = String::new();
+//! let mut emitter = StrictYamlEmitter::new(&mut out);
+//! emitter.encrypt("password".parse().unwrap(), Envelope::default(), encrypt);
+//! emitter.dump(doc).unwrap();
+//! assert_eq!(out, "---\nuser: bob\npassword: \"ENC[2retnuh]\"");
+//!
+//! let docs = StrictYamlLoader::load_from_str_with_filter(&out, Decrypt::new(Envelope::default(), decrypt))
+//!     .unwrap();
+//! assert_eq!(docs[0]["password"].as_str(), Some("hunter2"));
+//! ```
+
+use filter::{EventFilter, PathTracker};
+use parser::{Event, MarkedEventReceiver};
+use path::YamlPath;
+use scanner::{Marker, ScanError};
+
+/// The text marking a value as encrypted, around its ciphertext.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Envelope {
+    prefix: String,
+    suffix: String,
+}
+
+impl Envelope {
+    pub fn new(prefix: &str, suffix: &str) -> Envelope {
+        Envelope {
+            prefix: prefix.to_owned(),
+            suffix: suffix.to_owned(),
+        }
+    }
+
+    /// Returns the ciphertext of `value` if it is in the envelope.
+    pub fn open<'v>(&self, value: &'v str) -> Option<&'v str> {
+        value
+            .strip_prefix(self.prefix.as_str())
+            .and_then(|rest| rest.strip_suffix(self.suffix.as_str()))
+    }
+
+    /// Wraps `ciphertext` in the envelope.
+    pub fn seal(&self, ciphertext: &str) -> String {
+        format!("{}{}{}", self.prefix, ciphertext, self.suffix)
+    }
+}
+
+impl Default for Envelope {
+    /// `ENC[...]`.
+    fn default() -> Envelope {
+        Envelope::new("ENC[", "]")
+    }
+}
+
+/// Replaces the values in an envelope with what a function of their path and
+/// ciphertext returns. An error of the function stops loading.
+pub struct Decrypt<F> {
+    envelope: Envelope,
+    f: F,
+    tracker: PathTracker,
+}
+
+impl<F: FnMut(&YamlPath, &str) -> Result<String, String>> Decrypt<F> {
+    pub fn new(envelope: Envelope, f: F) -> Decrypt<F> {
+        Decrypt {
+            envelope,
+            f,
+            tracker: PathTracker::new(),
+        }
+    }
+}
+
+impl<F: FnMut(&YamlPath, &str) -> Result<String, String>> EventFilter for Decrypt<F> {
+    fn filter(
+        &mut self,
+        ev: Event,
+        mark: Marker,
+        out: &mut dyn MarkedEventReceiver,
+    ) -> Result<(), ScanError> {
+        self.tracker.update(&ev);
+        let ev = match ev {
+            Event::Scalar(value, style, aid) if !self.tracker.is_key() => {
+                let value = match self.envelope.open(&value) {
+                    Some(ciphertext) => match (self.f)(self.tracker.path(), ciphertext) {
+                        Ok(plain) => plain,
+                        Err(why) => {
+                            let info = format!(
+                                "cannot decrypt the value of {}: {}",
+                                self.tracker.path(),
+                                why
+                            );
+                            return Err(ScanError::decrypt(mark, &info));
+                        }
+                    },
+                    None => value,
+                };
+                Event::Scalar(value, style, aid)
+            }
+            ev => ev,
+        };
+        out.on_event(ev, mark)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use emitter::{ScalarStyle, StrictYamlEmitter};
+    use error::LoadError;
+    use strict_yaml::StrictYamlLoader;
+
+    #[test]
+    fn test_envelope() {
+        let envelope = Envelope::new("<<", ">>");
+        assert_eq!(envelope.open("<<abc>>"), Some("abc"));
+        assert_eq!(envelope.open("<<>>"), Some(""));
+        assert_eq!(envelope.open("<<abc"), None);
+        assert_eq!(envelope.open("<>"), None);
+        assert_eq!(envelope.seal("abc"), "<<abc>>");
+        assert_eq!(Envelope::default().seal("x"), "ENC[x]");
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let source = "db:\n  user: bob\n  password: hunter2\nkeys:\n  - k1\n  - ENC[already]\n";
+        let doc = &StrictYamlLoader::load_from_str(source).unwrap()[0];
+        let mut out = String::new();
+        let mut emitter = StrictYamlEmitter::new(&mut out);
+        emitter.encrypt(
+            "db.password".parse().unwrap(),
+            Envelope::default(),
+            |path, v| format!("{}:{}", path, v.to_uppercase()),
+        );
+        emitter.encrypt("keys[*]".parse().unwrap(), Envelope::default(), |_, v| {
+            v.to_uppercase()
+        });
+        emitter.dump(doc).unwrap();
+        assert_eq!(
+            out,
+            "---\ndb:\n  user: bob\n  password: \"ENC[db.password:HUNTER2]\"\nkeys:\n  - \"ENC[K1]\"\n  - \"ENC[already]\""
+        );
+
+        let decrypt = Decrypt::new(Envelope::default(), |path: &YamlPath, v: &str| {
+            let prefix = format!("{}:", path);
+            Ok(v.trim_start_matches(prefix.as_str()).to_lowercase())
+        });
+        let docs = StrictYamlLoader::load_from_str_with_filter(&out, decrypt).unwrap();
+        assert_eq!(docs[0]["db"]["password"].as_str(), Some("hunter2"));
+        assert_eq!(docs[0]["db"]["user"].as_str(), Some("bob"));
+        assert_eq!(docs[0]["keys"][0].as_str(), Some("k1"));
+
+        let failing = Decrypt::new(Envelope::default(), |_: &YamlPath, _: &str| {
+            Err("bad key".to_owned())
+        });
+        let err = StrictYamlLoader::load_from_str_with_filter(&out, failing).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "cannot decrypt the value of db.password: bad key at line 4 column 13"
+        );
+        assert!(matches!(LoadError::from(err), LoadError::Decrypt(_)));
+    }
+
+    #[test]
+    fn test_overlapping_patterns() {
+        let doc = &StrictYamlLoader::load_from_str("a: x\nb: y\nc: z").unwrap()[0];
+        let mut out = String::new();
+        let mut emitter = StrictYamlEmitter::new(&mut out);
+        emitter.encrypt("**".parse().unwrap(), Envelope::default(), |_, v| {
+            v.to_uppercase()
+        });
+        emitter.encrypt("b".parse().unwrap(), Envelope::new("<", ">"), |_, v| {
+            v.to_owned()
+        });
+        emitter.style("c".parse().unwrap(), ScalarStyle::Literal);
+        emitter.style("**".parse().unwrap(), ScalarStyle::Quoted);
+        emitter.dump(doc).unwrap();
+        assert_eq!(out, "---\na: \"ENC[X]\"\nb: \"<y>\"\nc: \"ENC[Z]\"");
+    }
+}
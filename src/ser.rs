@@ -0,0 +1,487 @@
+//! Serializing Rust values to strict YAML with serde.
+//!
+//! Values are first built as a `StrictYaml` tree, then written by
+//! `StrictYamlEmitter`. Numbers and booleans become plain strings, and `None`
+//! fields are left out of their mapping.
+//!
+//! ```
+//! # extern crate serde;
+//! use std::collections::BTreeMap;
+//! use strict_yaml_rust::ser::to_string;
+//!
+//! let mut ports = BTreeMap::new();
+//! ports.insert("http", 80);
+//! ports.insert("https", 443);
+//! assert_eq!(to_string(&ports).unwrap(), "---\nhttp: \"80\"\nhttps: \"443\"");
+//! ```
+
+use emitter::{EmitError, StrictYamlEmitter};
+use error::code;
+use serde::ser::{self, Serialize};
+use std::error;
+use std::fmt;
+use strict_yaml::{Hash, StrictYaml};
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Error(String);
+
+impl Error {
+    pub fn code(&self) -> &'static str {
+        code::SERIALIZE
+    }
+}
+
+impl error::Error for Error {}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl ser::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Error {
+        Error(msg.to_string())
+    }
+}
+
+impl From<EmitError> for Error {
+    fn from(e: EmitError) -> Error {
+        Error(e.to_string())
+    }
+}
+
+/// Serializes `value` as a strict YAML document.
+pub fn to_string<T: Serialize + ?Sized>(value: &T) -> Result<String, Error> {
+    let doc = to_strict_yaml(value)?;
+    let mut out = String::new();
+    StrictYamlEmitter::new(&mut out).dump(&doc)?;
+    Ok(out)
+}
+
+/// Serializes `value` as a `StrictYaml` tree.
+pub fn to_strict_yaml<T: Serialize + ?Sized>(value: &T) -> Result<StrictYaml, Error> {
+    match value.serialize(Serializer)? {
+        StrictYaml::BadValue => Err(Error("cannot serialize None outside of a mapping".into())),
+        doc => Ok(doc),
+    }
+}
+
+/// Builds a `StrictYaml` tree of a value. `None` gives `BadValue`, which
+/// mappings leave out.
+pub struct Serializer;
+
+fn scalar<T: ToString>(v: T) -> Result<StrictYaml, Error> {
+    Ok(StrictYaml::String(v.to_string()))
+}
+
+fn tagged(variant: &str, value: StrictYaml) -> StrictYaml {
+    let mut h = Hash::new();
+    h.insert(StrictYaml::String(variant.to_owned()), value);
+    StrictYaml::Hash(h)
+}
+
+impl ser::Serializer for Serializer {
+    type Ok = StrictYaml;
+    type Error = Error;
+
+    type SerializeSeq = SerializeSeq;
+    type SerializeTuple = SerializeSeq;
+    type SerializeTupleStruct = SerializeSeq;
+    type SerializeTupleVariant = SerializeSeq;
+    type SerializeMap = SerializeMap;
+    type SerializeStruct = SerializeMap;
+    type SerializeStructVariant = SerializeMap;
+
+    fn serialize_bool(self, v: bool) -> Result<StrictYaml, Error> {
+        scalar(v)
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<StrictYaml, Error> {
+        scalar(v)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<StrictYaml, Error> {
+        scalar(v)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<StrictYaml, Error> {
+        scalar(v)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<StrictYaml, Error> {
+        scalar(v)
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<StrictYaml, Error> {
+        scalar(v)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<StrictYaml, Error> {
+        scalar(v)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<StrictYaml, Error> {
+        scalar(v)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<StrictYaml, Error> {
+        scalar(v)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<StrictYaml, Error> {
+        scalar(v)
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<StrictYaml, Error> {
+        scalar(v)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<StrictYaml, Error> {
+        scalar(v)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<StrictYaml, Error> {
+        scalar(v)
+    }
+
+    fn serialize_char(self, v: char) -> Result<StrictYaml, Error> {
+        scalar(v)
+    }
+
+    fn serialize_str(self, v: &str) -> Result<StrictYaml, Error> {
+        scalar(v)
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<StrictYaml, Error> {
+        let bytes = v.iter().map(|b| StrictYaml::String(b.to_string()));
+        Ok(StrictYaml::Array(bytes.collect()))
+    }
+
+    fn serialize_none(self) -> Result<StrictYaml, Error> {
+        Ok(StrictYaml::BadValue)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<StrictYaml, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<StrictYaml, Error> {
+        scalar("")
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<StrictYaml, Error> {
+        scalar("")
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<StrictYaml, Error> {
+        scalar(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<StrictYaml, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<StrictYaml, Error> {
+        Ok(tagged(variant, to_strict_yaml(value)?))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SerializeSeq, Error> {
+        Ok(SerializeSeq {
+            variant: None,
+            items: Vec::with_capacity(len.unwrap_or(0)),
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SerializeSeq, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SerializeSeq, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeSeq, Error> {
+        Ok(SerializeSeq {
+            variant: Some(variant),
+            items: Vec::with_capacity(len),
+        })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<SerializeMap, Error> {
+        Ok(SerializeMap {
+            variant: None,
+            hash: Hash::new(),
+            key: None,
+        })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<SerializeMap, Error> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<SerializeMap, Error> {
+        Ok(SerializeMap {
+            variant: Some(variant),
+            hash: Hash::new(),
+            key: None,
+        })
+    }
+}
+
+pub struct SerializeSeq {
+    variant: Option<&'static str>,
+    items: Vec<StrictYaml>,
+}
+
+impl SerializeSeq {
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        match value.serialize(Serializer)? {
+            StrictYaml::BadValue => Err(Error("cannot serialize None in a sequence".into())),
+            item => {
+                self.items.push(item);
+                Ok(())
+            }
+        }
+    }
+
+    fn end(self) -> Result<StrictYaml, Error> {
+        let seq = StrictYaml::Array(self.items);
+        Ok(match self.variant {
+            Some(variant) => tagged(variant, seq),
+            None => seq,
+        })
+    }
+}
+
+impl ser::SerializeSeq for SerializeSeq {
+    type Ok = StrictYaml;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<StrictYaml, Error> {
+        SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTuple for SerializeSeq {
+    type Ok = StrictYaml;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<StrictYaml, Error> {
+        SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for SerializeSeq {
+    type Ok = StrictYaml;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<StrictYaml, Error> {
+        SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleVariant for SerializeSeq {
+    type Ok = StrictYaml;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<StrictYaml, Error> {
+        SerializeSeq::end(self)
+    }
+}
+
+pub struct SerializeMap {
+    variant: Option<&'static str>,
+    hash: Hash,
+    /// The key waiting for its value.
+    key: Option<StrictYaml>,
+}
+
+impl SerializeMap {
+    fn insert(&mut self, key: StrictYaml, value: StrictYaml) {
+        if value != StrictYaml::BadValue {
+            self.hash.insert(key, value);
+        }
+    }
+
+    fn end(self) -> Result<StrictYaml, Error> {
+        let map = StrictYaml::Hash(self.hash);
+        Ok(match self.variant {
+            Some(variant) => tagged(variant, map),
+            None => map,
+        })
+    }
+}
+
+impl ser::SerializeMap for SerializeMap {
+    type Ok = StrictYaml;
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+        match key.serialize(Serializer)? {
+            key @ StrictYaml::String(_) => {
+                self.key = Some(key);
+                Ok(())
+            }
+            _ => Err(Error("mapping keys must be scalars".into())),
+        }
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        let key = self
+            .key
+            .take()
+            .expect("serialize_value called before serialize_key");
+        let value = value.serialize(Serializer)?;
+        self.insert(key, value);
+        Ok(())
+    }
+
+    fn end(self) -> Result<StrictYaml, Error> {
+        SerializeMap::end(self)
+    }
+}
+
+impl ser::SerializeStruct for SerializeMap {
+    type Ok = StrictYaml;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        let value = value.serialize(Serializer)?;
+        self.insert(StrictYaml::String(key.to_owned()), value);
+        Ok(())
+    }
+
+    fn end(self) -> Result<StrictYaml, Error> {
+        SerializeMap::end(self)
+    }
+}
+
+impl ser::SerializeStructVariant for SerializeMap {
+    type Ok = StrictYaml;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        ser::SerializeStruct::serialize_field(self, key, value)
+    }
+
+    fn end(self) -> Result<StrictYaml, Error> {
+        SerializeMap::end(self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde::Serialize;
+    use std::collections::BTreeMap;
+
+    #[derive(Serialize)]
+    enum Shape {
+        Point,
+        Circle(f64),
+        Rect { w: u32, h: u32 },
+    }
+
+    #[derive(Serialize)]
+    struct Drawing {
+        title: String,
+        visible: bool,
+        note: Option<String>,
+        shapes: Vec<Shape>,
+        layers: BTreeMap<u8, Vec<u8>>,
+    }
+
+    #[test]
+    fn test_to_string() {
+        let mut layers = BTreeMap::new();
+        layers.insert(1, vec![]);
+        let drawing = Drawing {
+            title: "a: b".to_owned(),
+            visible: true,
+            note: None,
+            shapes: vec![Shape::Point, Shape::Circle(1.5), Shape::Rect { w: 2, h: 3 }],
+            layers,
+        };
+        assert_eq!(
+            to_string(&drawing).unwrap(),
+            "---
+title: \"a: b\"
+visible: \"true\"
+shapes:
+  - Point
+  - Circle: \"1.5\"
+  - Rect:
+      w: \"2\"
+      h: \"3\"
+layers:
+  \"1\": []"
+        );
+
+        assert_eq!(
+            to_string(&vec![Some(1), None]).unwrap_err().to_string(),
+            "cannot serialize None in a sequence"
+        );
+        let mut complex = BTreeMap::new();
+        complex.insert(vec![1], 2);
+        assert_eq!(
+            to_string(&complex).unwrap_err().to_string(),
+            "mapping keys must be scalars"
+        );
+    }
+}
@@ -0,0 +1,209 @@
+//! Constant-memory, SAX-style processing of strict YAML streams.
+//!
+//! Loading a document with `StrictYamlLoader` materializes the whole tree. When
+//! only a handful of values are needed from a very large input, the event API can
+//! be used instead:
+//!
+//! * `skim` walks every document and reports each scalar together with its
+//!   `YamlPath`, stopping as soon as the callback asks it to;
+//! * `Parser::skip_node` discards a whole subtree without building it;
+//! * `StrictYamlLoader::load_node` materializes only the next subtree.
+//!
+//! ```
+//! use strict_yaml_rust::skim::skim;
+//!
+//! let source = "name: demo\nitems:\n  - a\n  - b\nversion: 3\n";
+//! let mut version = None;
+//! skim(source, |path, value, _| {
+//!     if path.to_string() == "version" {
+//!         version = Some(value.to_owned());
+//!         return false; // stop here, the rest of the input is never parsed
+//!     }
+//!     true
+//! }).unwrap();
+//! assert_eq!(version, Some("3".to_owned()));
+//! ```
+
+use parser::{Event, Parser};
+use path::{PathSegment, YamlPath};
+use scanner::{Marker, ScanError};
+
+enum Frame {
+    /// Index of the next item.
+    Sequence(usize),
+    /// Whether the key of the current entry has been seen.
+    Mapping(bool),
+}
+
+/// Reports every scalar of every document of `source` to `f`, with the path of
+/// the scalar within its document and its marker.
+///
+/// Mapping keys are not reported themselves, they become part of the path of
+/// their value. Entries with a complex (collection) key are skipped. The walk
+/// stops early when `f` returns `false`. Only the current path is kept in
+/// memory, never the document itself.
+pub fn skim<F>(source: &str, mut f: F) -> Result<(), ScanError>
+where
+    F: FnMut(&YamlPath, &str, Marker) -> bool,
+{
+    let mut parser = Parser::new(source.chars());
+    let mut path = YamlPath::new();
+    let mut frames: Vec<Frame> = Vec::new();
+
+    loop {
+        // entering a node: extend the path or handle a mapping key
+        match frames.last_mut() {
+            Some(&mut Frame::Sequence(index)) if parser.peek()?.0 != Event::SequenceEnd => {
+                path.push(PathSegment::Index(index));
+            }
+            Some(&mut Frame::Mapping(ref mut has_key)) if !*has_key => {
+                match parser.peek()?.0 {
+                    Event::Scalar(..) => {
+                        if let (Event::Scalar(key, ..), _) = parser.next()? {
+                            path.push(PathSegment::Key(key));
+                        }
+                        *has_key = true;
+                    }
+                    Event::SequenceStart(_) | Event::MappingStart(_) => {
+                        // complex key: skip the whole entry
+                        parser.skip_node()?;
+                        parser.skip_node()?;
+                        continue;
+                    }
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+
+        let (ev, mark) = parser.next()?;
+        match ev {
+            Event::StreamEnd => return Ok(()),
+            Event::Scalar(ref value, ..) => {
+                if !f(&path, value, mark) {
+                    return Ok(());
+                }
+                leave_node(&mut frames, &mut path);
+            }
+            Event::SequenceStart(_) => frames.push(Frame::Sequence(0)),
+            Event::MappingStart(_) => frames.push(Frame::Mapping(false)),
+            Event::SequenceEnd | Event::MappingEnd => {
+                frames.pop();
+                leave_node(&mut frames, &mut path);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Moves past a completed node: drops its path segment and advances its parent.
+fn leave_node(frames: &mut [Frame], path: &mut YamlPath) {
+    match frames.last_mut() {
+        Some(&mut Frame::Sequence(ref mut index)) => {
+            *index += 1;
+            path.pop();
+        }
+        Some(&mut Frame::Mapping(ref mut has_key)) => {
+            *has_key = false;
+            path.pop();
+        }
+        None => {}
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn collect(source: &str) -> Vec<(String, String)> {
+        let mut found = Vec::new();
+        skim(source, |path, value, _| {
+            found.push((path.to_string(), value.to_owned()));
+            true
+        })
+        .unwrap();
+        found
+    }
+
+    #[test]
+    fn test_skim_paths() {
+        let s = "
+a: 1
+b:
+  - x
+  - c: 2
+    d: [3]
+  - - y
+? - complex
+: skipped
+e: 4
+";
+        let pairs: Vec<(&str, &str)> = vec![
+            ("a", "1"),
+            ("b[0]", "x"),
+            ("b[1].c", "2"),
+            ("b[1].d", "[3]"),
+            ("b[2][0]", "y"),
+            ("e", "4"),
+        ];
+        let expected: Vec<(String, String)> = pairs
+            .into_iter()
+            .map(|(p, v)| (p.to_owned(), v.to_owned()))
+            .collect();
+        assert_eq!(collect(s), expected);
+    }
+
+    #[test]
+    fn test_skim_multi_doc_and_stop() {
+        assert_eq!(
+            collect("--- a\n---\nb: c"),
+            vec![
+                ("".to_owned(), "a".to_owned()),
+                ("b".to_owned(), "c".to_owned())
+            ]
+        );
+
+        // the error in the second document is never reached
+        let mut count = 0;
+        skim("---\na: 1\n---\nb: c: d", |_, _, _| {
+            count += 1;
+            false
+        })
+        .unwrap();
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn test_skip_and_load_node() {
+        use strict_yaml::StrictYamlLoader;
+
+        let mut parser = Parser::new("big: [1]\nsmall:\n  x: y\n".chars());
+        parser.next().unwrap(); // StreamStart
+        parser.next().unwrap(); // DocumentStart
+        parser.next().unwrap(); // MappingStart
+        parser.skip_node().unwrap(); // key `big`
+        parser.skip_node().unwrap(); // its value
+        parser.skip_node().unwrap(); // key `small`
+        let node = StrictYamlLoader::load_node(&mut parser).unwrap();
+        assert_eq!(node["x"].as_str(), Some("y"));
+        assert_eq!(parser.next().unwrap().0, Event::MappingEnd);
+    }
+
+    #[test]
+    fn test_load_node_at_end() {
+        use strict_yaml::StrictYamlLoader;
+
+        let mut parser = Parser::new("- a".chars());
+        parser.next().unwrap(); // StreamStart
+        parser.next().unwrap(); // DocumentStart
+        parser.next().unwrap(); // SequenceStart
+        parser.skip_node().unwrap(); // `a`
+        let err = StrictYamlLoader::load_node(&mut parser).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("expected a node, found SequenceEnd"),
+            "{}",
+            err
+        );
+    }
+}
@@ -0,0 +1,189 @@
+//! Where each node of a loaded document is in the source, by path, for
+//! reporting problems found after loading.
+//!
+//! ```
+//! use strict_yaml_rust::path::{PathSegment, YamlPath};
+//! use strict_yaml_rust::StrictYamlLoader;
+//!
+//! let source = "name: demo\nports:\n  - 80\n  - http\n";
+//! let (docs, map) = StrictYamlLoader::load_from_str_with_markers(source).unwrap();
+//! let path = YamlPath::from(vec![PathSegment::Key("ports".to_owned()), PathSegment::Index(1)]);
+//! assert_eq!(docs[0]["ports"][1].as_str(), Some("http"));
+//! let span = map.span(0, &path).unwrap();
+//! assert_eq!((span.start().line(), span.start().col() + 1), (4, 5));
+//! ```
+
+use filter::PathTracker;
+use parser::{Event, MarkedEventReceiver, Parser, SpannedEventReceiver};
+use path::YamlPath;
+use scanner::{Marker, ScanError, Span, TScalarStyle};
+use std::collections::HashMap;
+use strict_yaml::{StrictYaml, StrictYamlLoader};
+
+/// The spans of the nodes of loaded documents.
+///
+/// The span of a scalar covers its text, including quotes; the empty value of
+/// `key:` has an empty span at its key. The span of a sequence or mapping
+/// goes from its first `-` or key to the end of its last scalar.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct SourceMap {
+    docs: Vec<HashMap<YamlPath, Span>>,
+}
+
+impl SourceMap {
+    /// The span of the node at `path` in the document at index `doc`.
+    pub fn span(&self, doc: usize, path: &YamlPath) -> Option<Span> {
+        self.docs
+            .get(doc)
+            .and_then(|spans| spans.get(path))
+            .copied()
+    }
+
+    /// Number of documents mapped.
+    pub fn len(&self) -> usize {
+        self.docs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.docs.is_empty()
+    }
+}
+
+/// Loads all the documents of `source` with their source map.
+pub(crate) fn load(source: &str) -> Result<(Vec<StrictYaml>, SourceMap), ScanError> {
+    let mut recv = SourceMapReceiver {
+        loader: StrictYamlLoader::new(),
+        tracker: PathTracker::new(),
+        open: Vec::new(),
+        key: None,
+        map: SourceMap::default(),
+    };
+    Parser::new(source.chars()).load_spanned(&mut recv, true)?;
+    Ok((recv.loader.take_documents(), recv.map))
+}
+
+/// A collection being loaded, with the span of its content so far.
+struct Open {
+    start: Option<Marker>,
+    end: Marker,
+}
+
+impl Open {
+    fn extend(&mut self, span: Span) {
+        self.start.get_or_insert(span.start());
+        if span.end().index() > self.end.index() {
+            self.end = span.end();
+        }
+    }
+}
+
+struct SourceMapReceiver {
+    loader: StrictYamlLoader,
+    tracker: PathTracker,
+    open: Vec<Open>,
+    /// The span of the key just before, for an empty value.
+    key: Option<Span>,
+    map: SourceMap,
+}
+
+impl SourceMapReceiver {
+    fn record(&mut self, span: Span) {
+        if let Some(parent) = self.open.last_mut() {
+            parent.extend(span);
+        }
+        if let Some(spans) = self.map.docs.last_mut() {
+            spans.insert(self.tracker.path().clone(), span);
+        }
+    }
+}
+
+impl SpannedEventReceiver for SourceMapReceiver {
+    fn on_spanned_event(&mut self, ev: Event, span: Span) -> Result<(), ScanError> {
+        self.tracker.update(&ev);
+        let key = self.key.take();
+        match ev {
+            Event::DocumentStart => self.map.docs.push(HashMap::new()),
+            Event::SequenceStart(_) => self.open.push(Open {
+                // marked at its first `-`
+                start: Some(span.start()),
+                end: span.end(),
+            }),
+            // marked past its first key, which starts it
+            Event::MappingStart(_) => self.open.push(Open {
+                start: None,
+                end: span.end(),
+            }),
+            Event::SequenceEnd | Event::MappingEnd => {
+                let open = self.open.pop().unwrap();
+                let span = match open.start {
+                    Some(start) => Span::new(start, open.end),
+                    None => Span::empty(span.start()),
+                };
+                self.record(span);
+            }
+            Event::Scalar(..) if self.tracker.is_key() => {
+                self.key = Some(span);
+                if let Some(parent) = self.open.last_mut() {
+                    parent.extend(span);
+                }
+            }
+            Event::Scalar(ref v, style, _) => {
+                let span = match key {
+                    Some(key) if v.is_empty() && style == TScalarStyle::Plain => {
+                        Span::empty(key.start())
+                    }
+                    _ => span,
+                };
+                self.record(span);
+            }
+            _ => {}
+        }
+        self.loader.on_event(ev, span.start())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use path::PathSegment;
+
+    /// The path written `s`, such as `a[1].b`.
+    fn path(s: &str) -> YamlPath {
+        let mut path = YamlPath::new();
+        for part in s.split('.').filter(|p| !p.is_empty()) {
+            let mut pieces = part.split('[');
+            path.push(PathSegment::Key(pieces.next().unwrap().to_owned()));
+            for index in pieces {
+                path.push(PathSegment::Index(
+                    index.trim_end_matches(']').parse().unwrap(),
+                ));
+            }
+        }
+        path
+    }
+
+    #[test]
+    fn test_spans() {
+        let source = "a: 'x'\nb:\n  - c: d\n    e:\n  - f\ng: |\n  h\n\n---\ni\n";
+        let (docs, map) = load(source).unwrap();
+        assert_eq!(docs.len(), 2);
+        assert_eq!(map.len(), 2);
+        let text = |doc: usize, p: &str| {
+            let span = map.span(doc, &path(p)).unwrap();
+            source[span.start().index()..span.end().index()].to_owned()
+        };
+        assert_eq!(text(0, "a"), "'x'");
+        assert_eq!(text(0, "b[0].c"), "d");
+        assert_eq!(text(0, "b[0]"), "c: d\n    e");
+        assert_eq!(text(0, "b"), "- c: d\n    e:\n  - f");
+        assert_eq!(text(0, "g"), "h");
+        assert_eq!(text(0, ""), &source[..source.find("\n\n").unwrap()]);
+        assert_eq!(text(1, ""), "i");
+
+        let e = map.span(0, &path("b[0].e")).unwrap();
+        assert!(e.is_empty());
+        assert_eq!((e.start().line(), e.start().col()), (4, 4));
+        assert!(map.span(0, &path("z")).is_none());
+        assert!(map.span(2, &path("")).is_none());
+    }
+}
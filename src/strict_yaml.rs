@@ -1,13 +1,37 @@
-use linked_hash_map::LinkedHashMap;
+#[cfg(feature = "futures-io")]
+use async_read::LoadFromAsyncReader;
+use canonical::{self, CanonicalOptions};
+use cst::{describe, EditError};
+use emitter::{ScalarStyle, StrictYamlEmitter};
+use error::{code, LoadError};
+use filter::{EventFilter, Pipeline};
+#[cfg(feature = "futures-io")]
+use futures_io::AsyncRead;
+#[cfg(feature = "icu_collator")]
+use icu_collator::{Collator, CollatorOptions};
+use linked_hash_map::{self, LinkedHashMap};
+use options::{
+    check_nulls, decode, too_deep, CheckLineBreaks, CountBytes, LoadOptions, OptionsReceiver,
+    OptionsState, ReadChars, DEFAULT_MAX_DEPTH,
+};
 use parser::*;
+use path::{PathSegment, PatternError, YamlPath};
 use scanner::{Marker, ScanError, TScalarStyle};
+use source_map::{self, SourceMap};
+use std::cell::Cell;
+use std::cmp::Ordering;
 use std::error::Error;
 use std::fmt;
+use std::fs;
+use std::io;
 use std::mem;
-use std::ops::Index;
+use std::ops::{Index, IndexMut};
+use std::path::{Path, PathBuf};
+use std::slice;
 use std::str;
 use std::string;
 use std::vec;
+use visit::{visit_mut, VisitMut};
 
 /// A YAML node is stored as this `Yaml` enumeration, which provides an easy way to
 /// access your YAML document.
@@ -46,6 +70,8 @@ pub enum StrictYaml {
 #[derive(Clone, PartialEq, Debug, Eq)]
 enum StoreError {
     RepeatedHashKey,
+    /// The events do not describe a well-formed document.
+    UnexpectedEvent,
 }
 
 impl Error for StoreError {}
@@ -56,6 +82,56 @@ impl fmt::Display for StoreError {
             StoreError::RepeatedHashKey => {
                 write!(formatter, "Key already exists in the hash map")
             }
+            StoreError::UnexpectedEvent => write!(formatter, "Unexpected event"),
+        }
+    }
+}
+
+/// Error of `StrictYamlLoader::load_from_file`, naming the file it occurred in.
+#[derive(Debug)]
+pub enum FileError {
+    /// The file could not be read.
+    Io(PathBuf, io::Error),
+    /// The file could not be decoded or loaded.
+    Scan(PathBuf, ScanError),
+}
+
+impl FileError {
+    pub fn path(&self) -> &Path {
+        match *self {
+            FileError::Io(ref path, _) | FileError::Scan(ref path, _) => path,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match *self {
+            FileError::Io(..) => code::IO,
+            FileError::Scan(_, ref err) => err.code(),
+        }
+    }
+}
+
+impl Error for FileError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            FileError::Io(_, ref err) => Some(err),
+            FileError::Scan(_, ref err) => Some(err),
+        }
+    }
+}
+
+impl fmt::Display for FileError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FileError::Io(ref path, ref err) => write!(formatter, "{}: {}", path.display(), err),
+            FileError::Scan(ref path, ref err) => write!(
+                formatter,
+                "{}:{}:{}: {}",
+                path.display(),
+                err.marker().line(),
+                err.marker().col() + 1,
+                err.info()
+            ),
         }
     }
 }
@@ -63,65 +139,127 @@ impl fmt::Display for StoreError {
 pub type Array = Vec<StrictYaml>;
 pub type Hash = LinkedHashMap<StrictYaml, StrictYaml>;
 
+/// Builds a `StrictYaml` from inline syntax, like `serde_json::json!`:
+/// `[...]` for a sequence, `{key: value, ...}` for a mapping, and any other
+/// value converted with `StrictYaml::from`, which turns numbers and booleans
+/// into their string and keeps `StrictYaml` values as they are.
+///
+/// Keys and values are single tokens: wrap expressions such as `-1` or
+/// `port + 1` in parentheses.
+///
+/// ```
+/// #[macro_use]
+/// extern crate strict_yaml_rust;
+///
+/// use strict_yaml_rust::StrictYamlLoader;
+///
+/// # fn main() {
+/// let port = 8080;
+/// let doc = strict_yaml!({
+///     "name": "demo",
+///     "server": {"port": port, "debug": false},
+///     "hosts": ["a", (format!("b{}", 2))],
+/// });
+/// let expected = &StrictYamlLoader::load_from_str(
+///     "name: demo\nserver:\n  port: 8080\n  debug: false\nhosts:\n  - a\n  - b2",
+/// )
+/// .unwrap()[0];
+/// assert_eq!(&doc, expected);
+/// # }
+/// ```
+#[macro_export]
+macro_rules! strict_yaml {
+    ([ $($item:tt),* $(,)? ]) => {
+        $crate::StrictYaml::Array(vec![$($crate::strict_yaml!($item)),*])
+    };
+    ({ $($key:tt : $value:tt),* $(,)? }) => {{
+        #[allow(unused_mut)]
+        let mut hash = $crate::strict_yaml::Hash::new();
+        $(
+            hash.insert($crate::strict_yaml!(@key $key), $crate::strict_yaml!($value));
+        )*
+        $crate::StrictYaml::Hash(hash)
+    }};
+    (@key $key:expr) => {
+        $crate::StrictYaml::from($key)
+    };
+    ($value:expr) => {
+        $crate::StrictYaml::from($value)
+    };
+}
+
 pub struct StrictYamlLoader {
     docs: Vec<StrictYaml>,
     // states
     // (current node, anchor_id) tuple
     doc_stack: Vec<(StrictYaml, usize)>,
     key_stack: Vec<StrictYaml>,
+    /// Levels of nested collections allowed, see `LoadOptions::max_depth`.
+    max_depth: usize,
 }
 
 impl MarkedEventReceiver for StrictYamlLoader {
     fn on_event(&mut self, ev: Event, mark: Marker) -> Result<(), ScanError> {
         // println!("EV {:?}", ev);
+        if let Event::SequenceStart(_) | Event::MappingStart(_) = ev {
+            // only collections stay on the stack below the root
+            if self.doc_stack.len() >= self.max_depth {
+                return Err(too_deep(mark, self.max_depth));
+            }
+        }
         let res = match ev {
             Event::DocumentStart => {
                 Ok(())
                 // do nothing
             }
-            Event::DocumentEnd => {
-                match self.doc_stack.len() {
-                    // empty document
-                    0 => self.docs.push(StrictYaml::BadValue),
-                    1 => self.docs.push(self.doc_stack.pop().unwrap().0),
-                    _ => unreachable!(),
+            Event::DocumentEnd => match self.doc_stack.len() {
+                // empty document
+                0 => {
+                    self.docs.push(StrictYaml::BadValue);
+                    Ok(())
                 }
-                Ok(())
-            }
+                1 => {
+                    let (node, _) = self.doc_stack.remove(0);
+                    self.docs.push(node);
+                    Ok(())
+                }
+                _ => Err(StoreError::UnexpectedEvent),
+            },
             Event::SequenceStart(aid) => {
                 self.doc_stack.push((StrictYaml::Array(Vec::new()), aid));
                 Ok(())
             }
-            Event::SequenceEnd => {
-                let node = self.doc_stack.pop().unwrap();
-                self.insert_new_node(node)
-            }
+            Event::SequenceEnd => match self.doc_stack.pop() {
+                Some(node @ (StrictYaml::Array(_), _)) => self.insert_new_node(node),
+                _ => Err(StoreError::UnexpectedEvent),
+            },
             Event::MappingStart(aid) => {
                 self.doc_stack.push((StrictYaml::Hash(Hash::new()), aid));
                 self.key_stack.push(StrictYaml::BadValue);
                 Ok(())
             }
-            Event::MappingEnd => {
-                self.key_stack.pop().unwrap();
-                let node = self.doc_stack.pop().unwrap();
-                self.insert_new_node(node)
-            }
-            Event::Scalar(v, style, aid) => {
-                let node = if style != TScalarStyle::Plain {
-                    StrictYaml::String(v)
-                } else {
-                    // Datatype is not specified, or unrecognized
-                    StrictYaml::from_str(&v)
-                };
-
-                self.insert_new_node((node, aid))
+            Event::MappingEnd => match (self.key_stack.pop(), self.doc_stack.pop()) {
+                (Some(StrictYaml::BadValue), Some(node @ (StrictYaml::Hash(_), _))) => {
+                    self.insert_new_node(node)
+                }
+                _ => Err(StoreError::UnexpectedEvent),
+            },
+            Event::Scalar(v, _, aid) => {
+                // every scalar is a string, whatever its style
+                self.insert_new_node((StrictYaml::String(v), aid))
             }
             _ => {
                 Ok(()) /* ignore */
             }
         };
 
-        res.map_err(|e| ScanError::new(mark, &format!("Error handling node: {}", e)))
+        res.map_err(|e| {
+            let info = format!("Error handling node: {}", e);
+            match e {
+                StoreError::RepeatedHashKey => ScanError::duplicate_key(mark, &info),
+                StoreError::UnexpectedEvent => ScanError::parser(mark, &info),
+            }
+        })
 
         // println!("DOC {:?}", self.doc_stack);
     }
@@ -130,47 +268,309 @@ impl MarkedEventReceiver for StrictYamlLoader {
 impl StrictYamlLoader {
     fn insert_new_node(&mut self, node: (StrictYaml, usize)) -> Result<(), StoreError> {
         // valid anchor id starts from 1
-        if self.doc_stack.is_empty() {
-            self.doc_stack.push(node);
-        } else {
-            let parent = self.doc_stack.last_mut().unwrap();
-            match *parent {
-                (StrictYaml::Array(ref mut v), _) => v.push(node.0),
-                (StrictYaml::Hash(ref mut h), _) => {
-                    let cur_key = self.key_stack.last_mut().unwrap();
-
-                    // current node is a key
-                    if cur_key.is_badvalue() {
-                        *cur_key = node.0;
-                    // current node is a value
-                    } else {
-                        let mut newkey = StrictYaml::BadValue;
-                        mem::swap(&mut newkey, cur_key);
+        match self.doc_stack.last_mut() {
+            None => self.doc_stack.push(node),
+            Some(&mut (StrictYaml::Array(ref mut v), _)) => v.push(node.0),
+            Some(&mut (StrictYaml::Hash(ref mut h), _)) => {
+                let cur_key = match self.key_stack.last_mut() {
+                    Some(key) => key,
+                    None => return Err(StoreError::UnexpectedEvent),
+                };
 
-                        if h.contains_key(&newkey) {
-                            return Err(StoreError::RepeatedHashKey);
-                        } else {
-                            h.insert(newkey, node.0);
-                        }
+                // current node is a key
+                if cur_key.is_badvalue() {
+                    *cur_key = node.0;
+                // current node is a value
+                } else {
+                    let mut newkey = StrictYaml::BadValue;
+                    mem::swap(&mut newkey, cur_key);
+
+                    if h.contains_key(&newkey) {
+                        return Err(StoreError::RepeatedHashKey);
+                    } else {
+                        h.insert(newkey, node.0);
                     }
                 }
-                _ => unreachable!(),
             }
+            // a second root node
+            Some(_) => return Err(StoreError::UnexpectedEvent),
         }
 
         Ok(())
     }
 
-    pub fn load_from_str(source: &str) -> Result<Vec<StrictYaml>, ScanError> {
-        let mut loader = StrictYamlLoader {
+    pub(crate) fn new() -> StrictYamlLoader {
+        StrictYamlLoader::with_max_depth(DEFAULT_MAX_DEPTH)
+    }
+
+    pub(crate) fn with_max_depth(max_depth: usize) -> StrictYamlLoader {
+        StrictYamlLoader {
             docs: Vec::new(),
             doc_stack: Vec::new(),
             key_stack: Vec::new(),
-        };
+            max_depth,
+        }
+    }
+
+    pub(crate) fn take_documents(&mut self) -> Vec<StrictYaml> {
+        mem::take(&mut self.docs)
+    }
+
+    pub fn load_from_str(source: &str) -> Result<Vec<StrictYaml>, ScanError> {
+        let mut loader = StrictYamlLoader::new();
         let mut parser = Parser::new(source.chars());
         parser.load(&mut loader, true)?;
         Ok(loader.docs)
     }
+
+    /// Like `load_from_str`, for encoded bytes. The input is read as UTF-8,
+    /// unless a byte order mark or the null bytes around the first character
+    /// indicate UTF-16 or UTF-32. Invalid sequences are errors.
+    pub fn load_from_bytes(source: &[u8]) -> Result<Vec<StrictYaml>, ScanError> {
+        let text = decode(source, &LoadOptions::new())?;
+        StrictYamlLoader::load_from_str(&text)
+    }
+
+    /// Like `load_from_bytes`, applying `options` while decoding and loading.
+    pub fn load_from_bytes_with_options(
+        source: &[u8],
+        options: &LoadOptions,
+    ) -> Result<Vec<StrictYaml>, ScanError> {
+        let text = decode(source, options)?;
+        StrictYamlLoader::load_from_str_with_options(&text, options)
+    }
+
+    /// Loads all the documents of `source` with the span of each of their
+    /// nodes, see `source_map`.
+    pub fn load_from_str_with_markers(
+        source: &str,
+    ) -> Result<(Vec<StrictYaml>, SourceMap), ScanError> {
+        source_map::load(source)
+    }
+
+    /// Loads all the documents read from `reader`, decoding its UTF-8 as it is
+    /// read rather than reading the whole input first.
+    ///
+    /// A read error is reported as `LoadError::Io`, with the position the
+    /// input was read up to.
+    ///
+    /// ```no_run
+    /// use std::io;
+    /// use strict_yaml_rust::StrictYamlLoader;
+    ///
+    /// let docs = StrictYamlLoader::load_from_reader(io::stdin().lock()).unwrap();
+    /// ```
+    pub fn load_from_reader<R: io::Read>(reader: R) -> Result<Vec<StrictYaml>, LoadError> {
+        let error = Cell::new(None);
+        let mut loader = StrictYamlLoader::new();
+        let res = Parser::new(ReadChars::new(reader, &error)).load(&mut loader, true);
+        // a read error cuts the input short, so report it over what came next
+        if let Some(e) = error.take() {
+            return Err(e);
+        }
+        res?;
+        Ok(loader.docs)
+    }
+
+    /// Reads, decodes and loads the file at `path`, see `load_from_bytes`.
+    /// Errors name the file, and the line and column for errors in its content.
+    ///
+    /// ```no_run
+    /// use strict_yaml_rust::StrictYamlLoader;
+    ///
+    /// match StrictYamlLoader::load_from_file("config.yaml") {
+    ///     Ok(docs) => println!("{:?}", docs[0]["name"]),
+    ///     // e.g. "config.yaml:3:7: mapping values are not allowed in this context"
+    ///     Err(e) => eprintln!("{}", e),
+    /// }
+    /// ```
+    pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Vec<StrictYaml>, FileError> {
+        StrictYamlLoader::load_from_file_with_options(path, &LoadOptions::new())
+    }
+
+    /// Like `load_from_file`, applying `options` while decoding and loading.
+    pub fn load_from_file_with_options<P: AsRef<Path>>(
+        path: P,
+        options: &LoadOptions,
+    ) -> Result<Vec<StrictYaml>, FileError> {
+        let path = path.as_ref();
+        let bytes = fs::read(path).map_err(|e| FileError::Io(path.to_owned(), e))?;
+        StrictYamlLoader::load_from_bytes_with_options(&bytes, options)
+            .map_err(|e| FileError::Scan(path.to_owned(), e))
+    }
+
+    /// Like `load_from_str`, applying `options` while loading.
+    pub fn load_from_str_with_options(
+        source: &str,
+        options: &LoadOptions,
+    ) -> Result<Vec<StrictYaml>, ScanError> {
+        let mut loader = StrictYamlLoader::with_max_depth(options.get_max_depth());
+        StrictYamlLoader::load_with_options(source, options, &mut loader)?;
+        Ok(loader.docs)
+    }
+
+    pub(crate) fn load_with_options<R: SpannedEventReceiver>(
+        source: &str,
+        options: &LoadOptions,
+        recv: &mut R,
+    ) -> Result<(), ScanError> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("load", bytes = source.len()).entered();
+        if options.is_bom_rejected() && source.starts_with('\u{feff}') {
+            return Err(ScanError::limit(
+                Marker::new(0, 1, 0),
+                "byte order mark at the start of the input",
+            ));
+        }
+        if options.are_control_chars_rejected() {
+            check_nulls(source)?;
+        }
+        let bytes = Cell::new(0);
+        let error = Cell::new(None);
+        let chars = CheckLineBreaks::new(source.chars(), options, &error);
+        let mut parser = Parser::new(CountBytes::new(chars, &bytes));
+        options.configure(&mut parser);
+        let mut state = OptionsState::new();
+        let mut recv = OptionsReceiver::new(recv, options, &bytes, &mut state);
+        let res = parser.load_spanned(&mut recv, true);
+        // an input error cuts the input short, so report it over what came next
+        if let Some(e) = error.take() {
+            return Err(e);
+        }
+        res
+    }
+
+    /// Like `load_from_str`, passing the events through `filter` before they are
+    /// turned into nodes.
+    pub fn load_from_str_with_filter<F: EventFilter>(
+        source: &str,
+        filter: F,
+    ) -> Result<Vec<StrictYaml>, ScanError> {
+        let mut loader = StrictYamlLoader::new();
+        let mut parser = Parser::new(source.chars());
+        parser.load(&mut Pipeline::new(filter, &mut loader), true)?;
+        Ok(loader.docs)
+    }
+
+    /// Loads all the documents read from `reader` without blocking.
+    ///
+    /// The input is parsed as it arrives, so only the pending input and the
+    /// documents under construction are held in memory. Scan errors are reported as
+    /// `io::ErrorKind::InvalidData` errors wrapping the `ScanError`.
+    #[cfg(feature = "futures-io")]
+    pub fn load_from_async_reader<R: AsyncRead + Unpin>(reader: R) -> LoadFromAsyncReader<R> {
+        LoadFromAsyncReader::new(reader, &LoadOptions::new())
+    }
+
+    /// Like `load_from_async_reader`, applying `options` as
+    /// `load_from_str_with_options` does, for untrusted uploads. The
+    /// `line_breaks` option is not applied: line breaks are read as
+    /// `load_from_async_reader` reads them.
+    #[cfg(feature = "futures-io")]
+    pub fn load_from_async_reader_with_options<R: AsyncRead + Unpin>(
+        reader: R,
+        options: &LoadOptions,
+    ) -> LoadFromAsyncReader<R> {
+        LoadFromAsyncReader::new(reader, options)
+    }
+
+    /// Builds only the next node of `parser` (and its children), leaving the parser
+    /// positioned right after it.
+    ///
+    /// Combined with `Parser::peek` and `Parser::skip_node`, this allows walking a
+    /// huge document event by event and materializing just the subtrees of interest.
+    pub fn load_node<T: Iterator<Item = char>>(
+        parser: &mut Parser<T>,
+    ) -> Result<StrictYaml, ScanError> {
+        let mut loader = StrictYamlLoader::new();
+        let mut depth = 0usize;
+        loop {
+            let (ev, mark) = parser.next()?;
+            match ev {
+                Event::SequenceStart(_) | Event::MappingStart(_) => depth += 1,
+                Event::SequenceEnd | Event::MappingEnd if depth > 0 => depth -= 1,
+                Event::Scalar(..) => {}
+                _ => {
+                    return Err(ScanError::parser(
+                        mark,
+                        &format!("expected a node, found {:?}", ev),
+                    ))
+                }
+            }
+            loader.on_event(ev, mark)?;
+            if depth == 0 {
+                break;
+            }
+        }
+        Ok(loader.doc_stack.pop().map_or(StrictYaml::BadValue, |n| n.0))
+    }
+
+    /// Loads each document of a multi-document stream on its own, so that a
+    /// malformed document only loses itself rather than the whole stream. The
+    /// eager form of `iter_documents`.
+    ///
+    /// ```
+    /// use strict_yaml_rust::StrictYamlLoader;
+    ///
+    /// let docs = StrictYamlLoader::load_from_str_per_document("a: b\n---\nc: d: e\n---\nf: g");
+    /// assert_eq!(docs.len(), 3);
+    /// assert!(docs[1].is_err());
+    /// assert_eq!(docs[2].as_ref().unwrap()["f"].as_str(), Some("g"));
+    /// ```
+    pub fn load_from_str_per_document(source: &str) -> Vec<Result<StrictYaml, ScanError>> {
+        StrictYamlLoader::iter_documents(source).collect()
+    }
+
+    /// Lazily loads the documents of a (possibly huge) multi-document stream.
+    ///
+    /// Each document is built only when the iterator is advanced, so memory use is
+    /// bounded by the largest single document. A malformed document yields an `Err`
+    /// and iteration resumes at the next `---` marker instead of aborting the stream.
+    ///
+    /// ```
+    /// use strict_yaml_rust::StrictYamlLoader;
+    ///
+    /// let docs: Vec<_> = StrictYamlLoader::iter_documents("--- a\n--- b: c: d\n--- e").collect();
+    /// assert_eq!(docs.len(), 3);
+    /// assert!(docs[1].is_err());
+    /// assert_eq!(docs[2].as_ref().unwrap().as_str(), Some("e"));
+    /// ```
+    pub fn iter_documents<'a>(source: &'a str) -> DocumentIter<str::Chars<'a>> {
+        DocumentIter {
+            parser: Parser::new(source.chars()),
+            done: false,
+        }
+    }
+}
+
+/// Iterator over the documents of a stream, see `StrictYamlLoader::iter_documents`.
+pub struct DocumentIter<T> {
+    parser: Parser<T>,
+    done: bool,
+}
+
+impl<T: Iterator<Item = char>> Iterator for DocumentIter<T> {
+    type Item = Result<StrictYaml, ScanError>;
+
+    fn next(&mut self) -> Option<Result<StrictYaml, ScanError>> {
+        if self.done {
+            return None;
+        }
+        let mut loader = StrictYamlLoader::new();
+        match self.parser.load(&mut loader, false) {
+            Ok(()) => match loader.docs.pop() {
+                Some(doc) => Some(Ok(doc)),
+                None => {
+                    self.done = true;
+                    None
+                }
+            },
+            Err(e) => {
+                self.parser.skip_to_next_document();
+                Some(Err(e))
+            }
+        }
+    }
 }
 
 macro_rules! define_as_ref (
@@ -195,6 +595,16 @@ pub fn $name(self) -> Option<$t> {
     );
 );
 
+macro_rules! define_get (
+    ($name:ident, $t:ty, $as:ident) => (
+/// The value of `key` in a hash, like `self[key]`, `None` when this is not a
+/// hash, the key is missing or the value is of another kind.
+pub fn $name(&self, key: &str) -> Option<$t> {
+    self[key].$as()
+}
+    );
+);
+
 impl StrictYaml {
     define_as_ref!(as_str, &str, String);
     define_as_ref!(as_hash, &Hash, Hash);
@@ -204,6 +614,10 @@ impl StrictYaml {
     define_into!(into_hash, Hash, Hash);
     define_into!(into_vec, Array, Array);
 
+    define_get!(get_str, &str, as_str);
+    define_get!(get_hash, &Hash, as_hash);
+    define_get!(get_vec, &Array, as_vec);
+
     pub fn is_badvalue(&self) -> bool {
         matches!(*self, StrictYaml::BadValue)
     }
@@ -211,36 +625,937 @@ impl StrictYaml {
     pub fn is_array(&self) -> bool {
         matches!(*self, StrictYaml::Array(_))
     }
+
+    /// Parses the string of a scalar with `FromStr`, `None` for other nodes
+    /// and strings `T` does not parse.
+    ///
+    /// ```
+    /// use strict_yaml_rust::StrictYamlLoader;
+    ///
+    /// let doc = &StrictYamlLoader::load_from_str("port: 8080\nhost: localhost").unwrap()[0];
+    /// assert_eq!(doc["port"].as_parsed::<u16>(), Some(8080));
+    /// assert_eq!(doc["host"].as_parsed::<u16>(), None);
+    /// assert_eq!(doc["missing"].as_parsed::<u16>(), None);
+    /// ```
+    pub fn as_parsed<T: str::FromStr>(&self) -> Option<T> {
+        self.as_str().and_then(|s| s.parse().ok())
+    }
+
+    /// Reads a scalar written as decimal digits with an optional leading `-`
+    /// or `+`, such as `42` or `-7`, within the range of `i64`. Blanks, `_`
+    /// separators, and `0x` or `0o` prefixes are not accepted.
+    pub fn as_i64(&self) -> Option<i64> {
+        self.as_str().and_then(parse_integer)
+    }
+
+    /// Reads a scalar written as decimal digits with an optional leading `-`
+    /// or `+`, an optional fraction and an optional exponent, such as `1.5`,
+    /// `-.5`, `3.` or `2e-10`. Blanks, `_` separators, `inf` or `nan` in
+    /// any spelling, and numbers too large for `f64` such as `1e400` are not
+    /// accepted.
+    pub fn as_f64(&self) -> Option<f64> {
+        self.as_str()
+            .and_then(parse_decimal)
+            .filter(|f| f.is_finite())
+    }
+
+    /// Reads `true` or `false`, in lowercase. The YAML 1.1 forms `yes`, `no`,
+    /// `on`, `off` and `y`, and other cases such as `True`, are not accepted.
+    pub fn as_bool(&self) -> Option<bool> {
+        self.as_str().and_then(parse_bool)
+    }
+
+    /// The node at `path` below this one.
+    pub fn get(&self, path: &YamlPath) -> Option<&StrictYaml> {
+        let mut node = self;
+        for segment in path.segments() {
+            node = match (node, segment) {
+                (StrictYaml::Hash(h), PathSegment::Key(key)) => {
+                    h.get(&StrictYaml::String(key.clone()))?
+                }
+                (StrictYaml::Array(v), &PathSegment::Index(i)) => v.get(i)?,
+                _ => return None,
+            };
+        }
+        Some(node)
+    }
+
+    /// The node at `path`, written in the notation of `YamlPath`: keys
+    /// separated by `.`, indices in brackets, and `.`, `[`, `]` and `\` in keys
+    /// escaped with a backslash. `None` when there is no such node or `path`
+    /// is not valid.
+    ///
+    /// ```
+    /// use strict_yaml_rust::StrictYamlLoader;
+    ///
+    /// let source = "server:\n  tls:\n    cert: a.pem\n  hosts:\n    - a\n    - b\n";
+    /// let doc = &StrictYamlLoader::load_from_str(source).unwrap()[0];
+    /// assert_eq!(doc.get_path("server.tls.cert").unwrap().as_str(), Some("a.pem"));
+    /// assert_eq!(doc.get_path("server.hosts[1]").unwrap().as_str(), Some("b"));
+    /// assert!(doc.get_path("server.port").is_none());
+    /// ```
+    pub fn get_path(&self, path: &str) -> Option<&StrictYaml> {
+        path.parse().ok().and_then(|path| self.get(&path))
+    }
+
+    /// Sets the node at `path` to `value`, returning the node it replaces.
+    ///
+    /// Missing keys are added, with empty hashes for those followed by a key,
+    /// and a replaced key keeps its place in its hash. Sequences are not
+    /// grown: an index must be within its sequence. The node is left as it
+    /// was when an error is returned.
+    pub fn set(
+        &mut self,
+        path: &YamlPath,
+        value: StrictYaml,
+    ) -> Result<Option<StrictYaml>, EditError> {
+        self.check_set(path)?;
+        let segments = path.segments();
+        let (last, parents) = match segments.split_last() {
+            Some(split) => split,
+            None => return Ok(Some(mem::replace(self, value))),
+        };
+        let mut node = self;
+        // `check_set` found every parent, or a missing key to add
+        for segment in parents {
+            node = match (node, segment) {
+                (StrictYaml::Hash(h), PathSegment::Key(key)) => h
+                    .entry(StrictYaml::String(key.clone()))
+                    .or_insert_with(|| StrictYaml::Hash(Hash::new())),
+                (StrictYaml::Array(v), &PathSegment::Index(i)) => &mut v[i],
+                _ => unreachable!(),
+            };
+        }
+        match (node, last) {
+            (StrictYaml::Hash(h), PathSegment::Key(key)) => {
+                let key = StrictYaml::String(key.clone());
+                match h.get_mut(&key) {
+                    Some(node) => Ok(Some(mem::replace(node, value))),
+                    None => Ok(h.insert(key, value)),
+                }
+            }
+            (StrictYaml::Array(v), &PathSegment::Index(i)) => {
+                Ok(Some(mem::replace(&mut v[i], value)))
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    /// Checks that `set` can reach `path` before it changes anything. A
+    /// missing key is added as an empty hash, so only keys can follow it.
+    fn check_set(&self, path: &YamlPath) -> Result<(), EditError> {
+        let segments = path.segments();
+        let no_node = |depth: usize| {
+            let prefix = YamlPath::from(segments[..=depth].to_vec());
+            Err(EditError::new(format!("no node at {}", describe(&prefix))))
+        };
+        // `None` once past a missing key
+        let mut node = Some(self);
+        for (depth, segment) in segments.iter().enumerate() {
+            let last = depth + 1 == segments.len();
+            node = match (node, segment) {
+                (None, PathSegment::Key(_)) => None,
+                (Some(StrictYaml::Hash(h)), PathSegment::Key(key)) => {
+                    h.get(&StrictYaml::String(key.clone()))
+                }
+                (Some(StrictYaml::Array(v)), &PathSegment::Index(i)) if i < v.len() => v.get(i),
+                _ => return no_node(depth),
+            };
+            if node.is_none() && !last {
+                if let PathSegment::Index(_) = segments[depth + 1] {
+                    return no_node(depth);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Sets the node at `path`, in the notation of `get_path`, to `value`.
+    /// See `set`.
+    ///
+    /// ```
+    /// use strict_yaml_rust::{StrictYaml, StrictYamlLoader};
+    ///
+    /// let mut doc = StrictYamlLoader::load_from_str("server:\n  port: 80\n").unwrap().remove(0);
+    /// let old = doc.set_path("server.port", StrictYaml::String("8080".into())).unwrap();
+    /// assert_eq!(old, Some(StrictYaml::String("80".into())));
+    /// doc.set_path("server.tls.cert", StrictYaml::String("a.pem".into())).unwrap();
+    /// assert_eq!(doc["server"]["tls"]["cert"].as_str(), Some("a.pem"));
+    /// ```
+    pub fn set_path(
+        &mut self,
+        path: &str,
+        value: StrictYaml,
+    ) -> Result<Option<StrictYaml>, EditError> {
+        let path = path
+            .parse()
+            .map_err(|e: PatternError| EditError::new(e.to_string()))?;
+        self.set(&path, value)
+    }
+
+    /// Removes the hash entry or sequence item at `path`, returning it.
+    /// `None` when there is no such node, and for the root.
+    pub fn remove(&mut self, path: &YamlPath) -> Option<StrictYaml> {
+        let (last, parents) = path.segments().split_last()?;
+        let mut node = self;
+        for segment in parents {
+            node = match (node, segment) {
+                (StrictYaml::Hash(h), PathSegment::Key(key)) => {
+                    h.get_mut(&StrictYaml::String(key.clone()))?
+                }
+                (StrictYaml::Array(v), &PathSegment::Index(i)) => v.get_mut(i)?,
+                _ => return None,
+            };
+        }
+        match (node, last) {
+            (StrictYaml::Hash(h), PathSegment::Key(key)) => {
+                h.remove(&StrictYaml::String(key.clone()))
+            }
+            (StrictYaml::Array(v), &PathSegment::Index(i)) if i < v.len() => Some(v.remove(i)),
+            _ => None,
+        }
+    }
+
+    /// Removes the node at `path`, in the notation of `get_path`. See
+    /// `remove`.
+    pub fn remove_path(&mut self, path: &str) -> Option<StrictYaml> {
+        path.parse().ok().and_then(|path| self.remove(&path))
+    }
+
+    /// The items of an array, or an error for other nodes, which array
+    /// mutations leave as they are.
+    fn items_mut(&mut self) -> Result<&mut Array, EditError> {
+        match *self {
+            StrictYaml::Array(ref mut v) => Ok(v),
+            _ => Err(EditError::new("the node is not a sequence".to_owned())),
+        }
+    }
+
+    /// Appends `value` to an array. Fails on other nodes.
+    ///
+    /// ```
+    /// use strict_yaml_rust::StrictYaml;
+    ///
+    /// let mut doc: StrictYaml = "- a".parse().unwrap();
+    /// doc.push("b").unwrap();
+    /// doc.insert(0, "z").unwrap();
+    /// doc.extend(vec!["c", "d"]).unwrap();
+    /// assert_eq!(doc.pop(), Some(StrictYaml::from("d")));
+    /// assert_eq!(doc, "- z\n- a\n- b\n- c".parse().unwrap());
+    /// assert!(doc["missing"].clone().push("x").is_err());
+    /// ```
+    pub fn push<T: Into<StrictYaml>>(&mut self, value: T) -> Result<(), EditError> {
+        self.items_mut()?.push(value.into());
+        Ok(())
+    }
+
+    /// Inserts `value` at `index` in an array, shifting the items after it.
+    /// Fails on other nodes, and when `index` is past the end of the array.
+    pub fn insert<T: Into<StrictYaml>>(&mut self, index: usize, value: T) -> Result<(), EditError> {
+        let items = self.items_mut()?;
+        if index > items.len() {
+            return Err(EditError::new(format!(
+                "index {} is past the end of a sequence of {}",
+                index,
+                items.len()
+            )));
+        }
+        items.insert(index, value.into());
+        Ok(())
+    }
+
+    /// Appends `values` to an array. Fails on other nodes.
+    pub fn extend<I>(&mut self, values: I) -> Result<(), EditError>
+    where
+        I: IntoIterator,
+        I::Item: Into<StrictYaml>,
+    {
+        self.items_mut()?.extend(values.into_iter().map(Into::into));
+        Ok(())
+    }
+
+    /// Removes the last item of an array. `None` when it is empty, and for
+    /// other nodes.
+    pub fn pop(&mut self) -> Option<StrictYaml> {
+        self.items_mut().ok()?.pop()
+    }
+
+    /// Removes `key` from a hash, returning its value. `None` when the key is
+    /// missing, and for other nodes.
+    ///
+    /// ```
+    /// use strict_yaml_rust::StrictYaml;
+    ///
+    /// let mut config: StrictYaml = "host: a\nport: 80\ncolor: red".parse().unwrap();
+    /// let host = config.remove_key("host");
+    /// let port = config.remove_key("port");
+    /// assert_eq!((host.unwrap().as_str(), port.unwrap().as_str()), (Some("a"), Some("80")));
+    /// let leftovers: Vec<_> = config.as_hash().unwrap().keys().collect();
+    /// assert_eq!(leftovers, [&StrictYaml::from("color")]);
+    /// ```
+    pub fn remove_key(&mut self, key: &str) -> Option<StrictYaml> {
+        match *self {
+            StrictYaml::Hash(ref mut h) => h.remove(&StrictYaml::String(key.to_owned())),
+            _ => None,
+        }
+    }
+
+    /// Removes the item at `index` from an array, shifting the items after
+    /// it. `None` when `index` is out of bounds, and for other nodes.
+    pub fn remove_index(&mut self, index: usize) -> Option<StrictYaml> {
+        let items = self.items_mut().ok()?;
+        if index < items.len() {
+            Some(items.remove(index))
+        } else {
+            None
+        }
+    }
+
+    /// Keeps the entries of a hash `f` returns true for, in their order, like
+    /// `HashMap::retain`. Other nodes are left as they are.
+    ///
+    /// ```
+    /// use strict_yaml_rust::StrictYaml;
+    ///
+    /// let mut doc: StrictYaml = "name: a\nx-internal: b\nx-debug: c".parse().unwrap();
+    /// doc.retain_entries(|k, _| !k.as_str().unwrap_or("").starts_with("x-"));
+    /// assert_eq!(doc, "name: a".parse().unwrap());
+    /// ```
+    pub fn retain_entries<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&StrictYaml, &mut StrictYaml) -> bool,
+    {
+        if let StrictYaml::Hash(ref mut h) = *self {
+            *h = mem::take(h)
+                .into_iter()
+                .filter_map(|(k, mut v)| if f(&k, &mut v) { Some((k, v)) } else { None })
+                .collect();
+        }
+    }
+
+    /// Keeps the items of an array `f` returns true for, like `Vec::retain`.
+    /// Other nodes are left as they are.
+    pub fn retain_items<F>(&mut self, f: F)
+    where
+        F: FnMut(&StrictYaml) -> bool,
+    {
+        if let StrictYaml::Array(ref mut v) = *self {
+            v.retain(f);
+        }
+    }
+
+    /// Every node below this one with its path, depth first: each node comes
+    /// before its children, in document order. This node itself is not
+    /// included.
+    ///
+    /// ```
+    /// use strict_yaml_rust::StrictYaml;
+    ///
+    /// let doc: StrictYaml = "a:\n  b: x\nc:\n  - y".parse().unwrap();
+    /// let paths: Vec<String> = doc.iter_paths().map(|(path, _)| path.to_string()).collect();
+    /// assert_eq!(paths, ["a", "a.b", "c", "c[0]"]);
+    /// ```
+    pub fn iter_paths(&self) -> PathIter<'_> {
+        let mut iter = PathIter { stack: Vec::new() };
+        iter.push_children(&YamlPath::new(), self);
+        iter
+    }
+
+    /// A copy of this node with each scalar value replaced by what `f`
+    /// returns for its path and string. Mapping keys are kept, see
+    /// `map_keys`.
+    ///
+    /// ```
+    /// use strict_yaml_rust::StrictYaml;
+    ///
+    /// let doc: StrictYaml = "a: ' x '\nb:\n  - 'y '".parse().unwrap();
+    /// let trimmed = doc.map_scalars(|_, s| s.trim().to_owned());
+    /// assert_eq!(trimmed, "a: x\nb:\n  - y".parse().unwrap());
+    /// ```
+    pub fn map_scalars<F>(&self, f: F) -> StrictYaml
+    where
+        F: FnMut(&YamlPath, &str) -> String,
+    {
+        struct MapScalars<F>(F);
+
+        impl<F: FnMut(&YamlPath, &str) -> String> VisitMut for MapScalars<F> {
+            fn visit_scalar_mut(&mut self, path: &YamlPath, value: &mut String) {
+                *value = (self.0)(path, value);
+            }
+        }
+
+        let mut node = self.clone();
+        visit_mut(&mut MapScalars(f), &mut node);
+        node
+    }
+
+    /// A copy of this node with each string key of a mapping replaced by what
+    /// `f` returns for the path of its entry and the key. Entries keep their
+    /// order; when keys become equal, the last entry wins.
+    ///
+    /// ```
+    /// use strict_yaml_rust::StrictYaml;
+    ///
+    /// let doc: StrictYaml = "Name: a\nServer:\n  Port: 80".parse().unwrap();
+    /// let lower = doc.map_keys(|_, k| k.to_lowercase());
+    /// assert_eq!(lower, "name: a\nserver:\n  port: 80".parse().unwrap());
+    /// ```
+    pub fn map_keys<F>(&self, mut f: F) -> StrictYaml
+    where
+        F: FnMut(&YamlPath, &str) -> String,
+    {
+        self.map_keys_at(&YamlPath::new(), &mut f)
+    }
+
+    fn map_keys_at(
+        &self,
+        path: &YamlPath,
+        f: &mut dyn FnMut(&YamlPath, &str) -> String,
+    ) -> StrictYaml {
+        match *self {
+            StrictYaml::Array(ref v) => StrictYaml::Array(
+                v.iter()
+                    .enumerate()
+                    .map(|(i, item)| item.map_keys_at(&path.child(PathSegment::Index(i)), f))
+                    .collect(),
+            ),
+            StrictYaml::Hash(ref h) => {
+                let mut mapped = Hash::new();
+                for (k, v) in h {
+                    let (key, entry_path) = match *k {
+                        StrictYaml::String(ref s) => {
+                            let entry_path = path.child(PathSegment::Key(s.clone()));
+                            (StrictYaml::String(f(&entry_path, s)), entry_path)
+                        }
+                        _ => (k.clone(), path.child(PathSegment::Key(String::new()))),
+                    };
+                    mapped.insert(key, v.map_keys_at(&entry_path, f));
+                }
+                StrictYaml::Hash(mapped)
+            }
+            ref node => node.clone(),
+        }
+    }
+
+    /// Moves the node out, leaving `BadValue` in its place.
+    ///
+    /// ```
+    /// use strict_yaml_rust::StrictYaml;
+    ///
+    /// let mut doc: StrictYaml = "db:\n  host: a\nname: b".parse().unwrap();
+    /// let db = doc["db"].take();
+    /// assert_eq!(db["host"].as_str(), Some("a"));
+    /// assert!(doc["db"].is_badvalue());
+    /// ```
+    pub fn take(&mut self) -> StrictYaml {
+        mem::replace(self, StrictYaml::BadValue)
+    }
+
+    /// Puts `node` in place of this node, returning the node it replaces.
+    pub fn replace(&mut self, node: StrictYaml) -> StrictYaml {
+        mem::replace(self, node)
+    }
+
+    /// The node at a JSON Pointer (RFC 6901), like `serde_json::Value::pointer`:
+    /// `""` is this node, and each `/` is followed by a key, or an index
+    /// without leading zeros in a sequence, where `~1` stands for `/` and
+    /// `~0` for `~`.
+    ///
+    /// ```
+    /// use strict_yaml_rust::StrictYamlLoader;
+    ///
+    /// let source = "servers:\n  - host: a\n  - host: b\na/b: c\n";
+    /// let doc = &StrictYamlLoader::load_from_str(source).unwrap()[0];
+    /// assert_eq!(doc.pointer("/servers/1/host").unwrap().as_str(), Some("b"));
+    /// assert_eq!(doc.pointer("/a~1b").unwrap().as_str(), Some("c"));
+    /// assert!(doc.pointer("/servers/01").is_none());
+    /// ```
+    pub fn pointer(&self, pointer: &str) -> Option<&StrictYaml> {
+        let mut node = self;
+        for token in pointer_tokens(pointer)? {
+            node = match *node {
+                StrictYaml::Hash(ref h) => h.get(&StrictYaml::String(token))?,
+                StrictYaml::Array(ref v) => v.get(pointer_index(&token)?)?,
+                _ => return None,
+            };
+        }
+        Some(node)
+    }
+
+    /// The node at a JSON Pointer, mutably. See `pointer`.
+    pub fn pointer_mut(&mut self, pointer: &str) -> Option<&mut StrictYaml> {
+        let mut node = self;
+        for token in pointer_tokens(pointer)? {
+            node = match *node {
+                StrictYaml::Hash(ref mut h) => h.get_mut(&StrictYaml::String(token))?,
+                StrictYaml::Array(ref mut v) => v.get_mut(pointer_index(&token)?)?,
+                _ => return None,
+            };
+        }
+        Some(node)
+    }
+
+    /// Returns the events a `Parser` would produce for this node as a document,
+    /// from `DocumentStart` to `DocumentEnd`.
+    ///
+    /// Scalars are reported with the plain style. A `BadValue` document has no
+    /// content; a nested `BadValue` is reported as an empty scalar.
+    ///
+    /// ```
+    /// use strict_yaml_rust::{Event, StrictYamlLoader};
+    ///
+    /// let doc = &StrictYamlLoader::load_from_str("- a").unwrap()[0];
+    /// let events: Vec<Event> = doc.events().collect();
+    /// assert_eq!(events.len(), 5);
+    /// assert_eq!(events[1], Event::SequenceStart(0));
+    /// ```
+    pub fn events<'a>(&'a self) -> Events<'a> {
+        Events {
+            started: false,
+            pending: if self.is_badvalue() { None } else { Some(self) },
+            stack: Vec::new(),
+            ended: false,
+        }
+    }
+
+    /// Sorts the entries of this mapping and of the nested mappings by key,
+    /// comparing the keys with `Ord`: strings are in code point order.
+    ///
+    /// ```
+    /// use strict_yaml_rust::StrictYamlLoader;
+    ///
+    /// let mut doc = StrictYamlLoader::load_from_str("b: x\na:\n  d: y\n  c: z").unwrap().remove(0);
+    /// doc.sort_keys();
+    /// let keys: Vec<&str> = doc.as_hash().unwrap().keys().map(|k| k.as_str().unwrap()).collect();
+    /// assert_eq!(keys, ["a", "b"]);
+    /// assert_eq!(doc["a"].as_hash().unwrap().keys().next().unwrap().as_str(), Some("c"));
+    /// ```
+    pub fn sort_keys(&mut self) {
+        self.sort_keys_by(&mut Ord::cmp);
+    }
+
+    /// A copy of this node in canonical form: the same for nodes that only
+    /// differ in what `options` normalizes, see the `canonical` module.
+    pub fn canonicalize(&self, options: &CanonicalOptions) -> StrictYaml {
+        canonical::canonicalize(self, options)
+    }
+
+    /// The canonical form of this node, emitted with every scalar value
+    /// double quoted, so that the text does not depend on how the scalars were
+    /// written.
+    pub fn to_canonical_string(&self, options: &CanonicalOptions) -> String {
+        let mut out = String::new();
+        let mut emitter = StrictYamlEmitter::new(&mut out);
+        emitter.style("**".parse().unwrap(), ScalarStyle::Quoted);
+        emitter
+            .dump(&self.canonicalize(options))
+            .expect("writing to a String");
+        out
+    }
+
+    /// A digest of the canonical form of this node with the default
+    /// `CanonicalOptions`, which does not depend on how the document is
+    /// written: the 128-bit FNV-1a hash of `to_canonical_string`. It stays the
+    /// same across releases, for use as a cache key.
+    ///
+    /// ```
+    /// use strict_yaml_rust::StrictYaml;
+    ///
+    /// let a: StrictYaml = "a: 1\nb: 'x'".parse().unwrap();
+    /// let b: StrictYaml = "b: x  # comment\na: \"1\"".parse().unwrap();
+    /// assert_eq!(a.content_hash(), b.content_hash());
+    /// assert_ne!(a.content_hash(), "a: 2\nb: x".parse::<StrictYaml>().unwrap().content_hash());
+    /// ```
+    pub fn content_hash(&self) -> u128 {
+        const OFFSET: u128 = 0x6c62_272e_07bb_0142_62b8_2175_6295_c58d;
+        const PRIME: u128 = 0x0000_0000_0100_0000_0000_0000_0000_013b;
+        let text = self.to_canonical_string(&CanonicalOptions::new());
+        text.bytes()
+            .fold(OFFSET, |hash, b| (hash ^ u128::from(b)).wrapping_mul(PRIME))
+    }
+
+    /// Compares with `other` without regard to the order of mapping entries,
+    /// which `==` takes into account. Sequences must have their items in the
+    /// same order.
+    ///
+    /// ```
+    /// use strict_yaml_rust::StrictYaml;
+    ///
+    /// let a: StrictYaml = "a: x\nb:\n  - 1\n  - 2".parse().unwrap();
+    /// let b: StrictYaml = "b:\n  - 1\n  - 2\na: x".parse().unwrap();
+    /// assert!(a != b);
+    /// assert!(a.eq_unordered(&b));
+    /// ```
+    pub fn eq_unordered(&self, other: &StrictYaml) -> bool {
+        eq_unordered(self, other, false)
+    }
+
+    /// Like `eq_unordered`, also comparing sequences as multisets: they are
+    /// equal when they have the same items, as many times each, in any order.
+    pub fn eq_unordered_sequences(&self, other: &StrictYaml) -> bool {
+        eq_unordered(self, other, true)
+    }
+
+    /// Like `sort_keys`, comparing the keys with `compare`. Entries with equal
+    /// keys keep their order.
+    pub fn sort_keys_by<F>(&mut self, compare: &mut F)
+    where
+        F: FnMut(&StrictYaml, &StrictYaml) -> Ordering,
+    {
+        match *self {
+            StrictYaml::Hash(ref mut h) => {
+                let mut entries: Vec<(StrictYaml, StrictYaml)> = mem::take(h).into_iter().collect();
+                entries.sort_by(|a, b| compare(&a.0, &b.0));
+                for (k, mut v) in entries {
+                    v.sort_keys_by(compare);
+                    h.insert(k, v);
+                }
+            }
+            StrictYaml::Array(ref mut v) => {
+                for v in v {
+                    v.sort_keys_by(compare);
+                }
+            }
+            StrictYaml::String(_) | StrictYaml::BadValue => {}
+        }
+    }
+
+    /// Like `sort_keys`, ordering string keys with the Unicode collation
+    /// algorithm and its root collation, which does not depend on any locale:
+    /// e.g. `apple`, `Apple`, `éclair`, `zeta` rather than `Apple`, `apple`,
+    /// `zeta`, `éclair`. Keys the collation finds equal are ordered by `Ord`.
+    #[cfg(feature = "icu_collator")]
+    pub fn sort_keys_collated(&mut self) {
+        let collator = Collator::try_new(&Default::default(), CollatorOptions::new())
+            .expect("the root collation is compiled in");
+        self.sort_keys_by(&mut |a, b| match (a, b) {
+            (StrictYaml::String(a), StrictYaml::String(b)) => {
+                collator.compare(a, b).then_with(|| a.cmp(b))
+            }
+            _ => a.cmp(b),
+        });
+    }
+
+    /// Estimates the heap memory used by this node and its children, in bytes:
+    /// the capacity of strings, sequences and mappings, and the entries of the
+    /// mappings. The size of the node itself is not included.
+    ///
+    /// Allocator overhead is ignored, so the actual usage is somewhat higher.
+    pub fn deep_size_of(&self) -> usize {
+        match *self {
+            StrictYaml::String(ref s) => s.capacity(),
+            StrictYaml::Array(ref v) => {
+                v.capacity() * mem::size_of::<StrictYaml>()
+                    + v.iter().map(StrictYaml::deep_size_of).sum::<usize>()
+            }
+            StrictYaml::Hash(ref h) => {
+                // each entry is a separate allocation holding the links to its
+                // neighbours, and the table holds a key pointer, a node
+                // pointer and a control byte per slot
+                let entry = 2 * mem::size_of::<usize>() + 2 * mem::size_of::<StrictYaml>();
+                let slot = 2 * mem::size_of::<usize>() + 1;
+                h.capacity() * slot
+                    + h.len() * entry
+                    + h.iter()
+                        .map(|(k, v)| k.deep_size_of() + v.deep_size_of())
+                        .sum::<usize>()
+            }
+            StrictYaml::BadValue => 0,
+        }
+    }
 }
 
-#[cfg_attr(feature = "cargo-clippy", allow(should_implement_trait))]
+#[allow(clippy::should_implement_trait)]
 impl StrictYaml {
+    /// A string scalar of `v`. To load a document, use `v.parse()`.
     pub fn from_str(v: &str) -> StrictYaml {
         StrictYaml::String(v.to_owned())
     }
 }
 
+/// Loads a source of exactly one document, failing on an empty source or on
+/// the start of a second document.
+///
+/// ```
+/// use strict_yaml_rust::StrictYaml;
+///
+/// let doc: StrictYaml = "a: 1".parse().unwrap();
+/// assert_eq!(doc["a"].as_str(), Some("1"));
+/// let err = "a: 1\n---\nb: 2".parse::<StrictYaml>().unwrap_err();
+/// assert_eq!(err.to_string(), "expected a single document, found another at line 2 column 1");
+/// ```
+impl str::FromStr for StrictYaml {
+    type Err = ScanError;
+
+    fn from_str(source: &str) -> Result<StrictYaml, ScanError> {
+        let mut recv = SingleDocument {
+            loader: StrictYamlLoader::new(),
+            started: false,
+        };
+        Parser::new(source.chars()).load(&mut recv, true)?;
+        match recv.loader.docs.pop() {
+            Some(doc) => Ok(doc),
+            None => Err(ScanError::parser(
+                Marker::new(0, 1, 0),
+                "expected a single document, found none",
+            )),
+        }
+    }
+}
+
+/// Loads a document, rejecting any after it.
+struct SingleDocument {
+    loader: StrictYamlLoader,
+    started: bool,
+}
+
+impl MarkedEventReceiver for SingleDocument {
+    fn on_event(&mut self, ev: Event, mark: Marker) -> Result<(), ScanError> {
+        if let Event::DocumentStart = ev {
+            if self.started {
+                return Err(ScanError::parser(
+                    mark,
+                    "expected a single document, found another",
+                ));
+            }
+            self.started = true;
+        }
+        self.loader.on_event(ev, mark)
+    }
+}
+
+/// See `StrictYaml::eq_unordered` and `eq_unordered_sequences`.
+fn eq_unordered(a: &StrictYaml, b: &StrictYaml, sequences: bool) -> bool {
+    match (a, b) {
+        (StrictYaml::Array(a), StrictYaml::Array(b)) if sequences => {
+            // each item of `a` takes the first unmatched equal item of `b`
+            let mut matched = vec![false; b.len()];
+            a.len() == b.len()
+                && a.iter().all(|x| {
+                    let found =
+                        (0..b.len()).find(|&i| !matched[i] && eq_unordered(x, &b[i], sequences));
+                    found.map(|i| matched[i] = true).is_some()
+                })
+        }
+        (StrictYaml::Array(a), StrictYaml::Array(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(x, y)| eq_unordered(x, y, sequences))
+        }
+        (StrictYaml::Hash(a), StrictYaml::Hash(b)) => {
+            a.len() == b.len()
+                && a.iter().all(|(k, v)| {
+                    // collection keys are rare, and only equal up to order
+                    let other = match *k {
+                        StrictYaml::String(_) => b.get(k),
+                        _ => b
+                            .iter()
+                            .find(|&(bk, _)| eq_unordered(k, bk, sequences))
+                            .map(|(_, bv)| bv),
+                    };
+                    other.is_some_and(|w| eq_unordered(v, w, sequences))
+                })
+        }
+        _ => a == b,
+    }
+}
+
+/// The unescaped reference tokens of a JSON Pointer, `None` when it is not
+/// empty and does not start with `/`.
+fn pointer_tokens(pointer: &str) -> Option<Vec<String>> {
+    if pointer.is_empty() {
+        return Some(Vec::new());
+    }
+    let tokens = pointer.strip_prefix('/')?.split('/');
+    Some(
+        tokens
+            .map(|t| t.replace("~1", "/").replace("~0", "~"))
+            .collect(),
+    )
+}
+
+/// A JSON Pointer array index: digits, without leading zeros.
+fn pointer_index(token: &str) -> Option<usize> {
+    if token.is_empty()
+        || (token.len() > 1 && token.starts_with('0'))
+        || !token.bytes().all(|b| b.is_ascii_digit())
+    {
+        return None;
+    }
+    token.parse().ok()
+}
+
+/// Parses an integer in decimal notation, see `StrictYaml::as_i64`.
+pub(crate) fn parse_integer(v: &str) -> Option<i64> {
+    let digits = v.strip_prefix(['-', '+']).unwrap_or(v);
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    v.parse().ok()
+}
+
+/// Parses `true` or `false`, see `StrictYaml::as_bool`.
+pub(crate) fn parse_bool(v: &str) -> Option<bool> {
+    match v {
+        "true" => Some(true),
+        "false" => Some(false),
+        _ => None,
+    }
+}
+
+/// Parses a number in decimal notation, see `StrictYaml::as_f64`. Numbers
+/// too large for `f64` are infinite.
+pub(crate) fn parse_decimal(v: &str) -> Option<f64> {
+    let digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+    let unsigned = v.strip_prefix(['-', '+']).unwrap_or(v);
+    let (mantissa, exponent) = match unsigned.find(['e', 'E']) {
+        Some(i) => (&unsigned[..i], Some(&unsigned[i + 1..])),
+        None => (unsigned, None),
+    };
+    let (int, frac) = match mantissa.find('.') {
+        Some(i) => (&mantissa[..i], &mantissa[i + 1..]),
+        None => (mantissa, ""),
+    };
+    let valid_mantissa = digits(int) && digits(frac) && !(int.is_empty() && frac.is_empty());
+    let valid_exponent = exponent.is_none_or(|e| {
+        let e = e.strip_prefix(['-', '+']).unwrap_or(e);
+        !e.is_empty() && digits(e)
+    });
+    if valid_mantissa && valid_exponent {
+        v.parse().ok()
+    } else {
+        None
+    }
+}
+
 static BAD_VALUE: StrictYaml = StrictYaml::BadValue;
 impl<'a> Index<&'a str> for StrictYaml {
     type Output = StrictYaml;
 
-    fn index(&self, idx: &'a str) -> &StrictYaml {
-        let key = StrictYaml::String(idx.to_owned());
-        match self.as_hash() {
-            Some(h) => h.get(&key).unwrap_or(&BAD_VALUE),
-            None => &BAD_VALUE,
-        }
+    fn index(&self, idx: &'a str) -> &StrictYaml {
+        let key = StrictYaml::String(idx.to_owned());
+        match self.as_hash() {
+            Some(h) => h.get(&key).unwrap_or(&BAD_VALUE),
+            None => &BAD_VALUE,
+        }
+    }
+}
+
+impl Index<usize> for StrictYaml {
+    type Output = StrictYaml;
+
+    fn index(&self, idx: usize) -> &StrictYaml {
+        if let Some(v) = self.as_vec() {
+            return v.get(idx).unwrap_or(&BAD_VALUE);
+        }
+        &BAD_VALUE
+    }
+}
+
+/// Indexing a hash by a missing key inserts it with a `BadValue`, and
+/// indexing a `BadValue` turns it into an empty hash first, so that
+/// `doc["server"]["port"] = ...` creates both levels as needed.
+///
+/// # Panics
+///
+/// When indexing a `String` or an `Array`.
+impl<'a> IndexMut<&'a str> for StrictYaml {
+    fn index_mut(&mut self, idx: &'a str) -> &mut StrictYaml {
+        if let StrictYaml::BadValue = *self {
+            *self = StrictYaml::Hash(Hash::new());
+        }
+        match *self {
+            StrictYaml::Hash(ref mut h) => h
+                .entry(StrictYaml::String(idx.to_owned()))
+                .or_insert(StrictYaml::BadValue),
+            _ => panic!("cannot index a non-hash node by key {:?}", idx),
+        }
+    }
+}
+
+/// Indexing an array gives its item in place; arrays are not grown.
+///
+/// # Panics
+///
+/// When `idx` is out of bounds, or when indexing anything but an `Array`.
+impl IndexMut<usize> for StrictYaml {
+    fn index_mut(&mut self, idx: usize) -> &mut StrictYaml {
+        match *self {
+            StrictYaml::Array(ref mut v) => {
+                let len = v.len();
+                v.get_mut(idx)
+                    .unwrap_or_else(|| panic!("index {} out of bounds of an array of {}", idx, len))
+            }
+            _ => panic!("cannot index a non-array node by {}", idx),
+        }
+    }
+}
+
+impl<'a> From<&'a str> for StrictYaml {
+    fn from(v: &'a str) -> StrictYaml {
+        StrictYaml::String(v.to_owned())
+    }
+}
+
+impl From<String> for StrictYaml {
+    fn from(v: String) -> StrictYaml {
+        StrictYaml::String(v)
+    }
+}
+
+/// Scalars of the string a value displays as.
+macro_rules! from_display {
+    ($($t:ty),*) => {
+        $(
+            impl From<$t> for StrictYaml {
+                fn from(v: $t) -> StrictYaml {
+                    StrictYaml::String(v.to_string())
+                }
+            }
+        )*
+    };
+}
+
+from_display!(bool, char, i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+
+/// Finite floats keep a fraction, `1.0` rather than `1`, to read back with
+/// `as_f64`. `NaN`, `inf` and `-inf` are written as such, and do not read
+/// back, as `as_f64` accepts finite numbers only.
+macro_rules! from_float {
+    ($($t:ty),*) => {
+        $(
+            impl From<$t> for StrictYaml {
+                fn from(v: $t) -> StrictYaml {
+                    StrictYaml::String(format!("{:?}", v))
+                }
+            }
+        )*
+    };
+}
+
+from_float!(f32, f64);
+
+impl<T: Into<StrictYaml>> From<Vec<T>> for StrictYaml {
+    fn from(v: Vec<T>) -> StrictYaml {
+        StrictYaml::Array(v.into_iter().map(Into::into).collect())
     }
 }
 
-impl Index<usize> for StrictYaml {
-    type Output = StrictYaml;
+impl From<Hash> for StrictYaml {
+    fn from(v: Hash) -> StrictYaml {
+        StrictYaml::Hash(v)
+    }
+}
 
-    fn index(&self, idx: usize) -> &StrictYaml {
-        if let Some(v) = self.as_vec() {
-            return v.get(idx).unwrap_or(&BAD_VALUE);
-        }
-        &BAD_VALUE
+/// Writes the node as `StrictYamlEmitter::dump` does, starting with `---`.
+///
+/// ```
+/// use strict_yaml_rust::StrictYamlLoader;
+///
+/// let doc = &StrictYamlLoader::load_from_str("a: x\nb:\n  - c").unwrap()[0];
+/// assert_eq!(doc.to_string(), "---\na: x\nb:\n  - c");
+/// ```
+impl fmt::Display for StrictYaml {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        StrictYamlEmitter::new(f).dump(self).map_err(|_| fmt::Error)
     }
 }
 
@@ -267,9 +1582,289 @@ impl Iterator for YamlIter {
     }
 }
 
+/// The nodes below a node with their paths, depth first and in document
+/// order, see `StrictYaml::iter_paths`.
+pub struct PathIter<'a> {
+    /// The nodes to yield, the next one last.
+    stack: Vec<(YamlPath, &'a StrictYaml)>,
+}
+
+impl<'a> PathIter<'a> {
+    fn push_children(&mut self, path: &YamlPath, node: &'a StrictYaml) {
+        match *node {
+            StrictYaml::Array(ref v) => {
+                for (i, item) in v.iter().enumerate().rev() {
+                    self.stack.push((path.child(PathSegment::Index(i)), item));
+                }
+            }
+            StrictYaml::Hash(ref h) => {
+                for (k, v) in h.iter().rev() {
+                    let key = k.as_str().unwrap_or_default().to_owned();
+                    self.stack.push((path.child(PathSegment::Key(key)), v));
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl<'a> Iterator for PathIter<'a> {
+    type Item = (YamlPath, &'a StrictYaml);
+
+    fn next(&mut self) -> Option<(YamlPath, &'a StrictYaml)> {
+        let (path, node) = self.stack.pop()?;
+        self.push_children(&path, node);
+        Some((path, node))
+    }
+}
+
+enum EventFrame<'a> {
+    Sequence(slice::Iter<'a, StrictYaml>),
+    /// Remaining entries, and the value of the entry whose key was just reported.
+    Mapping(
+        linked_hash_map::Iter<'a, StrictYaml, StrictYaml>,
+        Option<&'a StrictYaml>,
+    ),
+}
+
+/// Iterator returned by `StrictYaml::events`.
+pub struct Events<'a> {
+    started: bool,
+    pending: Option<&'a StrictYaml>,
+    stack: Vec<EventFrame<'a>>,
+    ended: bool,
+}
+
+impl<'a> Events<'a> {
+    fn enter(&mut self, node: &'a StrictYaml) -> Event {
+        match *node {
+            StrictYaml::String(ref v) => Event::Scalar(v.clone(), TScalarStyle::Plain, 0),
+            StrictYaml::Array(ref v) => {
+                self.stack.push(EventFrame::Sequence(v.iter()));
+                Event::SequenceStart(0)
+            }
+            StrictYaml::Hash(ref h) => {
+                self.stack.push(EventFrame::Mapping(h.iter(), None));
+                Event::MappingStart(0)
+            }
+            StrictYaml::BadValue => Event::Scalar(String::new(), TScalarStyle::Plain, 0),
+        }
+    }
+}
+
+impl<'a> Iterator for Events<'a> {
+    type Item = Event;
+
+    fn next(&mut self) -> Option<Event> {
+        if !self.started {
+            self.started = true;
+            return Some(Event::DocumentStart);
+        }
+        if let Some(node) = self.pending.take() {
+            return Some(self.enter(node));
+        }
+        let next = match self.stack.last_mut() {
+            None => {
+                if self.ended {
+                    return None;
+                }
+                self.ended = true;
+                return Some(Event::DocumentEnd);
+            }
+            Some(&mut EventFrame::Sequence(ref mut items)) => items.next(),
+            Some(&mut EventFrame::Mapping(ref mut entries, ref mut value)) => match value.take() {
+                Some(v) => Some(v),
+                None => entries.next().map(|(k, v)| {
+                    *value = Some(v);
+                    k
+                }),
+            },
+        };
+        match next {
+            Some(node) => Some(self.enter(node)),
+            None => match self.stack.pop() {
+                Some(EventFrame::Sequence(_)) => Some(Event::SequenceEnd),
+                _ => Some(Event::MappingEnd),
+            },
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
+    use scanner::Origin;
+    use std::env;
+    use std::fs;
+    use std::io;
     use strict_yaml::*;
+
+    #[test]
+    fn test_load_from_file() {
+        let path = env::temp_dir().join(format!("strict-yaml-{}.yaml", std::process::id()));
+        fs::write(&path, "a: b\nc: d: e\n").unwrap();
+        let err = StrictYamlLoader::load_from_file(&path).unwrap_err();
+        assert_eq!(err.path(), path.as_path());
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "{}:2:5: mapping values are not allowed in this context",
+                path.display()
+            )
+        );
+
+        fs::write(&path, "a: b\n").unwrap();
+        let docs = StrictYamlLoader::load_from_file(&path).unwrap();
+        assert_eq!(docs[0]["a"].as_str(), Some("b"));
+
+        fs::remove_file(&path).unwrap();
+        match StrictYamlLoader::load_from_file(&path) {
+            Err(FileError::Io(ref p, ref e)) => {
+                assert_eq!(p, &path);
+                assert_eq!(e.kind(), io::ErrorKind::NotFound);
+            }
+            res => panic!("unexpected result {:?}", res),
+        }
+    }
+
+    #[test]
+    fn test_sort_keys() {
+        let source =
+            "zeta: 1\nZebra: 2\néclair:\n  - b: 3\n    a: 4\neclair: 5\napple: 6\nApple: 7";
+        let keys = |doc: &StrictYaml| -> Vec<String> {
+            let keys = doc.as_hash().unwrap().keys();
+            keys.map(|k| k.as_str().unwrap().to_owned()).collect()
+        };
+        let mut doc = StrictYamlLoader::load_from_str(source).unwrap().remove(0);
+        doc.sort_keys();
+        assert_eq!(
+            keys(&doc),
+            ["Apple", "Zebra", "apple", "eclair", "zeta", "éclair"]
+        );
+        assert_eq!(keys(&doc["éclair"][0]), ["a", "b"]);
+
+        doc.sort_keys_by(&mut |a, b| b.cmp(a));
+        assert_eq!(
+            keys(&doc),
+            ["éclair", "zeta", "eclair", "apple", "Zebra", "Apple"]
+        );
+    }
+
+    #[cfg(feature = "icu_collator")]
+    #[test]
+    fn test_sort_keys_collated() {
+        let source =
+            "zeta: 1\nZebra: 2\néclair:\n  - b: 3\n    a: 4\neclair: 5\napple: 6\nApple: 7";
+        let mut doc = StrictYamlLoader::load_from_str(source).unwrap().remove(0);
+        doc.sort_keys_collated();
+        let keys: Vec<&str> = doc
+            .as_hash()
+            .unwrap()
+            .keys()
+            .map(|k| k.as_str().unwrap())
+            .collect();
+        assert_eq!(
+            keys,
+            ["apple", "Apple", "eclair", "éclair", "Zebra", "zeta"]
+        );
+        assert_eq!(
+            doc["éclair"][0].as_hash().unwrap().keys().next(),
+            Some(&StrictYaml::from_str("a"))
+        );
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn test_tracing() {
+        use std::fmt;
+        use std::sync::{Arc, Mutex};
+        use tracing::field::{Field, Visit};
+        use tracing::span::{Attributes, Id, Record};
+        use tracing::{Event, Metadata, Subscriber};
+
+        /// Collects the messages of the events, and the names of the spans.
+        struct Collect(Arc<Mutex<Vec<String>>>);
+
+        impl Visit for Collect {
+            fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+                if field.name() == "message" {
+                    self.0.lock().unwrap().push(format!("{:?}", value));
+                }
+            }
+        }
+
+        impl Subscriber for Collect {
+            fn enabled(&self, metadata: &Metadata) -> bool {
+                metadata.is_span() || *metadata.level() <= tracing::Level::DEBUG
+            }
+            fn new_span(&self, span: &Attributes) -> Id {
+                self.0
+                    .lock()
+                    .unwrap()
+                    .push(format!("[{}]", span.metadata().name()));
+                Id::from_u64(1)
+            }
+            fn record(&self, _: &Id, _: &Record) {}
+            fn record_follows_from(&self, _: &Id, _: &Id) {}
+            fn event(&self, event: &Event) {
+                event.record(&mut Collect(self.0.clone()));
+            }
+            fn enter(&self, _: &Id) {}
+            fn exit(&self, _: &Id) {}
+        }
+
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let options = LoadOptions::new().expand_aliases(1);
+        tracing::subscriber::with_default(Collect(seen.clone()), || {
+            StrictYamlLoader::load_from_str_with_options("a: b\n---\nc: [d", &options).unwrap();
+            StrictYamlLoader::load_from_str_with_options("a: &x\n  - b\nc: *x", &options)
+                .unwrap_err();
+        });
+        assert_eq!(
+            *seen.lock().unwrap(),
+            [
+                "[load]",
+                "[document]",
+                "document start",
+                "document end",
+                "[document]",
+                "document start",
+                "document end",
+                "[load]",
+                "[document]",
+                "document start",
+                "alias budget exceeded",
+                "parse error",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_deep_size_of() {
+        let word = mem::size_of::<usize>();
+        let node = mem::size_of::<StrictYaml>();
+        assert_eq!(StrictYaml::BadValue.deep_size_of(), 0);
+        assert_eq!(
+            StrictYaml::String(String::with_capacity(10)).deep_size_of(),
+            10
+        );
+
+        let mut v = Vec::with_capacity(4);
+        v.push(StrictYaml::from_str("abc"));
+        assert_eq!(StrictYaml::Array(v).deep_size_of(), 4 * node + 3);
+
+        let mut h = Hash::new();
+        h.insert(StrictYaml::from_str("ab"), StrictYaml::from_str("cde"));
+        let table = h.capacity() * (2 * word + 1);
+        assert_eq!(
+            StrictYaml::Hash(h).deep_size_of(),
+            table + 2 * word + 2 * node + 5
+        );
+
+        let small = &StrictYamlLoader::load_from_str("a: b").unwrap()[0];
+        let large = &StrictYamlLoader::load_from_str("a: b\nc:\n  - d\n  - e").unwrap()[0];
+        assert!(large.deep_size_of() > small.deep_size_of());
+    }
+
     #[test]
     fn test_coerce() {
         let s = "---
@@ -277,12 +1872,373 @@ a: 1
 b: 2.2
 c: [1, 2]
 ";
-        let out = StrictYamlLoader::load_from_str(&s).unwrap();
+        let out = StrictYamlLoader::load_from_str(s).unwrap();
         let doc = &out[0];
         assert_eq!(doc["a"].as_str().unwrap(), "1");
         assert_eq!(doc["b"].as_str().unwrap(), "2.2");
         assert_eq!(doc["c"].as_str().unwrap(), "[1, 2]");
         assert!(doc["d"][0].is_badvalue());
+        assert_eq!(doc["a"].as_parsed::<i32>(), Some(1));
+        assert_eq!(doc["b"].as_parsed::<f64>(), Some(2.2));
+        assert_eq!(doc["b"].as_parsed::<i32>(), None);
+        assert_eq!(doc.as_parsed::<String>(), None);
+    }
+
+    #[test]
+    fn test_strict_accessors() {
+        let s = |v: &str| StrictYaml::String(v.to_owned());
+        assert_eq!(s("42").as_i64(), Some(42));
+        assert_eq!(s("-7").as_i64(), Some(-7));
+        assert_eq!(s("+7").as_i64(), Some(7));
+        for v in [
+            "",
+            "-",
+            " 1",
+            "1_000",
+            "0x1f",
+            "1.0",
+            "99999999999999999999",
+        ] {
+            assert_eq!(s(v).as_i64(), None, "{:?}", v);
+        }
+        assert_eq!(s("1.5").as_f64(), Some(1.5));
+        assert_eq!(s("-.5").as_f64(), Some(-0.5));
+        assert_eq!(s("3.").as_f64(), Some(3.0));
+        assert_eq!(s("2e-1").as_f64(), Some(0.2));
+        assert_eq!(s("12").as_f64(), Some(12.0));
+        assert_eq!(s("1e-400").as_f64(), Some(0.0));
+        for v in [
+            "", ".", "e5", "1e", "inf", ".inf", "NaN", "1_0.0", " 1", "1e400", "-1e400",
+        ] {
+            assert_eq!(s(v).as_f64(), None, "{:?}", v);
+        }
+        assert_eq!(s("true").as_bool(), Some(true));
+        assert_eq!(s("false").as_bool(), Some(false));
+        for v in ["True", "yes", "no", "on", "off", "y", "1"] {
+            assert_eq!(s(v).as_bool(), None, "{:?}", v);
+        }
+        assert_eq!(StrictYaml::Array(vec![]).as_i64(), None);
+    }
+
+    #[test]
+    fn test_get_path() {
+        let source = "a.b:\n  c: 1\nlist:\n  - x: 2\n'*': 3\n";
+        let doc = &StrictYamlLoader::load_from_str(source).unwrap()[0];
+        let get = |p: &str| doc.get_path(p).and_then(StrictYaml::as_str);
+        assert_eq!(get("a\\.b.c"), Some("1"));
+        assert_eq!(get("list[0].x"), Some("2"));
+        assert_eq!(get("*"), Some("3"));
+        assert_eq!(doc.get_path(""), Some(doc));
+        for p in [
+            "a.b.c",
+            "list[1].x",
+            "list.x",
+            "list[0][0]",
+            "a\\.b.c.d",
+            "list[*]",
+            "a..b",
+        ] {
+            assert_eq!(doc.get_path(p), None, "{}", p);
+        }
+    }
+
+    #[test]
+    fn test_set_and_remove_path() {
+        let source = "a: 1\nb: 2\nlist:\n  - x: 3\n";
+        let mut doc = StrictYamlLoader::load_from_str(source).unwrap().remove(0);
+        let s = |v: &str| StrictYaml::String(v.to_owned());
+        assert_eq!(doc.set_path("a", s("4")), Ok(Some(s("1"))));
+        assert_eq!(doc.set_path("list[0].x", s("5")), Ok(Some(s("3"))));
+        assert_eq!(doc.set_path("list[0].y", s("6")), Ok(None));
+        assert_eq!(doc.set_path("c.d.e", s("7")), Ok(None));
+        let keys: Vec<_> = doc.as_hash().unwrap().keys().cloned().collect();
+        assert_eq!(keys, [s("a"), s("b"), s("list"), s("c")]);
+        assert_eq!(doc.get_path("c.d.e"), Some(&s("7")));
+        assert_eq!(doc.get_path("list[0].y"), Some(&s("6")));
+
+        let err = |path: &str| doc.clone().set_path(path, s("x")).unwrap_err().to_string();
+        assert_eq!(err("list[1]"), "no node at list[1]");
+        assert_eq!(err("list[1].x"), "no node at list[1]");
+        assert_eq!(err("a.b"), "no node at a.b");
+        assert_eq!(err("new[0]"), "no node at new");
+        assert_eq!(err("a..b"), "empty key in path `a..b`");
+        assert!(doc.get_path("new").is_none());
+        let before = doc.clone();
+        assert!(doc.set_path("q.r[0]", s("x")).is_err());
+        assert!(doc.set_path("c.d.f.g[0]", s("x")).is_err());
+        assert_eq!(doc, before);
+
+        assert_eq!(doc.remove_path("list[0].x"), Some(s("5")));
+        assert_eq!(doc.remove_path("list[0].x"), None);
+        assert_eq!(doc.remove_path("b"), Some(s("2")));
+        assert_eq!(doc.remove_path("a.b"), None);
+        assert_eq!(doc.remove_path(""), None);
+        assert!(doc.remove_path("list[0]").is_some());
+        assert_eq!(doc.get_path("list"), Some(&StrictYaml::Array(vec![])));
+
+        let old = doc.clone();
+        assert_eq!(doc.set_path("", s("root")), Ok(Some(old)));
+        assert_eq!(doc, s("root"));
+    }
+
+    #[test]
+    fn test_pointer() {
+        let source = "a:\n  - x\n  - y\n'':\n  '': e\nm~n/o: p\n'0': z\n";
+        let mut doc = StrictYamlLoader::load_from_str(source).unwrap().remove(0);
+        let get = |doc: &StrictYaml, p: &str| {
+            doc.pointer(p)
+                .and_then(StrictYaml::as_str)
+                .map(str::to_owned)
+        };
+        assert_eq!(doc.pointer(""), Some(&doc));
+        assert_eq!(get(&doc, "/a/1").as_deref(), Some("y"));
+        assert_eq!(get(&doc, "/m~0n~1o").as_deref(), Some("p"));
+        assert_eq!(get(&doc, "//").as_deref(), Some("e"));
+        assert_eq!(get(&doc, "/0").as_deref(), Some("z"));
+        for p in ["a", "/a/2", "/a/01", "/a/+1", "/a/-", "/a/0/b", "/b"] {
+            assert!(doc.pointer(p).is_none(), "{}", p);
+        }
+
+        *doc.pointer_mut("/a/0").unwrap() = StrictYaml::String("w".into());
+        assert_eq!(get(&doc, "/a/0").as_deref(), Some("w"));
+        assert!(doc.pointer_mut("/a/9").is_none());
+    }
+
+    #[test]
+    fn test_strict_yaml_macro() {
+        let nested = strict_yaml!(["x"]);
+        let doc = strict_yaml!({
+            "a": 1,
+            "b": (-2.5),
+            ("c".to_owned()): [true, 'd'],
+            "e": nested,
+            "f": (vec![1u8, 2]),
+        });
+        let expected = StrictYamlLoader::load_from_str(
+            "a: 1\nb: -2.5\nc:\n  - true\n  - d\ne:\n  - x\nf:\n  - 1\n  - 2",
+        )
+        .unwrap()
+        .remove(0);
+        assert_eq!(doc, expected);
+        assert_eq!(strict_yaml!([]), StrictYaml::Array(vec![]));
+        assert_eq!(strict_yaml!({}), StrictYaml::Hash(Hash::new()));
+        assert_eq!(strict_yaml!("s"), StrictYaml::String("s".into()));
+        assert_eq!(strict_yaml!(1.0).as_f64(), Some(1.0));
+        assert_eq!(StrictYaml::from(1e300).as_f64(), Some(1e300));
+        assert_eq!(StrictYaml::from(f64::NAN).as_str(), Some("NaN"));
+        assert_eq!(StrictYaml::from(f64::NAN).as_f64(), None);
+        assert_eq!(StrictYaml::from(f32::INFINITY).as_str(), Some("inf"));
+        assert_eq!(StrictYaml::from(f64::NEG_INFINITY).as_f64(), None);
+    }
+
+    #[test]
+    fn test_index_mut() {
+        let mut doc = StrictYamlLoader::load_from_str("server:\n  host: a\nlist:\n  - x")
+            .unwrap()
+            .remove(0);
+        doc["server"]["host"] = StrictYaml::String("b".into());
+        doc["server"]["port"] = StrictYaml::String("8080".into());
+        doc["tls"]["cert"] = StrictYaml::String("c.pem".into());
+        doc["list"][0] = StrictYaml::String("y".into());
+        assert_eq!(doc["server"]["host"].as_str(), Some("b"));
+        assert_eq!(doc["server"]["port"].as_str(), Some("8080"));
+        assert_eq!(doc["tls"]["cert"].as_str(), Some("c.pem"));
+        assert_eq!(doc["list"][0].as_str(), Some("y"));
+        let keys: Vec<_> = doc["server"]
+            .as_hash()
+            .unwrap()
+            .keys()
+            .map(|k| k.as_str().unwrap())
+            .collect();
+        assert_eq!(keys, ["host", "port"]);
+    }
+
+    #[test]
+    #[should_panic(expected = "out of bounds")]
+    fn test_index_mut_out_of_bounds() {
+        let mut doc = StrictYaml::Array(vec![]);
+        doc[0] = StrictYaml::String("x".into());
+    }
+
+    #[test]
+    #[should_panic(expected = "non-hash")]
+    fn test_index_mut_scalar() {
+        let mut doc = StrictYaml::String("x".into());
+        doc["a"] = StrictYaml::String("y".into());
+    }
+
+    #[test]
+    fn test_parse_single_document() {
+        let doc: StrictYaml = "---\na: 1\n...\n".parse().unwrap();
+        assert_eq!(doc, strict_yaml!({"a": "1"}));
+        assert_eq!("---".parse::<StrictYaml>(), Ok(strict_yaml!("")));
+        for source in ["", "# only a comment\n"] {
+            let err = source.parse::<StrictYaml>().unwrap_err();
+            assert_eq!(err.info(), "expected a single document, found none");
+        }
+        let err = "a\n---\nb\n---\nc".parse::<StrictYaml>().unwrap_err();
+        assert_eq!((err.marker().line(), err.marker().col()), (2, 0));
+        assert_eq!(LoadError::from(err).code(), code::PARSE);
+    }
+
+    #[test]
+    fn test_get_by_key() {
+        let doc: StrictYaml = "a: x\nb:\n  c: y\nd:\n  - z".parse().unwrap();
+        assert_eq!(doc.get_str("a"), Some("x"));
+        assert_eq!(doc.get_hash("b").unwrap().len(), 1);
+        assert_eq!(doc.get_vec("d"), Some(&vec![strict_yaml!("z")]));
+        assert_eq!(doc.get_str("b"), None);
+        assert_eq!(doc.get_vec("a"), None);
+        assert_eq!(doc.get_hash("missing"), None);
+        assert_eq!(doc["d"].get_str("a"), None);
+    }
+
+    #[test]
+    fn test_array_mutation() {
+        let mut doc = strict_yaml!(["b"]);
+        doc.push("c").unwrap();
+        doc.insert(0, "a").unwrap();
+        doc.insert(3, strict_yaml!({"d": "e"})).unwrap();
+        doc.extend(Vec::<StrictYaml>::new()).unwrap();
+        assert_eq!(doc, strict_yaml!(["a", "b", "c", {"d": "e"}]));
+        let err = doc.insert(5, "x").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "index 5 is past the end of a sequence of 4"
+        );
+        assert_eq!(doc.pop(), Some(strict_yaml!({"d": "e"})));
+
+        let mut scalar = strict_yaml!("s");
+        assert_eq!(
+            scalar.push("x").unwrap_err().to_string(),
+            "the node is not a sequence"
+        );
+        assert!(scalar.extend(vec!["x"]).is_err());
+        assert_eq!(scalar.pop(), None);
+        assert_eq!(scalar, strict_yaml!("s"));
+        assert_eq!(strict_yaml!([]).pop(), None);
+    }
+
+    #[test]
+    fn test_remove_key_and_index() {
+        let mut doc = strict_yaml!({"a": "1", "b": ["x", "y", "z"], "c": "3"});
+        assert_eq!(doc.remove_key("a"), Some(strict_yaml!("1")));
+        assert_eq!(doc.remove_key("a"), None);
+        assert_eq!(doc["b"].remove_index(1), Some(strict_yaml!("y")));
+        assert_eq!(doc["b"].remove_index(2), None);
+        assert_eq!(doc["c"].remove_key("a"), None);
+        assert_eq!(doc["c"].remove_index(0), None);
+        assert_eq!(doc, strict_yaml!({"b": ["x", "z"], "c": "3"}));
+    }
+
+    #[test]
+    fn test_retain() {
+        let mut doc = strict_yaml!({"a": ["1", "22", "3"], "x-b": "4", "c": "5"});
+        doc.retain_entries(|k, v| {
+            v.retain_items(|item| item.as_str().is_some_and(|s| s.len() == 1));
+            k.as_str() != Some("x-b")
+        });
+        assert_eq!(doc, strict_yaml!({"a": ["1", "3"], "c": "5"}));
+        doc["a"].retain_entries(|_, _| false);
+        doc["c"].retain_items(|_| false);
+        assert_eq!(doc, strict_yaml!({"a": ["1", "3"], "c": "5"}));
+    }
+
+    #[test]
+    fn test_iter_paths() {
+        let doc: StrictYaml = "a: x\nb:\n  - c: y\n    d:\n      - z\n  - w\ne.f: v"
+            .parse()
+            .unwrap();
+        let leaves: Vec<(String, &str)> = doc
+            .iter_paths()
+            .filter_map(|(path, node)| node.as_str().map(|s| (path.to_string(), s)))
+            .collect();
+        assert_eq!(
+            leaves,
+            [
+                ("a".to_owned(), "x"),
+                ("b[0].c".to_owned(), "y"),
+                ("b[0].d[0]".to_owned(), "z"),
+                ("b[1]".to_owned(), "w"),
+                ("e\\.f".to_owned(), "v"),
+            ]
+        );
+        assert_eq!(doc.iter_paths().count(), 8);
+        for (path, node) in doc.iter_paths() {
+            assert_eq!(doc.get(&path), Some(node));
+        }
+        assert_eq!(strict_yaml!("s").iter_paths().count(), 0);
+    }
+
+    #[test]
+    fn test_map_scalars_and_keys() {
+        let doc = strict_yaml!({"A": "x", "B": ["y", {"C": "z"}]});
+        let mut paths = Vec::new();
+        let upper = doc.map_scalars(|path, s| {
+            paths.push(path.to_string());
+            s.to_uppercase()
+        });
+        assert_eq!(upper, strict_yaml!({"A": "X", "B": ["Y", {"C": "Z"}]}));
+        assert_eq!(paths, ["A", "B[0]", "B[1].C"]);
+
+        let mut paths = Vec::new();
+        let lower = doc.map_keys(|path, k| {
+            paths.push(path.to_string());
+            k.to_lowercase()
+        });
+        assert_eq!(lower, strict_yaml!({"a": "x", "b": ["y", {"c": "z"}]}));
+        assert_eq!(paths, ["A", "B", "B[1].C"]);
+        assert_eq!(doc, strict_yaml!({"A": "x", "B": ["y", {"C": "z"}]}));
+
+        let merged = strict_yaml!({"a": "1", "A": "2"}).map_keys(|_, k| k.to_lowercase());
+        assert_eq!(merged, strict_yaml!({"a": "2"}));
+    }
+
+    #[test]
+    fn test_content_hash() {
+        let doc = strict_yaml!({"b": "x", "a": " "});
+        assert_eq!(
+            doc.content_hash(),
+            strict_yaml!({"a": "", "b": "x"}).content_hash()
+        );
+        assert_ne!(
+            doc.content_hash(),
+            strict_yaml!({"a": "", "b": "y"}).content_hash()
+        );
+        // the hash of "---\na: \"\"\nb: \"x\"" must not change across releases
+        assert_eq!(
+            doc.content_hash(),
+            0xd18b_60e5_cbaa_f67b_0df6_15a4_758a_b785
+        );
+    }
+
+    #[test]
+    fn test_eq_unordered() {
+        let a = strict_yaml!({"a": ["x", "y"], "b": {"c": "1", "d": "2"}});
+        let b = strict_yaml!({"b": {"d": "2", "c": "1"}, "a": ["x", "y"]});
+        assert_ne!(a, b);
+        assert!(a.eq_unordered(&b));
+        assert!(!a.eq_unordered(&strict_yaml!({"a": ["x", "y"], "b": {"c": "1"}})));
+
+        let c = strict_yaml!({"a": ["y", "x"], "b": {"c": "1", "d": "2"}});
+        assert!(!a.eq_unordered(&c));
+        assert!(a.eq_unordered_sequences(&c));
+        let d = strict_yaml!(["x", "x", "y"]);
+        assert!(!d.eq_unordered_sequences(&strict_yaml!(["x", "y", "y"])));
+        assert!(d.eq_unordered_sequences(&strict_yaml!(["y", "x", "x"])));
+        assert!(!d.eq_unordered_sequences(&strict_yaml!({"x": "y"})));
+    }
+
+    #[test]
+    fn test_take_and_replace() {
+        let mut doc = strict_yaml!({"a": ["x", "y"], "b": "z"});
+        let a = doc["a"].take();
+        assert_eq!(a, strict_yaml!(["x", "y"]));
+        assert_eq!(doc["a"], StrictYaml::BadValue);
+        assert_eq!(doc["b"].replace(strict_yaml!("w")), strict_yaml!("z"));
+        assert_eq!(doc, strict_yaml!({"a": (StrictYaml::BadValue), "b": "w"}));
+        assert_eq!(doc.take()["b"].as_str(), Some("w"));
+        assert!(doc.is_badvalue());
     }
 
     #[test]
@@ -329,7 +2285,7 @@ a7: 你好
 ---
 'a scalar'
 ";
-        let out = StrictYamlLoader::load_from_str(&s).unwrap();
+        let out = StrictYamlLoader::load_from_str(s).unwrap();
         assert_eq!(out.len(), 3);
     }
 
@@ -364,7 +2320,7 @@ a7: 你好
 - +12345
 - [ true, false ]
 ";
-        let out = StrictYamlLoader::load_from_str(&s).unwrap();
+        let out = StrictYamlLoader::load_from_str(s).unwrap();
         let doc = &out[0];
 
         assert_eq!(doc[0].as_str().unwrap(), "string");
@@ -436,7 +2392,7 @@ a7: 你好
 - .NAN
 - !!float .INF
 ";
-        let mut out = StrictYamlLoader::load_from_str(&s).unwrap().into_iter();
+        let mut out = StrictYamlLoader::load_from_str(s).unwrap().into_iter();
         let mut doc = out.next().unwrap().into_iter();
 
         assert_eq!(doc.next().unwrap().into_string().unwrap(), "string");
@@ -468,7 +2424,7 @@ b: ~
 a: ~
 c: ~
 ";
-        let out = StrictYamlLoader::load_from_str(&s).unwrap();
+        let out = StrictYamlLoader::load_from_str(s).unwrap();
         let first = out.into_iter().next().unwrap();
         let mut iter = first.into_hash().unwrap().into_iter();
         assert_eq!(
@@ -495,14 +2451,202 @@ c: ~
         assert_eq!(None, iter.next());
     }
 
+    #[test]
+    fn test_iter_documents() {
+        let s = "
+a: 1
+---
+- b
+- c
+---
+'d'
+";
+        let docs: Vec<_> = StrictYamlLoader::iter_documents(s)
+            .map(|d| d.unwrap())
+            .collect();
+        assert_eq!(docs, StrictYamlLoader::load_from_str(s).unwrap());
+        assert!(StrictYamlLoader::iter_documents("").next().is_none());
+    }
+
+    /// Reads at most three bytes at a time, then fails if `fail` is set.
+    struct Trickle {
+        data: &'static [u8],
+        fail: bool,
+    }
+
+    impl io::Read for Trickle {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.data.is_empty() && self.fail {
+                return Err(io::Error::new(io::ErrorKind::BrokenPipe, "gone"));
+            }
+            let n = self.data.len().min(buf.len()).min(3);
+            buf[..n].copy_from_slice(&self.data[..n]);
+            self.data = &self.data[n..];
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn test_load_from_reader() {
+        let s = "a: 1\nb:\n  - x\n  - 你好 🦀\n---\nc\n";
+        let reader = Trickle {
+            data: s.as_bytes(),
+            fail: false,
+        };
+        let docs = StrictYamlLoader::load_from_reader(reader).unwrap();
+        assert_eq!(docs, StrictYamlLoader::load_from_str(s).unwrap());
+
+        let reader = Trickle {
+            data: b"a: b\nc: d: e\n",
+            fail: false,
+        };
+        let err = StrictYamlLoader::load_from_reader(reader).unwrap_err();
+        assert!(matches!(err, LoadError::Scan(_)));
+        assert_eq!(
+            err.to_string(),
+            "mapping values are not allowed in this context at line 2 column 5"
+        );
+
+        let reader = Trickle {
+            data: b"a: b\nc: \xe4\xbd\x20\n",
+            fail: false,
+        };
+        let err = StrictYamlLoader::load_from_reader(reader).unwrap_err();
+        let err = err.scan_error().unwrap();
+        let seq = err.invalid_sequence().unwrap();
+        assert_eq!((seq.offset(), seq.bytes()), (8, &[0xe4, 0xbd][..]));
+        assert_eq!((err.marker().line(), err.marker().col()), (2, 3));
+
+        let reader = Trickle {
+            data: b"a: b\nc: d\xe4",
+            fail: false,
+        };
+        let err = StrictYamlLoader::load_from_reader(reader).unwrap_err();
+        assert!(err.scan_error().unwrap().invalid_sequence().is_some());
+
+        let reader = Trickle {
+            data: b"a: b\nc: d",
+            fail: true,
+        };
+        match StrictYamlLoader::load_from_reader(reader).unwrap_err() {
+            LoadError::Io { error, marker } => {
+                assert_eq!(error.kind(), io::ErrorKind::BrokenPipe);
+                assert_eq!((marker.line(), marker.col()), (2, 4));
+            }
+            e => panic!("unexpected error: {}", e),
+        }
+    }
+
+    #[test]
+    fn test_load_from_str_per_document() {
+        let docs = StrictYamlLoader::load_from_str_per_document(
+            "- a\n- b: c: d\n---\n\"e\n---\nf\n...\n--- g: h: i",
+        );
+        let docs: Vec<_> = docs
+            .into_iter()
+            .map(|d| d.map_err(|e| e.to_string()))
+            .collect();
+        assert_eq!(
+            docs,
+            vec![
+                Err("mapping values are not allowed in this context at line 2 column 7".to_owned()),
+                Err("while scanning a quoted scalar, found unexpected document indicator at line 4 column 1"
+                    .to_owned()),
+                Ok(StrictYaml::from_str("f")),
+                Err("mapping values are not allowed in this context at line 8 column 6".to_owned()),
+            ]
+        );
+        assert!(StrictYamlLoader::load_from_str_per_document("").is_empty());
+    }
+
+    #[test]
+    fn test_iter_documents_recovers() {
+        let s = "---
+a: 1
+a: 2
+---
+key: \"unterminated
+---
+b: 3
+---
+c: [4
+ d: 5
+---
+e: 6
+";
+        let docs: Vec<_> = StrictYamlLoader::iter_documents(s).collect();
+        assert_eq!(docs.len(), 5);
+        assert!(docs[0].is_err());
+        assert_eq!(docs[1].as_ref().unwrap_err().marker().line(), 5);
+        assert_eq!(docs[2].as_ref().unwrap()["b"].as_str(), Some("3"));
+        assert!(docs[3].is_err());
+        assert_eq!(docs[4].as_ref().unwrap()["e"].as_str(), Some("6"));
+    }
+
     #[test]
     fn test_duplicate_keys() {
         let s = "
 a: 10
 a: 15
 ";
-        let out = StrictYamlLoader::load_from_str(&s);
+        let out = StrictYamlLoader::load_from_str(s);
         assert!(out.is_err());
         //assert_eq!(out.err(), Actual error type);
     }
+
+    #[test]
+    fn test_max_depth() {
+        let nested = |depth: usize| format!("{}x", "- ".repeat(depth));
+        assert!(StrictYamlLoader::load_from_str(&nested(256)).is_ok());
+        let err = StrictYamlLoader::load_from_str(&nested(100_000)).unwrap_err();
+        assert_eq!(err.origin(), Origin::Limit);
+
+        let options = LoadOptions::new().max_depth(3);
+        let load = |source: &str| {
+            StrictYamlLoader::load_from_str_with_options(source, &options)
+                .map_err(|e| e.to_string())
+        };
+        assert!(load("a:\n  - b: [c]").is_ok());
+        assert_eq!(
+            load("a:\n  - b:\n      - c"),
+            Err("collections nested deeper than 3 levels at line 3 column 7".to_owned())
+        );
+        let options = LoadOptions::new().max_depth(1000);
+        let docs = StrictYamlLoader::load_from_str_with_options(&nested(1000), &options).unwrap();
+        assert!(docs[0].is_array());
+    }
+
+    #[test]
+    fn test_display() {
+        let doc = strict_yaml!({"a": "x: y", "b": ["1", "|"]});
+        assert_eq!(
+            format!("{}", doc),
+            "---\na: \"x: y\"\nb:\n  - \"1\"\n  - \"|\""
+        );
+        assert_eq!(StrictYaml::String("s".into()).to_string(), "---\ns");
+    }
+
+    #[test]
+    fn test_events_round_trip() {
+        let s = "
+a: 1
+b:
+  - x
+  - c: 2
+    d: []
+  - - y
+  -
+e: |
+  text
+";
+        let doc = &StrictYamlLoader::load_from_str(s).unwrap()[0];
+        let mut loader = StrictYamlLoader::new();
+        for ev in doc.events() {
+            loader.on_event(ev, Marker::new(0, 1, 0)).unwrap();
+        }
+        assert_eq!(loader.docs, vec![doc.clone()]);
+
+        let events: Vec<Event> = StrictYaml::BadValue.events().collect();
+        assert_eq!(events, vec![Event::DocumentStart, Event::DocumentEnd]);
+    }
 }
@@ -0,0 +1,419 @@
+//! Assertions for tests comparing documents.
+//!
+//! Documents are compared semantically: the order of mapping entries does not
+//! matter. On failure, the differences are listed with their paths, instead of
+//! the debug output of both documents.
+//!
+//! ```
+//! #[macro_use]
+//! extern crate strict_yaml_rust;
+//!
+//! use strict_yaml_rust::StrictYamlLoader;
+//!
+//! # fn main() {
+//! let left = &StrictYamlLoader::load_from_str("a: 1\nb: [x, y]").unwrap()[0];
+//! let right = &StrictYamlLoader::load_from_str("b: [x, y]\na: 1").unwrap()[0];
+//! assert_strict_yaml_eq!(left, right);
+//! # }
+//! ```
+//!
+//! Golden files hold the expected output of a test as a fixture, see
+//! `assert_golden`. `verify_roundtrip` checks that a document survives being
+//! emitted and loaded again.
+
+use emitter::{EmitError, StrictYamlEmitter};
+use options::LoadOptions;
+use path::{PathSegment, YamlPath};
+use scanner::ScanError;
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use strict_yaml::{StrictYaml, StrictYamlLoader};
+
+/// Environment variable that makes `assert_golden` rewrite the fixtures
+/// instead of comparing against them, when set to anything but `0`.
+pub const UPDATE_GOLDEN_ENV: &str = "STRICT_YAML_UPDATE_GOLDEN";
+
+/// A difference between two documents, found by `diff`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Difference {
+    /// The nodes at the path differ, as left and right nodes.
+    Changed(YamlPath, StrictYaml, StrictYaml),
+    /// The node at the path only exists in the left document.
+    OnlyLeft(YamlPath, StrictYaml),
+    /// The node at the path only exists in the right document.
+    OnlyRight(YamlPath, StrictYaml),
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Difference::Changed(ref path, ref left, ref right) => {
+                write!(f, "{}: {} != {}", Root(path), Summary(left), Summary(right))
+            }
+            Difference::OnlyLeft(ref path, ref node) => {
+                write!(f, "{}: only in left: {}", Root(path), Summary(node))
+            }
+            Difference::OnlyRight(ref path, ref node) => {
+                write!(f, "{}: only in right: {}", Root(path), Summary(node))
+            }
+        }
+    }
+}
+
+/// Displays the root path as `(root)` rather than as nothing.
+struct Root<'a>(&'a YamlPath);
+
+impl<'a> fmt::Display for Root<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.0.is_empty() {
+            f.write_str("(root)")
+        } else {
+            write!(f, "{}", self.0)
+        }
+    }
+}
+
+/// A one line description of a node.
+struct Summary<'a>(&'a StrictYaml);
+
+impl<'a> fmt::Display for Summary<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self.0 {
+            StrictYaml::String(ref s) => write!(f, "{:?}", s),
+            StrictYaml::Array(ref v) => write!(f, "sequence of {} items", v.len()),
+            StrictYaml::Hash(ref h) => write!(f, "mapping of {} entries", h.len()),
+            StrictYaml::BadValue => f.write_str("no value"),
+        }
+    }
+}
+
+/// Returns the differences between `left` and `right`, ignoring the order of
+/// mapping entries, in document order.
+pub fn diff(left: &StrictYaml, right: &StrictYaml) -> Vec<Difference> {
+    let mut differences = Vec::new();
+    diff_node(&mut YamlPath::new(), left, right, &mut differences);
+    differences
+}
+
+fn diff_node(
+    path: &mut YamlPath,
+    left: &StrictYaml,
+    right: &StrictYaml,
+    differences: &mut Vec<Difference>,
+) {
+    match (left, right) {
+        (StrictYaml::Array(l), StrictYaml::Array(r)) => {
+            for i in 0..l.len().max(r.len()) {
+                path.push(PathSegment::Index(i));
+                match (l.get(i), r.get(i)) {
+                    (Some(l), Some(r)) => diff_node(path, l, r, differences),
+                    (Some(l), None) => {
+                        differences.push(Difference::OnlyLeft(path.clone(), l.clone()))
+                    }
+                    (None, Some(r)) => {
+                        differences.push(Difference::OnlyRight(path.clone(), r.clone()))
+                    }
+                    (None, None) => unreachable!(),
+                }
+                path.pop();
+            }
+        }
+        (StrictYaml::Hash(l), StrictYaml::Hash(r)) => {
+            for (k, lv) in l {
+                path.push(key_segment(k));
+                match r.get(k) {
+                    Some(rv) => diff_node(path, lv, rv, differences),
+                    None => differences.push(Difference::OnlyLeft(path.clone(), lv.clone())),
+                }
+                path.pop();
+            }
+            for (k, rv) in r.iter().filter(|&(k, _)| !l.contains_key(k)) {
+                let path = path.child(key_segment(k));
+                differences.push(Difference::OnlyRight(path, rv.clone()));
+            }
+        }
+        _ if left == right => {}
+        _ => differences.push(Difference::Changed(
+            path.clone(),
+            left.clone(),
+            right.clone(),
+        )),
+    }
+}
+
+/// The path segment of a mapping key; keys that are not scalars are shown as
+/// their debug output.
+fn key_segment(key: &StrictYaml) -> PathSegment {
+    match *key {
+        StrictYaml::String(ref s) => PathSegment::Key(s.clone()),
+        ref key => PathSegment::Key(format!("{:?}", key)),
+    }
+}
+
+/// Why a document did not survive a round trip, see `verify_roundtrip`.
+#[derive(Clone, Debug)]
+pub enum RoundtripIssue {
+    /// The document could not be emitted.
+    Emit(EmitError),
+    /// The emitted text, given first, could not be loaded.
+    Load(String, ScanError),
+    /// The emitted text, given first, loaded as this number of documents
+    /// instead of one.
+    DocumentCount(String, usize),
+    /// The first difference between the document and the one loaded back.
+    Diverged(Box<Difference>),
+}
+
+impl Error for RoundtripIssue {}
+
+impl fmt::Display for RoundtripIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RoundtripIssue::Emit(ref e) => write!(f, "cannot emit the document: {}", e),
+            RoundtripIssue::Load(ref text, ref e) => {
+                write!(f, "cannot load the emitted document: {}\n{}", e, text)
+            }
+            RoundtripIssue::DocumentCount(ref text, n) => {
+                write!(f, "the emitted document loads as {} documents\n{}", n, text)
+            }
+            RoundtripIssue::Diverged(ref d) => write!(f, "changed by a round trip: {}", d),
+        }
+    }
+}
+
+/// Emits `doc`, loads the output and compares the result with `doc`, see
+/// `diff`.
+///
+/// ```
+/// use strict_yaml_rust::testing::verify_roundtrip;
+/// use strict_yaml_rust::StrictYamlLoader;
+///
+/// let doc = &StrictYamlLoader::load_from_str("a: \"1\"\nb:\n  - '# not a comment'").unwrap()[0];
+/// assert!(verify_roundtrip(doc).is_ok());
+/// ```
+pub fn verify_roundtrip(doc: &StrictYaml) -> Result<(), RoundtripIssue> {
+    let mut text = String::new();
+    StrictYamlEmitter::new(&mut text)
+        .dump(doc)
+        .map_err(RoundtripIssue::Emit)?;
+    let mut loaded = match StrictYamlLoader::load_from_str(&text) {
+        Ok(docs) => docs,
+        Err(e) => return Err(RoundtripIssue::Load(text, e)),
+    };
+    if loaded.len() != 1 {
+        return Err(RoundtripIssue::DocumentCount(text, loaded.len()));
+    }
+    match diff(doc, &loaded.remove(0)).into_iter().next() {
+        Some(d) => Err(RoundtripIssue::Diverged(Box::new(d))),
+        None => Ok(()),
+    }
+}
+
+#[doc(hidden)]
+pub fn assert_eq_impl(left: &StrictYaml, right: &StrictYaml, message: Option<fmt::Arguments>) {
+    let differences = diff(left, right);
+    if differences.is_empty() {
+        return;
+    }
+    let mut report = String::from("assertion failed: documents differ");
+    if let Some(message) = message {
+        report.push_str(&format!(": {}", message));
+    }
+    for d in differences {
+        report.push_str(&format!("\n  {}", d));
+    }
+    panic!("{}", report);
+}
+
+/// Asserts that `produced` matches the document in the fixture at `path`, see
+/// `diff`. When `STRICT_YAML_UPDATE_GOLDEN` is set, the fixture is (re)written
+/// from `produced` instead.
+///
+/// ```no_run
+/// use strict_yaml_rust::testing::assert_golden;
+/// use strict_yaml_rust::StrictYamlLoader;
+///
+/// let produced = &StrictYamlLoader::load_from_str("name: demo").unwrap()[0];
+/// assert_golden("tests/fixtures/demo.yaml", produced);
+/// ```
+pub fn assert_golden<P: AsRef<Path>>(path: P, produced: &StrictYaml) {
+    assert_golden_with_options(path, produced, &LoadOptions::new());
+}
+
+/// Like `assert_golden`, loading the fixture with `options`. `produced` goes
+/// through the same options by being emitted and loaded again, so that e.g.
+/// both sides are normalized alike.
+pub fn assert_golden_with_options<P: AsRef<Path>>(
+    path: P,
+    produced: &StrictYaml,
+    options: &LoadOptions,
+) {
+    let update = env::var_os(UPDATE_GOLDEN_ENV).is_some_and(|v| v != "0");
+    check_golden(path.as_ref(), produced, options, update);
+}
+
+/// See `assert_golden_with_options`, which reads `update` from the
+/// environment.
+fn check_golden(path: &Path, produced: &StrictYaml, options: &LoadOptions, update: bool) {
+    let mut text = String::new();
+    StrictYamlEmitter::new(&mut text)
+        .dump(produced)
+        .unwrap_or_else(|e| panic!("cannot emit the produced document: {:?}", e));
+    text.push('\n');
+
+    if update {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .unwrap_or_else(|e| panic!("cannot create {}: {}", dir.display(), e));
+        }
+        fs::write(path, text).unwrap_or_else(|e| panic!("cannot write {}: {}", path.display(), e));
+        return;
+    }
+
+    let expected = match StrictYamlLoader::load_from_file_with_options(path, options) {
+        Ok(docs) => docs,
+        Err(e) => panic!(
+            "{}\n(set {}=1 to write the fixture from the produced document)",
+            e, UPDATE_GOLDEN_ENV
+        ),
+    };
+    let produced = StrictYamlLoader::load_from_str_with_options(&text, options)
+        .unwrap_or_else(|e| panic!("cannot load the produced document: {}", e));
+    let message = format_args!(
+        "{} is out of date (set {}=1 to update it)",
+        path.display(),
+        UPDATE_GOLDEN_ENV
+    );
+    match (expected.len(), produced.len()) {
+        (1, 1) => assert_eq_impl(&expected[0], &produced[0], Some(message)),
+        (n, m) => panic!(
+            "{}: expected a single document, found {} in the fixture and {} produced",
+            path.display(),
+            n,
+            m
+        ),
+    }
+}
+
+/// Asserts that two documents are semantically equal, see the `testing`
+/// module. Like `assert_eq!`, accepts an optional message format.
+#[macro_export]
+macro_rules! assert_strict_yaml_eq {
+    ($left:expr, $right:expr) => {
+        $crate::testing::assert_eq_impl(&$left, &$right, None)
+    };
+    ($left:expr, $right:expr, $($arg:tt)+) => {
+        $crate::testing::assert_eq_impl(&$left, &$right, Some(format_args!($($arg)+)))
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::panic;
+    use strict_yaml::{Hash, StrictYamlLoader};
+
+    fn load(s: &str) -> StrictYaml {
+        StrictYamlLoader::load_from_str(s).unwrap().remove(0)
+    }
+
+    #[test]
+    fn test_diff() {
+        let left = load("a: 1\nb:\n  - x\n  - y\n  - z\nc:\n  d: e\nf.g: h\n");
+        let right = load("f.g: h\nc:\n  d: E\n  i: j\nb:\n  - x\n  - Y\na: 1\nk: l\n");
+        let differences: Vec<String> = diff(&left, &right).iter().map(|d| d.to_string()).collect();
+        assert_eq!(
+            differences,
+            vec![
+                "b[1]: \"y\" != \"Y\"",
+                "b[2]: only in left: \"z\"",
+                "c.d: \"e\" != \"E\"",
+                "c.i: only in right: \"j\"",
+                "k: only in right: \"l\"",
+            ]
+        );
+        assert_eq!(
+            diff(&load("- a"), &load("a: b"))[0].to_string(),
+            "(root): sequence of 1 items != mapping of 1 entries"
+        );
+        assert!(diff(&left, &left).is_empty());
+    }
+
+    #[test]
+    fn test_verify_roundtrip() {
+        for s in &["", "a\n", "- a", "~", "a: b", "# c", "x\ty", "---"] {
+            assert!(
+                verify_roundtrip(&StrictYaml::from_str(s)).is_ok(),
+                "{:?}",
+                s
+            );
+        }
+        assert!(verify_roundtrip(&load("a:\n  - b\n  - c: d")).is_ok());
+
+        // an empty sequence is emitted in flow style, which reads as a string
+        let mut h = Hash::new();
+        h.insert(StrictYaml::from_str("a"), StrictYaml::from_str("x"));
+        h.insert(StrictYaml::from_str("b"), StrictYaml::Array(vec![]));
+        let issue = verify_roundtrip(&StrictYaml::Hash(h)).unwrap_err();
+        assert_eq!(
+            issue.to_string(),
+            "changed by a round trip: b: sequence of 0 items != \"[]\""
+        );
+    }
+
+    #[test]
+    fn test_golden() {
+        let dir = env::temp_dir().join(format!("strict-yaml-golden-{}", std::process::id()));
+        let path = dir.join("out.yaml");
+        let produced = load("name: demo\nitems:\n  - 1\n  - 2");
+
+        let missing = panic::catch_unwind(|| assert_golden(&path, &produced));
+        assert!(missing.is_err());
+
+        check_golden(&path, &produced, &LoadOptions::new(), true);
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "---\nname: demo\nitems:\n  - \"1\"\n  - \"2\"\n"
+        );
+
+        fs::write(&path, "items: [1, 2]\nname: demo\n").unwrap();
+        let options = LoadOptions::new().flow_collections(true);
+        assert_golden_with_options(&path, &produced, &options);
+        let changed = load("name: demo\nitems:\n  - 1");
+        let res = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+            assert_golden_with_options(&path, &changed, &options)
+        }));
+        let payload = res.unwrap_err();
+        assert!(payload
+            .downcast_ref::<String>()
+            .unwrap()
+            .ends_with("to update it)\n  items[1]: only in left: \"2\""));
+
+        fs::write(&path, "--- a\n--- b\n").unwrap();
+        let res = panic::catch_unwind(|| assert_golden(&path, &produced));
+        assert!(res
+            .unwrap_err()
+            .downcast_ref::<String>()
+            .unwrap()
+            .ends_with("expected a single document, found 2 in the fixture and 1 produced"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_assert_strict_yaml_eq() {
+        assert_strict_yaml_eq!(load("a: b\nc: d"), load("c: d\na: b"));
+
+        let res = panic::catch_unwind(|| {
+            assert_strict_yaml_eq!(load("a: [1]"), load("a: [2]"), "in case {}", 3);
+        });
+        let payload = res.unwrap_err();
+        assert_eq!(
+            payload.downcast_ref::<String>().unwrap(),
+            "assertion failed: documents differ: in case 3\n  a: \"[1]\" != \"[2]\""
+        );
+    }
+}
@@ -0,0 +1,457 @@
+//! Layout-preserving rewriting of strict YAML streams.
+//!
+//! `transcode` runs the events of a source through an `EventFilter` and writes
+//! the result, touching the source text only where the filter changed something:
+//!
+//! * a scalar replaced by another scalar is rewritten in place, everything around
+//!   it (comments, indentation, quoting of the other scalars) is kept as is;
+//! * a document whose structure changed (events dropped or injected) is emitted
+//!   again as a whole with `StrictYamlEmitter`;
+//! * all other documents are copied byte for byte.
+//!
+//! ```
+//! use strict_yaml_rust::filter::MapScalars;
+//! use strict_yaml_rust::transcode::transcode;
+//!
+//! let source = "# web tier\nimage: nginx:1.24  # pinned\nreplicas: 3\n";
+//! let bump = MapScalars::new(|path, value: String| {
+//!     if path.to_string() == "image" { value.replace("1.24", "1.25") } else { value }
+//! });
+//! let mut out = String::new();
+//! transcode(source, bump, &mut out).unwrap();
+//! assert_eq!(out, "# web tier\nimage: nginx:1.25  # pinned\nreplicas: 3\n");
+//! ```
+
+use emitter::{escape_str, need_quotes, EmitError, StrictYamlEmitter};
+use filter::EventFilter;
+use parser::{Event, MarkedEventReceiver, Parser};
+use scanner::{Marker, ScanError, TScalarStyle};
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::str::CharIndices;
+use strict_yaml::StrictYamlLoader;
+
+#[derive(Clone, Debug)]
+pub enum TranscodeError {
+    ScanError(ScanError),
+    EmitError(EmitError),
+}
+
+impl TranscodeError {
+    pub fn code(&self) -> &'static str {
+        match *self {
+            TranscodeError::ScanError(ref err) => err.code(),
+            TranscodeError::EmitError(ref err) => err.code(),
+        }
+    }
+}
+
+impl Error for TranscodeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            TranscodeError::ScanError(ref err) => Some(err),
+            TranscodeError::EmitError(ref err) => Some(err),
+        }
+    }
+}
+
+impl Display for TranscodeError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TranscodeError::ScanError(ref err) => Display::fmt(err, formatter),
+            TranscodeError::EmitError(ref err) => Display::fmt(err, formatter),
+        }
+    }
+}
+
+impl From<ScanError> for TranscodeError {
+    fn from(e: ScanError) -> Self {
+        TranscodeError::ScanError(e)
+    }
+}
+
+impl From<EmitError> for TranscodeError {
+    fn from(e: EmitError) -> Self {
+        TranscodeError::EmitError(e)
+    }
+}
+
+impl From<fmt::Error> for TranscodeError {
+    fn from(e: fmt::Error) -> Self {
+        TranscodeError::EmitError(EmitError::FmtError(e))
+    }
+}
+
+struct Collect(Vec<(Event, Marker)>);
+
+impl MarkedEventReceiver for Collect {
+    fn on_event(&mut self, ev: Event, mark: Marker) -> Result<(), ScanError> {
+        self.0.push((ev, mark));
+        Ok(())
+    }
+}
+
+/// Converts the char indices of markers to byte offsets, moving forward.
+struct Offsets<'a> {
+    source: &'a str,
+    chars: CharIndices<'a>,
+    index: usize,
+    byte: usize,
+}
+
+impl<'a> Offsets<'a> {
+    fn new(source: &'a str) -> Offsets<'a> {
+        Offsets {
+            source,
+            chars: source.char_indices(),
+            index: 0,
+            byte: 0,
+        }
+    }
+
+    fn byte(&mut self, index: usize) -> usize {
+        if index < self.index {
+            *self = Offsets::new(self.source);
+        }
+        while self.index < index {
+            self.chars.next();
+            self.index += 1;
+            self.byte = self
+                .chars
+                .clone()
+                .next()
+                .map_or(self.source.len(), |(b, _)| b);
+        }
+        self.byte
+    }
+}
+
+/// A scalar rewritten in place: `source[start..end]` becomes `text`.
+struct Patch {
+    start: usize,
+    end: usize,
+    text: String,
+}
+
+#[derive(Default)]
+struct Document {
+    start: usize,
+    patches: Vec<Patch>,
+    /// The filtered events, to emit the document again if its structure changed.
+    events: Vec<(Event, Marker)>,
+    dirty: bool,
+}
+
+/// Writes `source` to `sink` with the changes made by `filter` to its events,
+/// keeping the text of unchanged parts identical.
+pub fn transcode<F: EventFilter>(
+    source: &str,
+    mut filter: F,
+    sink: &mut dyn fmt::Write,
+) -> Result<(), TranscodeError> {
+    let mut parser = Parser::new(source.chars());
+    let mut offsets = Offsets::new(source);
+    // source bytes already written out
+    let mut copied = 0;
+    let mut doc = Document::default();
+
+    loop {
+        let (ev, mark) = parser.next()?;
+        let start = offsets.byte(mark.index());
+        let mut out = Collect(Vec::new());
+        filter.filter(ev.clone(), mark, &mut out)?;
+        let unchanged = out.0.len() == 1 && out.0[0].0 == ev;
+
+        match ev {
+            Event::StreamStart => {}
+            Event::StreamEnd => {
+                sink.write_str(&source[copied..])?;
+                return Ok(());
+            }
+            Event::DocumentStart => {
+                doc = Document {
+                    start,
+                    dirty: !unchanged,
+                    ..Document::default()
+                };
+                doc.events.extend(out.0);
+            }
+            Event::DocumentEnd => {
+                doc.dirty |= !unchanged;
+                doc.events.extend(out.0);
+                if doc.dirty {
+                    sink.write_str(&source[copied..doc.start])?;
+                    emit_document(&doc, &source[doc.start..], sink)?;
+                    copied = start;
+                } else {
+                    for patch in doc.patches.drain(..) {
+                        sink.write_str(&source[copied..patch.start])?;
+                        sink.write_str(&patch.text)?;
+                        copied = patch.end;
+                    }
+                }
+            }
+            Event::Scalar(ref value, style, _) if !unchanged => {
+                let patch = match out.0.first() {
+                    Some(&(Event::Scalar(ref new, ..), _)) if out.0.len() == 1 => {
+                        let bound = match parser.peek() {
+                            Ok(&(_, next)) => offsets.byte(next.index()),
+                            Err(_) => source.len(),
+                        };
+                        // the marker of a block scalar is at its content, not its header
+                        let start = match style {
+                            TScalarStyle::Literal | TScalarStyle::Foled => {
+                                block_header(&source[..start])
+                            }
+                            _ => Some(start),
+                        };
+                        start.and_then(|start| {
+                            scalar_len(&source[start..bound], style, value).map(|len| Patch {
+                                start,
+                                end: start + len,
+                                text: render(new, style),
+                            })
+                        })
+                    }
+                    _ => None,
+                };
+                match patch {
+                    Some(patch) => doc.patches.push(patch),
+                    None => doc.dirty = true,
+                }
+                doc.events.extend(out.0);
+            }
+            _ => {
+                doc.dirty |= !unchanged;
+                doc.events.extend(out.0);
+            }
+        }
+        // the marker of an implicit document start may be past its first node
+        if doc.start > start {
+            doc.start = start;
+        }
+    }
+}
+
+/// Renders `value` in the style of the scalar it replaces when possible, as the
+/// emitter would otherwise.
+fn render(value: &str, style: TScalarStyle) -> String {
+    let mut text = String::new();
+    match style {
+        TScalarStyle::Plain if plain_safe(value) => text.push_str(value),
+        TScalarStyle::SingleQuoted if !value.contains('\n') => {
+            text.push('\'');
+            text.push_str(&value.replace('\'', "''"));
+            text.push('\'');
+        }
+        _ if need_quotes(value) => {
+            // writing to a String cannot fail
+            let _ = escape_str(&mut text, value);
+        }
+        _ => text.push_str(value),
+    }
+    text
+}
+
+/// Whether `value`, written as is on a single line, reads back as the same plain
+/// scalar.
+fn plain_safe(value: &str) -> bool {
+    match value.chars().next() {
+        None => false,
+        Some(c) if "-?:,[]{}#&*!|>'\"%@`".contains(c) || c.is_whitespace() => false,
+        _ => {
+            !value.ends_with(char::is_whitespace)
+                && !value.ends_with(':')
+                && !value.contains(": ")
+                && !value.contains(" #")
+                && !value.contains(|c: char| c.is_control())
+        }
+    }
+}
+
+/// Builds the document from its filtered events and emits it.
+fn emit_document(
+    doc: &Document,
+    source: &str,
+    sink: &mut dyn fmt::Write,
+) -> Result<(), TranscodeError> {
+    let mut loader = StrictYamlLoader::new();
+    for &(ref ev, mark) in &doc.events {
+        loader.on_event(ev.clone(), mark)?;
+    }
+    for node in loader.take_documents() {
+        let mut text = String::new();
+        StrictYamlEmitter::new(&mut text).dump(&node)?;
+        // keep the document header only if the source had one
+        let text = if source.starts_with("---") {
+            &text[..]
+        } else {
+            text.trim_start_matches("---\n")
+        };
+        sink.write_str(text)?;
+        sink.write_str("\n")?;
+    }
+    Ok(())
+}
+
+/// Length in bytes of the text of the scalar starting `text`, given its style
+/// and value. `text` ends where the next event starts.
+fn scalar_len(text: &str, style: TScalarStyle, value: &str) -> Option<usize> {
+    match style {
+        TScalarStyle::SingleQuoted => {
+            let mut chars = text.char_indices().skip(1).peekable();
+            while let Some((i, c)) = chars.next() {
+                if c == '\'' {
+                    if chars.peek().map(|&(_, c)| c) != Some('\'') {
+                        return Some(i + 1);
+                    }
+                    chars.next();
+                }
+            }
+            None
+        }
+        TScalarStyle::DoubleQuoted => {
+            let mut chars = text.char_indices().skip(1);
+            while let Some((i, c)) = chars.next() {
+                match c {
+                    '\\' => {
+                        chars.next();
+                    }
+                    '"' => return Some(i + 1),
+                    _ => {}
+                }
+            }
+            None
+        }
+        TScalarStyle::Literal | TScalarStyle::Foled => Some(block_len(text)),
+        TScalarStyle::Plain | TScalarStyle::Any => {
+            // match the value against the text, line folding included
+            let mut src = text.char_indices().peekable();
+            let mut val = value.chars().peekable();
+            while let Some(&v) = val.peek() {
+                let (_, s) = *src.peek()?;
+                if s == v {
+                    src.next();
+                    val.next();
+                } else if (v == ' ' || v == '\n') && s.is_whitespace() {
+                    while src.peek().is_some_and(|&(_, c)| c.is_whitespace()) {
+                        src.next();
+                    }
+                    while val.peek().is_some_and(|&c| c == ' ' || c == '\n') {
+                        val.next();
+                    }
+                } else {
+                    return None;
+                }
+            }
+            Some(src.peek().map_or(text.len(), |&(i, _)| i))
+        }
+    }
+}
+
+/// Offset of the indicator of the block scalar whose content starts at the end
+/// of `text`.
+fn block_header(text: &str) -> Option<usize> {
+    let line_start = text.trim_end().rfind('\n').map_or(0, |i| i + 1);
+    let line = &text[line_start..];
+    let mut prev = ' ';
+    for (i, c) in line.char_indices() {
+        if (c == '|' || c == '>') && prev.is_whitespace() {
+            return Some(line_start + i);
+        }
+        prev = c;
+    }
+    None
+}
+
+/// Length of a block scalar, from its indicator to the end of its last content
+/// line, leaving out trailing blank lines and less indented comments.
+fn block_len(text: &str) -> usize {
+    let header = text.find('\n').unwrap_or(text.len());
+    let mut end = header;
+    let mut content_indent = None;
+    let mut offset = header;
+    for line in text[header..].split('\n').skip(1) {
+        let line_start = offset + 1;
+        offset = line_start + line.len();
+        let trimmed = line.trim_start_matches(' ');
+        if trimmed.trim().is_empty() {
+            continue;
+        }
+        let indent = line.len() - trimmed.len();
+        if indent < *content_indent.get_or_insert(indent) {
+            break;
+        }
+        end = line_start + line.trim_end_matches('\r').len();
+    }
+    end.min(text.len())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use filter::{DropPaths, MapScalars};
+    use path::{PathSegment, YamlPath};
+
+    fn run<F: EventFilter>(source: &str, filter: F) -> String {
+        let mut out = String::new();
+        transcode(source, filter, &mut out).unwrap();
+        out
+    }
+
+    const SOURCE: &str = "# leading comment
+name:   'it''s'   # quoted
+tags:
+  - \"a\\tb\"
+  - plain words
+    folded over lines
+text: |
+  keep
+    me
+
+# trailing comment
+---
+other: doc
+";
+
+    #[test]
+    fn test_identity() {
+        let identity =
+            |ev: Event, mark: Marker, out: &mut dyn MarkedEventReceiver| out.on_event(ev, mark);
+        assert_eq!(run(SOURCE, identity), SOURCE);
+    }
+
+    #[test]
+    fn test_rewrite_scalars() {
+        let upper = MapScalars::new(|_: &YamlPath, v: String| v.to_uppercase());
+        let expected = "# leading comment
+name:   'IT''S'   # quoted
+tags:
+  - \"A\\tB\"
+  - PLAIN WORDS FOLDED OVER LINES
+text: \"KEEP\\n  ME\\n\"
+
+# trailing comment
+---
+other: DOC
+";
+        let out = run(SOURCE, upper);
+        assert_eq!(out, expected);
+        assert_eq!(
+            StrictYamlLoader::load_from_str(&out).unwrap()[0]["text"].as_str(),
+            Some("KEEP\n  ME\n")
+        );
+    }
+
+    #[test]
+    fn test_structural_change() {
+        let tags = YamlPath::from(vec![PathSegment::Key("tags".to_owned())]);
+        let expected = "# leading comment
+name: \"it's\"
+text: \"keep\\n  me\\n\"
+---
+other: doc
+";
+        assert_eq!(run(SOURCE, DropPaths::new(vec![tags])), expected);
+    }
+}
@@ -0,0 +1,367 @@
+//! Typed YAML values, for users who want the strict syntax but typed scalars.
+//!
+//! Enabled by the `typed` feature. `YamlLoader` reads the same syntax as
+//! `StrictYamlLoader`, then resolves plain scalars to nulls, booleans, integers
+//! and floats following the YAML 1.2 core schema. Quoted and block scalars are
+//! always strings.
+//!
+//! ```
+//! use strict_yaml_rust::typed::{Yaml, YamlLoader};
+//!
+//! let docs = YamlLoader::load_from_str("port: 8080\ndebug: false\nname: '42'").unwrap();
+//! assert_eq!(docs[0]["port"].as_i64(), Some(8080));
+//! assert_eq!(docs[0]["debug"].as_bool(), Some(false));
+//! assert_eq!(docs[0]["name"], Yaml::String("42".to_owned()));
+//! ```
+
+use linked_hash_map::LinkedHashMap;
+use parser::{Event, MarkedEventReceiver, Parser};
+use scanner::{Marker, ScanError, TScalarStyle};
+use std::f64;
+use std::ops::Index;
+use std::vec;
+use strict_yaml::parse_decimal;
+
+/// A YAML node with a resolved type.
+///
+/// Floats are kept as their source text, so that `Yaml` can be `Eq`, `Ord` and
+/// `Hash` and used as a mapping key; `as_f64` parses them.
+#[derive(Clone, PartialEq, PartialOrd, Debug, Eq, Ord, Hash)]
+pub enum Yaml {
+    Real(String),
+    Integer(i64),
+    String(String),
+    Boolean(bool),
+    Array(Array),
+    Hash(Hash),
+    Null,
+    /// Accessing a nonexistent node via the Index trait returns `BadValue`, as
+    /// with `StrictYaml`.
+    BadValue,
+}
+
+pub type Array = Vec<Yaml>;
+pub type Hash = LinkedHashMap<Yaml, Yaml>;
+
+/// Builds `Yaml` documents from parser events.
+#[derive(Default)]
+pub struct YamlLoader {
+    docs: Vec<Yaml>,
+    doc_stack: Vec<Yaml>,
+    key_stack: Vec<Yaml>,
+}
+
+impl MarkedEventReceiver for YamlLoader {
+    fn on_event(&mut self, ev: Event, mark: Marker) -> Result<(), ScanError> {
+        match ev {
+            Event::DocumentEnd => match self.doc_stack.len() {
+                0 => self.docs.push(Yaml::BadValue),
+                1 => {
+                    let node = self.doc_stack.remove(0);
+                    self.docs.push(node);
+                }
+                _ => return Err(unexpected(mark)),
+            },
+            Event::SequenceStart(_) => self.doc_stack.push(Yaml::Array(Vec::new())),
+            Event::SequenceEnd => match self.doc_stack.pop() {
+                Some(node @ Yaml::Array(_)) => self.insert_new_node(node, mark)?,
+                _ => return Err(unexpected(mark)),
+            },
+            Event::MappingStart(_) => {
+                self.doc_stack.push(Yaml::Hash(Hash::new()));
+                self.key_stack.push(Yaml::BadValue);
+            }
+            Event::MappingEnd => match (self.key_stack.pop(), self.doc_stack.pop()) {
+                (Some(Yaml::BadValue), Some(node @ Yaml::Hash(_))) => {
+                    self.insert_new_node(node, mark)?
+                }
+                _ => return Err(unexpected(mark)),
+            },
+            Event::Scalar(v, style, _) => {
+                let node = if style == TScalarStyle::Plain {
+                    Yaml::from_str(&v)
+                } else {
+                    Yaml::String(v)
+                };
+                self.insert_new_node(node, mark)?
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+fn unexpected(mark: Marker) -> ScanError {
+    ScanError::parser(mark, "Error handling node: Unexpected event")
+}
+
+impl YamlLoader {
+    pub fn new() -> YamlLoader {
+        YamlLoader::default()
+    }
+
+    /// The documents loaded so far.
+    pub fn documents(&self) -> &[Yaml] {
+        &self.docs
+    }
+
+    fn insert_new_node(&mut self, node: Yaml, mark: Marker) -> Result<(), ScanError> {
+        match self.doc_stack.last_mut() {
+            None => self.doc_stack.push(node),
+            Some(&mut Yaml::Array(ref mut v)) => v.push(node),
+            Some(&mut Yaml::Hash(ref mut h)) => {
+                let cur_key = match self.key_stack.last_mut() {
+                    Some(key) => key,
+                    None => return Err(unexpected(mark)),
+                };
+                if cur_key.is_badvalue() {
+                    *cur_key = node;
+                } else {
+                    let key = std::mem::replace(cur_key, Yaml::BadValue);
+                    if h.contains_key(&key) {
+                        return Err(ScanError::duplicate_key(
+                            mark,
+                            "Error handling node: Key already exists in the hash map",
+                        ));
+                    }
+                    h.insert(key, node);
+                }
+            }
+            Some(_) => return Err(unexpected(mark)),
+        }
+        Ok(())
+    }
+
+    pub fn load_from_str(source: &str) -> Result<Vec<Yaml>, ScanError> {
+        let mut loader = YamlLoader::new();
+        let mut parser = Parser::new(source.chars());
+        parser.load(&mut loader, true)?;
+        Ok(loader.docs)
+    }
+}
+
+macro_rules! define_as (
+    ($name:ident, $t:ident, $yt:ident) => (
+pub fn $name(&self) -> Option<$t> {
+    match *self {
+        Yaml::$yt(v) => Some(v),
+        _ => None
+    }
+}
+    );
+);
+
+macro_rules! define_as_ref (
+    ($name:ident, $t:ty, $yt:ident) => (
+pub fn $name(&self) -> Option<$t> {
+    match *self {
+        Yaml::$yt(ref v) => Some(v),
+        _ => None
+    }
+}
+    );
+);
+
+macro_rules! define_into (
+    ($name:ident, $t:ty, $yt:ident) => (
+pub fn $name(self) -> Option<$t> {
+    match self {
+        Yaml::$yt(v) => Some(v),
+        _ => None
+    }
+}
+    );
+);
+
+impl Yaml {
+    define_as!(as_bool, bool, Boolean);
+    define_as!(as_i64, i64, Integer);
+
+    define_as_ref!(as_str, &str, String);
+    define_as_ref!(as_hash, &Hash, Hash);
+    define_as_ref!(as_vec, &Array, Array);
+
+    define_into!(into_bool, bool, Boolean);
+    define_into!(into_i64, i64, Integer);
+    define_into!(into_string, String, String);
+    define_into!(into_hash, Hash, Hash);
+    define_into!(into_vec, Array, Array);
+
+    pub fn is_null(&self) -> bool {
+        matches!(*self, Yaml::Null)
+    }
+
+    pub fn is_badvalue(&self) -> bool {
+        matches!(*self, Yaml::BadValue)
+    }
+
+    pub fn is_array(&self) -> bool {
+        matches!(*self, Yaml::Array(_))
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            Yaml::Real(ref v) => parse_f64(v),
+            _ => None,
+        }
+    }
+
+    pub fn into_f64(self) -> Option<f64> {
+        self.as_f64()
+    }
+}
+
+#[allow(clippy::should_implement_trait)]
+impl Yaml {
+    /// Resolves the type of a plain scalar.
+    ///
+    /// ```
+    /// use strict_yaml_rust::typed::Yaml;
+    ///
+    /// assert_eq!(Yaml::from_str("0x1f"), Yaml::Integer(31));
+    /// assert_eq!(Yaml::from_str("-.inf").as_f64(), Some(std::f64::NEG_INFINITY));
+    /// assert_eq!(Yaml::from_str("~"), Yaml::Null);
+    /// assert_eq!(Yaml::from_str("yes"), Yaml::String("yes".to_owned()));
+    /// ```
+    pub fn from_str(v: &str) -> Yaml {
+        match v {
+            "" | "~" | "null" | "Null" | "NULL" => return Yaml::Null,
+            "true" | "True" | "TRUE" => return Yaml::Boolean(true),
+            "false" | "False" | "FALSE" => return Yaml::Boolean(false),
+            _ => {}
+        }
+        if let Some(i) = parse_i64(v) {
+            return Yaml::Integer(i);
+        }
+        if parse_f64(v).is_some() {
+            return Yaml::Real(v.to_owned());
+        }
+        Yaml::String(v.to_owned())
+    }
+}
+
+fn parse_i64(v: &str) -> Option<i64> {
+    let radix = |digits: &str, radix: u32| {
+        if !digits.is_empty() && digits.chars().all(|c| c.is_digit(radix)) {
+            i64::from_str_radix(digits, radix).ok()
+        } else {
+            None
+        }
+    };
+    if let Some(hex) = v.strip_prefix("0x") {
+        return radix(hex, 16);
+    }
+    if let Some(octal) = v.strip_prefix("0o") {
+        return radix(octal, 8);
+    }
+    let digits = v.strip_prefix(['-', '+']).unwrap_or(v);
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    v.parse().ok()
+}
+
+/// Parses floats as written in YAML 1.2, which `str::parse` is more lenient than.
+fn parse_f64(v: &str) -> Option<f64> {
+    match v {
+        ".inf" | ".Inf" | ".INF" | "+.inf" | "+.Inf" | "+.INF" => return Some(f64::INFINITY),
+        "-.inf" | "-.Inf" | "-.INF" => return Some(f64::NEG_INFINITY),
+        ".nan" | ".NaN" | ".NAN" => return Some(f64::NAN),
+        _ => {}
+    }
+    parse_decimal(v)
+}
+
+static BAD_VALUE: Yaml = Yaml::BadValue;
+
+impl<'a> Index<&'a str> for Yaml {
+    type Output = Yaml;
+
+    fn index(&self, idx: &'a str) -> &Yaml {
+        let key = Yaml::String(idx.to_owned());
+        match self.as_hash() {
+            Some(h) => h.get(&key).unwrap_or(&BAD_VALUE),
+            None => &BAD_VALUE,
+        }
+    }
+}
+
+impl Index<usize> for Yaml {
+    type Output = Yaml;
+
+    fn index(&self, idx: usize) -> &Yaml {
+        match self.as_vec() {
+            Some(v) => v.get(idx).unwrap_or(&BAD_VALUE),
+            None => &BAD_VALUE,
+        }
+    }
+}
+
+impl IntoIterator for Yaml {
+    type Item = Yaml;
+    type IntoIter = vec::IntoIter<Yaml>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.into_vec().unwrap_or_default().into_iter()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_resolve() {
+        let s = "
+tilde: ~
+empty:
+bool: True
+int: -17
+hex: 0xFF
+octal: 0o17
+big: 123456789012345678901234567890
+float: 6.8523015e+5
+huge: 1e400
+short: .5
+inf: -.inf
+quoted: 'true'
+block: |
+  12
+version: 1.2.3
+not_hex: 0x
+list:
+  - 1
+  - '1'
+";
+        let doc = &YamlLoader::load_from_str(s).unwrap()[0];
+        assert!(doc["tilde"].is_null());
+        assert!(doc["empty"].is_null());
+        assert_eq!(doc["bool"].as_bool(), Some(true));
+        assert_eq!(doc["int"].as_i64(), Some(-17));
+        assert_eq!(doc["hex"].as_i64(), Some(255));
+        assert_eq!(doc["octal"].as_i64(), Some(15));
+        assert_eq!(doc["big"].as_f64(), Some(1.2345678901234568e29));
+        assert_eq!(doc["float"].as_f64(), Some(685230.15));
+        assert_eq!(doc["huge"], Yaml::Real("1e400".to_owned()));
+        assert_eq!(doc["huge"].as_f64(), Some(f64::INFINITY));
+        assert_eq!(doc["short"].as_f64(), Some(0.5));
+        assert_eq!(doc["inf"].as_f64(), Some(f64::NEG_INFINITY));
+        assert_eq!(doc["quoted"].as_str(), Some("true"));
+        assert_eq!(doc["block"].as_str(), Some("12\n"));
+        assert_eq!(doc["version"].as_str(), Some("1.2.3"));
+        assert_eq!(doc["not_hex"].as_str(), Some("0x"));
+        assert_eq!(doc["list"][0], Yaml::Integer(1));
+        assert_eq!(doc["list"][1], Yaml::String("1".to_owned()));
+        assert!(doc["missing"][0].is_badvalue());
+    }
+
+    #[test]
+    fn test_typed_keys() {
+        let docs = YamlLoader::load_from_str("1: a\n'1': b").unwrap();
+        let h = docs[0].as_hash().unwrap();
+        assert_eq!(h[&Yaml::Integer(1)].as_str(), Some("a"));
+        assert_eq!(h[&Yaml::String("1".to_owned())].as_str(), Some("b"));
+
+        assert!(YamlLoader::load_from_str("a: 1\na: 2").is_err());
+    }
+}
@@ -0,0 +1,218 @@
+//! Traversal of `StrictYaml` trees, with the path of each node.
+//!
+//! Implement `Visit` to look at nodes, or `VisitMut` to change them, by
+//! overriding the hooks of interest. The hooks for collections walk into
+//! their children by default; an override calls the matching `walk_`
+//! function to keep going deeper, or returns to skip the subtree.
+//!
+//! ```
+//! use strict_yaml_rust::path::YamlPath;
+//! use strict_yaml_rust::visit::{self, Visit};
+//! use strict_yaml_rust::StrictYaml;
+//!
+//! /// Paths of the empty scalars.
+//! struct Empty(Vec<String>);
+//!
+//! impl Visit for Empty {
+//!     fn visit_scalar(&mut self, path: &YamlPath, value: &str) {
+//!         if value.is_empty() {
+//!             self.0.push(path.to_string());
+//!         }
+//!     }
+//! }
+//!
+//! let doc: StrictYaml = "a:\nb:\n  - x\n  - ''".parse().unwrap();
+//! let mut empty = Empty(Vec::new());
+//! visit::visit(&mut empty, &doc);
+//! assert_eq!(empty.0, ["a", "b[1]"]);
+//! ```
+
+use path::{PathSegment, YamlPath};
+use strict_yaml::StrictYaml;
+
+/// The path of the value of `key`, in the mapping at `path`. Keys that are
+/// not scalars have an empty segment.
+fn key_path(path: &YamlPath, key: &StrictYaml) -> YamlPath {
+    path.child(PathSegment::Key(
+        key.as_str().unwrap_or_default().to_owned(),
+    ))
+}
+
+/// Hooks called on the nodes of a tree, see `visit`.
+pub trait Visit {
+    /// Any node, by default passed on to the hook for its kind.
+    fn visit_node(&mut self, path: &YamlPath, node: &StrictYaml) {
+        walk_node(self, path, node);
+    }
+
+    fn visit_scalar(&mut self, _path: &YamlPath, _value: &str) {}
+
+    /// An entry of the mapping at `path`, by default visiting its value.
+    fn visit_mapping_entry(&mut self, path: &YamlPath, key: &StrictYaml, value: &StrictYaml) {
+        walk_mapping_entry(self, path, key, value);
+    }
+
+    /// An item of the sequence at `path`, by default visiting it.
+    fn visit_sequence_item(&mut self, path: &YamlPath, index: usize, item: &StrictYaml) {
+        walk_sequence_item(self, path, index, item);
+    }
+}
+
+/// Visits `node` and all the nodes below it, from the root path.
+pub fn visit<V: Visit + ?Sized>(visitor: &mut V, node: &StrictYaml) {
+    visitor.visit_node(&YamlPath::new(), node);
+}
+
+pub fn walk_node<V: Visit + ?Sized>(visitor: &mut V, path: &YamlPath, node: &StrictYaml) {
+    match *node {
+        StrictYaml::String(ref s) => visitor.visit_scalar(path, s),
+        StrictYaml::Array(ref items) => {
+            for (index, item) in items.iter().enumerate() {
+                visitor.visit_sequence_item(path, index, item);
+            }
+        }
+        StrictYaml::Hash(ref h) => {
+            for (key, value) in h {
+                visitor.visit_mapping_entry(path, key, value);
+            }
+        }
+        StrictYaml::BadValue => {}
+    }
+}
+
+pub fn walk_mapping_entry<V: Visit + ?Sized>(
+    visitor: &mut V,
+    path: &YamlPath,
+    key: &StrictYaml,
+    value: &StrictYaml,
+) {
+    visitor.visit_node(&key_path(path, key), value);
+}
+
+pub fn walk_sequence_item<V: Visit + ?Sized>(
+    visitor: &mut V,
+    path: &YamlPath,
+    index: usize,
+    item: &StrictYaml,
+) {
+    visitor.visit_node(&path.child(PathSegment::Index(index)), item);
+}
+
+/// Hooks called on the nodes of a tree to change them, see `visit_mut`.
+/// Mapping keys are not changed, as they index their mapping.
+pub trait VisitMut {
+    /// Any node, by default passed on to the hook for its kind.
+    fn visit_node_mut(&mut self, path: &YamlPath, node: &mut StrictYaml) {
+        walk_node_mut(self, path, node);
+    }
+
+    fn visit_scalar_mut(&mut self, _path: &YamlPath, _value: &mut String) {}
+
+    /// An entry of the mapping at `path`, by default visiting its value.
+    fn visit_mapping_entry_mut(
+        &mut self,
+        path: &YamlPath,
+        key: &StrictYaml,
+        value: &mut StrictYaml,
+    ) {
+        walk_mapping_entry_mut(self, path, key, value);
+    }
+
+    /// An item of the sequence at `path`, by default visiting it.
+    fn visit_sequence_item_mut(&mut self, path: &YamlPath, index: usize, item: &mut StrictYaml) {
+        walk_sequence_item_mut(self, path, index, item);
+    }
+}
+
+/// Visits `node` and all the nodes below it, from the root path, to change
+/// them.
+pub fn visit_mut<V: VisitMut + ?Sized>(visitor: &mut V, node: &mut StrictYaml) {
+    visitor.visit_node_mut(&YamlPath::new(), node);
+}
+
+pub fn walk_node_mut<V: VisitMut + ?Sized>(
+    visitor: &mut V,
+    path: &YamlPath,
+    node: &mut StrictYaml,
+) {
+    match *node {
+        StrictYaml::String(ref mut s) => visitor.visit_scalar_mut(path, s),
+        StrictYaml::Array(ref mut items) => {
+            for (index, item) in items.iter_mut().enumerate() {
+                visitor.visit_sequence_item_mut(path, index, item);
+            }
+        }
+        StrictYaml::Hash(ref mut h) => {
+            for (key, value) in h.iter_mut() {
+                visitor.visit_mapping_entry_mut(path, key, value);
+            }
+        }
+        StrictYaml::BadValue => {}
+    }
+}
+
+pub fn walk_mapping_entry_mut<V: VisitMut + ?Sized>(
+    visitor: &mut V,
+    path: &YamlPath,
+    key: &StrictYaml,
+    value: &mut StrictYaml,
+) {
+    visitor.visit_node_mut(&key_path(path, key), value);
+}
+
+pub fn walk_sequence_item_mut<V: VisitMut + ?Sized>(
+    visitor: &mut V,
+    path: &YamlPath,
+    index: usize,
+    item: &mut StrictYaml,
+) {
+    visitor.visit_node_mut(&path.child(PathSegment::Index(index)), item);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Records the hooks called, without going into the entries of `skip`.
+    struct Trace(Vec<String>);
+
+    impl Visit for Trace {
+        fn visit_scalar(&mut self, path: &YamlPath, value: &str) {
+            self.0.push(format!("{} = {}", path, value));
+        }
+
+        fn visit_mapping_entry(&mut self, path: &YamlPath, key: &StrictYaml, value: &StrictYaml) {
+            if key.as_str() != Some("skip") {
+                walk_mapping_entry(self, path, key, value);
+            }
+        }
+
+        fn visit_sequence_item(&mut self, path: &YamlPath, index: usize, item: &StrictYaml) {
+            self.0.push(format!("{}[{}]", path, index));
+            walk_sequence_item(self, path, index, item);
+        }
+    }
+
+    #[test]
+    fn test_visit() {
+        let doc: StrictYaml = "a: x\nb:\n  - y\n  - c: z\nskip:\n  - w".parse().unwrap();
+        let mut trace = Trace(Vec::new());
+        visit(&mut trace, &doc);
+        assert_eq!(trace.0, ["a = x", "b[0]", "b[0] = y", "b[1]", "b[1].c = z"]);
+    }
+
+    struct Trim;
+
+    impl VisitMut for Trim {
+        fn visit_scalar_mut(&mut self, _: &YamlPath, value: &mut String) {
+            *value = value.trim().to_owned();
+        }
+    }
+
+    #[test]
+    fn test_visit_mut() {
+        let mut doc: StrictYaml = "a: ' x '\nb:\n  - ' y'\n  - c: 'z '".parse().unwrap();
+        visit_mut(&mut Trim, &mut doc);
+        assert_eq!(doc, "a: x\nb:\n  - y\n  - c: z".parse().unwrap());
+    }
+}
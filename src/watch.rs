@@ -0,0 +1,190 @@
+//! Reloading configuration files whenever they change.
+//!
+//! ```no_run
+//! use strict_yaml_rust::watch::{ConfigWatcher, Reload};
+//! use strict_yaml_rust::LoadOptions;
+//!
+//! let validate = |docs: &[_]| match docs.len() {
+//!     1 => Ok(()),
+//!     n => Err(format!("expected a single document, found {}", n)),
+//! };
+//! let (_watcher, reloads) = ConfigWatcher::new("config.yaml", LoadOptions::new(), validate)
+//!     .expect("cannot watch config.yaml");
+//! for reload in reloads {
+//!     match reload {
+//!         Reload::Loaded(_, docs) => println!("new config: {:?}", docs[0]),
+//!         Reload::Failed(e) => eprintln!("{}", e),
+//!         Reload::Invalid(path, why) => eprintln!("{}: {}", path.display(), why),
+//!     }
+//! }
+//! ```
+
+use notify::{self, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use options::LoadOptions;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use strict_yaml::{FileError, StrictYaml, StrictYamlLoader};
+
+/// What is delivered for a watched file, once when watching starts and then
+/// after each change.
+#[derive(Debug)]
+pub enum Reload {
+    /// The documents of the file, accepted by the validation function.
+    Loaded(PathBuf, Vec<StrictYaml>),
+    /// The file could not be read or loaded.
+    Failed(FileError),
+    /// The file was loaded, but the validation function rejected it.
+    Invalid(PathBuf, String),
+}
+
+/// Watches a file, or the `.yaml` and `.yml` files of a directory, reloading
+/// them on change. Watching stops when the watcher is dropped.
+///
+/// A single save may be seen as several changes, so the same content can be
+/// delivered more than once.
+pub struct ConfigWatcher {
+    _inner: RecommendedWatcher,
+}
+
+impl ConfigWatcher {
+    /// Starts watching `path`, loading with `options` and checking each load
+    /// with `validate`. The initial state of the watched files is delivered
+    /// right away.
+    pub fn new<P, V>(
+        path: P,
+        options: LoadOptions,
+        validate: V,
+    ) -> notify::Result<(ConfigWatcher, Receiver<Reload>)>
+    where
+        P: AsRef<Path>,
+        V: Fn(&[StrictYaml]) -> Result<(), String> + Send + 'static,
+    {
+        let path = path.as_ref();
+        // a file is watched through its directory, as editors often save by
+        // replacing the file
+        let (dir, file, initial) = if path.is_dir() {
+            let mut paths: Vec<PathBuf> = fs::read_dir(path)?
+                .filter_map(|e| e.ok().map(|e| e.path()))
+                .filter(|p| p.is_file() && is_yaml(p))
+                .collect();
+            paths.sort();
+            (path.to_owned(), None, paths)
+        } else {
+            let dir = match path.parent() {
+                Some(dir) if !dir.as_os_str().is_empty() => dir.to_owned(),
+                _ => PathBuf::from("."),
+            };
+            let file = path.file_name().map(OsStr::to_owned);
+            (dir, file, vec![path.to_owned()])
+        };
+        let watched = move |p: &Path| match file {
+            Some(ref name) => p.file_name() == Some(name),
+            None => is_yaml(p),
+        };
+
+        let (tx, rx) = channel();
+        for p in initial {
+            reload(&p, &options, &validate, &tx);
+        }
+
+        let mut inner = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+            let event = match res {
+                Ok(event) => event,
+                Err(_) => return,
+            };
+            if let EventKind::Create(_) | EventKind::Modify(_) = event.kind {
+                for p in event.paths.iter().filter(|p| watched(p) && p.is_file()) {
+                    reload(p, &options, &validate, &tx);
+                }
+            }
+        })?;
+        inner.watch(&dir, RecursiveMode::NonRecursive)?;
+        Ok((ConfigWatcher { _inner: inner }, rx))
+    }
+}
+
+fn is_yaml(path: &Path) -> bool {
+    match path.extension().and_then(OsStr::to_str) {
+        Some(ext) => ext == "yaml" || ext == "yml",
+        None => false,
+    }
+}
+
+fn reload<V>(path: &Path, options: &LoadOptions, validate: &V, tx: &Sender<Reload>)
+where
+    V: Fn(&[StrictYaml]) -> Result<(), String>,
+{
+    let reload = match StrictYamlLoader::load_from_file_with_options(path, options) {
+        Ok(docs) => match validate(&docs) {
+            Ok(()) => Reload::Loaded(path.to_owned(), docs),
+            Err(why) => Reload::Invalid(path.to_owned(), why),
+        },
+        Err(e) => Reload::Failed(e),
+    };
+    // the receiver is gone, nobody is listening anymore
+    let _ = tx.send(reload);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::env;
+    use std::time::Duration;
+
+    #[test]
+    fn test_watch_dir() {
+        let dir = env::temp_dir().join(format!("strict-yaml-watch-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("a.yaml"), "a: 1\n").unwrap();
+        fs::write(dir.join("notes.txt"), "a: [\n").unwrap();
+
+        let validate = |docs: &[StrictYaml]| match docs.first().and_then(|d| d["a"].as_str()) {
+            Some("bad") => Err("a is bad".to_owned()),
+            _ => Ok(()),
+        };
+        let (watcher, reloads) = ConfigWatcher::new(&dir, LoadOptions::new(), validate).unwrap();
+        match reloads.recv_timeout(Duration::from_secs(5)).unwrap() {
+            Reload::Loaded(ref p, ref docs) => {
+                assert_eq!(p, &dir.join("a.yaml"));
+                assert_eq!(docs[0]["a"].as_str(), Some("1"));
+            }
+            r => panic!("unexpected reload {:?}", r),
+        }
+        // writes can be seen half done, so skip to the expected reload
+        let wait_for = |expected: &dyn Fn(&Reload) -> bool| loop {
+            let reload = reloads.recv_timeout(Duration::from_secs(5)).unwrap();
+            if expected(&reload) {
+                break;
+            }
+        };
+
+        fs::write(dir.join("a.yaml"), "a: bad\n").unwrap();
+        wait_for(&|r| match *r {
+            Reload::Invalid(ref p, ref why) => p == &dir.join("a.yaml") && why == "a is bad",
+            _ => false,
+        });
+        fs::write(dir.join("b.yml"), "b: c: d\n").unwrap();
+        wait_for(&|r| match *r {
+            Reload::Failed(ref e) => e.path() == dir.join("b.yml").as_path(),
+            _ => false,
+        });
+        fs::write(dir.join("notes.txt"), "b: c: d\n").unwrap();
+        fs::write(dir.join("a.yaml"), "a: 2\n").unwrap();
+        wait_for(&|r| match *r {
+            Reload::Loaded(ref p, ref docs) => {
+                assert_eq!(p, &dir.join("a.yaml"));
+                docs.len() == 1 && docs[0]["a"].as_str() == Some("2")
+            }
+            Reload::Failed(ref e) => {
+                assert_ne!(e.path(), dir.join("notes.txt").as_path());
+                false
+            }
+            _ => false,
+        });
+
+        drop(watcher);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
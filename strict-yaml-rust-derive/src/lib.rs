@@ -0,0 +1,142 @@
+//! `#[derive(StrictYamlConfig)]`, which reads a struct from a StrictYAML
+//! document checked against a schema generated from its fields.
+//!
+//! Each named field is a key of a `schema::Map`, validated and read as
+//! `StrictYamlConfig` says for its type. `Option` fields may be left out.
+//! Fields take two attributes:
+//!
+//! - `#[strict_yaml(rename = "key")]` reads the field from another key;
+//! - `#[strict_yaml(default = value)]` makes the key optional, reading
+//!   `value`, anything `StrictYaml` converts from, when it is left out.
+//!
+//! ```
+//! use strict_yaml_rust::schema::StrictYamlConfig;
+//! use strict_yaml_rust_derive::StrictYamlConfig;
+//!
+//! #[derive(StrictYamlConfig, Debug, PartialEq)]
+//! struct Server {
+//!     host: String,
+//!     port: u16,
+//!     #[strict_yaml(default = false)]
+//!     debug: bool,
+//!     #[strict_yaml(rename = "worker-threads")]
+//!     threads: Option<u8>,
+//! }
+//!
+//! let server = Server::load("host: a\nport: 8080\nworker-threads: 4").unwrap();
+//! assert_eq!(
+//!     server,
+//!     Server { host: "a".to_owned(), port: 8080, debug: false, threads: Some(4) }
+//! );
+//! let err = Server::load("host: a\nport: http").unwrap_err();
+//! assert_eq!(
+//!     err.to_string(),
+//!     "expected an integer from 0 to 65535, found \"http\" at port (line 2 column 7)"
+//! );
+//! ```
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Error, Expr, Field, Fields, LitStr};
+
+#[proc_macro_derive(StrictYamlConfig, attributes(strict_yaml))]
+pub fn derive_strict_yaml_config(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+/// The `strict_yaml` attributes of a field.
+struct FieldAttrs {
+    key: String,
+    default: Option<Expr>,
+}
+
+fn field_attrs(field: &Field) -> Result<FieldAttrs, Error> {
+    let mut attrs = FieldAttrs {
+        key: field.ident.as_ref().unwrap().to_string(),
+        default: None,
+    };
+    for attr in field
+        .attrs
+        .iter()
+        .filter(|a| a.path().is_ident("strict_yaml"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                attrs.key = meta.value()?.parse::<LitStr>()?.value();
+                Ok(())
+            } else if meta.path.is_ident("default") {
+                attrs.default = Some(meta.value()?.parse()?);
+                Ok(())
+            } else {
+                Err(meta.error("expected `rename` or `default`"))
+            }
+        })?;
+    }
+    Ok(attrs)
+}
+
+fn expand(input: &DeriveInput) -> Result<TokenStream2, Error> {
+    let fields = match input.data {
+        Data::Struct(ref data) => match data.fields {
+            Fields::Named(ref fields) => &fields.named,
+            _ => {
+                return Err(Error::new_spanned(
+                    &input.ident,
+                    "StrictYamlConfig needs a struct with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(Error::new_spanned(
+                &input.ident,
+                "StrictYamlConfig can only be derived for structs",
+            ))
+        }
+    };
+
+    let mut keys = Vec::new();
+    let mut reads = Vec::new();
+    for field in fields {
+        let attrs = field_attrs(field)?;
+        let (name, ty, key) = (&field.ident, &field.ty, &attrs.key);
+        let config = quote!(<#ty as ::strict_yaml_rust::schema::StrictYamlConfig>);
+        keys.push(match attrs.default {
+            Some(ref default) => quote! {
+                let map = map.optional_with_default(#key, #config::validator(), #default);
+            },
+            None => quote! {
+                let map = if #config::required() {
+                    map.required(#key, #config::validator())
+                } else {
+                    map.optional(#key, #config::validator())
+                };
+            },
+        });
+        reads.push(quote!(#name: #config::from_node(&node[#key])?));
+    }
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::strict_yaml_rust::schema::StrictYamlConfig for #ident #ty_generics
+        #where_clause
+        {
+            fn validator() -> ::std::boxed::Box<dyn ::strict_yaml_rust::schema::Validator> {
+                let map = ::strict_yaml_rust::schema::Map::new();
+                #(#keys)*
+                ::std::boxed::Box::new(map)
+            }
+
+            fn from_node(node: &::strict_yaml_rust::StrictYaml) -> ::std::option::Option<Self> {
+                ::std::option::Option::Some(#ident {
+                    #(#reads),*
+                })
+            }
+        }
+    })
+}
@@ -0,0 +1,94 @@
+use strict_yaml_rust::schema::{SchemaLoadError, StrictYamlConfig};
+use strict_yaml_rust_derive::StrictYamlConfig;
+
+#[derive(StrictYamlConfig, Debug, PartialEq)]
+struct Host {
+    name: String,
+    weight: Option<f64>,
+}
+
+#[derive(StrictYamlConfig, Debug, PartialEq)]
+struct Config {
+    name: String,
+    replicas: i64,
+    #[strict_yaml(default = 80)]
+    port: u16,
+    #[strict_yaml(rename = "log-level", default = "info")]
+    log_level: String,
+    debug: Option<bool>,
+    hosts: Vec<Host>,
+}
+
+#[test]
+fn test_load() {
+    let source = "name: web
+replicas: 3
+debug: yes
+hosts:
+  - name: a
+    weight: 0.5
+  - name: b
+";
+    let config = Config::load(source).unwrap();
+    assert_eq!(
+        config,
+        Config {
+            name: "web".to_owned(),
+            replicas: 3,
+            port: 80,
+            log_level: "info".to_owned(),
+            debug: Some(true),
+            hosts: vec![
+                Host {
+                    name: "a".to_owned(),
+                    weight: Some(0.5),
+                },
+                Host {
+                    name: "b".to_owned(),
+                    weight: None,
+                },
+            ],
+        }
+    );
+
+    let config =
+        Config::load("name: web\nreplicas: 1\nport: 8080\nlog-level: debug\nhosts:\n  - name: a")
+            .unwrap();
+    assert_eq!((config.port, config.log_level.as_str()), (8080, "debug"));
+    assert_eq!(config.debug, None);
+}
+
+#[test]
+fn test_errors() {
+    let source = "replicas: many
+port: 70000
+log_level: info
+hosts:
+  - weight: heavy
+";
+    let errors: Vec<String> = match Config::load(source).unwrap_err() {
+        SchemaLoadError::Schema(errors) => errors.iter().map(|e| e.to_string()).collect(),
+        e => panic!("unexpected error {}", e),
+    };
+    assert_eq!(
+        errors,
+        [
+            "missing key \"name\" (line 1 column 1)",
+            "expected an integer, found \"many\" at replicas (line 1 column 11)",
+            "expected an integer from 0 to 65535, found \"70000\" at port (line 2 column 7)",
+            "unexpected key \"log_level\" at log_level (line 3 column 1)",
+            "missing key \"name\" at hosts[0] (line 5 column 5)",
+            "expected a decimal, found \"heavy\" at hosts[0].weight (line 5 column 13)",
+        ]
+    );
+
+    let err = Config::load("").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "expected a mapping, found nothing (line 1 column 1)"
+    );
+    assert!(matches!(
+        Config::load("a: 'b"),
+        Err(SchemaLoadError::Load(_))
+    ));
+}
@@ -0,0 +1,163 @@
+extern crate strict_yaml_rust;
+#[macro_use]
+extern crate quickcheck;
+
+use strict_yaml_rust::parser::{Event, MarkedEventReceiver, Parser};
+use strict_yaml_rust::scanner::{Marker, ScanError, TScalarStyle};
+use strict_yaml_rust::skim::skim;
+use strict_yaml_rust::{LoadOptions, PushParser, StrictYamlLoader};
+
+// Inputs that broke, or came close to breaking, the scanner and parser.
+const CORPUS: &[&str] = &[
+    "",
+    "\0",
+    "\u{feff}",
+    "---",
+    "...",
+    "--- ...",
+    "a:",
+    ":",
+    "? ",
+    "?\n:",
+    "- ",
+    "-\n-",
+    "|",
+    ">",
+    "|2",
+    "|0",
+    "|9\n x",
+    "|+-",
+    ">\n\n\n",
+    "'",
+    "\"",
+    "\"\\",
+    "\"\\x",
+    "\"\\xZZ\"",
+    "\"\\u12\"",
+    "\"\\U0011FFFF\"",
+    "\"\\ud800\"",
+    "'''",
+    "a: b: c",
+    "a:\n- b\n  c: d",
+    "  a\nb",
+    "a\n  - b",
+    "- - - -\n",
+    "? - a\n  - b\n: - c",
+    "\t",
+    "a:\tb",
+    "\r",
+    "\r\r\n",
+    "a: \u{85}b\u{2028}c",
+    "%YAML 1.2\n---",
+    "%YAML 1.2\n%YAML 1.2\n---",
+    "%TAG ! x\n---",
+    "%FOO\n---",
+    "&a *a",
+    "&a &b x",
+    "- &a\n- *a",
+    "? &a\n: *a",
+    "!x !y",
+    "[{]}",
+    "[a: b, ? c]",
+    "{a, : b, [c]: d}",
+    "[&a x, *a]",
+    "{a: [b,\n- c]}",
+    "#\n#",
+    "a: |\n  b\n c",
+    "- a\n b: c",
+];
+
+fn check(s: &str) {
+    let _ = StrictYamlLoader::load_from_str(s);
+    for _ in StrictYamlLoader::iter_documents(s).take(100) {}
+    let _ = skim(s, |_, _, _| true);
+    let aliases = LoadOptions::new().expand_aliases(1000);
+    let _ = StrictYamlLoader::load_from_str_with_options(s, &aliases);
+    let flow = aliases.flow_collections(true);
+    let _ = StrictYamlLoader::load_from_str_with_options(s, &flow);
+
+    let mut parser = PushParser::new();
+    let mut sink = Sink;
+    let fed = s
+        .as_bytes()
+        .iter()
+        .all(|b| parser.feed(&[*b], &mut sink).is_ok());
+    if fed {
+        let _ = parser.finish(&mut sink);
+    }
+}
+
+struct Sink;
+
+impl MarkedEventReceiver for Sink {
+    fn on_event(&mut self, _: Event, _: Marker) -> Result<(), ScanError> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_corpus() {
+    for s in CORPUS {
+        check(s);
+    }
+}
+
+#[test]
+fn test_unbalanced_filter_output() {
+    let scalar = || Event::Scalar("x".to_owned(), TScalarStyle::Plain, 0);
+    let outputs: Vec<Vec<Event>> = vec![
+        vec![Event::SequenceEnd],
+        vec![Event::MappingEnd],
+        vec![scalar(), scalar()],
+        vec![Event::MappingStart(0), scalar(), Event::SequenceEnd],
+        vec![Event::SequenceStart(0), Event::DocumentEnd],
+    ];
+    for output in outputs {
+        let mut injected = false;
+        let filter = move |ev: Event, mark: Marker, out: &mut dyn MarkedEventReceiver| {
+            if let Event::Scalar(..) = ev {
+                if !injected {
+                    injected = true;
+                    for o in &output {
+                        out.on_event(o.clone(), mark)?;
+                    }
+                    return Ok(());
+                }
+            }
+            out.on_event(ev, mark)
+        };
+        assert!(StrictYamlLoader::load_from_str_with_filter("a: b\nc: d", filter).is_err());
+    }
+}
+
+#[test]
+fn test_load_after_manual_advance() {
+    let mut parser = Parser::new("a: b".chars());
+    parser.next().unwrap(); // StreamStart
+    parser.next().unwrap(); // DocumentStart
+    assert!(parser.load(&mut Sink, true).is_err());
+}
+
+// Building blocks for random inputs, so that generated strings are likely to
+// hit the interesting parts of the grammar.
+#[rustfmt::skip]
+const ATOMS: &[&str] = &[
+    "a", "b", ":", " ", "  ", "\n", "- ", "? ", "|", ">", "'", "\"", "\\", "#", "---", "...", "\t",
+    "\r", "\r\n", "[", "]", "{", "}", ",", "&", "*", "!", "%", "\\x", "\\u", "9", "+", "-", "é",
+    "\u{feff}", "\0", "|2", ">-", "|+", "    ", "\n  ", ": ", "%YAML 1.2", "\\N", "- - ", "? - ",
+    ": - ", "\n- ", "\n? ", "\n: ", "\n  - ", "x: y", "\u{85}", "\u{2028}",
+    "&a ", "*a", "&b", "*b ",
+];
+
+quickcheck! {
+    fn test_no_panic_on_atoms(xs: Vec<u8>) -> bool {
+        let s: String = xs.iter().map(|&i| ATOMS[i as usize % ATOMS.len()]).collect();
+        check(&s);
+        true
+    }
+
+    fn test_no_panic_on_strings(s: String) -> bool {
+        check(&s);
+        true
+    }
+}